@@ -1,4 +1,4 @@
-use lisp_rpc_rust_parser::{Parser, TypeValue, data::*}; // import the data module
+use lisp_rpc_rust_parser::{Parser, data::*}; // import the data module
 
 fn main() {
     // client send some data
@@ -41,16 +41,17 @@ fn main() {
     // check the msg name
     let _ = client_request_data.get_name();
 
-    let &Data::Value(TypeValue::Number(version_v)) = client_request_data.get("version").unwrap()
-    else {
-        panic!()
-    };
-
-    let &Data::Value(TypeValue::Number(aa_v)) = client_request_data.get("aa").unwrap() else {
-        panic!()
-    };
+    // typed getters return the error if the key is missing or the value is the wrong type
+    let version_v = client_request_data.get_i64("version").unwrap();
+    let aa_v = client_request_data.get_i64("aa").unwrap();
 
     let _ = client_request_data.get("bb");
+    assert!(
+        client_request_data
+            .get_i64("bb")
+            .unwrap_err()
+            .is_missing_key()
+    );
 
     // server side for some reason want to format str to send data
     let server_response_data = Data::from_str(
@@ -74,5 +75,20 @@ fn main() {
         response_client_get,
         response_client_get.to_string(),
         response_client_get.get("result")
-    )
+    );
+
+    // or use the conventional envelopes instead of making the response by hand
+    let ok_response = Data::ok(version_v + aa_v);
+    println!("ok envelope is\n{}\n", ok_response);
+    assert_eq!(ok_response.result(), Some(&3.into_rpc_data()));
+
+    let error_response = Data::error("bad-version", "version has to be 1", None);
+    println!("error envelope is\n{}\n", error_response);
+    if error_response.is_error() {
+        println!(
+            "client get the error {:?}: {:?}",
+            error_response.error_code(),
+            error_response.error_message()
+        );
+    }
 }
@@ -0,0 +1,126 @@
+//! generate the rust code of the specs in the build script of the crate
+//! using them. The code is written to the OUT_DIR and included by the crate,
+//! the build reruns when the spec files change
+//!
+//! ```ignore
+//! // build.rs
+//! fn main() {
+//!     lisp_rpc_build::compile("specs/library.lisprpc").unwrap();
+//! }
+//!
+//! // src/lib.rs, the package of the spec is library
+//! mod library {
+//!     use super::*;
+//!     include!(concat!(env!("OUT_DIR"), "/library.rs"));
+//! }
+//! ```
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+pub use lisp_rpc_rust_generator::{GenOptions, GenerateOptions, GenerateTarget};
+use lisp_rpc_rust_generator::{SpecFile, kebab_to_snake_case};
+
+/// generate the rust code of the spec file by the stock templates, it is
+/// written to OUT_DIR/<package>.rs. Return the path of the written file
+pub fn compile(spec: impl AsRef<Path>) -> Result<PathBuf> {
+    compile_with(spec, GenerateOptions::default())
+}
+
+/// same as compile, with the options of the code and the templates
+pub fn compile_with(spec: impl AsRef<Path>, opts: GenerateOptions) -> Result<PathBuf> {
+    let out_dir = env::var_os("OUT_DIR").context("no OUT_DIR, compile runs in the build script")?;
+    let (path, rerun) = compile_to(spec.as_ref(), Path::new(&out_dir), opts)?;
+    for r in rerun {
+        println!("cargo:rerun-if-changed={}", r.display());
+    }
+    Ok(path)
+}
+
+/// write the code to the folder, return the written file and the files the
+/// build reruns for
+fn compile_to(
+    spec: &Path,
+    out_dir: &Path,
+    opts: GenerateOptions,
+) -> Result<(PathBuf, Vec<PathBuf>)> {
+    // the code is one file for the include!
+    let specs = SpecFile::new()
+        .config_options(GenOptions {
+            split_files: false,
+            ..opts.options
+        })
+        .read(&spec.to_string_lossy())?;
+
+    // the urls aren't watched
+    let mut rerun: Vec<PathBuf> = specs
+        .sources()
+        .iter()
+        .map(PathBuf::from)
+        .filter(|p| p.exists())
+        .collect();
+    rerun.extend(opts.templates.iter().cloned());
+
+    let output = specs.generate(opts.target, &opts.templates)?;
+    let code = output
+        .file("src/lib.rs")
+        .with_context(|| format!("the target {:?} has no rust code", opts.target))?;
+
+    // the same code isn't written again, the crate isn't rebuilt for it
+    let path = out_dir.join(format!("{}.rs", kebab_to_snake_case(&output.package)));
+    if fs::read_to_string(&path).ok().as_deref() != Some(code) {
+        fs::write(&path, code).with_context(|| format!("Failed to write file: {:?}", path))?;
+    }
+    Ok((path, rerun))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compile() {
+        let dir = env::temp_dir().join(format!("lisp-rpc-build-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("types.lisp"), "(def-msg book-info :title 'string)").unwrap();
+        fs::write(
+            dir.join("library.lisp"),
+            r#"(def-rpc-package my-library)
+(include "types.lisp")
+(def-rpc get-book '(:title 'string) 'book-info)"#,
+        )
+        .unwrap();
+
+        let (path, rerun) =
+            compile_to(&dir.join("library.lisp"), &dir, GenerateOptions::default()).unwrap();
+        assert_eq!(path, dir.join("my_library.rs"));
+        let code = fs::read_to_string(&path).unwrap();
+        assert!(code.contains("pub struct BookInfo {"));
+        assert!(code.contains("pub struct GetBook {"));
+
+        // the spec and the included file
+        let rerun: Vec<String> = rerun
+            .iter()
+            .map(|p| p.file_name().unwrap().to_string_lossy().to_string())
+            .collect();
+        assert_eq!(rerun, vec!["library.lisp", "types.lisp"]);
+
+        // the json schemas aren't the code
+        assert!(
+            compile_to(
+                &dir.join("library.lisp"),
+                &dir,
+                GenerateOptions {
+                    target: GenerateTarget::JsonSchema,
+                    ..Default::default()
+                }
+            )
+            .is_err()
+        );
+        assert!(compile("library.lisp").is_err());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
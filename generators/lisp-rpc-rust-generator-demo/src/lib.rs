@@ -1,5 +1,10 @@
 mod rpc_libs;
 
+use std::collections::HashMap;
+use std::error::Error;
+
+use lisp_rpc_rust_parser::data::{Data, DataError, FromData, GetAbleData};
+
 // macro_rules! impl_to_rpc_data {
 //     ($($type:ty),*) => {
 //         $(
@@ -56,6 +61,107 @@ impl<T: ToRPCData> ToRPCData for Vec<T> {
     }
 }
 
-trait FromRPCData {
-    fn from_rpc(&self) -> String;
+/// the keyword pairs '(:k v), the keys are sorted, so the output is stable
+impl<T: ToRPCData> ToRPCData for HashMap<String, T> {
+    fn to_rpc(&self) -> String {
+        let mut pairs: Vec<_> = self.iter().collect();
+        pairs.sort_by(|a, b| a.0.cmp(b.0));
+        "'(".to_string()
+            + &pairs
+                .iter()
+                .map(|(k, v)| format!(":{} {}", k, v.to_rpc()))
+                .collect::<Vec<_>>()
+                .join(" ")
+            + ")"
+    }
+}
+
+impl<T: ToRPCData> ToRPCData for Box<T> {
+    fn to_rpc(&self) -> String {
+        self.as_ref().to_rpc()
+    }
+}
+
+trait FromRPCData: Sized {
+    fn from_rpc(d: &Data) -> Result<Self, DataError>;
+}
+
+impl FromRPCData for String {
+    fn from_rpc(d: &Data) -> Result<Self, DataError> {
+        Self::from_rpc_data(d)
+    }
+}
+
+impl FromRPCData for i64 {
+    fn from_rpc(d: &Data) -> Result<Self, DataError> {
+        Self::from_rpc_data(d)
+    }
+}
+
+impl<T: FromRPCData> FromRPCData for Box<T> {
+    fn from_rpc(d: &Data) -> Result<Self, DataError> {
+        T::from_rpc(d).map(Box::new)
+    }
+}
+
+impl<T: FromRPCData> FromRPCData for Vec<T> {
+    fn from_rpc(d: &Data) -> Result<Self, DataError> {
+        Vec::<Data>::from_rpc_data(d)?
+            .iter()
+            .map(T::from_rpc)
+            .collect()
+    }
+}
+
+impl<T: FromRPCData> FromRPCData for HashMap<String, T> {
+    fn from_rpc(d: &Data) -> Result<Self, DataError> {
+        HashMap::<String, Data>::from_rpc_data(d)?
+            .iter()
+            .map(|(k, v)| Ok((k.clone(), T::from_rpc(v)?)))
+            .collect()
+    }
+}
+
+/// the way the client sends the request and gets the response, like tcp or http
+pub trait Transport {
+    fn send(&self, req: &str) -> Result<String, Box<dyn Error>>;
+
+    /// send the notification without the response. The default sends it as
+    /// the request and drops the response, the transports which don't wait
+    /// for the responses override it
+    fn notify(&self, msg: &str) -> Result<(), Box<dyn Error>> {
+        self.send(msg).map(|_| ())
+    }
+}
+
+/// the async version of Transport, the futures can be spawned by tokio
+pub trait AsyncTransport: Send + Sync {
+    fn send(
+        &self,
+        req: &str,
+    ) -> impl Future<Output = Result<String, Box<dyn Error + Send + Sync>>> + Send;
+
+    /// the async version of Transport::notify
+    fn notify(
+        &self,
+        msg: &str,
+    ) -> impl Future<Output = Result<(), Box<dyn Error + Send + Sync>>> + Send {
+        async move { self.send(msg).await.map(|_| ()) }
+    }
+}
+
+/// the typed client, the methods of rpcs are generated. The transport is
+/// Transport or AsyncTransport
+pub struct Client<T> {
+    transport: T,
+}
+
+impl<T> Client<T> {
+    pub fn new(transport: T) -> Self {
+        Self { transport }
+    }
+
+    pub fn transport(&self) -> &T {
+        &self.transport
+    }
 }
@@ -12,6 +12,15 @@ impl ToRPCData for LanguagePerfer {
     }
 }
 
+impl FromRPCData for LanguagePerfer {
+    fn from_rpc(d: &Data) -> Result<Self, DataError> {
+        let d = d.check_tag("language-perfer")?;
+        Ok(Self {
+            lang: FromRPCData::from_rpc(GetAbleData::try_get(d, "lang")?)?,
+        })
+    }
+}
+
 #[derive(Debug)]
 pub struct BookInfo {
     lang: LanguagePerfer,
@@ -32,6 +41,18 @@ impl ToRPCData for BookInfo {
     }
 }
 
+impl FromRPCData for BookInfo {
+    fn from_rpc(d: &Data) -> Result<Self, DataError> {
+        let d = d.check_tag("book-info")?;
+        Ok(Self {
+            lang: FromRPCData::from_rpc(GetAbleData::try_get(d, "lang")?)?,
+            title: FromRPCData::from_rpc(GetAbleData::try_get(d, "title")?)?,
+            version: FromRPCData::from_rpc(GetAbleData::try_get(d, "version")?)?,
+            id: FromRPCData::from_rpc(GetAbleData::try_get(d, "id")?)?,
+        })
+    }
+}
+
 // rpc + keyword name
 pub struct GetBookLang {
     lang: String,
@@ -65,6 +86,13 @@ impl ToRPCData for GetBook {
     }
 }
 
+impl<T: Transport> Client<T> {
+    pub fn get_book(&self, req: GetBook) -> Result<BookInfo, Box<dyn std::error::Error>> {
+        let resp = self.transport().send(&req.to_rpc())?;
+        Ok(FromRPCData::from_rpc(&Data::from_root_str(&resp, None)?)?)
+    }
+}
+
 // test below for making sure
 #[cfg(test)]
 mod tests {
@@ -102,4 +130,43 @@ mod tests {
             r#"(book-info :id "123" :title "hello world" :version "1984" :lang (language-perfer :lang "english"))"#
         )
     }
+
+    #[test]
+    fn test_book_info_from_rpc() {
+        let d = Data::from_root_str(
+            r#"(book-info :id "123" :title "hello world" :version "1984" :lang (language-perfer :lang "english"))"#,
+            None,
+        )
+        .unwrap();
+        let bi = BookInfo::from_rpc(&d).unwrap();
+        assert_eq!(bi.lang.lang, "english");
+        assert_eq!(bi.to_rpc(), d.to_string());
+
+        let d = Data::from_root_str(r#"(book-info :id "123")"#, None).unwrap();
+        assert!(BookInfo::from_rpc(&d).unwrap_err().is_missing_key());
+    }
+    struct EchoBook;
+
+    impl Transport for EchoBook {
+        fn send(&self, req: &str) -> Result<String, Box<dyn std::error::Error>> {
+            assert!(req.starts_with("(get-book "));
+            Ok(r#"(book-info :id "123" :title "hello world" :version "1984" :lang (language-perfer :lang "english"))"#.to_string())
+        }
+    }
+
+    #[test]
+    fn test_client_get_book() {
+        let client = Client::new(EchoBook);
+        let bi = client
+            .get_book(GetBook {
+                title: "hello world".to_string(),
+                version: "1984".to_string(),
+                lang: GetBookLang {
+                    lang: "english".to_string(),
+                    encoding: 11,
+                },
+            })
+            .unwrap();
+        assert_eq!(bi.id, "123");
+    }
 }
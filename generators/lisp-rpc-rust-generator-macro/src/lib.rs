@@ -60,3 +60,128 @@ pub fn print_fields_derive(input: TokenStream) -> TokenStream {
 
     expanded.into()
 }
+
+/// make the rust name to kebab case, BookInfo => book-info, book_id => book-id.
+/// The run of capitals is one word, HTTPServer => http-server, and the raw
+/// prefix is dropped, r#type => type
+fn kebab_case(name: &str) -> String {
+    let name = name.strip_prefix("r#").unwrap_or(name);
+    let chars: Vec<char> = name.chars().collect();
+    let mut res = String::new();
+    for (i, c) in chars.iter().enumerate() {
+        if c.is_uppercase() {
+            // the new word starts after the lower case, or at the last
+            // capital of the run before the lower case
+            let new_word = i != 0
+                && (!chars[i - 1].is_uppercase()
+                    || chars.get(i + 1).is_some_and(|n| n.is_lowercase()));
+            if new_word && !res.ends_with('-') {
+                res.push('-');
+            }
+            res.extend(c.to_lowercase());
+        } else if *c == '_' {
+            res.push('-');
+        } else {
+            res.push(*c);
+        }
+    }
+    res
+}
+
+/// the named fields and their keywords of the struct
+fn keyword_fields(input: &DeriveInput, derive_name: &str) -> Vec<(Ident, String)> {
+    let fields = match &input.data {
+        Data::Struct(data_struct) => &data_struct.fields,
+        _ => panic!("{} can only be derived on structs", derive_name),
+    };
+
+    fields
+        .iter()
+        .map(|field| {
+            let field_name = field
+                .ident
+                .clone()
+                .unwrap_or_else(|| panic!("{} needs named fields", derive_name));
+            let kw = kebab_case(&field_name.to_string());
+            (field_name, kw)
+        })
+        .collect()
+}
+
+/// Derive the IntoData of lisp-rpc-rust-parser. The struct becomes the data
+/// (struct-name :field-name value...)
+///
+/// ```ignore
+/// #[derive(IntoData)]
+/// pub struct BookInfo {
+///     book_id: i64,
+///     title: String,
+/// }
+///
+/// // (book-info :book-id 1 :title "1984")
+/// BookInfo { book_id: 1, title: "1984".to_string() }.into_rpc_data();
+/// ```
+#[proc_macro_derive(IntoData)]
+pub fn into_data_derive(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    let struct_name = &input.ident;
+    let data_name = kebab_case(&struct_name.to_string());
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let kws = keyword_fields(&input, "IntoData")
+        .into_iter()
+        .map(|(field_name, kw)| {
+            quote! {
+                .kw(#kw, &self.#field_name)
+            }
+        });
+
+    let expanded = quote! {
+        impl #impl_generics ::lisp_rpc_rust_parser::data::IntoData for #struct_name #ty_generics #where_clause {
+            fn into_rpc_data(&self) -> ::lisp_rpc_rust_parser::data::Data {
+                ::lisp_rpc_rust_parser::data::DataBuilder::new(#data_name)
+                    #(#kws)*
+                    .build()
+                    .expect("derived data should be valid")
+            }
+        }
+    };
+
+    expanded.into()
+}
+
+/// Derive the FromData of lisp-rpc-rust-parser. The reverse of the IntoData,
+/// every field is read from the kebab case keyword. The tagged data
+/// `(the type-name data)` has to be tagged with the kebab case struct name
+#[proc_macro_derive(FromData)]
+pub fn from_data_derive(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    let struct_name = &input.ident;
+    let data_name = kebab_case(&struct_name.to_string());
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let fields = keyword_fields(&input, "FromData")
+        .into_iter()
+        .map(|(field_name, kw)| {
+            quote! {
+                #field_name: ::lisp_rpc_rust_parser::data::GetAbleData::get_as(d, #kw)?,
+            }
+        });
+
+    let expanded = quote! {
+        impl #impl_generics ::lisp_rpc_rust_parser::data::FromData for #struct_name #ty_generics #where_clause {
+            fn from_rpc_data(
+                d: &::lisp_rpc_rust_parser::data::Data,
+            ) -> Result<Self, ::lisp_rpc_rust_parser::data::DataError> {
+                let d = d.check_tag(#data_name)?;
+                Ok(Self {
+                    #(#fields)*
+                })
+            }
+        }
+    };
+
+    expanded.into()
+}
@@ -0,0 +1,73 @@
+use std::collections::HashMap;
+
+use lisp_rpc_rust_generator_macro::{FromData, IntoData};
+use lisp_rpc_rust_parser::{
+    Parser,
+    data::{Data, FromData, FromStr, IntoData},
+};
+
+#[derive(Debug, PartialEq, IntoData, FromData)]
+struct BookInfo {
+    book_id: i64,
+    title: String,
+    tags: Vec<String>,
+    hardcover: bool,
+    isbn: Option<String>,
+    lang: HashMap<String, String>,
+}
+
+#[test]
+fn test_derive_into_and_from_data() {
+    let b = BookInfo {
+        book_id: 1,
+        title: "1984".to_string(),
+        tags: vec!["novel".to_string()],
+        hardcover: true,
+        isbn: None,
+        lang: HashMap::from([("lang".to_string(), "en".to_string())]),
+    };
+
+    let d = b.into_rpc_data();
+    assert_eq!(
+        d.to_string(),
+        r#"(book-info :book-id 1 :title "1984" :tags '("novel") :hardcover t :isbn nil :lang '(:lang "en"))"#
+    );
+
+    let d = Data::from_str(&Parser::new(), &d.to_string()).unwrap();
+    assert_eq!(BookInfo::from_rpc_data(&d).unwrap(), b);
+
+    // the tagged data has to be tagged with the struct name
+    let tagged = Data::from_str(&Parser::new(), &format!("(the book-info {})", d)).unwrap();
+    assert_eq!(BookInfo::from_rpc_data(&tagged).unwrap(), b);
+    let tagged = Data::from_str(&Parser::new(), &format!("(the author {})", d)).unwrap();
+    assert!(
+        BookInfo::from_rpc_data(&tagged)
+            .unwrap_err()
+            .is_wrong_type()
+    );
+
+    let d = Data::from_str(&Parser::new(), r#"(book-info :book-id "1")"#).unwrap();
+    assert!(BookInfo::from_rpc_data(&d).unwrap_err().is_wrong_type());
+}
+
+#[derive(Debug, PartialEq, IntoData, FromData)]
+struct HTTPServer {
+    r#type: String,
+    max_ttl: i64,
+}
+
+#[test]
+fn test_derive_kebab_names() {
+    let s = HTTPServer {
+        r#type: "proxy".to_string(),
+        max_ttl: 30,
+    };
+
+    // the raw prefix is dropped and the capitals run is one word
+    let d = s.into_rpc_data();
+    assert_eq!(
+        d.to_string(),
+        r#"(http-server :type "proxy" :max-ttl 30)"#
+    );
+    assert_eq!(HTTPServer::from_rpc_data(&d).unwrap(), s);
+}
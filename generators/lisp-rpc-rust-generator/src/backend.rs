@@ -0,0 +1,143 @@
+//! the backends generating the code of the other languages. SpecFile converts
+//! the specs to the CodegenUnit, the language-agnostic GeneratedStructs and
+//! GeneratedRPCs, and the backend renders them to the files
+//!
+//! the rust code is generated by SpecFile::gen_code_to_file from the same
+//! GeneratedStructs, the python module is the PythonBackend. The new targets
+//! implement CodegenBackend and call SpecFile::gen_backend_to_file
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::{DefAlias, DefConst, GeneratedStruct, VersionRange};
+
+/// the rpc of the specs, the request is the GeneratedStruct with the same
+/// data name
+#[derive(Debug, Serialize, PartialEq, Eq, Clone)]
+pub struct GeneratedRPC {
+    pub data_name: String,
+    pub doc: Option<String>,
+
+    /// the spec type of the result, None if the rpc returns nothing
+    pub returns: Option<String>,
+
+    /// the versions of the spec having this rpc
+    pub versions: VersionRange,
+
+    /// the def-service of the rpc, None for the def-rpc
+    pub service: Option<String>,
+
+    /// the codes of the declared errors
+    pub errors: Vec<String>,
+
+    /// the one-way notification of def-notify, it has no response
+    pub notify: bool,
+}
+
+/// all specs for the backend, before the boxing and the renaming of rust
+#[derive(Debug, Serialize)]
+pub struct CodegenUnit {
+    /// the first package, it is the name of the generated library
+    pub package: String,
+
+    /// the version of (def-version n)
+    pub version: Option<i64>,
+
+    /// the constants of def-const
+    pub consts: Vec<DefConst>,
+
+    /// the aliases of def-alias, the spec types of the fields and the rpcs
+    /// can be their names
+    pub aliases: Vec<DefAlias>,
+
+    /// the msgs, the rpc requests, and their anonymous maps. The anonymous
+    /// maps are before their owners
+    pub structs: Vec<GeneratedStruct>,
+
+    pub rpcs: Vec<GeneratedRPC>,
+
+    /// the rust names of the renamed structs, the default names to the
+    /// rust names
+    pub renames: BTreeMap<String, String>,
+}
+
+/// the target language of the generated code
+pub trait CodegenBackend {
+    /// the files of the unit, the paths are relative to the folder of the
+    /// package. The files are written with the generated marker
+    fn generate(&self, unit: &CodegenUnit) -> Result<Vec<(PathBuf, String)>>;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use lisp_rpc_rust_parser::Parser;
+
+    use super::*;
+    use crate::SpecFile;
+
+    /// one line per struct and rpc
+    struct Outline;
+
+    impl CodegenBackend for Outline {
+        fn generate(&self, unit: &CodegenUnit) -> Result<Vec<(PathBuf, String)>> {
+            let mut lines: Vec<String> = unit
+                .structs
+                .iter()
+                .map(|s| {
+                    let keys: Vec<&str> = s.fields.iter().map(|f| f.key_name()).collect();
+                    format!("{} {}", s.data_name(), keys.join(" "))
+                })
+                .collect();
+            lines.extend(unit.rpcs.iter().map(|r| {
+                format!(
+                    "{}.{} -> {}",
+                    r.service.as_deref().unwrap_or(&unit.package),
+                    r.data_name,
+                    r.returns.as_deref().unwrap_or("nil")
+                )
+            }));
+            Ok(vec![(PathBuf::from("outline.txt"), lines.join("\n"))])
+        }
+    }
+
+    #[test]
+    fn test_gen_backend() {
+        let mut specs = SpecFile::new();
+        for e in Parser::new()
+            .parse_root(Cursor::new(
+                r#"(def-rpc-package library)
+(def-msg book-info "the book" :title ('string :doc "the title") :extra '(:cover 'bytes))
+(def-rpc get-book '(:title 'string) 'book-info)
+(def-service shelf (put-book '(:book 'book-info)))"#,
+            ))
+            .unwrap()
+        {
+            specs.record_expr(&e).unwrap();
+        }
+
+        let unit = specs.codegen_unit().unwrap();
+        assert_eq!(unit.package, "library");
+        assert_eq!(unit.structs[1].doc.as_deref(), Some("the book"));
+        assert_eq!(unit.structs[1].fields[0].doc.as_deref(), Some("the title"));
+        assert_eq!(unit.structs[1].fields[1].spec_type, "book-info-extra");
+
+        assert_eq!(
+            specs.gen_backend(&Outline).unwrap(),
+            vec![(
+                PathBuf::from("outline.txt"),
+                "book-info-extra cover
+book-info title extra
+get-book title
+put-book book
+library.get-book -> book-info
+shelf.put-book -> nil"
+                    .to_string()
+            )]
+        );
+    }
+}
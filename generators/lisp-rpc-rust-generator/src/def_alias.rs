@@ -0,0 +1,240 @@
+//! the mod that handle def-alias expr
+
+use super::*;
+use crate::resolve::PRIMARY_TYPES;
+use anyhow::Context;
+use lisp_rpc_rust_parser::{Atom, Expr, TypeValue};
+use serde::Serialize;
+use tera::Tera;
+
+pub const ALIAS_TEMPLATE: &str = "def_alias.rs";
+
+/// the names of the type forms, they can't be the alias names
+const TYPE_FORMS: &[&str] = &["list", "map", "optional"];
+
+/// the primary types having the Default of rust, the struct with the default
+/// values fills the other fields by it
+const DEFAULT_TYPES: &[&str] = &["string", "number", "bytes", "uuid"];
+
+#[doc = r#"the struct of def-alias expression
+(def-alias name ["docstring"] 'type)
+"#]
+#[derive(Debug, Clone, Eq, PartialEq, Serialize)]
+pub struct DefAlias {
+    name: String,
+
+    /// the primary type of the alias, like string
+    alias_type: String,
+
+    /// the docstring after the name, it becomes the rustdoc of the alias
+    doc: Option<String>,
+}
+
+impl DefAlias {
+    pub fn if_def_alias_expr(expr: &Expr) -> bool {
+        match &expr {
+            Expr::List(e) => matches!(
+                e.first(),
+                Some(Expr::Atom(Atom {
+                    value: TypeValue::Symbol(s),
+                })) if s == "def-alias"
+            ),
+            _ => false,
+        }
+    }
+
+    pub fn from_expr(expr: &Expr) -> Result<Self> {
+        let rest = match &expr {
+            Expr::List(e) if Self::if_def_alias_expr(expr) => &e[1..],
+            _ => anyhow::bail!("parsing failed, the first symbol should be def-alias"),
+        };
+
+        let (name, rest) = match rest {
+            [
+                Expr::Atom(Atom {
+                    value: TypeValue::Symbol(name),
+                }),
+                rest @ ..,
+            ] if TypeValue::is_valid_symbol(name) => (name, rest),
+            _ => anyhow::bail!("parsing failed, the alias name has to be one symbol"),
+        };
+        if PRIMARY_TYPES.contains(&name.as_str()) || TYPE_FORMS.contains(&name.as_str()) {
+            anyhow::bail!("parsing failed, {} can't be the alias name", name)
+        }
+
+        let (doc, ty) = match rest {
+            [ty] => (None, ty),
+            [
+                Expr::Atom(Atom {
+                    value: TypeValue::String(doc),
+                }),
+                ty,
+            ] => (Some(doc.clone()), ty),
+            _ => anyhow::bail!("parsing failed, def-alias {} needs one type", name),
+        };
+
+        match ty {
+            Expr::Quote(box Expr::Atom(Atom {
+                value: TypeValue::Symbol(t),
+            })) if PRIMARY_TYPES.contains(&t.as_str()) => Ok(Self {
+                name: name.clone(),
+                alias_type: t.clone(),
+                doc,
+            }),
+            _ => anyhow::bail!(
+                "parsing failed, the type of def-alias {} has to be one of {}",
+                name,
+                PRIMARY_TYPES
+                    .iter()
+                    .map(|t| format!("'{}", t))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// the primary type of the alias
+    pub fn alias_type(&self) -> &str {
+        &self.alias_type
+    }
+
+    /// the newtype derives the Default if the primary type has it
+    pub fn has_default(&self) -> bool {
+        DEFAULT_TYPES.contains(&self.alias_type.as_str())
+    }
+
+    pub fn doc(&self) -> Option<&str> {
+        self.doc.as_deref()
+    }
+
+    /// Generate code with the exist tera instance
+    fn gen_code_with_tera(&self, templates: &Tera, options: &GenOptions) -> Result<String> {
+        let mut context = tera::Context::new();
+        options.insert_template(&mut context);
+        context.insert("comment", &self.doc.as_deref().map(doc_comment));
+        context.insert("name", &kebab_to_pascal_case(&self.name));
+        context.insert("alias_type", &type_translate(&self.alias_type));
+        context.insert("has_default", &self.has_default());
+        templates
+            .render(ALIAS_TEMPLATE, &context)
+            .context("render def alias wrong")
+    }
+}
+
+impl RPCSpec for DefAlias {
+    fn gen_code_with_tera(&self, templates: &Tera, options: &GenOptions) -> Result<String> {
+        self.gen_code_with_tera(templates, options)
+    }
+
+    fn file_target(&self) -> TargetFile {
+        TargetFile::Lib
+    }
+
+    fn to_spec_string(&self) -> String {
+        match &self.doc {
+            Some(d) => format!(
+                "(def-alias {} {} '{})",
+                self.name,
+                quote_string(d),
+                self.alias_type
+            ),
+            None => format!("(def-alias {} '{})", self.name, self.alias_type),
+        }
+    }
+
+    fn symbol_name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn alias(&self) -> Option<&DefAlias> {
+        Some(self)
+    }
+}
+
+/// replace the aliases inside the type by their primary types, for the
+/// targets without the newtypes
+pub(crate) fn unalias_type(ty: &Expr, aliases: &BTreeMap<&str, &str>) -> Expr {
+    match ty {
+        Expr::Atom(Atom {
+            value: TypeValue::Symbol(s),
+        }) => match aliases.get(s.as_str()) {
+            Some(t) => Expr::Atom(Atom {
+                value: TypeValue::Symbol(t.to_string()),
+            }),
+            None => ty.clone(),
+        },
+        Expr::Atom(_) => ty.clone(),
+        Expr::List(l) => Expr::List(l.iter().map(|e| unalias_type(e, aliases)).collect()),
+        Expr::Quote(e) => Expr::Quote(Box::new(unalias_type(e, aliases))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::Cursor, path::PathBuf};
+
+    use lisp_rpc_rust_parser::Parser;
+
+    use super::*;
+
+    fn from_str(source: &str) -> Result<DefAlias> {
+        DefAlias::from_expr(&Parser::new().parse_root_one(Cursor::new(source))?)
+    }
+
+    #[test]
+    fn test_def_alias() {
+        let da = from_str("(def-alias isbn \"the book id\" 'string)").unwrap();
+        assert_eq!(
+            da,
+            DefAlias {
+                name: "isbn".to_string(),
+                alias_type: "string".to_string(),
+                doc: Some("the book id".to_string()),
+            }
+        );
+        assert!(from_str("(def-alias isbn 'book-info)").is_err());
+        assert!(from_str("(def-alias isbn '(list 'string))").is_err());
+        assert!(from_str("(def-alias string 'string)").is_err());
+        assert!(from_str("(def-alias isbn)").is_err());
+
+        let project_root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let tera = load_templates(&[project_root.join("templates/def_alias.rs.template")]).unwrap();
+        let code = da.gen_code_with_tera(&tera, &Default::default()).unwrap();
+        assert!(code.starts_with(
+            "/// the book id
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Isbn(pub String);"
+        ));
+        assert!(code.contains(
+            "impl FromRPCData for Isbn {
+    fn from_rpc(d: &Data) -> Result<Self, DataError> {
+        FromRPCData::from_rpc(d).map(Self)
+    }
+}"
+        ));
+        assert_eq!(
+            da.gen_code_with_tera(
+                &tera,
+                &GenOptions {
+                    type_aliases: true,
+                    ..Default::default()
+                }
+            )
+            .unwrap(),
+            "/// the book id\npub type Isbn = String;"
+        );
+
+        // the aliases inside the list are replaced too
+        let ty = Parser::new()
+            .parse_root_one(Cursor::new("(:tags '(list (optional 'isbn)))"))
+            .unwrap();
+        assert_eq!(
+            unalias_type(&ty, &[("isbn", "string")].into_iter().collect()).to_string(),
+            "(:tags '(list (optional 'string)))"
+        );
+    }
+}
@@ -0,0 +1,217 @@
+//! the mod that handle def-const expr
+
+use super::*;
+use anyhow::Context;
+use lisp_rpc_rust_parser::{Atom, Expr, TypeValue};
+use serde::Serialize;
+use tera::Tera;
+
+pub const CONST_TEMPLATE: &str = "def_const.rs";
+
+/// the value of def-const, the number or the string
+#[derive(Debug, Clone, Eq, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum ConstValue {
+    Number(i64),
+    String(String),
+}
+
+impl ConstValue {
+    /// the rust type of the const
+    pub fn rust_type(&self) -> &'static str {
+        match self {
+            Self::Number(_) => "i64",
+            Self::String(_) => "&str",
+        }
+    }
+
+    /// the rust literal of the value, the string is escaped
+    pub fn rust_literal(&self) -> String {
+        match self {
+            Self::Number(n) => n.to_string(),
+            Self::String(s) => format!("{:?}", s),
+        }
+    }
+}
+
+#[doc = r#"the struct of def-const expression
+(def-const name ["docstring"] value)
+"#]
+#[derive(Debug, Clone, Eq, PartialEq, Serialize)]
+pub struct DefConst {
+    name: String,
+    value: ConstValue,
+
+    /// the docstring after the name, it becomes the rustdoc of the const
+    doc: Option<String>,
+}
+
+impl DefConst {
+    pub fn if_def_const_expr(expr: &Expr) -> bool {
+        match &expr {
+            Expr::List(e) => matches!(
+                e.first(),
+                Some(Expr::Atom(Atom {
+                    value: TypeValue::Symbol(s),
+                })) if s == "def-const"
+            ),
+            _ => false,
+        }
+    }
+
+    pub fn from_expr(expr: &Expr) -> Result<Self> {
+        let rest = match &expr {
+            Expr::List(e) if Self::if_def_const_expr(expr) => &e[1..],
+            _ => anyhow::bail!("parsing failed, the first symbol should be def-const"),
+        };
+
+        let (name, rest) = match rest {
+            [
+                Expr::Atom(Atom {
+                    value: TypeValue::Symbol(name),
+                }),
+                rest @ ..,
+            ] if TypeValue::is_valid_symbol(name) => (name, rest),
+            _ => anyhow::bail!("parsing failed, the const name has to be one symbol"),
+        };
+
+        let (doc, value) = match rest {
+            [value] => (None, value),
+            [
+                Expr::Atom(Atom {
+                    value: TypeValue::String(doc),
+                }),
+                value,
+            ] => (Some(doc.clone()), value),
+            _ => anyhow::bail!("parsing failed, def-const {} needs one value", name),
+        };
+
+        let value = match value {
+            Expr::Atom(Atom {
+                value: TypeValue::Number(n),
+            }) => ConstValue::Number(*n),
+            Expr::Atom(Atom {
+                value: TypeValue::String(s),
+            }) => ConstValue::String(s.clone()),
+            _ => anyhow::bail!(
+                "parsing failed, the value of def-const {} has to be the number or the string",
+                name
+            ),
+        };
+
+        Ok(Self {
+            name: name.clone(),
+            value,
+            doc,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn value(&self) -> &ConstValue {
+        &self.value
+    }
+
+    pub fn doc(&self) -> Option<&str> {
+        self.doc.as_deref()
+    }
+
+    /// the upper snake case name, `max-page-size` is `MAX_PAGE_SIZE`
+    pub fn rust_name(&self) -> String {
+        kebab_to_snake_case(&self.name).to_uppercase()
+    }
+
+    /// Generate code with the exist tera instance
+    fn gen_code_with_tera(&self, templates: &Tera, options: &GenOptions) -> Result<String> {
+        let mut context = tera::Context::new();
+        options.insert_template(&mut context);
+        context.insert("comment", &self.doc.as_deref().map(doc_comment));
+        context.insert("name", &self.rust_name());
+        context.insert("const_type", self.value.rust_type());
+        context.insert("value", &self.value.rust_literal());
+        templates
+            .render(CONST_TEMPLATE, &context)
+            .context("render def const wrong")
+    }
+}
+
+impl RPCSpec for DefConst {
+    fn gen_code_with_tera(&self, templates: &Tera, options: &GenOptions) -> Result<String> {
+        self.gen_code_with_tera(templates, options)
+    }
+
+    fn file_target(&self) -> TargetFile {
+        TargetFile::Lib
+    }
+
+    fn to_spec_string(&self) -> String {
+        let value = match &self.value {
+            ConstValue::Number(n) => n.to_string(),
+            ConstValue::String(s) => quote_string(s),
+        };
+        match &self.doc {
+            Some(d) => format!("(def-const {} {} {})", self.name, quote_string(d), value),
+            None => format!("(def-const {} {})", self.name, value),
+        }
+    }
+
+    fn symbol_name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn constant(&self) -> Option<&DefConst> {
+        Some(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::Cursor, path::PathBuf};
+
+    use lisp_rpc_rust_parser::Parser;
+
+    use super::*;
+
+    fn from_str(source: &str) -> Result<DefConst> {
+        DefConst::from_expr(&Parser::new().parse_root_one(Cursor::new(source))?)
+    }
+
+    #[test]
+    fn test_def_const() {
+        let dc = from_str("(def-const max-page-size 100)").unwrap();
+        assert_eq!(
+            dc,
+            DefConst {
+                name: "max-page-size".to_string(),
+                value: ConstValue::Number(100),
+                doc: None,
+            }
+        );
+        assert!(from_str("(def-const max-page-size)").is_err());
+        assert!(from_str("(def-const max-page-size 'number)").is_err());
+        assert!(from_str(r#"(def-const "magic" "LRPC")"#).is_err());
+        assert!(from_str("(def-const max-page-size 1 2)").is_err());
+
+        let project_root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let template = [project_root.join("templates/def_const.rs.template")];
+        let tera = load_templates(&template).unwrap();
+        assert_eq!(
+            dc.gen_code_with_tera(&tera, &Default::default()).unwrap(),
+            "pub const MAX_PAGE_SIZE: i64 = 100;"
+        );
+
+        let dc = from_str(
+            r#"(def-const protocol-magic "the first line of
+the connection" "LRPC \"1\"")"#,
+        )
+        .unwrap();
+        assert_eq!(
+            dc.gen_code_with_tera(&tera, &Default::default()).unwrap(),
+            r#"/// the first line of
+/// the connection
+pub const PROTOCOL_MAGIC: &str = "LRPC \"1\"";"#
+        );
+    }
+}
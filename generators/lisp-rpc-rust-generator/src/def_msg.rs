@@ -1,9 +1,12 @@
 //! the mod that handle def-msg expr
 
-use std::{error::Error, fmt::format, fs::File, io::Cursor, os::unix::fs::FileTypeExt, path::Path};
+use std::{error::Error, fmt::format, fs::File, io::Cursor, os::unix::fs::FileTypeExt};
 
 use anyhow::Result;
-use lisp_rpc_rust_parser::{Atom, Expr, Parser, TypeValue, data::MapData};
+use lisp_rpc_rust_parser::{
+    Atom, Expr, Parser, TypeValue,
+    data::{Data, MapData, Violation},
+};
 use tera::{Context, Tera};
 
 use super::*;
@@ -28,7 +31,7 @@ impl std::fmt::Display for DefMsgError {
 impl Error for DefMsgError {}
 
 #[doc = r#"the struct of def-msg expression
-(def-msg name :key value-type)
+(def-msg name ["docstring"] [:rust-name "Name"] [:since n] [:until n] :key value-type [(reserved :key)])
 "#]
 #[derive(Debug, Eq, PartialEq)]
 pub struct DefMsg {
@@ -39,10 +42,33 @@ pub struct DefMsg {
 
     /// anonymous msg can be the map
     msg_ty: RPCDataType,
+
+    /// the docstring after the name, it becomes the rustdoc of the struct
+    doc: Option<String>,
+
+    /// the struct name in rust, instead of the pascal case name
+    rust_name: Option<String>,
+
+    /// the keys of the removed fields, they can't be the fields again and
+    /// the validation ignores them
+    reserved: Vec<String>,
+
+    /// the versions of the spec having this msg
+    versions: VersionRange,
 }
 
 impl DefMsg {
     pub fn new(msg_name: &str, rest_expr: &[Expr], ty: RPCDataType) -> Result<Self> {
+        if !TypeValue::is_valid_symbol(msg_name) {
+            anyhow::bail!(DefMsgError {
+                msg: format!(
+                    "parsing failed, msg name {:?} is not a valid symbol",
+                    msg_name
+                ),
+                err_type: DefMsgErrorType::InvalidInput,
+            })
+        }
+
         if rest_expr.iter().array_chunks().all(|[k, _]| {
             matches!(
                 k,
@@ -55,6 +81,10 @@ impl DefMsg {
                 msg_name: msg_name.to_string(),
                 rest_expr: rest_expr.to_vec(),
                 msg_ty: ty,
+                doc: None,
+                rust_name: None,
+                reserved: vec![],
+                versions: VersionRange::default(),
             })
         } else {
             anyhow::bail!(DefMsgError {
@@ -90,7 +120,7 @@ impl DefMsg {
     }
 
     /// make new DefMsg from the one expr
-    /// (def-msg name :keyword value)
+    /// (def-msg name ["docstring"] [:rust-name "Name"] :keyword value [(reserved :keyword)])
     pub fn from_expr(expr: &Expr) -> Result<Self> {
         let rest_expr: &[Expr];
         if Self::if_def_msg_expr(expr) {
@@ -123,7 +153,80 @@ impl DefMsg {
             }
         };
 
-        Self::new(name, &rest_expr[1..], RPCDataType::Data)
+        let (doc, rest_expr) = match rest_expr.get(1) {
+            Some(Expr::Atom(Atom {
+                value: TypeValue::String(doc),
+            })) => (Some(doc.to_string()), &rest_expr[2..]),
+            _ => (None, &rest_expr[1..]),
+        };
+        let (rust_name, rest_expr) = rust_name_option(rest_expr)?;
+        let (versions, rest_expr) = version_range_option(rest_expr)?;
+        let (rest_expr, reserved) = split_reserved(rest_expr)?;
+
+        let mut dm = Self::new(name, &rest_expr, RPCDataType::Data)?;
+        dm.doc = doc;
+        dm.rust_name = rust_name;
+        dm.reserved = reserved;
+        dm.versions = versions;
+        Ok(dm)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.msg_name
+    }
+
+    pub fn doc(&self) -> Option<&str> {
+        self.doc.as_deref()
+    }
+
+    /// the keyword-type pairs in order
+    pub fn fields(&self) -> Vec<FieldSpec<'_>> {
+        field_specs(&self.rest_expr)
+    }
+
+    pub fn versions(&self) -> VersionRange {
+        self.versions
+    }
+
+    /// the renamed struct of this msg, the default name to the rust name
+    pub fn renames(&self) -> Vec<(String, String)> {
+        self.rust_name
+            .iter()
+            .map(|n| (kebab_to_pascal_case(&self.msg_name), n.clone()))
+            .collect()
+    }
+
+    /// check the data against this msg spec, return all violations.
+    /// empty means the data is valid
+    pub fn validate(&self, d: &Data) -> Vec<Violation> {
+        let mut out = vec![];
+        // the tagged data has to be tagged with this name
+        let d = match d.check_tag(&self.msg_name) {
+            Ok(d) => d,
+            Err(e) => {
+                out.push(Violation::new("", e.msg()));
+                return out;
+            }
+        };
+        match (&self.msg_ty, d) {
+            (RPCDataType::Data, Data::Data(e)) if e.get_name() != self.msg_name => {
+                out.push(Violation::new(
+                    "",
+                    format!("expect {} data, got {}", self.msg_name, e.get_name()),
+                ))
+            }
+            (RPCDataType::Data, Data::Data(_)) | (RPCDataType::Map, Data::Map(_)) => (),
+            _ => {
+                out.push(Violation::new(
+                    "",
+                    format!("expect {} data, got {}", self.msg_name, kind_of(d)),
+                ));
+                return out;
+            }
+        }
+
+        validate_fields("", &self.rest_expr, &self.reserved, d, &mut out);
+        out
     }
 
     /// convet this spec to GeneratedStructs (self and the anonymity type)
@@ -131,6 +234,11 @@ impl DefMsg {
         let mut res = vec![];
         let mut fields = vec![];
         for [k, v] in self.rest_expr.iter().array_chunks() {
+            if let Some(field) = wrapped_field(k, v)? {
+                fields.push(field);
+                continue;
+            }
+
             match (k, v) {
                 (
                     Expr::Atom(Atom {
@@ -175,8 +283,7 @@ impl DefMsg {
                                 value: TypeValue::Symbol(t),
                             })),
                         ) if l == "list" => {
-                            let new_type_name = format!("Vec<{}>", type_translate(t));
-                            fields.push(GeneratedField::new(f, &new_type_name, None));
+                            fields.push(GeneratedField::new_list(f, t, None));
                         }
                         _ => {
                             anyhow::bail!(DefMsgError {
@@ -199,52 +306,24 @@ impl DefMsg {
             }
         }
 
-        res.push(GeneratedStruct::new(
-            &self.msg_name,
-            None,
-            fields,
-            None,
-            self.msg_ty.clone(),
-        ));
+        res.push(
+            GeneratedStruct::new(&self.msg_name, None, fields, None, self.msg_ty.clone())
+                .with_doc(self.doc.as_deref())
+                .with_versions(self.versions),
+        );
 
         Ok(res)
     }
 
-    /// generate code with the slice of path of template
-    fn gen_code_with_files(&self, template_files: &[impl AsRef<Path>]) -> Result<String> {
-        let mut tera = Tera::default();
-        let mut context = Context::new();
-
-        let mut all_temps = vec![];
-        for p in template_files {
-            match p.as_ref().file_stem().map(|n| n.to_str()) {
-                Some(n) => {
-                    all_temps.push((p, n));
-                }
-                None => (),
-            }
-        }
-
-        tera.add_template_files(all_temps)?;
-
-        let mut bucket = vec![];
-        for s in self.create_gen_structs()? {
-            s.insert_template(&mut context);
-            bucket.push(tera.render("def_struct.rs", &context)?);
-            bucket.push(tera.render("rpc_impl", &context)?);
-        }
-
-        Ok(bucket.join("\n\n"))
-    }
-
     /// Generate code with the exist tera instance
-    fn gen_code_with_tera(&self, templates: &Tera) -> Result<String> {
+    fn gen_code_with_tera(&self, templates: &Tera, options: &GenOptions) -> Result<String> {
         let mut context = Context::new();
+        options.insert_template(&mut context);
         let mut bucket = vec![];
-        for s in self.create_gen_structs()? {
-            s.insert_template(&mut context);
-            bucket.push(templates.render("def_struct.rs", &context)?);
-            bucket.push(templates.render("rpc_impl", &context)?);
+        let structs = box_recursive(self.create_gen_structs()?, &options.recursive);
+        let structs = rename_structs(structs, &options.renames_with(self.renames()));
+        for s in alias_defaults(structs, &options.aliases) {
+            bucket.extend(s.render(templates, &mut context)?);
         }
 
         Ok(bucket.join("\n\n"))
@@ -252,21 +331,66 @@ impl DefMsg {
 }
 
 impl RPCSpec for DefMsg {
-    fn gen_code_with_temp_files(&self, temp_file_paths: &[String]) -> Result<String> {
-        self.gen_code_with_files(temp_file_paths)
-    }
-
-    fn gen_code_with_tera(&self, templates: &Tera) -> Result<String> {
-        self.gen_code_with_tera(templates)
+    fn gen_code_with_tera(&self, templates: &Tera, options: &GenOptions) -> Result<String> {
+        self.gen_code_with_tera(templates, options)
     }
 
     fn file_target(&self) -> TargetFile {
         TargetFile::Lib
     }
 
+    fn to_spec_string(&self) -> String {
+        let head = spec_head(
+            "def-msg",
+            &self.msg_name,
+            self.doc.as_deref(),
+            self.rust_name.as_deref(),
+            self.versions,
+        );
+        let fields = spec_field_lines(&self.fields(), &self.reserved);
+        std::iter::once(head)
+            .chain(fields.into_iter().map(|l| format!("  {}", l)))
+            .collect::<Vec<_>>()
+            .join("\n")
+            + ")"
+    }
+
+    fn struct_deps(&self) -> Result<Vec<(String, Vec<String>)>> {
+        Ok(self
+            .create_gen_structs()?
+            .iter()
+            .map(|s| s.deps())
+            .collect())
+    }
+
+    fn gen_structs(&self) -> Result<Vec<GeneratedStruct>> {
+        self.create_gen_structs()
+    }
+
+    fn renames(&self) -> Vec<(String, String)> {
+        self.renames()
+    }
+
+    fn spec_fields(&self) -> Vec<SpecFields<'_>> {
+        vec![SpecFields {
+            name: &self.msg_name,
+            fields: Cow::Borrowed(&self.rest_expr),
+            returns: None,
+            versions: self.versions,
+            doc: self.doc.as_deref(),
+            is_rpc: false,
+            notify: false,
+            errors: &[],
+        }]
+    }
+
     fn symbol_name(&self) -> String {
         self.msg_name.clone()
     }
+
+    fn msgs(&self) -> Vec<&DefMsg> {
+        vec![self]
+    }
 }
 
 #[cfg(test)]
@@ -290,6 +414,10 @@ mod tests {
                     Expr::Quote(Box::new(Expr::Atom(Atom::read("string"))))
                 ],
                 msg_ty: RPCDataType::Data,
+                doc: None,
+                rust_name: None,
+                reserved: vec![],
+                versions: VersionRange::default(),
             }
         );
 
@@ -306,6 +434,10 @@ mod tests {
                     Expr::Quote(Box::new(Expr::Atom(Atom::read("string"))))
                 ],
                 msg_ty: RPCDataType::Data,
+                doc: None,
+                rust_name: None,
+                reserved: vec![],
+                versions: VersionRange::default(),
             }
         );
 
@@ -324,10 +456,21 @@ mod tests {
                     Expr::Quote(Box::new(Expr::Atom(Atom::read("number"))))
                 ],
                 msg_ty: RPCDataType::Data,
+                doc: None,
+                rust_name: None,
+                reserved: vec![],
+                versions: VersionRange::default(),
             }
         );
     }
 
+    #[test]
+    fn test_new_def_msg() {
+        assert!(DefMsg::new("book-info", &[], RPCDataType::Data).is_ok());
+        assert!(DefMsg::new("book info", &[], RPCDataType::Data).is_err());
+        assert!(DefMsg::new("book:info", &[], RPCDataType::Data).is_err());
+    }
+
     #[test]
     fn test_create_gen_structs() {
         let spec = r#"(def-msg book-info
@@ -353,6 +496,64 @@ mod tests {
             ),],
         );
 
+        // optional fields
+
+        let spec = r#"(def-msg book-info
+    :title 'string
+    :subtitle (optional 'string)
+    :version '(optional 'number))"#;
+
+        let x = DefMsg::from_str(spec, None).unwrap();
+        assert_eq!(
+            x.create_gen_structs().unwrap(),
+            vec![GeneratedStruct::new(
+                "book-info",
+                None,
+                vec![
+                    GeneratedField::new("title", "string", None),
+                    GeneratedField::new("subtitle", "string", None).optional(),
+                    GeneratedField::new("version", "number", None).optional(),
+                ],
+                None,
+                RPCDataType::Data,
+            ),],
+        );
+
+        // the fields with the default value
+
+        let spec = r#"(def-msg language-perfer
+    :lang ('string :default "en")
+    :encoding '('number :default 8))"#;
+
+        let x = DefMsg::from_str(spec, None).unwrap();
+        assert_eq!(
+            x.create_gen_structs().unwrap(),
+            vec![GeneratedStruct::new(
+                "language-perfer",
+                None,
+                vec![
+                    GeneratedField::new("lang", "string", None)
+                        .with_default_value(DefaultValue::String("en".to_string())),
+                    GeneratedField::new("encoding", "number", None)
+                        .with_default_value(DefaultValue::Number(8)),
+                ],
+                None,
+                RPCDataType::Data,
+            ),],
+        );
+        assert_eq!(
+            x.create_gen_structs().unwrap()[0].fields[0]
+                .default
+                .as_deref(),
+            Some(r#""en".to_string()"#)
+        );
+        assert!(
+            DefMsg::from_str("(def-msg a :b ('string :default nil))", None)
+                .unwrap()
+                .create_gen_structs()
+                .is_err()
+        );
+
         // anonymous fields
 
         let spec = r#"(def-msg book-info
@@ -438,13 +639,34 @@ mod tests {
                 "book-info",
                 None,
                 vec![
-                    GeneratedField::new("langs", "Vec<String>", None),
+                    GeneratedField::new_list("langs", "string", None),
                     GeneratedField::new("version", "string", None),
                 ],
                 None,
                 RPCDataType::Data,
             ),],
         );
+        let spec = r#"(def-msg word-count
+    :counts (map 'string 'number)
+    :books '(map 'string 'book-info))"#;
+
+        let x = DefMsg::from_str(spec, None).unwrap();
+        let fields = x.create_gen_structs().unwrap().remove(0).fields;
+        assert_eq!(
+            fields,
+            vec![
+                GeneratedField::new_map("counts", "number", None),
+                GeneratedField::new_map("books", "book-info", None),
+            ]
+        );
+        assert_eq!(
+            fields[0].field_type,
+            "std::collections::HashMap<String, i64>"
+        );
+
+        let x =
+            DefMsg::from_str("(def-msg word-count :counts (map 'number 'number))", None).unwrap();
+        assert!(x.create_gen_structs().is_err());
     }
 
     #[test]
@@ -454,18 +676,31 @@ mod tests {
             project_root.join("templates/def_struct.rs.template"),
             project_root.join("templates/rpc_impl.template"),
         ];
+        let tera = load_templates(&template_file_path).unwrap();
 
         let case = r#"(def-msg language-perfer :lang 'string)"#;
         let dm = DefMsg::from_str(case, Default::default()).unwrap();
-        //dbg!(dm.gen_code_with_files(&template_file_path).unwrap());
+        //dbg!(dm.gen_code_with_tera(&tera, &Default::default()).unwrap());
 
         assert_eq!(
-            dm.gen_code_with_files(&template_file_path).unwrap(),
+            dm.gen_code_with_tera(&tera, &Default::default()).unwrap(),
             r#"#[derive(Debug)]
 pub struct LanguagePerfer {
     lang: String,
 }
 
+impl LanguagePerfer {
+    pub fn new(lang: String) -> Self {
+        Self {
+            lang,
+        }
+    }
+
+    pub fn lang(&self) -> &String {
+        &self.lang
+    }
+}
+
 impl ToRPCData for LanguagePerfer {
     fn to_rpc(&self) -> String {
         format!(
@@ -480,13 +715,30 @@ impl ToRPCData for LanguagePerfer {
         let case = r#"(def-msg language-perfer :lang 'string :version 'number)"#;
         let dm = DefMsg::from_str(case, Default::default()).unwrap();
         assert_eq!(
-            dm.gen_code_with_files(&template_file_path).unwrap(),
+            dm.gen_code_with_tera(&tera, &Default::default()).unwrap(),
             r#"#[derive(Debug)]
 pub struct LanguagePerfer {
     lang: String,
     version: i64,
 }
 
+impl LanguagePerfer {
+    pub fn new(lang: String, version: i64) -> Self {
+        Self {
+            lang,
+            version,
+        }
+    }
+
+    pub fn lang(&self) -> &String {
+        &self.lang
+    }
+
+    pub fn version(&self) -> &i64 {
+        &self.version
+    }
+}
+
 impl ToRPCData for LanguagePerfer {
     fn to_rpc(&self) -> String {
         format!(
@@ -506,15 +758,32 @@ impl ToRPCData for LanguagePerfer {
     :id 'string)"#;
 
         let dm = DefMsg::from_str(case, Default::default()).unwrap();
-        //dbg!(dm.gen_code_with_files(&template_file_path).unwrap());
+        //dbg!(dm.gen_code_with_tera(&tera, &Default::default()).unwrap());
         assert_eq!(
-            dm.gen_code_with_files(&template_file_path).unwrap(),
+            dm.gen_code_with_tera(&tera, &Default::default()).unwrap(),
             r#"#[derive(Debug)]
 pub struct BookInfoLang {
     a: String,
     b: i64,
 }
 
+impl BookInfoLang {
+    pub fn new(a: String, b: i64) -> Self {
+        Self {
+            a,
+            b,
+        }
+    }
+
+    pub fn a(&self) -> &String {
+        &self.a
+    }
+
+    pub fn b(&self) -> &i64 {
+        &self.b
+    }
+}
+
 impl ToRPCData for BookInfoLang {
     fn to_rpc(&self) -> String {
         format!(
@@ -533,6 +802,33 @@ pub struct BookInfo {
     id: String,
 }
 
+impl BookInfo {
+    pub fn new(lang: BookInfoLang, title: String, version: String, id: String) -> Self {
+        Self {
+            lang,
+            title,
+            version,
+            id,
+        }
+    }
+
+    pub fn lang(&self) -> &BookInfoLang {
+        &self.lang
+    }
+
+    pub fn title(&self) -> &String {
+        &self.title
+    }
+
+    pub fn version(&self) -> &String {
+        &self.version
+    }
+
+    pub fn id(&self) -> &String {
+        &self.id
+    }
+}
+
 impl ToRPCData for BookInfo {
     fn to_rpc(&self) -> String {
         format!(
@@ -543,6 +839,144 @@ impl ToRPCData for BookInfo {
             self.id.to_rpc()
         )
     }
+}"#
+        );
+    }
+
+    #[test]
+    fn test_gen_code_recursive() {
+        let project_root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let template_file_path = vec![
+            project_root.join("templates/def_struct.rs.template"),
+            project_root.join("templates/rpc_impl.template"),
+        ];
+        let tera = load_templates(&template_file_path).unwrap();
+
+        let case = r#"(def-msg node :value 'number :next (optional 'node) :children (list 'node))"#;
+        let dm = DefMsg::from_str(case, Default::default()).unwrap();
+        assert_eq!(
+            dm.struct_deps().unwrap(),
+            vec![(
+                "Node".to_string(),
+                vec![
+                    "i64".to_string(),
+                    "Node".to_string(),
+                    "Vec<Node>".to_string()
+                ]
+            )]
+        );
+
+        let code = dm.gen_code_with_tera(&tera, &Default::default()).unwrap();
+        assert!(code.starts_with(
+            r#"#[derive(Debug)]
+pub struct Node {
+    value: i64,
+    next: Option<Box<Node>>,
+    children: Vec<Node>,
+}"#
+        ));
+    }
+
+    #[test]
+    fn test_gen_code_with_rename() {
+        let project_root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let template_file_path = vec![
+            project_root.join("templates/def_struct.rs.template"),
+            project_root.join("templates/rpc_impl.template"),
+        ];
+        let tera = load_templates(&template_file_path).unwrap();
+
+        let case = r#"(def-msg result "the result" :rust-name "BookResult"
+    :type ('string :rust-name "kind"))"#;
+        let dm = DefMsg::from_str(case, Default::default()).unwrap();
+        assert_eq!(
+            dm.renames(),
+            vec![("Result".to_string(), "BookResult".to_string())]
+        );
+        let code = dm.gen_code_with_tera(&tera, &Default::default()).unwrap();
+        assert!(code.contains("pub struct BookResult {\n    kind: String,\n}"));
+        assert!(code.contains(r#""(result :type {})""#));
+        assert!(code.contains("self.kind.to_rpc()"));
+
+        assert!(DefMsg::from_str(r#"(def-msg result :rust-name "book-result")"#, None).is_err());
+        assert!(
+            DefMsg::from_str(r#"(def-msg result :type ('string :rust-name "a b"))"#, None)
+                .unwrap()
+                .create_gen_structs()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_deprecated_and_reserved() {
+        let project_root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let template_file_path = vec![
+            project_root.join("templates/def_struct.rs.template"),
+            project_root.join("templates/rpc_impl.template"),
+        ];
+        let tera = load_templates(&template_file_path).unwrap();
+
+        let case = r#"(def-msg book
+    :title 'string
+    (reserved :legacy-id)
+    :old-title ('string :deprecated "use title"))"#;
+        let dm = DefMsg::from_str(case, Default::default()).unwrap();
+        assert_eq!(dm.reserved, vec!["legacy-id".to_string()]);
+        assert_eq!(dm.rest_expr.len(), 4);
+
+        let code = dm.gen_code_with_tera(&tera, &Default::default()).unwrap();
+        assert!(code.contains("    #[deprecated(note = \"use title\")]\n    old_title: String,"));
+        assert!(
+            code.contains("    #[deprecated(note = \"use title\")]\n    pub fn old_title(&self)")
+        );
+        assert!(code.contains("#[allow(deprecated)]\nimpl Book {"));
+        assert!(code.contains("#[allow(deprecated)]\nimpl ToRPCData for Book {"));
+
+        assert!(DefMsg::from_str("(def-msg book :id 'string (reserved :id))", None).is_err());
+        assert!(DefMsg::from_str("(def-msg book (reserved id))", None).is_err());
+    }
+
+    #[test]
+    fn test_gen_code_with_doc() {
+        let project_root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let template_file_path = vec![
+            project_root.join("templates/def_struct.rs.template"),
+            project_root.join("templates/rpc_impl.template"),
+        ];
+        let tera = load_templates(&template_file_path).unwrap();
+
+        let case = r#"(def-msg language-perfer "the language
+and the encoding of the book"
+    :lang ('string :doc "the language name")
+    :encoding 'number)"#;
+        let dm = DefMsg::from_str(case, Default::default()).unwrap();
+        let code = dm.gen_code_with_tera(&tera, &Default::default()).unwrap();
+        assert_eq!(
+            code.split("\n\nimpl ToRPCData").next().unwrap(),
+            r#"/// the language
+/// and the encoding of the book
+#[derive(Debug)]
+pub struct LanguagePerfer {
+    /// the language name
+    lang: String,
+    encoding: i64,
+}
+
+impl LanguagePerfer {
+    pub fn new(lang: String, encoding: i64) -> Self {
+        Self {
+            lang,
+            encoding,
+        }
+    }
+
+    pub fn lang(&self) -> &String {
+        &self.lang
+    }
+
+    pub fn encoding(&self) -> &i64 {
+        &self.encoding
+    }
 }"#
         );
     }
@@ -0,0 +1,222 @@
+//! the mod that handle def-notify expr
+
+use std::io::Cursor;
+
+use anyhow::Result;
+use lisp_rpc_rust_parser::{
+    Atom, Expr, Parser, TypeValue,
+    data::{Data, Violation},
+};
+use tera::{Context, Tera};
+
+use super::*;
+
+#[doc = r#"the struct of def-notify expression, the one-way rpc without the
+response
+(def-notify name ["docstring"] [:rust-name "Name"] [:since n] [:until n] '(:keyword value))
+"#]
+#[derive(Debug, Eq, PartialEq)]
+pub struct DefNotify {
+    /// same as the def-rpc without the return value and the errors
+    rpc: DefRPC,
+}
+
+impl DefNotify {
+    pub fn if_def_notify_expr(expr: &Expr) -> bool {
+        match &expr {
+            Expr::List(e) => matches!(
+                e.first(),
+                Some(Expr::Atom(Atom {
+                    value: TypeValue::Symbol(s),
+                })) if s == "def-notify"
+            ),
+            _ => false,
+        }
+    }
+
+    pub fn from_str(source: &str, parser: Option<Parser>) -> Result<Self> {
+        let mut p = parser.unwrap_or_default();
+
+        let expr = p.parse_root_one(Cursor::new(source))?;
+
+        Self::from_expr(&expr)
+    }
+
+    pub fn from_expr(expr: &Expr) -> Result<Self> {
+        let rest = match &expr {
+            Expr::List(e) if Self::if_def_notify_expr(expr) => &e[1..],
+            _ => anyhow::bail!("parsing failed, the first symbol should be def-notify"),
+        };
+
+        let mut def_rpc = vec![Expr::Atom(Atom::read("def-rpc"))];
+        def_rpc.extend(rest.iter().cloned());
+        let rpc = DefRPC::from_expr(&Expr::List(def_rpc))?;
+        if rpc.return_value().is_some() || !rpc.errors().is_empty() {
+            anyhow::bail!(
+                "parsing failed, def-notify {} has no response or errors",
+                rpc.rpc_name()
+            )
+        }
+
+        Ok(Self { rpc })
+    }
+
+    pub fn name(&self) -> &str {
+        self.rpc.rpc_name()
+    }
+
+    pub fn doc(&self) -> Option<&str> {
+        self.rpc.doc()
+    }
+
+    /// the keyword-type pairs of the notification in order
+    pub fn fields(&self) -> Vec<FieldSpec<'_>> {
+        self.rpc.fields()
+    }
+
+    pub fn method(&self) -> RPCMethod {
+        RPCMethod {
+            notify: true,
+            ..self.rpc.method()
+        }
+    }
+
+    /// check the notification data against this spec, return all violations
+    pub fn validate(&self, d: &Data) -> Vec<Violation> {
+        self.rpc.validate(d)
+    }
+
+    /// render the struct, and the sender of the client if the templates have
+    /// the client_impl
+    fn render(&self, templates: &Tera, options: &GenOptions) -> Result<String> {
+        let mut context = Context::new();
+        options.insert_template(&mut context);
+        let mut bucket = vec![];
+        let structs = box_recursive(self.rpc.create_gen_structs()?, &options.recursive);
+        let structs = rename_structs(structs, &options.renames_with(self.rpc.renames()));
+        for s in alias_defaults(structs, &options.aliases) {
+            bucket.extend(s.render(templates, &mut context)?);
+        }
+
+        if templates.get_template_names().any(|n| n == CLIENT_TEMPLATE) {
+            let method = self
+                .method()
+                .renamed(&options.renames_with(self.rpc.renames()));
+            let mut context = Context::from_serialize(method)?;
+            options.insert_template(&mut context);
+            bucket.push(templates.render(CLIENT_TEMPLATE, &context)?);
+        }
+
+        Ok(bucket.join("\n\n"))
+    }
+}
+
+impl RPCSpec for DefNotify {
+    fn gen_code_with_tera(&self, templates: &Tera, options: &GenOptions) -> Result<String> {
+        self.render(templates, options)
+    }
+
+    fn file_target(&self) -> TargetFile {
+        TargetFile::Lib
+    }
+
+    fn to_spec_string(&self) -> String {
+        self.rpc.spec_lines("def-notify").join("\n") + ")"
+    }
+
+    fn struct_deps(&self) -> Result<Vec<(String, Vec<String>)>> {
+        RPCSpec::struct_deps(&self.rpc)
+    }
+
+    fn gen_structs(&self) -> Result<Vec<GeneratedStruct>> {
+        self.rpc.create_gen_structs()
+    }
+
+    fn symbol_name(&self) -> String {
+        self.name().to_string()
+    }
+
+    fn renames(&self) -> Vec<(String, String)> {
+        self.rpc.renames()
+    }
+
+    fn spec_fields(&self) -> Vec<SpecFields<'_>> {
+        self.rpc
+            .spec_fields()
+            .into_iter()
+            .map(|f| SpecFields { notify: true, ..f })
+            .collect()
+    }
+
+    fn rpc_methods(&self) -> Vec<RPCMethod> {
+        vec![self.method()]
+    }
+
+    fn notification(&self) -> Option<&DefNotify> {
+        Some(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use lisp_rpc_rust_parser::data::FromStr;
+
+    #[test]
+    fn test_def_notify() {
+        let dn = DefNotify::from_str(
+            r#"(def-notify book-added "the new book" '(:id 'string))"#,
+            None,
+        )
+        .unwrap();
+        assert_eq!(dn.name(), "book-added");
+        assert!(dn.method().notify);
+        assert_eq!(dn.method().resp_type, None);
+        assert!(dn.spec_fields()[0].notify);
+        assert_eq!(
+            dn.gen_structs().unwrap(),
+            vec![
+                GeneratedStruct::new(
+                    "book-added",
+                    None,
+                    vec![GeneratedField::new("id", "string", None)],
+                    None,
+                    RPCDataType::Data,
+                )
+                .with_doc(Some("the new book"))
+            ]
+        );
+        let d = Data::from_str(&Parser::new(), r#"(book-added :id "1984")"#).unwrap();
+        assert!(dn.validate(&d).is_empty());
+
+        assert!(DefNotify::from_str("(def-notify ping)", None).is_ok());
+        assert!(
+            DefNotify::from_str("(def-notify book-added '(:id 'string) 'book-info)", None).is_err()
+        );
+        assert!(
+            DefNotify::from_str(
+                "(def-notify book-added '(:id 'string) :errors ('gone))",
+                None
+            )
+            .is_err()
+        );
+
+        let project_root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let tera = load_templates(&[
+            project_root.join("templates/def_struct.rs.template"),
+            project_root.join("templates/rpc_impl.template"),
+            project_root.join("templates/client_impl.template"),
+        ])
+        .unwrap();
+        let code = dn.gen_code_with_tera(&tera, &Default::default()).unwrap();
+        assert!(code.contains(
+            "impl<T: Transport> Client<T> {
+    pub fn book_added(&self, msg: BookAdded) -> Result<(), Box<dyn std::error::Error>> {
+        self.transport().notify(&msg.to_rpc())
+    }
+}"
+        ));
+    }
+}
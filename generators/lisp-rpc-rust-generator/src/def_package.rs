@@ -26,10 +26,59 @@ impl Error for DefPkgError {}
 
 #[doc = r#"the struct of def-rpc-package expression
 (def-rpc-package demo)
+(def-rpc-package demo :version "0.2.0" :edition 2021
+    :deps '(:uuid "1" :tokio ("1" :features '("rt" "net")))
+    :features '(:default '("serde")))
 "#]
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Default, Eq, PartialEq)]
 pub struct DefPkg {
     pkg_name: String,
+
+    /// the crate version, 0.1.0 by default
+    version: Option<String>,
+
+    /// the rust edition, 2024 by default
+    edition: Option<String>,
+
+    /// the dependencies of the generated crate, they override the ones the
+    /// generated code needs
+    deps: BTreeMap<String, CargoDep>,
+
+    /// the [features] of the generated crate
+    features: BTreeMap<String, Vec<String>>,
+}
+
+/// the dependency of :deps, the version string or ("version" :features '("f"))
+#[derive(Debug, Default, Eq, PartialEq)]
+struct CargoDep {
+    version: String,
+    features: Vec<String>,
+}
+
+impl CargoDep {
+    /// the lisp value of the dependency in :deps
+    fn lisp(&self) -> String {
+        match self.features.is_empty() {
+            true => quote_string(&self.version),
+            false => format!(
+                "({} :features {})",
+                quote_string(&self.version),
+                lisp_list(&self.features)
+            ),
+        }
+    }
+
+    /// the toml value of the dependency
+    fn toml(&self) -> String {
+        match self.features.is_empty() {
+            true => quote_string(&self.version),
+            false => format!(
+                "{{ version = {}, features = {} }}",
+                quote_string(&self.version),
+                toml_array(&self.features)
+            ),
+        }
+    }
 }
 
 impl DefPkg {
@@ -79,9 +128,105 @@ impl DefPkg {
             }
         };
 
-        Ok(Self {
+        let mut pkg = Self {
             pkg_name: name.to_string(),
-        })
+            ..Default::default()
+        };
+        let mut seen = vec![];
+        for pair in rest_expr[1..].chunks(2) {
+            let [
+                Expr::Atom(Atom {
+                    value: TypeValue::Keyword(k),
+                }),
+                v,
+            ] = pair
+            else {
+                anyhow::bail!(DefPkgError {
+                    msg: format!(
+                        "parsing failed, {} options have to be keyword-value pairs",
+                        name
+                    ),
+                    err_type: DefPkgErrorType::InvalidInput,
+                });
+            };
+            if seen.contains(&k) {
+                anyhow::bail!("the :{} of {} is given twice", k, name)
+            }
+            seen.push(k);
+
+            match (k.as_str(), v) {
+                (
+                    "version",
+                    Expr::Atom(Atom {
+                        value: TypeValue::String(v),
+                    }),
+                ) => pkg.version = Some(v.to_string()),
+                (
+                    "edition",
+                    Expr::Atom(Atom {
+                        value: TypeValue::Number(n),
+                    }),
+                ) => pkg.edition = Some(edition(&n.to_string())?),
+                (
+                    "edition",
+                    Expr::Atom(Atom {
+                        value: TypeValue::String(e),
+                    }),
+                ) => pkg.edition = Some(edition(e)?),
+                ("deps", v) => {
+                    for (dep, value) in keyword_pairs(v, "deps")? {
+                        pkg.deps.insert(dep, cargo_dep(value)?);
+                    }
+                }
+                ("features", v) => {
+                    for (feature, value) in keyword_pairs(v, "features")? {
+                        pkg.features.insert(feature, strings(value, "features")?);
+                    }
+                }
+                ("version" | "edition", v) => {
+                    anyhow::bail!("the :{} of {} has to be the string, got {}", k, name, v)
+                }
+                _ => anyhow::bail!(
+                    "unknown option :{} of {}, it can be :version, :edition, :deps, or :features",
+                    k,
+                    name
+                ),
+            }
+        }
+
+        Ok(pkg)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.pkg_name
+    }
+
+    /// the crate version, None is 0.1.0
+    pub fn version(&self) -> Option<&str> {
+        self.version.as_deref()
+    }
+
+    /// insert the cargo options of the package to the context
+    fn insert_cargo(&self, context: &mut tera::Context) {
+        context.insert("package_name", &self.pkg_name);
+        context.insert("version", self.version.as_deref().unwrap_or("0.1.0"));
+        context.insert("edition", self.edition.as_deref().unwrap_or("2024"));
+        context.insert(
+            "deps",
+            &self
+                .deps
+                .iter()
+                .map(|(k, d)| (k, d.toml()))
+                .collect::<BTreeMap<_, _>>(),
+        );
+        context.insert(
+            "features",
+            &self
+                .features
+                .iter()
+                .map(|(k, f)| (k, toml_array(f)))
+                .collect::<BTreeMap<_, _>>(),
+        );
     }
 
     fn from_str(source: &str, parser: Option<Parser>) -> Result<Self> {
@@ -95,53 +240,189 @@ impl DefPkg {
         Self::from_expr(&expr)
     }
 
-    fn gen_code_with_files(&self, template_files: &[impl AsRef<Path>]) -> Result<String> {
-        let mut tera = Tera::default();
+    /// Generate code with the exist tera instance
+    fn gen_code_with_tera(&self, templates: &Tera, options: &GenOptions) -> Result<String> {
         let mut context = tera::Context::new();
+        options_context(self, options, &mut context);
+        templates
+            .render("Cargo.toml", &context)
+            .context("render def package wrong")
+    }
+}
+
+fn options_context(pkg: &DefPkg, options: &GenOptions, context: &mut tera::Context) {
+    options.insert_template(context);
+    pkg.insert_cargo(context);
+}
+
+/// the edition 2021 or "2021"
+fn edition(e: &str) -> Result<String> {
+    match ["2015", "2018", "2021", "2024"].contains(&e) {
+        true => Ok(e.to_string()),
+        false => anyhow::bail!("{} is not the rust edition", e),
+    }
+}
+
+/// the keyword-value pairs of the quoted list like '(:uuid "1")
+fn keyword_pairs<'a>(e: &'a Expr, option: &str) -> Result<Vec<(String, &'a Expr)>> {
+    let pairs = match unquote(e) {
+        Expr::List(pairs) if pairs.len() % 2 == 0 => pairs,
+        _ => anyhow::bail!("the :{} has to be the list of keyword-value pairs", option),
+    };
+    pairs
+        .chunks(2)
+        .map(|p| match &p[0] {
+            Expr::Atom(Atom {
+                value: TypeValue::Keyword(k),
+            }) => Ok((k.to_string(), &p[1])),
+            k => anyhow::bail!(
+                "the :{} has to be keyword-value pairs, {} isn't keyword",
+                option,
+                k
+            ),
+        })
+        .collect()
+}
 
-        let mut all_temps = vec![];
-        for p in template_files {
-            match p.as_ref().file_stem().map(|n| n.to_str()) {
-                Some(n) => {
-                    all_temps.push((p, n));
+/// the dependency "1" or ("1" :features '("rt"))
+fn cargo_dep(e: &Expr) -> Result<CargoDep> {
+    if let Some(version) = string_value(e) {
+        return Ok(CargoDep {
+            version,
+            features: vec![],
+        });
+    }
+    match unquote(e) {
+        Expr::List(l) => match l.as_slice() {
+            [v, rest @ ..] if string_value(v).is_some() => {
+                let mut dep = CargoDep {
+                    version: string_value(v).unwrap_or_default(),
+                    features: vec![],
+                };
+                match rest {
+                    [] => (),
+                    [
+                        Expr::Atom(Atom {
+                            value: TypeValue::Keyword(k),
+                        }),
+                        f,
+                    ] if k == "features" => dep.features = strings(f, "features")?,
+                    _ => anyhow::bail!("the dependency {} can only have the :features", e),
                 }
-                None => (),
+                Ok(dep)
             }
-        }
-
-        tera.add_template_files(all_temps)?;
+            _ => anyhow::bail!("the dependency {} has to start with the version string", e),
+        },
+        _ => anyhow::bail!(
+            "the dependency {} has to be the version string or the list",
+            e
+        ),
+    }
+}
 
-        context.insert("package_name", &self.pkg_name);
-        tera.render("Cargo.toml", &context)
-            .context("render def package wrong")
+/// the strings of the quoted list like '("rt" "net")
+fn strings(e: &Expr, option: &str) -> Result<Vec<String>> {
+    match unquote(e) {
+        Expr::List(l) => l
+            .iter()
+            .map(|s| {
+                string_value(s)
+                    .with_context(|| format!("the :{} has to be strings, got {}", option, s))
+            })
+            .collect(),
+        _ => anyhow::bail!("the :{} has to be the list of strings, got {}", option, e),
     }
+}
 
-    /// Generate code with the exist tera instance
-    fn gen_code_with_tera(&self, templates: &Tera) -> Result<String> {
-        let mut context = tera::Context::new();
-        context.insert("package_name", &self.pkg_name);
-        templates
-            .render("Cargo.toml", &context)
-            .context("render def package wrong")
+fn string_value(e: &Expr) -> Option<String> {
+    match e {
+        Expr::Atom(Atom {
+            value: TypeValue::String(s),
+        }) => Some(s.to_string()),
+        _ => None,
     }
 }
 
-impl RPCSpec for DefPkg {
-    fn gen_code_with_temp_files(&self, temp_file_paths: &[String]) -> Result<String> {
-        self.gen_code_with_files(temp_file_paths)
+fn unquote(e: &Expr) -> &Expr {
+    match e {
+        Expr::Quote(box e) => unquote(e),
+        e => e,
     }
+}
+
+/// the quoted lisp list of the strings, like '("rt" "net")
+fn lisp_list(items: &[String]) -> String {
+    format!(
+        "'({})",
+        items
+            .iter()
+            .map(|i| quote_string(i))
+            .collect::<Vec<_>>()
+            .join(" ")
+    )
+}
 
-    fn gen_code_with_tera(&self, templates: &Tera) -> Result<String> {
-        self.gen_code_with_tera(templates)
+/// the toml array of the strings
+fn toml_array(items: &[String]) -> String {
+    format!(
+        "[{}]",
+        items
+            .iter()
+            .map(|i| quote_string(i))
+            .collect::<Vec<_>>()
+            .join(", ")
+    )
+}
+
+impl RPCSpec for DefPkg {
+    fn gen_code_with_tera(&self, templates: &Tera, options: &GenOptions) -> Result<String> {
+        self.gen_code_with_tera(templates, options)
     }
 
     fn file_target(&self) -> TargetFile {
         TargetFile::Cargo
     }
 
+    fn to_spec_string(&self) -> String {
+        let mut head = format!("(def-rpc-package {}", self.pkg_name);
+        if let Some(v) = &self.version {
+            head += &format!(" :version {}", quote_string(v));
+        }
+        if let Some(e) = &self.edition {
+            head += &format!(" :edition {}", e);
+        }
+
+        let mut lines = vec![head];
+        if !self.deps.is_empty() {
+            lines.push(format!(
+                "  :deps '({})",
+                self.deps
+                    .iter()
+                    .map(|(name, dep)| format!(":{} {}", name, dep.lisp()))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            ));
+        }
+        if !self.features.is_empty() {
+            lines.push(format!(
+                "  :features '({})",
+                self.features
+                    .iter()
+                    .map(|(name, f)| format!(":{} {}", name, lisp_list(f)))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            ));
+        }
+        lines.join("\n") + ")"
+    }
+
     fn symbol_name(&self) -> String {
         self.pkg_name.clone()
     }
+
+    fn package(&self) -> Option<&DefPkg> {
+        Some(self)
+    }
 }
 
 #[cfg(test)]
@@ -157,27 +438,116 @@ mod tests {
         assert_eq!(
             dp,
             DefPkg {
-                pkg_name: "demo".to_string()
+                pkg_name: "demo".to_string(),
+                ..Default::default()
+            }
+        );
+
+        let case = r#"(def-rpc-package demo :version "0.2.0" :edition 2021
+    :deps '(:uuid "1" :tokio ("1" :features '("rt" "net")))
+    :features '(:default '("uuid")))"#;
+        let dp = DefPkg::from_str(case, Default::default()).unwrap();
+        assert_eq!(dp.version.as_deref(), Some("0.2.0"));
+        assert_eq!(dp.edition.as_deref(), Some("2021"));
+        assert_eq!(
+            dp.deps["tokio"],
+            CargoDep {
+                version: "1".to_string(),
+                features: vec!["rt".to_string(), "net".to_string()]
             }
         );
+        assert_eq!(dp.features["default"], vec!["uuid".to_string()]);
+
+        for (case, err) in [
+            (
+                r#"(def-rpc-package demo :edition 2020)"#,
+                "2020 is not the rust edition",
+            ),
+            (
+                r#"(def-rpc-package demo :version 2)"#,
+                "the :version of demo has to be the string, got 2",
+            ),
+            (
+                r#"(def-rpc-package demo :deps '(:uuid 1))"#,
+                "the dependency 1 has to be the version string or the list",
+            ),
+            (
+                r#"(def-rpc-package demo :version "1" :version "2")"#,
+                "the :version of demo is given twice",
+            ),
+            (
+                r#"(def-rpc-package demo :license "MIT")"#,
+                "unknown option :license of demo",
+            ),
+        ] {
+            let e = DefPkg::from_str(case, Default::default()).unwrap_err();
+            assert!(e.to_string().starts_with(err), "{}", e);
+        }
     }
 
     #[test]
     fn test_gen_code() {
         let project_root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
         let template_file_path = vec![project_root.join("templates/Cargo.toml.template")];
+        let tera = load_templates(&template_file_path).unwrap();
 
         let case = r#"(def-rpc-package demo)"#;
         let dp = DefPkg::from_str(case, Default::default()).unwrap();
 
         assert_eq!(
-            dp.gen_code_with_files(&template_file_path).unwrap(),
+            dp.gen_code_with_tera(&tera, &Default::default()).unwrap(),
+            r#"[package]
+name = "demo"
+version = "0.1.0"
+edition = "2024"
+
+[dependencies]
+lisp-rpc-rust-parser = "0"
+"#,
+        );
+
+        let mut tera = Tera::default();
+        tera.add_raw_template(
+            "Cargo.toml",
+            include_str!("../templates/Cargo.toml.template"),
+        )
+        .unwrap();
+        let options = GenOptions {
+            serde: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            dp.gen_code_with_tera(&tera, &options).unwrap(),
             r#"[package]
 name = "demo"
 version = "0.1.0"
 edition = "2024"
 
 [dependencies]
+lisp-rpc-rust-parser = "0"
+serde = { version = "1", features = ["derive"] }
+"#,
+        );
+
+        // the declared serde overrides the one of the serde option
+        let case = r#"(def-rpc-package demo :version "0.2.0" :edition "2021"
+    :deps '(:serde ("1" :features '("derive" "rc")) :uuid "1")
+    :features '(:default '("uuid")))"#;
+        let dp = DefPkg::from_str(case, Default::default()).unwrap();
+        assert_eq!(
+            dp.gen_code_with_tera(&tera, &options).unwrap(),
+            r#"[package]
+name = "demo"
+version = "0.2.0"
+edition = "2021"
+
+[dependencies]
+lisp-rpc-rust-parser = "0"
+serde = { version = "1", features = ["derive", "rc"] }
+uuid = "1"
+
+[features]
+default = ["uuid"]
 "#,
         )
     }
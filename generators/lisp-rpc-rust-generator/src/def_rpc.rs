@@ -1,7 +1,11 @@
-use std::{error::Error, fs::File, io::Cursor, path::Path};
+use std::{collections::BTreeMap, error::Error, fs::File, io::Cursor};
 
 use anyhow::Result;
-use lisp_rpc_rust_parser::{Atom, Expr, Parser, TypeValue, data::MapData};
+use lisp_rpc_rust_parser::{
+    Atom, Expr, Parser, TypeValue,
+    data::{Data, MapData, Violation},
+};
+use serde::Serialize;
 use tera::{Context, Tera};
 
 use super::*;
@@ -25,6 +29,50 @@ impl std::fmt::Display for DefRPCError {
 
 impl Error for DefRPCError {}
 
+/// the codes of the (error ...) envelopes of the generated server, they
+/// can't be the declared errors
+const ENVELOPE_CODES: &[&str] = &[
+    "bad-request",
+    "unknown-rpc",
+    "unsupported-version",
+    "internal",
+];
+
+/// the rpc as the method of the generated client, trait, and dispatcher
+#[derive(Debug, Clone, Serialize)]
+pub struct RPCMethod {
+    pub method: String,
+    pub data_name: String,
+    pub req_type: String,
+
+    /// None if the rpc returns nothing
+    pub resp_type: Option<String>,
+
+    /// the enum of the declared errors, the handler returns the Result of it.
+    /// None if the rpc declares no error
+    pub error_type: Option<String>,
+
+    /// the one-way notification of def-notify, the client doesn't wait and
+    /// the server calls the hook without the response
+    pub notify: bool,
+
+    /// the versions of the spec having this rpc, the server rejects the
+    /// requests of the other versions
+    pub versions: VersionRange,
+}
+
+impl RPCMethod {
+    /// rename the request and the response types
+    pub fn renamed(self, renames: &BTreeMap<String, String>) -> Self {
+        Self {
+            req_type: rename_type(&self.req_type, renames),
+            resp_type: self.resp_type.map(|t| rename_type(&t, renames)),
+            error_type: self.error_type.map(|t| rename_type(&t, renames)),
+            ..self
+        }
+    }
+}
+
 #[derive(Debug, Eq, PartialEq)]
 pub struct DefRPC {
     rpc_name: String,
@@ -34,6 +82,27 @@ pub struct DefRPC {
 
     ///
     return_value: Option<String>,
+
+    /// the msg of the anonymous return type '(:keyword value), it is named
+    /// <rpc-name>-response and the return value is it
+    response: Option<DefMsg>,
+
+    /// the docstring after the name, it becomes the rustdoc of the struct
+    doc: Option<String>,
+
+    /// the request struct name in rust, instead of the pascal case name
+    rust_name: Option<String>,
+
+    /// the keys of the removed arguments, they can't be the arguments again
+    /// and the validation ignores them
+    reserved: Vec<String>,
+
+    /// the versions of the spec having this rpc
+    versions: VersionRange,
+
+    /// the codes of the declared errors, the handler returns them in the
+    /// (error ...) envelope
+    errors: Vec<String>,
 }
 
 impl DefRPC {
@@ -62,7 +131,9 @@ impl DefRPC {
     }
 
     /// make new DefRPC from the one expr
-    /// (def-rpc name '(:keyword value) 'return-value)
+    /// (def-rpc name ["docstring"] [:rust-name "Name"] [:since n] [:until n] '(:keyword value) 'return-value [:errors ('code ...)])
+    /// the return value can be the anonymous '(:keyword value) too, and the
+    /// arguments can be '() or omitted
     pub fn from_expr(expr: &Expr) -> Result<Self> {
         let rest_expr: &[Expr];
 
@@ -83,11 +154,11 @@ impl DefRPC {
             });
         }
 
-        let rpc_name = match &rest_expr[0] {
-            Expr::Atom(Atom {
+        let rpc_name = match rest_expr.first() {
+            Some(Expr::Atom(Atom {
                 value: TypeValue::Symbol(s),
                 ..
-            }) => s.to_string(),
+            })) => s.to_string(),
             _ => {
                 anyhow::bail!(DefRPCError {
                     msg: "parsing failed, rpc name should be symbol".to_string(),
@@ -96,9 +167,38 @@ impl DefRPC {
             }
         };
 
+        let (doc, rest_expr) = match rest_expr.get(1) {
+            Some(Expr::Atom(Atom {
+                value: TypeValue::String(doc),
+            })) => (Some(doc.to_string()), &rest_expr[1..]),
+            _ => (None, rest_expr),
+        };
+
+        // the :errors clause is the last one
+        let (rest_expr, errors) = match rest_expr {
+            [
+                rest @ ..,
+                Expr::Atom(Atom {
+                    value: TypeValue::Keyword(k),
+                }),
+                errors,
+            ] if k == "errors" => (rest, parse_errors(&rpc_name, errors)?),
+            _ => (rest_expr, vec![]),
+        };
+
+        // keep the name at the first, the arguments are the second
+        let (rust_name, rest_expr) = match rust_name_option(&rest_expr[1..])? {
+            (Some(n), _) => (Some(n), &rest_expr[2..]),
+            (None, _) => (None, rest_expr),
+        };
+        let (versions, rest) = version_range_option(&rest_expr[1..])?;
+        let rest_expr = &rest_expr[rest_expr.len() - rest.len() - 1..];
+
         //dbg!(&rest_expr);
-        let arguments = match de_quoted(&rest_expr[1]) {
-            Expr::List(exprs) => exprs,
+        // the rpc without the arguments, (def-rpc ping) is same as '()
+        let arguments: &[Expr] = match rest_expr.get(1).map(de_quoted) {
+            Some(Expr::List(exprs)) => exprs,
+            None => &[],
             _ => {
                 anyhow::bail!(DefRPCError {
                     msg: "parsing failed, second arguments has to be list of keyword-value pairs"
@@ -108,11 +208,18 @@ impl DefRPC {
             }
         };
 
+        let mut response = None;
         let return_value = match rest_expr.get(2) {
             Some(Expr::Quote(box e)) => match e {
                 Expr::Atom(Atom {
                     value: TypeValue::Symbol(rn),
                 }) => Some(rn.to_string()),
+                // anonymous return type, it is the msg named after the rpc
+                Expr::List(exprs) if !exprs.is_empty() => {
+                    let name = format!("{}-response", rpc_name);
+                    response = Some(DefMsg::new(&name, exprs, RPCDataType::Data)?);
+                    Some(name)
+                }
                 _ => {
                     anyhow::bail!(DefRPCError {
                         msg: "parsing failed, quoted quoted".to_string(),
@@ -129,18 +236,206 @@ impl DefRPC {
             }
         };
 
+        let (args, reserved) = split_reserved(arguments)?;
+
         Ok(Self {
             rpc_name,
-            args: arguments.to_vec(),
+            args,
             return_value,
+            response,
+            doc,
+            rust_name,
+            reserved,
+            versions,
+            errors,
         })
     }
 
+    /// the renamed request struct of this rpc, the default name to the rust name
+    pub fn renames(&self) -> Vec<(String, String)> {
+        self.rust_name
+            .iter()
+            .map(|n| (kebab_to_pascal_case(&self.rpc_name), n.clone()))
+            .collect()
+    }
+
+    pub fn rpc_name(&self) -> &str {
+        &self.rpc_name
+    }
+
+    /// the name of the return msg, None if this rpc returns nothing
+    pub fn return_value(&self) -> Option<&str> {
+        self.return_value.as_deref()
+    }
+
+    pub fn doc(&self) -> Option<&str> {
+        self.doc.as_deref()
+    }
+
+    /// the keyword-type pairs of the request in order
+    pub fn fields(&self) -> Vec<FieldSpec<'_>> {
+        field_specs(&self.args)
+    }
+
+    /// the msg of the anonymous return type, named <rpc-name>-response
+    pub fn response(&self) -> Option<&DefMsg> {
+        self.response.as_ref()
+    }
+
+    pub fn versions(&self) -> VersionRange {
+        self.versions
+    }
+
+    /// the codes of the declared errors
+    pub fn errors(&self) -> &[String] {
+        &self.errors
+    }
+
+    /// the enum name of the declared errors, None if there is no error
+    pub fn error_type(&self) -> Option<String> {
+        match self.errors.is_empty() {
+            true => None,
+            false => Some(kebab_to_pascal_case(&self.rpc_name) + "Error"),
+        }
+    }
+
+    /// the lines of the written form without the closing paren, the
+    /// arguments and the response are the quoted lists after the head
+    pub(crate) fn spec_lines(&self, form: &str) -> Vec<String> {
+        let mut lines = vec![spec_head(
+            form,
+            &self.rpc_name,
+            self.doc.as_deref(),
+            self.rust_name.as_deref(),
+            self.versions,
+        )];
+        let response = match (&self.response, &self.return_value) {
+            (Some(r), _) => quoted_lines(spec_field_lines(&r.fields(), &[])),
+            (None, Some(rv)) => vec![format!("'{}", rv)],
+            (None, None) => vec![],
+        };
+        let args = spec_field_lines(&self.fields(), &self.reserved);
+        // the arguments can be omitted only if nothing is after them
+        if !args.is_empty() || !response.is_empty() {
+            lines.extend(quoted_lines(args));
+        }
+        lines.extend(response);
+        if !self.errors.is_empty() {
+            lines.push(format!(
+                ":errors ({})",
+                self.errors
+                    .iter()
+                    .map(|e| format!("'{}", e))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            ));
+        }
+
+        let head = lines.remove(0);
+        std::iter::once(head)
+            .chain(lines.into_iter().map(|l| format!("  {}", l)))
+            .collect()
+    }
+
+    pub fn method(&self) -> RPCMethod {
+        RPCMethod {
+            method: kebab_to_snake_case(&self.rpc_name),
+            data_name: self.rpc_name.to_string(),
+            req_type: kebab_to_pascal_case(&self.rpc_name),
+            resp_type: self.return_value.as_deref().map(type_translate),
+            error_type: self.error_type(),
+            notify: false,
+            versions: self.versions,
+        }
+    }
+
+    /// render the enum of the declared errors, None if there is no error or
+    /// the templates don't have the rpc_error.rs
+    pub(crate) fn render_error(
+        &self,
+        templates: &Tera,
+        options: &GenOptions,
+    ) -> Result<Option<String>> {
+        let Some(name) = self.error_type() else {
+            return Ok(None);
+        };
+        if !templates.get_template_names().any(|n| n == ERROR_TEMPLATE) {
+            return Ok(None);
+        }
+
+        let mut context = Context::new();
+        options.insert_template(&mut context);
+        context.insert("name", &rename_type(&name, &options.renames));
+        context.insert("data_name", &self.rpc_name);
+        context.insert(
+            "variants",
+            &self
+                .errors
+                .iter()
+                .map(|e| BTreeMap::from([("name", kebab_to_pascal_case(e)), ("code", e.clone())]))
+                .collect::<Vec<_>>(),
+        );
+        Ok(Some(templates.render(ERROR_TEMPLATE, &context)?))
+    }
+
+    /// render the typed client method of this rpc, None if the templates
+    /// don't have the client_impl
+    pub(crate) fn render_client(
+        &self,
+        templates: &Tera,
+        options: &GenOptions,
+    ) -> Result<Option<String>> {
+        if !templates.get_template_names().any(|n| n == CLIENT_TEMPLATE) {
+            return Ok(None);
+        }
+
+        let method = self.method().renamed(&options.renames_with(self.renames()));
+        let mut context = Context::from_serialize(method)?;
+        options.insert_template(&mut context);
+        Ok(Some(templates.render(CLIENT_TEMPLATE, &context)?))
+    }
+
+    /// check the request data against this rpc spec, return all violations.
+    /// empty means the data is valid
+    pub fn validate(&self, d: &Data) -> Vec<Violation> {
+        let mut out = vec![];
+        // the tagged data has to be tagged with this name
+        let d = match d.check_tag(&self.rpc_name) {
+            Ok(d) => d,
+            Err(e) => {
+                out.push(Violation::new("", e.msg()));
+                return out;
+            }
+        };
+        match d {
+            Data::Data(e) if e.get_name() != self.rpc_name => out.push(Violation::new(
+                "",
+                format!("expect {} data, got {}", self.rpc_name, e.get_name()),
+            )),
+            Data::Data(_) => (),
+            _ => {
+                out.push(Violation::new(
+                    "",
+                    format!("expect {} data, got {}", self.rpc_name, kind_of(d)),
+                ));
+                return out;
+            }
+        }
+
+        validate_fields("", &self.args, &self.reserved, d, &mut out);
+        out
+    }
+
     /// convet this spec to GeneratedStructs (self and the anonymity type)
     pub fn create_gen_structs(&self) -> Result<Vec<GeneratedStruct>> {
         let mut res = vec![];
         let mut fields = vec![];
         for [field, ty] in self.args.iter().array_chunks() {
+            if let Some(field) = wrapped_field(field, ty)? {
+                fields.push(field);
+                continue;
+            }
+
             match (field, ty) {
                 (
                     Expr::Atom(Atom {
@@ -158,6 +453,21 @@ impl DefRPC {
                     }),
                     Expr::Quote(box Expr::List(inner_exprs)) | Expr::List(inner_exprs),
                 ) => {
+                    // list type, the first ele is "list"
+                    if let [
+                        Expr::Atom(Atom {
+                            value: TypeValue::Symbol(l),
+                        }),
+                        Expr::Quote(box Expr::Atom(Atom {
+                            value: TypeValue::Symbol(t),
+                        })),
+                    ] = inner_exprs.as_slice()
+                        && l == "list"
+                    {
+                        fields.push(GeneratedField::new_list(f, t, None));
+                        continue;
+                    }
+
                     // anonymity msg type
                     let new_msg_name = self.rpc_name.to_string() + "-" + f;
                     res.append(
@@ -178,74 +488,154 @@ impl DefRPC {
             }
         }
 
-        res.push(GeneratedStruct::new(
-            &self.rpc_name,
-            None,
-            fields,
-            None,
-            RPCDataType::Data,
-        ));
-
-        Ok(res)
-    }
-
-    /// use the GeneratedStruct to generate the code
-    fn gen_code_with_files(&self, template_files: &[impl AsRef<Path>]) -> Result<String> {
-        let mut tera = Tera::default();
-        let mut context = Context::new();
-
-        let mut all_temps = vec![];
-        for p in template_files {
-            match p.as_ref().file_stem().map(|n| n.to_str()) {
-                Some(n) => {
-                    all_temps.push((p, n));
-                }
-                None => (),
-            }
-        }
-
-        tera.add_template_files(all_temps)?;
-
-        let mut bucket = vec![];
-        for s in self.create_gen_structs()? {
-            s.insert_template(&mut context);
-            bucket.push(tera.render("def_struct.rs", &context)?);
-            bucket.push(tera.render("rpc_impl", &context)?);
+        res.push(
+            GeneratedStruct::new(&self.rpc_name, None, fields, None, RPCDataType::Data)
+                .with_doc(self.doc.as_deref())
+                .with_versions(self.versions),
+        );
+        if let Some(r) = &self.response {
+            res.extend(r.create_gen_structs()?);
         }
 
-        Ok(bucket.join("\n\n"))
+        Ok(res)
     }
 
     /// Generate code with the exist tera instance
-    fn gen_code_with_tera(&self, templates: &Tera) -> Result<String> {
+    fn gen_code_with_tera(&self, templates: &Tera, options: &GenOptions) -> Result<String> {
         let mut context = Context::new();
+        options.insert_template(&mut context);
         let mut bucket = vec![];
-        for s in self.create_gen_structs()? {
-            s.insert_template(&mut context);
-            bucket.push(templates.render("def_struct.rs", &context)?);
-            bucket.push(templates.render("rpc_impl", &context)?);
+        let structs = box_recursive(self.create_gen_structs()?, &options.recursive);
+        let structs = rename_structs(structs, &options.renames_with(self.renames()));
+        for s in alias_defaults(structs, &options.aliases) {
+            bucket.extend(s.render(templates, &mut context)?);
         }
+        bucket.extend(self.render_error(templates, options)?);
+        bucket.extend(self.render_client(templates, options)?);
 
         Ok(bucket.join("\n\n"))
     }
 }
 
 impl RPCSpec for DefRPC {
-    fn gen_code_with_temp_files(&self, temp_file_paths: &[String]) -> Result<String> {
-        self.gen_code_with_files(temp_file_paths)
-    }
-
-    fn gen_code_with_tera(&self, templates: &Tera) -> Result<String> {
-        self.gen_code_with_tera(templates)
+    fn gen_code_with_tera(&self, templates: &Tera, options: &GenOptions) -> Result<String> {
+        self.gen_code_with_tera(templates, options)
     }
 
     fn file_target(&self) -> TargetFile {
         TargetFile::Lib
     }
 
+    fn to_spec_string(&self) -> String {
+        self.spec_lines("def-rpc").join("\n") + ")"
+    }
+
+    fn struct_deps(&self) -> Result<Vec<(String, Vec<String>)>> {
+        Ok(self
+            .create_gen_structs()?
+            .iter()
+            .map(|s| s.deps())
+            .collect())
+    }
+
+    fn gen_structs(&self) -> Result<Vec<GeneratedStruct>> {
+        self.create_gen_structs()
+    }
+
     fn symbol_name(&self) -> String {
         self.rpc_name.to_string()
     }
+
+    fn renames(&self) -> Vec<(String, String)> {
+        self.renames()
+    }
+
+    /// the rpc, and the msg of the anonymous return type
+    fn spec_fields(&self) -> Vec<SpecFields<'_>> {
+        let mut fields = vec![SpecFields {
+            name: &self.rpc_name,
+            fields: Cow::Borrowed(&self.args),
+            returns: self.return_value.as_deref(),
+            versions: self.versions,
+            doc: self.doc.as_deref(),
+            is_rpc: true,
+            notify: false,
+            errors: &self.errors,
+        }];
+        fields.extend(self.response.iter().flat_map(|r| r.spec_fields()));
+        fields
+    }
+
+    fn rpc_methods(&self) -> Vec<RPCMethod> {
+        vec![self.method()]
+    }
+
+    fn msgs(&self) -> Vec<&DefMsg> {
+        self.response.iter().collect()
+    }
+
+    fn rpcs(&self) -> Vec<&DefRPC> {
+        vec![self]
+    }
+}
+
+/// the codes of the :errors clause, ('not-found 'forbidden)
+fn parse_errors(rpc_name: &str, expr: &Expr) -> Result<Vec<String>> {
+    let exprs = match de_quoted(expr) {
+        Expr::List(exprs) if !exprs.is_empty() => exprs,
+        _ => anyhow::bail!(DefRPCError {
+            msg: format!(
+                "parsing failed, the :errors of {} has to be the list of symbols",
+                rpc_name
+            ),
+            err_type: DefRPCErrorType::InvalidInput,
+        }),
+    };
+
+    let mut errors: Vec<String> = vec![];
+    for e in exprs {
+        let code = match de_quoted(e) {
+            Expr::Atom(Atom {
+                value: TypeValue::Symbol(s),
+            }) => s,
+            _ => anyhow::bail!(DefRPCError {
+                msg: format!(
+                    "parsing failed, the error {} of {} has to be symbol",
+                    e, rpc_name
+                ),
+                err_type: DefRPCErrorType::InvalidInput,
+            }),
+        };
+        if ENVELOPE_CODES.contains(&code.as_str()) || errors.contains(code) {
+            anyhow::bail!(DefRPCError {
+                msg: format!(
+                    "parsing failed, {} can't be the error of {} again",
+                    code, rpc_name
+                ),
+                err_type: DefRPCErrorType::InvalidInput,
+            })
+        }
+        errors.push(code.clone());
+    }
+    Ok(errors)
+}
+
+/// the lines of the quoted list '(...), the lines after the first one are
+/// aligned to it
+fn quoted_lines(lines: Vec<String>) -> Vec<String> {
+    let mut quoted: Vec<String> = lines
+        .into_iter()
+        .enumerate()
+        .map(|(i, l)| match i {
+            0 => format!("'({}", l),
+            _ => format!("  {}", l),
+        })
+        .collect();
+    match quoted.last_mut() {
+        Some(last) => last.push(')'),
+        None => quoted.push("'()".to_string()),
+    }
+    quoted
 }
 
 fn de_quoted(e: &Expr) -> &Expr {
@@ -262,6 +652,7 @@ mod tests {
     use std::path::PathBuf;
 
     use super::*;
+    use lisp_rpc_rust_parser::data::FromStr;
 
     #[test]
     fn test_parse_def_rpc() {
@@ -282,7 +673,13 @@ mod tests {
                     Expr::Atom(Atom::read_keyword("lang")),
                     Expr::Quote(Box::new(Expr::Atom(Atom::read("language-perfer")))),
                 ],
-                return_value: Some("book-info".to_string())
+                return_value: Some("book-info".to_string()),
+                response: None,
+                doc: None,
+                rust_name: None,
+                reserved: vec![],
+                versions: VersionRange::default(),
+                errors: vec![],
             }
         );
 
@@ -308,9 +705,57 @@ mod tests {
                         Expr::Quote(Box::new(Expr::Atom(Atom::read("number")))),
                     ]))),
                 ],
-                return_value: Some("book-info".to_string())
+                return_value: Some("book-info".to_string()),
+                response: None,
+                doc: None,
+                rust_name: None,
+                reserved: vec![],
+                versions: VersionRange::default(),
+                errors: vec![],
             }
-        )
+        );
+
+        let case = r#"(def-rpc get-book "doc" :rust-name "Fetch" :since 2 :until 4 '(:title 'string) 'book-info)"#;
+        let dr = DefRPC::from_str(case, Default::default()).unwrap();
+        assert_eq!(dr.rust_name, Some("Fetch".to_string()));
+        assert_eq!(
+            dr.method().versions,
+            VersionRange {
+                since: Some(2),
+                until: Some(4)
+            }
+        );
+        assert_eq!(dr.return_value, Some("book-info".to_string()));
+        assert!(
+            DefRPC::from_str(
+                r#"(def-rpc get-book :since 2 :since 3 '(:title 'string))"#,
+                None
+            )
+            .is_err()
+        );
+
+        let case = r#"(def-rpc get-book "get the book by title" '(:title ('string :doc "the title")) 'book-info)"#;
+        let dr = DefRPC::from_str(case, Default::default()).unwrap();
+        assert_eq!(dr.doc, Some("get the book by title".to_string()));
+        assert_eq!(dr.return_value, Some("book-info".to_string()));
+        assert_eq!(
+            dr.create_gen_structs().unwrap(),
+            vec![
+                GeneratedStruct::new(
+                    "get-book",
+                    None,
+                    vec![GeneratedField::new("title", "string", None).with_doc(Some("the title"))],
+                    None,
+                    RPCDataType::Data,
+                )
+                .with_doc(Some("get the book by title"))
+            ]
+        );
+        let s = dr.create_gen_structs().unwrap().remove(0);
+        assert_eq!(s.comment.as_deref(), Some("/// get the book by title"));
+        assert_eq!(s.fields[0].comment.as_deref(), Some("/// the title"));
+        let d = Data::from_str(&Parser::new(), r#"(get-book :title "1984")"#).unwrap();
+        assert!(dr.validate(&d).is_empty());
     }
 
     #[test]
@@ -415,7 +860,174 @@ mod tests {
                     RPCDataType::Data,
                 ),
             ]
+        );
+
+        let spec = r#"(def-rpc get-books
+      '(:titles (list 'string) :infos '(list 'book-info))
+    'book-info)"#;
+
+        let dr = DefRPC::from_str(spec, None).unwrap();
+        assert_eq!(
+            dr.create_gen_structs().unwrap(),
+            vec![GeneratedStruct::new(
+                "get-books",
+                None,
+                vec![
+                    GeneratedField::new_list("titles", "string", None),
+                    GeneratedField::new_list("infos", "book-info", None),
+                ],
+                None,
+                RPCDataType::Data,
+            )]
+        );
+
+        // the anonymous return type is the msg named after the rpc
+        let spec = r#"(def-rpc find-books
+      '(:tag 'string)
+    '(:count 'number :items (list 'book-info)))"#;
+
+        let dr = DefRPC::from_str(spec, None).unwrap();
+        assert_eq!(dr.return_value(), Some("find-books-response"));
+        assert_eq!(dr.method().resp_type.as_deref(), Some("FindBooksResponse"));
+        assert_eq!(
+            dr.create_gen_structs().unwrap(),
+            vec![
+                GeneratedStruct::new(
+                    "find-books",
+                    None,
+                    vec![GeneratedField::new("tag", "string", None)],
+                    None,
+                    RPCDataType::Data,
+                ),
+                GeneratedStruct::new(
+                    "find-books-response",
+                    None,
+                    vec![
+                        GeneratedField::new("count", "number", None),
+                        GeneratedField::new_list("items", "book-info", None),
+                    ],
+                    None,
+                    RPCDataType::Data,
+                ),
+            ]
+        );
+        assert_eq!(
+            dr.spec_fields()
+                .iter()
+                .map(|f| (f.name, f.is_rpc))
+                .collect::<Vec<_>>(),
+            vec![("find-books", true), ("find-books-response", false)]
+        );
+        assert!(
+            DefRPC::from_str(
+                "(def-rpc find-books '(:tag 'string) '(count 'number))",
+                None
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_declared_errors() {
+        let dr = DefRPC::from_str(
+            "(def-rpc get-book '(:title 'string) 'book-info :errors ('not-found 'forbidden))",
+            None,
+        )
+        .unwrap();
+        assert_eq!(dr.errors(), ["not-found", "forbidden"]);
+        assert_eq!(dr.return_value(), Some("book-info"));
+        assert_eq!(dr.method().error_type.as_deref(), Some("GetBookError"));
+        assert_eq!(dr.spec_fields()[0].errors, ["not-found", "forbidden"]);
+
+        // the rpc returning nothing, and the quoted list
+        let dr = DefRPC::from_str("(def-rpc ping :errors '(timeout))", None).unwrap();
+        assert_eq!(dr.errors(), ["timeout"]);
+        assert_eq!(dr.return_value(), None);
+        assert!(dr.args.is_empty());
+        assert_eq!(
+            DefRPC::from_str("(def-rpc ping)", None)
+                .unwrap()
+                .method()
+                .error_type,
+            None
+        );
+
+        for spec in [
+            "(def-rpc ping '() :errors ())",
+            "(def-rpc ping '() :errors ('not-found 'not-found))",
+            "(def-rpc ping '() :errors ('internal))",
+            r#"(def-rpc ping '() :errors ("not-found"))"#,
+        ] {
+            assert!(DefRPC::from_str(spec, None).is_err(), "{}", spec);
+        }
+
+        let project_root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let tera = load_templates(&[
+            project_root.join("templates/rpc_error.rs.template"),
+            project_root.join("templates/client_impl.template"),
+        ])
+        .unwrap();
+        let dr = DefRPC::from_str(
+            "(def-rpc get-book '(:title 'string) 'book-info :errors ('not-found 'forbidden))",
+            None,
         )
+        .unwrap();
+        let code = dr
+            .render_error(&tera, &GenOptions::default())
+            .unwrap()
+            .unwrap();
+        assert!(code.contains(
+            "pub enum GetBookError {
+    NotFound(String),
+    Forbidden(String),
+}"
+        ));
+        assert!(code.contains("            Self::NotFound(m) | Self::Forbidden(m) => m,"));
+        assert!(code.contains(r#"            "forbidden" => Some(Self::Forbidden(message)),"#));
+        assert!(
+            dr.render_client(&tera, &GenOptions::default())
+                .unwrap()
+                .unwrap()
+                .contains(
+                    "        if let Some(e) = GetBookError::from_error_data(&d) {
+            return Err(Box::new(e));
+        }"
+                )
+        );
+        assert!(
+            DefRPC::from_str("(def-rpc ping)", None)
+                .unwrap()
+                .render_error(&tera, &GenOptions::default())
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_zero_argument_rpc() {
+        let ping = GeneratedStruct::new("ping", None, vec![], None, RPCDataType::Data);
+        for (spec, returns) in [
+            ("(def-rpc ping '() 'pong)", Some("pong")),
+            ("(def-rpc ping ())", None),
+            ("(def-rpc ping)", None),
+        ] {
+            let dr = DefRPC::from_str(spec, None).unwrap();
+            assert_eq!(dr.return_value(), returns, "{}", spec);
+            assert_eq!(dr.create_gen_structs().unwrap(), vec![ping.clone()]);
+        }
+
+        // the options follow the name directly
+        let dr = DefRPC::from_str("(def-rpc ping :since 2)", None).unwrap();
+        assert!(dr.args.is_empty());
+        assert_eq!(dr.versions.since, Some(2));
+
+        let dr = DefRPC::from_str(r#"(def-rpc ping "are you there")"#, None).unwrap();
+        assert_eq!(dr.doc.as_deref(), Some("are you there"));
+        assert!(dr.args.is_empty());
+        assert!(DefRPC::from_str("(def-rpc ping 'pong)", None).is_err());
+
+        let d = Data::from_str(&Parser::new(), "(ping)").unwrap();
+        assert!(dr.validate(&d).is_empty());
     }
 
     #[test]
@@ -425,6 +1037,7 @@ mod tests {
             project_root.join("templates/def_struct.rs.template"),
             project_root.join("templates/rpc_impl.template"),
         ];
+        let tera = load_templates(&template_file_path).unwrap();
 
         let case = r#"(def-rpc get-book
       '(:title 'string :version 'string :lang '(:lang 'string :encoding 'number))
@@ -434,13 +1047,30 @@ mod tests {
         //dbg!(dm.gen_code_with_file(&template_file_path).unwrap());
 
         assert_eq!(
-            dm.gen_code_with_files(&template_file_path).unwrap(),
+            dm.gen_code_with_tera(&tera, &Default::default()).unwrap(),
             r#"#[derive(Debug)]
 pub struct GetBookLang {
     lang: String,
     encoding: i64,
 }
 
+impl GetBookLang {
+    pub fn new(lang: String, encoding: i64) -> Self {
+        Self {
+            lang,
+            encoding,
+        }
+    }
+
+    pub fn lang(&self) -> &String {
+        &self.lang
+    }
+
+    pub fn encoding(&self) -> &i64 {
+        &self.encoding
+    }
+}
+
 impl ToRPCData for GetBookLang {
     fn to_rpc(&self) -> String {
         format!(
@@ -458,6 +1088,28 @@ pub struct GetBook {
     lang: GetBookLang,
 }
 
+impl GetBook {
+    pub fn new(title: String, version: String, lang: GetBookLang) -> Self {
+        Self {
+            title,
+            version,
+            lang,
+        }
+    }
+
+    pub fn title(&self) -> &String {
+        &self.title
+    }
+
+    pub fn version(&self) -> &String {
+        &self.version
+    }
+
+    pub fn lang(&self) -> &GetBookLang {
+        &self.lang
+    }
+}
+
 impl ToRPCData for GetBook {
     fn to_rpc(&self) -> String {
         format!(
@@ -470,4 +1122,62 @@ impl ToRPCData for GetBook {
 }"#
         );
     }
+
+    #[test]
+    fn test_gen_client() {
+        let mut tera = Tera::default();
+        tera.add_raw_template(
+            CLIENT_TEMPLATE,
+            include_str!("../templates/client_impl.template"),
+        )
+        .unwrap();
+
+        let dr = DefRPC::from_str("(def-rpc get-book '(:title 'string) 'book-info)", None).unwrap();
+        assert_eq!(
+            dr.render_client(&tera, &GenOptions::default())
+                .unwrap()
+                .unwrap(),
+            r#"impl<T: Transport> Client<T> {
+    pub fn get_book(&self, req: GetBook) -> Result<BookInfo, Box<dyn std::error::Error>> {
+        let resp = self.transport().send(&req.to_rpc())?;
+        Ok(FromRPCData::from_rpc(&Data::from_root_str(&resp, None)?)?)
+    }
+}"#
+        );
+
+        let dr = DefRPC::from_str("(def-rpc return-book '(:id 'string))", None).unwrap();
+        assert_eq!(
+            dr.render_client(&tera, &GenOptions::default())
+                .unwrap()
+                .unwrap(),
+            r#"impl<T: Transport> Client<T> {
+    pub fn return_book(&self, req: ReturnBook) -> Result<(), Box<dyn std::error::Error>> {
+        self.transport().send(&req.to_rpc())?;
+        Ok(())
+    }
+}"#
+        );
+
+        let async_options = GenOptions {
+            async_api: true,
+            ..Default::default()
+        };
+        let dr = DefRPC::from_str("(def-rpc get-book '(:title 'string) 'book-info)", None).unwrap();
+        assert_eq!(
+            dr.render_client(&tera, &async_options).unwrap().unwrap(),
+            r#"impl<T: AsyncTransport> Client<T> {
+    pub async fn get_book(&self, req: GetBook) -> Result<BookInfo, Box<dyn std::error::Error + Send + Sync>> {
+        let resp = self.transport().send(&req.to_rpc()).await?;
+        let d = Data::from_root_str(&resp, None).map_err(|e| e.to_string())?;
+        Ok(FromRPCData::from_rpc(&d)?)
+    }
+}"#
+        );
+
+        assert!(
+            dr.render_client(&Tera::default(), &GenOptions::default())
+                .unwrap()
+                .is_none()
+        );
+    }
 }
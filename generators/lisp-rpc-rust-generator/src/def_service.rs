@@ -0,0 +1,267 @@
+//! the mod that handle def-service expr
+
+use std::io::Cursor;
+
+use anyhow::Result;
+use lisp_rpc_rust_parser::{Atom, Expr, Parser, TypeValue};
+use tera::{Context, Tera};
+
+use super::*;
+
+/// the name of the template of the service trait and its dispatcher
+pub const SERVICE_TEMPLATE: &str = "def_service.rs";
+
+#[doc = r#"the struct of def-service expression, the group of rpcs
+(def-service name ["docstring"] (rpc-name '(:keyword value) 'return-value) ...)
+"#]
+#[derive(Debug, Eq, PartialEq)]
+pub struct DefService {
+    service_name: String,
+
+    /// the rpcs, same as the def-rpc without the def-rpc symbol
+    rpcs: Vec<DefRPC>,
+
+    doc: Option<String>,
+}
+
+impl DefService {
+    pub fn if_def_service_expr(expr: &Expr) -> bool {
+        match &expr {
+            Expr::List(e) => match &e[0] {
+                Expr::Atom(Atom {
+                    value: TypeValue::Symbol(s),
+                    ..
+                }) => s == "def-service",
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    pub fn from_str(source: &str, parser: Option<Parser>) -> Result<Self> {
+        let mut p = parser.unwrap_or_default();
+
+        let expr = p.parse_root_one(Cursor::new(source))?;
+
+        Self::from_expr(&expr)
+    }
+
+    /// make new DefService from the one expr
+    /// (def-service name ["docstring"] (rpc-name '(:keyword value) 'return-value) ...)
+    pub fn from_expr(expr: &Expr) -> Result<Self> {
+        let rest_expr = match &expr {
+            Expr::List(e) if Self::if_def_service_expr(expr) => &e[1..],
+            _ => {
+                anyhow::bail!("parsing failed, the first symbol should be def-service");
+            }
+        };
+
+        let service_name = match rest_expr.first() {
+            Some(Expr::Atom(Atom {
+                value: TypeValue::Symbol(s),
+            })) => s.to_string(),
+            _ => {
+                anyhow::bail!("parsing failed, service name should be symbol");
+            }
+        };
+
+        let (doc, rest_expr) = match rest_expr.get(1) {
+            Some(Expr::Atom(Atom {
+                value: TypeValue::String(doc),
+            })) => (Some(doc.to_string()), &rest_expr[2..]),
+            _ => (None, &rest_expr[1..]),
+        };
+
+        let mut rpcs = vec![];
+        for e in rest_expr {
+            match e {
+                Expr::List(inner) => {
+                    let mut def_rpc = vec![Expr::Atom(Atom::read("def-rpc"))];
+                    def_rpc.extend(inner.iter().cloned());
+                    rpcs.push(DefRPC::from_expr(&Expr::List(def_rpc))?);
+                }
+                _ => {
+                    anyhow::bail!("parsing failed, {} is not the rpc of the service", e);
+                }
+            }
+        }
+
+        Ok(Self {
+            service_name,
+            rpcs,
+            doc,
+        })
+    }
+
+    /// render the structs of all rpcs, then the trait and the dispatcher
+    fn render(&self, templates: &Tera, options: &GenOptions) -> Result<String> {
+        let mut context = Context::new();
+        options.insert_template(&mut context);
+        let renames = options.renames_with(RPCSpec::renames(self));
+        let mut bucket = vec![];
+        for r in &self.rpcs {
+            let structs = box_recursive(r.create_gen_structs()?, &options.recursive);
+            for s in alias_defaults(rename_structs(structs, &renames), &options.aliases) {
+                bucket.extend(s.render(templates, &mut context)?);
+            }
+            bucket.extend(r.render_error(templates, options)?);
+            bucket.extend(r.render_client(templates, options)?);
+        }
+
+        let mut context = Context::new();
+        options.insert_template(&mut context);
+        context.insert("name", &kebab_to_pascal_case(&self.service_name));
+        context.insert("method_prefix", &kebab_to_snake_case(&self.service_name));
+        context.insert("comment", &self.doc.as_deref().map(doc_comment));
+        context.insert(
+            "rpcs",
+            &self
+                .rpcs
+                .iter()
+                .map(|r| r.method().renamed(&renames))
+                .collect::<Vec<_>>(),
+        );
+        bucket.push(templates.render(SERVICE_TEMPLATE, &context)?);
+
+        Ok(bucket.join("\n\n"))
+    }
+}
+
+impl RPCSpec for DefService {
+    fn gen_code_with_tera(&self, templates: &Tera, options: &GenOptions) -> Result<String> {
+        self.render(templates, options)
+    }
+
+    fn file_target(&self) -> TargetFile {
+        TargetFile::Lib
+    }
+
+    fn to_spec_string(&self) -> String {
+        let mut lines = vec![format!("(def-service {}", self.service_name)];
+        if let Some(d) = &self.doc {
+            lines[0] += &format!(" {}", quote_string(d));
+        }
+        for r in &self.rpcs {
+            // the rpcs are the def-rpc forms without the def-rpc symbol
+            let mut rpc = r.spec_lines("def-rpc");
+            rpc[0] = rpc[0].replacen("(def-rpc ", "(", 1);
+            if let Some(last) = rpc.last_mut() {
+                last.push(')');
+            }
+            lines.extend(rpc.into_iter().map(|l| format!("  {}", l)));
+        }
+        lines.join("\n") + ")"
+    }
+
+    fn struct_deps(&self) -> Result<Vec<(String, Vec<String>)>> {
+        let mut deps = vec![];
+        for r in &self.rpcs {
+            deps.extend(r.struct_deps()?);
+        }
+        Ok(deps)
+    }
+
+    fn gen_structs(&self) -> Result<Vec<GeneratedStruct>> {
+        let mut structs = vec![];
+        for r in &self.rpcs {
+            structs.extend(r.create_gen_structs()?);
+        }
+        Ok(structs)
+    }
+
+    fn renames(&self) -> Vec<(String, String)> {
+        self.rpcs.iter().flat_map(|r| r.renames()).collect()
+    }
+
+    fn spec_fields(&self) -> Vec<SpecFields<'_>> {
+        self.rpcs.iter().flat_map(|r| r.spec_fields()).collect()
+    }
+
+    fn service(&self) -> Option<(&str, Option<&str>)> {
+        Some((&self.service_name, self.doc.as_deref()))
+    }
+
+    fn msgs(&self) -> Vec<&DefMsg> {
+        self.rpcs.iter().flat_map(|r| r.response()).collect()
+    }
+
+    fn rpcs(&self) -> Vec<&DefRPC> {
+        self.rpcs.iter().collect()
+    }
+
+    fn symbol_name(&self) -> String {
+        self.service_name.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_def_service() {
+        let case = r#"(def-service library "the library"
+    (get-book '(:title 'string) 'book-info)
+    (return-book '(:id 'string)))"#;
+        let ds = DefService::from_str(case, None).unwrap();
+        assert_eq!(ds.service_name, "library");
+        assert_eq!(ds.doc, Some("the library".to_string()));
+        assert_eq!(
+            ds.rpcs
+                .iter()
+                .map(|r| (r.rpc_name(), r.return_value()))
+                .collect::<Vec<_>>(),
+            vec![("get-book", Some("book-info")), ("return-book", None)]
+        );
+
+        assert!(DefService::from_str("(def-service library get-book)", None).is_err());
+        assert!(DefService::from_str("(def-rpc library)", None).is_err());
+    }
+
+    #[test]
+    fn test_gen_code() {
+        let project_root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let template_file_path = vec![
+            project_root.join("templates/def_struct.rs.template"),
+            project_root.join("templates/rpc_impl.template"),
+            project_root.join("templates/def_service.rs.template"),
+        ];
+        let tera = load_templates(&template_file_path).unwrap();
+
+        let case = r#"(def-service library
+    (get-book '(:title 'string) 'book-info)
+    (return-book '(:id 'string)))"#;
+        let ds = DefService::from_str(case, None).unwrap();
+        let code = ds.gen_code_with_tera(&tera, &Default::default()).unwrap();
+        assert!(code.contains("pub struct GetBook {"));
+        assert!(code.contains("pub struct ReturnBook {"));
+        assert!(code.ends_with(
+            r#"pub trait Library {
+    fn get_book(&self, req: GetBook) -> BookInfo;
+    fn return_book(&self, req: ReturnBook);
+}
+
+/// route the request data to the method of Library by the data name, and
+/// return the response. None if no rpc of Library has the name
+pub fn dispatch_library(service: &impl Library, d: &Data) -> Option<Result<String, DataError>> {
+    let name = match d.untagged() {
+        Data::Data(e) => e.get_name(),
+        _ => return None,
+    };
+
+    match name {
+        "get-book" => Some(FromRPCData::from_rpc(d).map(|req| {
+            service.get_book(req).to_rpc()
+        })),
+        "return-book" => Some(FromRPCData::from_rpc(d).map(|req| {
+            service.return_book(req);
+            "nil".to_string()
+        })),
+        _ => None,
+    }
+}"#
+        ));
+    }
+}
@@ -0,0 +1,111 @@
+//! the mod that handle def-version expr
+
+use super::*;
+use anyhow::Context;
+use lisp_rpc_rust_parser::{Atom, Expr, TypeValue};
+use tera::Tera;
+
+pub const VERSION_TEMPLATE: &str = "def_version.rs";
+
+#[doc = r#"the struct of def-version expression
+(def-version 3)
+"#]
+#[derive(Debug, Eq, PartialEq)]
+pub struct DefVersion {
+    version: i64,
+}
+
+impl DefVersion {
+    pub fn if_def_version_expr(expr: &Expr) -> bool {
+        match &expr {
+            Expr::List(e) => matches!(
+                e.first(),
+                Some(Expr::Atom(Atom {
+                    value: TypeValue::Symbol(s),
+                })) if s == "def-version"
+            ),
+            _ => false,
+        }
+    }
+
+    pub fn from_expr(expr: &Expr) -> Result<Self> {
+        match &expr {
+            Expr::List(e) if Self::if_def_version_expr(expr) => match &e[1..] {
+                [
+                    Expr::Atom(Atom {
+                        value: TypeValue::Number(n),
+                    }),
+                ] if *n > 0 => Ok(Self { version: *n }),
+                _ => anyhow::bail!("parsing failed, the version has to be one positive number"),
+            },
+            _ => anyhow::bail!("parsing failed, the first symbol should be def-version"),
+        }
+    }
+
+    pub fn version(&self) -> i64 {
+        self.version
+    }
+
+    /// Generate code with the exist tera instance
+    fn gen_code_with_tera(&self, templates: &Tera, options: &GenOptions) -> Result<String> {
+        let mut context = tera::Context::new();
+        options.insert_template(&mut context);
+        context.insert("version", &self.version);
+        templates
+            .render(VERSION_TEMPLATE, &context)
+            .context("render def version wrong")
+    }
+}
+
+impl RPCSpec for DefVersion {
+    fn gen_code_with_tera(&self, templates: &Tera, options: &GenOptions) -> Result<String> {
+        self.gen_code_with_tera(templates, options)
+    }
+
+    fn file_target(&self) -> TargetFile {
+        TargetFile::Lib
+    }
+
+    fn to_spec_string(&self) -> String {
+        format!("(def-version {})", self.version)
+    }
+
+    fn symbol_name(&self) -> String {
+        "spec-version".to_string()
+    }
+
+    fn spec_version(&self) -> Option<i64> {
+        Some(self.version)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::Cursor, path::PathBuf};
+
+    use lisp_rpc_rust_parser::Parser;
+
+    use super::*;
+
+    fn from_str(source: &str) -> Result<DefVersion> {
+        DefVersion::from_expr(&Parser::new().parse_root_one(Cursor::new(source))?)
+    }
+
+    #[test]
+    fn test_def_version() {
+        let dv = from_str("(def-version 3)").unwrap();
+        assert_eq!(dv, DefVersion { version: 3 });
+        assert!(from_str("(def-version 0)").is_err());
+        assert!(from_str("(def-version 1 2)").is_err());
+
+        let project_root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let tera =
+            load_templates(&[project_root.join("templates/def_version.rs.template")]).unwrap();
+        assert_eq!(
+            dv.gen_code_with_tera(&tera, &Default::default()).unwrap(),
+            "/// the version of the spec, the client sends it in the (request :version ...)
+/// envelope
+pub const SPEC_VERSION: i64 = 3;"
+        );
+    }
+}
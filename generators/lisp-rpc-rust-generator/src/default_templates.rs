@@ -0,0 +1,98 @@
+//! the stock templates in the binary, the generator works without the
+//! templates folder. The templates of the folders override them
+
+/// the names and the contents of the stock templates
+pub const DEFAULT_TEMPLATES: &[(&str, &str)] = &[
+    (
+        "Cargo.toml",
+        include_str!("../templates/Cargo.toml.template"),
+    ),
+    (
+        "client_impl",
+        include_str!("../templates/client_impl.template"),
+    ),
+    (
+        "def_alias.rs",
+        include_str!("../templates/def_alias.rs.template"),
+    ),
+    (
+        "def_const.rs",
+        include_str!("../templates/def_const.rs.template"),
+    ),
+    (
+        "def_service.rs",
+        include_str!("../templates/def_service.rs.template"),
+    ),
+    (
+        "def_struct.rs",
+        include_str!("../templates/def_struct.rs.template"),
+    ),
+    (
+        "def_version.rs",
+        include_str!("../templates/def_version.rs.template"),
+    ),
+    ("docs.html", include_str!("../templates/docs.html.template")),
+    ("docs.md", include_str!("../templates/docs.md.template")),
+    (
+        "from_rpc_impl",
+        include_str!("../templates/from_rpc_impl.template"),
+    ),
+    (
+        "package.lisp",
+        include_str!("../templates/package.lisp.template"),
+    ),
+    (
+        "package.proto",
+        include_str!("../templates/package.proto.template"),
+    ),
+    (
+        "package.py",
+        include_str!("../templates/package.py.template"),
+    ),
+    (
+        "rpc_error.rs",
+        include_str!("../templates/rpc_error.rs.template"),
+    ),
+    ("rpc_impl", include_str!("../templates/rpc_impl.template")),
+    (
+        "rpc_method.rs",
+        include_str!("../templates/rpc_method.rs.template"),
+    ),
+    ("server.rs", include_str!("../templates/server.rs.template")),
+];
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::template_names;
+
+    #[test]
+    fn test_default_templates() {
+        // all templates of the folder are in the binary
+        let folder =
+            template_names(&[Path::new(env!("CARGO_MANIFEST_DIR")).join("templates")]).unwrap();
+        assert_eq!(
+            folder.keys().map(|n| n.as_str()).collect::<Vec<_>>(),
+            DEFAULT_TEMPLATES
+                .iter()
+                .map(|(n, _)| *n)
+                .collect::<Vec<_>>()
+        );
+
+        // the stock templates are the base of the overridden ones
+        let dir = std::env::temp_dir().join(format!("lisp-rpc-defaults-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("rpc_impl.template"),
+            r#"{% extends "base/rpc_impl" %}"#,
+        )
+        .unwrap();
+        let tera = crate::load_templates_over(DEFAULT_TEMPLATES, &[&dir]).unwrap();
+        let names: Vec<&str> = tera.get_template_names().collect();
+        assert_eq!(names.len(), DEFAULT_TEMPLATES.len() + 1);
+        assert!(names.contains(&"base/rpc_impl"));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,319 @@
+//! compare two versions of the spec, the breaking changes fail the clients
+//! or the servers of the old version
+
+use std::collections::BTreeMap;
+
+use lisp_rpc_rust_parser::{Atom, Expr, TypeValue, data::Violation};
+
+use crate::{SpecFields, map_type, optional_type, type_options};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChangeKind {
+    AddedSpec,
+    RemovedSpec,
+    /// the new field is optional or has the default value
+    AddedOptionalField,
+    AddedRequiredField,
+    RemovedField,
+    /// the old type and the new type
+    ChangedType(String, String),
+    ChangedReturnType(Option<String>, Option<String>),
+}
+
+impl ChangeKind {
+    pub fn is_breaking(&self) -> bool {
+        !matches!(self, Self::AddedSpec | Self::AddedOptionalField)
+    }
+}
+
+impl std::fmt::Display for ChangeKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::AddedSpec => write!(f, "added"),
+            Self::RemovedSpec => write!(f, "removed"),
+            Self::AddedOptionalField => write!(f, "added optional field"),
+            Self::AddedRequiredField => write!(f, "added required field"),
+            Self::RemovedField => write!(f, "removed field"),
+            Self::ChangedType(o, n) => write!(f, "changed type {} to {}", o, n),
+            Self::ChangedReturnType(o, n) => write!(
+                f,
+                "changed return type {} to {}",
+                o.as_deref().unwrap_or("nothing"),
+                n.as_deref().unwrap_or("nothing")
+            ),
+        }
+    }
+}
+
+/// the change of the spec, the path is the spec name and the keywords
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpecChange {
+    pub path: String,
+    pub kind: ChangeKind,
+}
+
+impl std::fmt::Display for SpecChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: {} ({})",
+            self.path,
+            self.kind,
+            match self.kind.is_breaking() {
+                true => "breaking",
+                false => "compatible",
+            }
+        )
+    }
+}
+
+/// all changes between the old spec and the new spec
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SpecDiff {
+    pub changes: Vec<SpecChange>,
+}
+
+impl SpecDiff {
+    pub fn is_breaking(&self) -> bool {
+        self.changes.iter().any(|c| c.kind.is_breaking())
+    }
+
+    pub fn breaking_changes(&self) -> impl Iterator<Item = &SpecChange> {
+        self.changes.iter().filter(|c| c.kind.is_breaking())
+    }
+
+    fn push(&mut self, path: &str, kind: ChangeKind) {
+        self.changes.push(SpecChange {
+            path: path.to_string(),
+            kind,
+        })
+    }
+}
+
+impl std::fmt::Display for SpecDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for c in &self.changes {
+            writeln!(f, "{}", c)?;
+        }
+        Ok(())
+    }
+}
+
+/// compare the msgs and the rpcs of two versions by their names
+pub(crate) fn diff<'a>(
+    old: impl IntoIterator<Item = SpecFields<'a>>,
+    new: impl IntoIterator<Item = SpecFields<'a>>,
+) -> SpecDiff {
+    let mut d = SpecDiff::default();
+    let old: BTreeMap<&str, SpecFields> = old.into_iter().map(|f| (f.name, f)).collect();
+    let new: Vec<SpecFields> = new.into_iter().collect();
+
+    for o in old.values() {
+        if !new.iter().any(|n| n.name == o.name) {
+            d.push(o.name, ChangeKind::RemovedSpec)
+        }
+    }
+
+    for n in &new {
+        let o = match old.get(n.name) {
+            Some(o) => o,
+            None => {
+                d.push(n.name, ChangeKind::AddedSpec);
+                continue;
+            }
+        };
+        diff_fields(n.name, &o.fields, &n.fields, &mut d);
+        if o.returns != n.returns {
+            d.push(
+                n.name,
+                ChangeKind::ChangedReturnType(
+                    o.returns.map(|r| r.to_string()),
+                    n.returns.map(|r| r.to_string()),
+                ),
+            )
+        }
+    }
+
+    d
+}
+
+/// the keyword-type pairs of the fields
+fn pairs(fields: &[Expr]) -> Vec<(&str, &Expr)> {
+    fields
+        .iter()
+        .array_chunks()
+        .filter_map(|[k, ty]| match k {
+            Expr::Atom(Atom {
+                value: TypeValue::Keyword(k),
+            }) => Some((k.as_str(), ty)),
+            _ => None,
+        })
+        .collect()
+}
+
+fn diff_fields(path: &str, old: &[Expr], new: &[Expr], d: &mut SpecDiff) {
+    let (old, new) = (pairs(old), pairs(new));
+
+    for (k, _) in &old {
+        if !new.iter().any(|(nk, _)| nk == k) {
+            d.push(&Violation::join_path(path, k), ChangeKind::RemovedField)
+        }
+    }
+
+    for (k, n_ty) in &new {
+        let field_path = Violation::join_path(path, k);
+        let o_ty = match old.iter().find(|(ok, _)| ok == k) {
+            Some((_, o_ty)) => o_ty,
+            None => {
+                let kind = match optional_type(n_ty).is_some()
+                    || type_options(n_ty).is_some_and(|o| o.default.is_some())
+                {
+                    true => ChangeKind::AddedOptionalField,
+                    false => ChangeKind::AddedRequiredField,
+                };
+                d.push(&field_path, kind);
+                continue;
+            }
+        };
+
+        match (anonymous_map(o_ty), anonymous_map(n_ty)) {
+            (Some(o), Some(n)) => diff_fields(&field_path, o, n, d),
+            _ => {
+                let (o, n) = (type_key(o_ty), type_key(n_ty));
+                if o != n {
+                    d.push(&field_path, ChangeKind::ChangedType(o, n))
+                }
+            }
+        }
+    }
+}
+
+/// the fields of the anonymous map type '(:a 'string)
+fn anonymous_map(ty: &Expr) -> Option<&[Expr]> {
+    match ty {
+        Expr::Quote(box Expr::List(inner)) | Expr::List(inner) => match inner.first() {
+            Some(Expr::Atom(Atom {
+                value: TypeValue::Keyword(_),
+            })) => Some(inner),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// the type without the quotes and the options, like the :doc, they don't
+/// change the data
+pub(crate) fn type_key(ty: &Expr) -> String {
+    if let Some(o) = type_options(ty) {
+        return type_key(o.inner);
+    }
+    if let Some(t) = optional_type(ty) {
+        return format!("(optional {})", t);
+    }
+    if let Some((k, v)) = map_type(ty) {
+        return format!("(map {} {})", k, v);
+    }
+
+    match ty {
+        Expr::Quote(box Expr::Atom(Atom {
+            value: TypeValue::Symbol(t),
+        })) => t.to_string(),
+        Expr::Quote(box Expr::List(inner)) | Expr::List(inner) => match inner.as_slice() {
+            [
+                Expr::Atom(Atom {
+                    value: TypeValue::Symbol(l),
+                }),
+                ele,
+            ] if l == "list" => format!("(list {})", type_key(ele)),
+            [
+                Expr::Atom(Atom {
+                    value: TypeValue::Keyword(_),
+                }),
+                ..,
+            ] => format!(
+                "({})",
+                pairs(inner)
+                    .iter()
+                    .map(|(k, t)| format!(":{} {}", k, type_key(t)))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            ),
+            _ => ty.to_string(),
+        },
+        _ => ty.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use lisp_rpc_rust_parser::Parser;
+
+    use crate::SpecFile;
+
+    fn spec(s: &str) -> SpecFile {
+        let mut specs = SpecFile::new();
+        for e in Parser::new().parse_root(Cursor::new(s)).unwrap() {
+            specs.record_expr(&e).unwrap();
+        }
+        specs
+    }
+
+    #[test]
+    fn test_diff() {
+        let old = spec(
+            r#"(def-msg book-info :title 'string :id 'number :tags '(list 'string) :extra '(:a 'string))
+(def-msg author :name 'string)
+(def-rpc get-book-info '(:title 'string) 'book-info)"#,
+        );
+
+        // only the doc and the quote are different
+        let same = spec(
+            r#"(def-msg book-info :title ('string :doc "the title") :id 'number :tags (list 'string) :extra '(:a 'string))
+(def-msg author :name 'string)
+(def-rpc get-book-info '(:title 'string) 'book-info)"#,
+        );
+        assert!(SpecFile::diff(&old, &same).changes.is_empty());
+
+        let compatible = spec(
+            r#"(def-msg book-info :title 'string :id 'number :tags '(list 'string) :extra '(:a 'string :b (optional 'number)))
+(def-msg author :name 'string :age ('number :default 0))
+(def-msg shelf :books '(list 'book-info))
+(def-rpc get-book-info '(:title 'string) 'book-info)"#,
+        );
+        let d = SpecFile::diff(&old, &compatible);
+        assert!(!d.is_breaking());
+        assert_eq!(
+            d.to_string(),
+            "book-info.extra.b: added optional field (compatible)
+author.age: added optional field (compatible)
+shelf: added (compatible)
+"
+        );
+
+        let breaking = spec(
+            r#"(def-msg book-info :title 'string :id 'string :extra '(:a 'number) :isbn 'string)
+(def-rpc get-book-info '(:title 'string) 'author)
+(def-msg author :name 'string)"#,
+        );
+        let d = SpecFile::diff(&old, &breaking);
+        assert!(d.is_breaking());
+        assert_eq!(
+            d.breaking_changes()
+                .map(|c| c.to_string())
+                .collect::<Vec<_>>(),
+            vec![
+                "book-info.tags: removed field (breaking)",
+                "book-info.id: changed type number to string (breaking)",
+                "book-info.extra.a: changed type string to number (breaking)",
+                "book-info.isbn: added required field (breaking)",
+                "get-book-info: changed return type book-info to author (breaking)",
+            ]
+        );
+        assert_eq!(
+            SpecFile::diff(&breaking, &old).changes[0].to_string(),
+            "book-info.isbn: removed field (breaking)"
+        );
+    }
+}
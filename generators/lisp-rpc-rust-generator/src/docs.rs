@@ -0,0 +1,288 @@
+//! render the api documents of the specs, every package, msg, and rpc with
+//! the field types, the return types, and the docstrings
+
+use std::str::FromStr;
+
+use anyhow::Result;
+use lisp_rpc_rust_parser::{Atom, Expr, TypeValue};
+use serde::Serialize;
+use tera::{Context, Tera};
+
+use crate::{SpecFields, diff::type_key, map_type, optional_type, type_options};
+
+/// the format of the api documents
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DocFormat {
+    Markdown,
+    Html,
+}
+
+impl DocFormat {
+    /// the template rendering this format
+    pub fn template(&self) -> &'static str {
+        match self {
+            Self::Markdown => "docs.md",
+            Self::Html => "docs.html",
+        }
+    }
+
+    /// the document file in the generated crate
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            Self::Markdown => "API.md",
+            Self::Html => "API.html",
+        }
+    }
+}
+
+impl FromStr for DocFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "markdown" | "md" => Ok(Self::Markdown),
+            "html" => Ok(Self::Html),
+            _ => anyhow::bail!("unknown doc format {}, it can be markdown or html", s),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct DocField {
+    key: String,
+    ty: String,
+
+    /// the msg of the type, the document links to it
+    msg: Option<String>,
+    doc: Option<String>,
+    default: Option<String>,
+    deprecated: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct DocItem {
+    /// msg, rpc, or notify
+    kind: &'static str,
+    name: String,
+    doc: Option<String>,
+    fields: Vec<DocField>,
+    returns: Option<String>,
+
+    /// the return type is the msg, the document links to it
+    returns_msg: bool,
+
+    /// the declared error codes of the rpc
+    errors: Vec<String>,
+    since: Option<i64>,
+    until: Option<i64>,
+}
+
+/// the specs of the package, the name is None for the specs before any
+/// package
+#[derive(Debug, Serialize)]
+pub(crate) struct DocPackage {
+    name: Option<String>,
+    items: Vec<DocItem>,
+}
+
+impl DocPackage {
+    pub(crate) fn new(name: Option<String>) -> Self {
+        Self {
+            name,
+            items: vec![],
+        }
+    }
+
+    pub(crate) fn push(&mut self, f: SpecFields<'_>) {
+        self.items.push(DocItem {
+            kind: match (f.notify, f.is_rpc) {
+                (true, _) => "notify",
+                (false, true) => "rpc",
+                (false, false) => "msg",
+            },
+            name: f.name.to_string(),
+            doc: f.doc.map(|d| d.to_string()),
+            fields: f
+                .fields
+                .iter()
+                .array_chunks()
+                .filter_map(|[k, ty]| match k {
+                    Expr::Atom(Atom {
+                        value: TypeValue::Keyword(k),
+                    }) => Some(doc_field(k, ty)),
+                    _ => None,
+                })
+                .collect(),
+            returns: f.returns.map(|r| r.to_string()),
+            returns_msg: false,
+            errors: f.errors.to_vec(),
+            since: f.versions.since,
+            until: f.versions.until,
+        })
+    }
+
+    fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+}
+
+fn doc_field(key: &str, ty: &Expr) -> DocField {
+    let options = type_options(ty);
+    DocField {
+        key: key.to_string(),
+        ty: type_key(ty),
+        msg: type_name(ty).map(|t| t.to_string()),
+        doc: options.as_ref().and_then(|o| o.doc).map(|d| d.to_string()),
+        default: options
+            .as_ref()
+            .and_then(|o| o.default)
+            .map(|d| d.to_string()),
+        deprecated: options
+            .as_ref()
+            .and_then(|o| o.deprecated)
+            .map(|d| d.to_string()),
+    }
+}
+
+/// the named type inside the type, like book-info of (list 'book-info)
+fn type_name(ty: &Expr) -> Option<&str> {
+    if let Some(o) = type_options(ty) {
+        return Some(o.ty);
+    }
+    if let Some(t) = optional_type(ty) {
+        return Some(t);
+    }
+    if let Some((_, v)) = map_type(ty) {
+        return Some(v);
+    }
+
+    match ty {
+        Expr::Quote(box Expr::Atom(Atom {
+            value: TypeValue::Symbol(t),
+        })) => Some(t),
+        Expr::Quote(box Expr::List(inner)) | Expr::List(inner) => match inner.as_slice() {
+            [
+                Expr::Atom(Atom {
+                    value: TypeValue::Symbol(l),
+                }),
+                ele,
+            ] if l == "list" => type_name(ele),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// render the document of the packages, the types of the fields link to the
+/// msgs defined in the specs
+pub(crate) fn render_docs(
+    templates: &Tera,
+    title: &str,
+    version: Option<i64>,
+    mut packages: Vec<DocPackage>,
+    format: DocFormat,
+) -> Result<String> {
+    packages.retain(|p| !p.is_empty());
+
+    let msgs: Vec<String> = packages
+        .iter()
+        .flat_map(|p| &p.items)
+        .filter(|i| i.kind == "msg")
+        .map(|i| i.name.clone())
+        .collect();
+    for i in packages.iter_mut().flat_map(|p| &mut p.items) {
+        i.returns_msg = i.returns.as_ref().is_some_and(|r| msgs.contains(r));
+        for f in &mut i.fields {
+            f.msg = f.msg.take().filter(|m| msgs.contains(m));
+        }
+    }
+
+    let mut context = Context::new();
+    context.insert("title", title);
+    context.insert("version", &version);
+    context.insert("packages", &packages);
+    Ok(templates.render(format.template(), &context)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use lisp_rpc_rust_parser::Parser;
+
+    use super::*;
+    use crate::SpecFile;
+
+    #[test]
+    fn test_render_docs() {
+        let mut specs = SpecFile::new();
+        for e in Parser::new()
+            .parse_root(Cursor::new(
+                r#"(def-rpc-package library)
+(def-version 2)
+(def-msg book-info "the book" :title ('string :doc "the title | name") :tags '(list 'string) :lang (optional 'language-perfer))
+(def-msg language-perfer :lang ('string :default "en" :deprecated "use locale"))
+(def-rpc get-book :since 2 '(:title 'string :extra '(:a 'number)) 'book-info)"#,
+            ))
+            .unwrap()
+        {
+            specs.record_expr(&e).unwrap();
+        }
+
+        let mut tera = Tera::default();
+        tera.add_raw_templates(vec![
+            ("docs.md", include_str!("../templates/docs.md.template")),
+            ("docs.html", include_str!("../templates/docs.html.template")),
+        ])
+        .unwrap();
+
+        assert_eq!(
+            specs.gen_docs(&tera, DocFormat::Markdown).unwrap(),
+            r#"# library
+
+spec version 2
+
+## book-info
+
+*msg*
+
+the book
+
+| keyword | type | description |
+|---|---|---|
+| `:title` | `string` | the title \| name |
+| `:tags` | `(list string)` |  |
+| `:lang` | [`(optional language-perfer)`](#language-perfer) |  |
+
+## language-perfer
+
+*msg*
+
+| keyword | type | description |
+|---|---|---|
+| `:lang` | `string` | default `"en"`, **deprecated**: use locale |
+
+## get-book
+
+*rpc*, since version 2
+
+| keyword | type | description |
+|---|---|---|
+| `:title` | `string` |  |
+| `:extra` | `(:a number)` |  |
+
+returns [`book-info`](#book-info)"#
+        );
+
+        let html = specs.gen_docs(&tera, DocFormat::Html).unwrap();
+        assert!(html.contains(r#"<h2 id="book-info">book-info</h2>"#));
+        assert!(html.contains(
+            r##"<td><a href="#language-perfer"><code>(optional language-perfer)</code></a></td>"##
+        ));
+        assert!(html.contains(
+            "<td>default <code>&quot;en&quot;</code>, <strong>deprecated</strong>: use locale</td>"
+        ));
+        assert_eq!("md".parse::<DocFormat>().unwrap(), DocFormat::Markdown);
+        assert!("pdf".parse::<DocFormat>().is_err());
+    }
+}
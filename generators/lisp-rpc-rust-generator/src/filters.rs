@@ -0,0 +1,83 @@
+//! the tera filters of the templates, the names in the templates can be
+//! transformed instead of using the pre-baked context keys:
+//!
+//! + `pascal`, `snake`, `camel`, `kebab`: the naming conventions, the input
+//!   can be any of them, like `{{ data_name | pascal }}`
+//! + `rpc_type`: the rust type of the spec type, like `{{ "number" | rpc_type }}`
+
+use std::collections::HashMap;
+
+use tera::{Tera, Value};
+
+use crate::{
+    kebab_to_camel_case, kebab_to_pascal_case, kebab_to_snake_case, to_kebab_case, type_translate,
+};
+
+/// register the filters into the tera
+pub fn register_filters(tera: &mut Tera) {
+    tera.register_filter(
+        "pascal",
+        string_filter("pascal", |s| kebab_to_pascal_case(&to_kebab_case(s))),
+    );
+    tera.register_filter(
+        "snake",
+        string_filter("snake", |s| kebab_to_snake_case(&to_kebab_case(s))),
+    );
+    tera.register_filter(
+        "camel",
+        string_filter("camel", |s| kebab_to_camel_case(&to_kebab_case(s))),
+    );
+    tera.register_filter("kebab", string_filter("kebab", to_kebab_case));
+    tera.register_filter("rpc_type", string_filter("rpc_type", type_translate));
+}
+
+/// the filter of the string value
+fn string_filter(
+    name: &'static str,
+    f: fn(&str) -> String,
+) -> impl Fn(&Value, &HashMap<String, Value>) -> tera::Result<Value> {
+    move |v, _| match v.as_str() {
+        Some(s) => Ok(Value::String(f(s))),
+        None => Err(tera::Error::msg(format!(
+            "the {} filter needs the string, got {}",
+            name, v
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tera::Context;
+
+    use super::*;
+
+    #[test]
+    fn test_filters() {
+        let mut tera = Tera::default();
+        register_filters(&mut tera);
+        let mut context = Context::new();
+        context.insert("name", "book-info");
+        context.insert("rust_name", "BookInfo");
+
+        for (template, expect) in [
+            ("{{ name | pascal }}", "BookInfo"),
+            ("{{ name | snake }}", "book_info"),
+            ("{{ name | camel }}", "bookInfo"),
+            ("{{ name | kebab }}", "book-info"),
+            ("{{ rust_name | snake }}", "book_info"),
+            ("{{ rust_name | kebab | camel }}", "bookInfo"),
+            ("{{ \"book_info\" | pascal }}", "BookInfo"),
+            ("{{ \"number\" | rpc_type }}", "i64"),
+            ("{{ \"language-perfer\" | rpc_type }}", "LanguagePerfer"),
+        ] {
+            assert_eq!(
+                tera.render_str(template, &context).unwrap(),
+                expect,
+                "{}",
+                template
+            );
+        }
+
+        assert!(tera.render_str("{{ 1 | pascal }}", &context).is_err());
+    }
+}
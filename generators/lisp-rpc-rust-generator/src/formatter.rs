@@ -0,0 +1,99 @@
+//! the formatter of the generated rust files. The templates render the
+//! whitespace as they are, the formatter makes the files look like the
+//! handwritten code, and the diffs of the generated crates are only the
+//! changes of the specs
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::str::FromStr;
+
+use anyhow::{Context, Result};
+
+use crate::format_rust;
+
+/// the formatter of the generated rust files
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RustFormatter {
+    /// the rustfmt command, it keeps the comments
+    Rustfmt,
+    /// the prettyplease library, the normal comments are dropped
+    Prettyplease,
+}
+
+impl RustFormatter {
+    /// the formatted rust code, the invalid rust fails
+    pub fn format(&self, code: &str) -> Result<String> {
+        match self {
+            Self::Rustfmt => rustfmt(code),
+            Self::Prettyplease => format_rust(code),
+        }
+    }
+}
+
+impl FromStr for RustFormatter {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "rustfmt" => Ok(Self::Rustfmt),
+            "prettyplease" => Ok(Self::Prettyplease),
+            _ => anyhow::bail!("unknown formatter {}, it can be rustfmt or prettyplease", s),
+        }
+    }
+}
+
+/// format the code by the rustfmt in the PATH through the stdin
+fn rustfmt(code: &str) -> Result<String> {
+    let mut child = Command::new("rustfmt")
+        .args(["--edition", "2024"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("Failed to run rustfmt, is it installed?")?;
+
+    // rustfmt reads all the stdin before writing, dropping the stdin ends it
+    child
+        .stdin
+        .take()
+        .context("no stdin of rustfmt")?
+        .write_all(code.as_bytes())?;
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "rustfmt failed:\n{}",
+            String::from_utf8_lossy(&output.stderr).trim_end()
+        )
+    }
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_formatter() {
+        let code =
+            "// the book\npub struct BookInfo { title: String,\n\n\n    tags: Vec<String> }\n";
+        assert_eq!(
+            RustFormatter::Prettyplease.format(code).unwrap(),
+            "pub struct BookInfo {\n    title: String,\n    tags: Vec<String>,\n}\n"
+        );
+        assert!(RustFormatter::Prettyplease.format("pub struct {").is_err());
+        assert_eq!(
+            "prettyplease".parse::<RustFormatter>().unwrap(),
+            RustFormatter::Prettyplease
+        );
+        assert!("gofmt".parse::<RustFormatter>().is_err());
+
+        // the rustfmt can be missing in the PATH
+        if Command::new("rustfmt").arg("--version").output().is_ok() {
+            assert_eq!(
+                RustFormatter::Rustfmt.format(code).unwrap(),
+                "// the book\npub struct BookInfo {\n    title: String,\n\n    tags: Vec<String>,\n}\n"
+            );
+            assert!(RustFormatter::Rustfmt.format("pub struct {").is_err());
+        }
+    }
+}
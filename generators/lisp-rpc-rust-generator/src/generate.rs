@@ -0,0 +1,221 @@
+//! the generator as the library, the specs to the generated files in the
+//! memory. The build scripts and the services generate the code by it
+//! without running the generator command
+
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+use crate::{DocFormat, GENERATED_MARKER, GenOptions, SpecFile};
+
+/// what is generated from the specs, same as the flags of the generator
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum GenerateTarget {
+    /// the rust crate by the templates
+    #[default]
+    Rust,
+    /// the rust crate by the syn tokens, the options of the rust code are
+    /// ignored
+    Tokens,
+    /// the api document, API.md or API.html
+    Docs(DocFormat),
+    /// the json schemas of the msgs in the schemas folder
+    JsonSchema,
+    /// the proto3 files of the packages in the proto folder
+    Proto,
+    /// the common lisp package in the lisp folder
+    Lisp,
+    /// the python module in the python folder
+    Python,
+}
+
+/// the options of generate
+#[derive(Debug, Clone)]
+pub struct GenerateOptions {
+    /// the options of the generated code and of reading the specs
+    pub options: GenOptions,
+
+    pub target: GenerateTarget,
+
+    /// the local templates folders, the later ones override the earlier ones
+    /// and the stock templates
+    pub templates: Vec<PathBuf>,
+}
+
+impl Default for GenerateOptions {
+    /// the rust crate by the stock templates
+    fn default() -> Self {
+        Self {
+            options: GenOptions {
+                default_templates: true,
+                ..Default::default()
+            },
+            target: GenerateTarget::default(),
+            templates: vec![],
+        }
+    }
+}
+
+/// the generated files of the specs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GeneratedOutput {
+    /// the name of the first package, the folder of the files
+    pub package: String,
+
+    /// the files relative to the package folder and their contents, with the
+    /// generated marker and the stamp
+    pub files: Vec<(PathBuf, String)>,
+}
+
+impl GeneratedOutput {
+    /// the content of the file, like src/lib.rs
+    pub fn file(&self, path: impl AsRef<Path>) -> Option<&str> {
+        self.files
+            .iter()
+            .find(|(p, _)| p == path.as_ref())
+            .map(|(_, c)| c.as_str())
+    }
+
+    /// write the files to the package folder of the output path. The files
+    /// generated before are overwritten, the other files are kept unless it
+    /// is forced. The file same as the generated one isn't written again
+    pub fn write_to(&self, output_path: &Path, force: bool) -> Result<()> {
+        let crate_path = output_path.join(&self.package);
+        let files: Vec<(PathBuf, &str)> = self
+            .files
+            .iter()
+            .map(|(p, c)| (crate_path.join(p), c.as_str()))
+            .collect();
+
+        // only the generated files are overwritten, unless it is forced. All
+        // files are checked before writing any
+        let mut unchanged = vec![];
+        for (file_path, content) in &files {
+            if !file_path.exists() {
+                continue;
+            }
+            let old = fs::read_to_string(file_path)
+                .with_context(|| format!("Failed to read file: {:?}", file_path))?;
+            // the json has no comments, the marker is the "$comment" on
+            // the second line
+            if !force && !old.lines().take(2).any(|l| l.contains(GENERATED_MARKER)) {
+                anyhow::bail!(
+                    "{:?} is not generated by lisp-rpc, use --force to overwrite it",
+                    file_path
+                )
+            }
+            if old == *content {
+                unchanged.push(file_path.clone());
+            }
+        }
+
+        // start to create files
+        for (file_path, content) in files {
+            if unchanged.contains(&file_path) {
+                continue;
+            }
+
+            // create the parents
+            if let Some(parent) = file_path.parent() {
+                fs::create_dir_all(parent)
+                    .with_context(|| format!("Failed to create directory: {:?}", parent))?;
+            }
+
+            fs::write(&file_path, content)
+                .with_context(|| format!("Failed to write file: {:?}", file_path))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// generate the files of the spec source in the memory, the includes of the
+/// spec are relative to the current folder
+pub fn generate(spec: impl Read, opts: GenerateOptions) -> Result<GeneratedOutput> {
+    SpecFile::new()
+        .config_options(opts.options)
+        .read_from(spec)?
+        .generate(opts.target, &opts.templates)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate() {
+        let spec = r#"(def-rpc-package demo)
+(def-msg book-info :title 'string)
+(def-rpc get-book '(:title 'string) 'book-info)"#;
+
+        let output = generate(spec.as_bytes(), GenerateOptions::default()).unwrap();
+        assert_eq!(output.package, "demo");
+        assert_eq!(
+            output
+                .files
+                .iter()
+                .map(|(p, _)| p.to_string_lossy())
+                .collect::<Vec<_>>(),
+            vec!["src/lib.rs", "Cargo.toml"]
+        );
+        let lib = output.file("src/lib.rs").unwrap();
+        assert!(lib.starts_with(&format!("// {}\n", GENERATED_MARKER)));
+        assert!(lib.contains("pub struct BookInfo {"));
+        assert!(lib.contains("pub struct GetBook {"));
+        assert!(
+            output
+                .file("Cargo.toml")
+                .unwrap()
+                .contains("name = \"demo\"")
+        );
+
+        let output = generate(
+            spec.as_bytes(),
+            GenerateOptions {
+                target: GenerateTarget::Docs(DocFormat::Markdown),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert!(output.file("API.md").unwrap().contains("get-book"));
+
+        // the problems of the spec fail before generating
+        assert!(generate("(def-msg book :lang 'nope)".as_bytes(), Default::default()).is_err());
+
+        // the output is written to the package folder
+        let dir = std::env::temp_dir().join(format!("lisp-rpc-generate-{}", std::process::id()));
+        let output = generate(spec.as_bytes(), GenerateOptions::default()).unwrap();
+        output.write_to(&dir, false).unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.join("demo/src/lib.rs")).unwrap(),
+            output.file("src/lib.rs").unwrap()
+        );
+        fs::write(dir.join("demo/Cargo.toml"), "[package]").unwrap();
+        assert!(output.write_to(&dir, false).is_err());
+        output.write_to(&dir, true).unwrap();
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_generate_in_order() {
+        let mut spec = "(def-rpc-package demo)".to_string();
+        for i in 0..50 {
+            spec += &format!("\n(def-msg msg-{} :id 'string)", i);
+        }
+
+        // the specs rendered in parallel are in the spec order
+        let output = generate(spec.as_bytes(), GenerateOptions::default()).unwrap();
+        let lib = output.file("src/lib.rs").unwrap();
+        let positions: Vec<usize> = (0..50)
+            .map(|i| lib.find(&format!("pub struct Msg{} {{", i)).unwrap())
+            .collect();
+        assert!(positions.is_sorted());
+        assert_eq!(
+            generate(spec.as_bytes(), GenerateOptions::default()).unwrap(),
+            output
+        );
+    }
+}
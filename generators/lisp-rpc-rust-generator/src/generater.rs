@@ -1,6 +1,97 @@
 use super::*;
+use lisp_rpc_rust_parser::{Atom, Expr, TypeValue};
 use serde::Serialize;
-use tera::Context;
+use std::collections::{BTreeMap, BTreeSet};
+use tera::{Context, Tera};
+
+/// the name of the optional template of the FromRPCData impls
+pub const FROM_RPC_TEMPLATE: &str = "from_rpc_impl";
+
+/// the name of the optional template of the typed client methods of the rpcs
+pub const CLIENT_TEMPLATE: &str = "client_impl";
+
+/// the name of the optional template of the enums of the declared errors of
+/// the rpcs
+pub const ERROR_TEMPLATE: &str = "rpc_error.rs";
+
+/// the name of the optional template of the enum of the methods of all rpcs
+pub const METHOD_TEMPLATE: &str = "rpc_method.rs";
+
+/// the name of the optional template of the server and the handler trait of
+/// all rpcs
+pub const SERVER_TEMPLATE: &str = "server.rs";
+
+/// the options of the generated code, they are the `options` in the templates
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct GenOptions {
+    /// the fields are pub, instead of the getters
+    pub pub_fields: bool,
+
+    /// generate the builders with the fluent setters
+    pub builders: bool,
+
+    /// generate the async client, service, and server. The futures are Send
+    pub async_api: bool,
+
+    /// derive the serde Serialize and Deserialize of the generated structs,
+    /// and add serde to the dependencies
+    pub serde: bool,
+
+    /// the groups of the mutually recursive struct names, the fields referring
+    /// the struct in the same group are boxed. SpecFile fills it with all specs
+    #[serde(skip)]
+    pub recursive: Vec<BTreeSet<String>>,
+
+    /// the rust names of the renamed structs, keyed by their default names.
+    /// SpecFile fills it with all specs
+    #[serde(skip)]
+    pub renames: BTreeMap<String, String>,
+
+    /// the def-alias is the type alias instead of the newtype
+    pub type_aliases: bool,
+
+    /// the names of the def-alias, the default values of their fields are
+    /// converted to them. SpecFile fills it with all specs
+    #[serde(skip)]
+    pub aliases: BTreeSet<String>,
+
+    /// write one file per spec and the lib.rs re-exporting them, instead of
+    /// all in the lib.rs. The package is the folder with the mod.rs
+    pub split_files: bool,
+
+    /// overwrite the output files even they aren't generated
+    pub force: bool,
+
+    /// the specs in different packages can have the same name, they are in
+    /// different modules. SpecFile checks it when reading the specs
+    pub allow_shadowing: bool,
+
+    /// the spec version of (def-version n), the client sends it in the
+    /// request envelope and the server checks it. SpecFile fills it
+    pub version: Option<i64>,
+
+    /// the templates are over the stock templates in the binary, the
+    /// templates only override some of them
+    pub default_templates: bool,
+
+    /// format the generated rust files before writing them, the invalid rust
+    /// fails the generating
+    #[serde(skip)]
+    pub format: Option<RustFormatter>,
+}
+
+impl GenOptions {
+    pub fn insert_template(&self, ctx: &mut Context) {
+        ctx.insert("options", self);
+    }
+
+    /// the renames of all specs and the renames of this spec
+    pub fn renames_with(&self, own: Vec<(String, String)>) -> BTreeMap<String, String> {
+        let mut renames = self.renames.clone();
+        renames.extend(own);
+        renames
+    }
+}
 
 #[derive(Debug, Serialize, PartialEq, Eq, Clone)]
 pub enum RPCDataType {
@@ -9,15 +100,64 @@ pub enum RPCDataType {
     Data,
 }
 
-#[derive(Debug, Serialize, PartialEq, Eq)]
+/// the literal of the default value ('type :default value)
+#[derive(Debug, Serialize, PartialEq, Eq, Clone)]
+#[serde(untagged)]
+pub enum DefaultValue {
+    Number(i64),
+    String(String),
+}
+
+impl DefaultValue {
+    /// the rust expr of the value
+    pub fn rust_expr(&self) -> String {
+        match self {
+            Self::Number(n) => n.to_string(),
+            Self::String(s) => format!("{:?}.to_string()", s),
+        }
+    }
+}
+
+/// the field of the GeneratedStruct. The name, the field_type, the comment,
+/// the default, and the deprecated are the rust code, the other languages
+/// use the key name, the spec type, the doc, and the flags
+#[derive(Debug, Serialize, PartialEq, Eq, Clone)]
 pub struct GeneratedField {
     pub name: String,
     pub field_type: String,
     pub comment: Option<String>,
 
+    /// the docstring of ('type :doc "doc"), the comment is the rust doc
+    /// comment of it
+    pub doc: Option<String>,
+
+    /// the spec type name, the element type of the list and the value type
+    /// of the map. The other languages translate it by themselves
+    pub spec_type: String,
+
     /// the original keyword name
     /// for insert the impl block of gen_data
     key_name: String,
+
+    /// the (optional 'type) field, it can be missing or nil
+    pub optional: bool,
+
+    /// the rust expr of the default value ('type :default value), it
+    /// fills the missing keyword
+    pub default: Option<String>,
+
+    /// the literal of the default value, the default is the rust expr of it
+    pub default_value: Option<DefaultValue>,
+
+    /// the (list 'type) field, it is encoded element by element
+    pub list: bool,
+
+    /// the (map 'string 'type) field
+    pub map: bool,
+
+    /// the rust string literal of the note ('type :deprecated "note"), the
+    /// field, the getter, and the setter are #[deprecated]
+    pub deprecated: Option<String>,
 }
 
 impl GeneratedField {
@@ -26,21 +166,664 @@ impl GeneratedField {
             name: kebab_to_snake_case(key_name),
             field_type: type_translate(field_type),
             comment,
+            doc: None,
+            spec_type: field_type.to_string(),
 
             key_name: key_name.to_string(),
+            optional: false,
+            default: None,
+            default_value: None,
+            list: false,
+            map: false,
+            deprecated: None,
+        }
+    }
+
+    /// the (list 'type) field, the elements can be the primary types or the msgs
+    pub fn new_list(key_name: &str, element_type: &str, comment: Option<String>) -> Self {
+        Self {
+            field_type: format!("Vec<{}>", type_translate(element_type)),
+            list: true,
+            ..Self::new(key_name, element_type, comment)
+        }
+    }
+
+    /// the (map 'string 'type) field, it is encoded as the keyword pairs '(:k v)
+    pub fn new_map(key_name: &str, value_type: &str, comment: Option<String>) -> Self {
+        Self {
+            field_type: format!(
+                "std::collections::HashMap<String, {}>",
+                type_translate(value_type)
+            ),
+            map: true,
+            ..Self::new(key_name, value_type, comment)
         }
     }
+
+    /// make this field optional, the type is wrapped by Option
+    pub fn optional(mut self) -> Self {
+        self.field_type = format!("Option<{}>", self.field_type);
+        self.optional = true;
+        self
+    }
+
+    /// the type of this field without the Option
+    fn struct_type(&self) -> &str {
+        match self.optional {
+            true => &self.field_type["Option<".len()..self.field_type.len() - 1],
+            false => &self.field_type,
+        }
+    }
+
+    /// give this field the default value, it is the rust expr
+    pub fn with_default(mut self, default: &str) -> Self {
+        self.default = Some(default.to_string());
+        self
+    }
+
+    /// give this field the default value of the literal
+    pub fn with_default_value(self, value: DefaultValue) -> Self {
+        Self {
+            default_value: Some(value.clone()),
+            ..self.with_default(&value.rust_expr())
+        }
+    }
+
+    /// give this field the docstring, it is the rust doc comment too
+    pub fn with_doc(mut self, doc: Option<&str>) -> Self {
+        self.comment = doc.map(doc_comment);
+        self.doc = doc.map(|d| d.to_string());
+        self
+    }
+
+    /// the original keyword name
+    pub fn key_name(&self) -> &str {
+        &self.key_name
+    }
+}
+
+/// the field of the optional type or the type with the options, None if the
+/// type is neither of them
+pub(crate) fn wrapped_field(k: &Expr, ty: &Expr) -> Result<Option<GeneratedField>> {
+    let f = match k {
+        Expr::Atom(Atom {
+            value: TypeValue::Keyword(f),
+        }) => f,
+        _ => return Ok(None),
+    };
+
+    if let Some(t) = optional_type(ty) {
+        return Ok(Some(GeneratedField::new(f, t, None).optional()));
+    }
+
+    if let Some((k_ty, v_ty)) = map_type(ty) {
+        if k_ty != "string" {
+            anyhow::bail!("the key type of the map {} can only be string", f)
+        }
+        return Ok(Some(GeneratedField::new_map(f, v_ty, None)));
+    }
+
+    match type_options(ty) {
+        Some(o) => {
+            let mut field = GeneratedField::new(f, o.ty, None).with_doc(o.doc);
+            if let Some(n) = o.rust_name {
+                if !is_rust_ident(n) {
+                    anyhow::bail!("the rust name {:?} of {} is not an identifier", n, f)
+                }
+                field.name = n.to_string();
+            }
+            field.deprecated = o.deprecated.map(|n| format!("{:?}", n));
+            if let Some(v) = o.default {
+                let default = match v {
+                    Expr::Atom(Atom {
+                        value: TypeValue::Number(n),
+                    }) => DefaultValue::Number(*n),
+                    Expr::Atom(Atom {
+                        value: TypeValue::String(s),
+                    }) => DefaultValue::String(s.to_string()),
+                    _ => anyhow::bail!("the default value of {} can only be number or string", f),
+                };
+                field = field.with_default_value(default);
+            }
+            Ok(Some(field))
+        }
+        None => Ok(None),
+    }
+}
+
+/// the key and value type names of the map type `(map 'key 'value)` or `'(map 'key 'value)`
+pub(crate) fn map_type(ty: &Expr) -> Option<(&str, &str)> {
+    match ty {
+        Expr::Quote(box Expr::List(inner)) | Expr::List(inner) => match inner.as_slice() {
+            [
+                Expr::Atom(Atom {
+                    value: TypeValue::Symbol(m),
+                }),
+                Expr::Quote(box Expr::Atom(Atom {
+                    value: TypeValue::Symbol(k),
+                })),
+                Expr::Quote(box Expr::Atom(Atom {
+                    value: TypeValue::Symbol(v),
+                })),
+            ] if m == "map" => Some((k, v)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// the inner type name of the optional type `(optional 'type)` or `'(optional 'type)`
+pub(crate) fn optional_type(ty: &Expr) -> Option<&str> {
+    match ty {
+        Expr::Quote(box Expr::List(inner)) | Expr::List(inner) => match inner.as_slice() {
+            [
+                Expr::Atom(Atom {
+                    value: TypeValue::Symbol(o),
+                }),
+                Expr::Quote(box Expr::Atom(Atom {
+                    value: TypeValue::Symbol(t),
+                })),
+            ] if o == "optional" => Some(t),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// the type with the options `('type :default value :doc "comment")`
+pub(crate) struct TypeOptions<'a> {
+    /// the quoted type expr inside
+    pub inner: &'a Expr,
+    pub ty: &'a str,
+    pub default: Option<&'a Expr>,
+    pub doc: Option<&'a str>,
+
+    /// the field name in rust, instead of the snake case keyword
+    pub rust_name: Option<&'a str>,
+
+    /// the note of the deprecated field
+    pub deprecated: Option<&'a str>,
+}
+
+/// parse the type with the options, quoted or not. None if it isn't this
+/// form or it has the unknown options
+pub(crate) fn type_options(ty: &Expr) -> Option<TypeOptions<'_>> {
+    let inner = match ty {
+        Expr::Quote(box Expr::List(inner)) | Expr::List(inner) => inner,
+        _ => return None,
+    };
+
+    let mut o = match inner.first() {
+        Some(
+            e @ Expr::Quote(box Expr::Atom(Atom {
+                value: TypeValue::Symbol(t),
+            })),
+        ) if inner.len() % 2 == 1 => TypeOptions {
+            inner: e,
+            ty: t,
+            default: None,
+            doc: None,
+            rust_name: None,
+            deprecated: None,
+        },
+        _ => return None,
+    };
+
+    for [k, v] in inner[1..].iter().array_chunks() {
+        match (k, v) {
+            (
+                Expr::Atom(Atom {
+                    value: TypeValue::Keyword(k),
+                }),
+                v,
+            ) if k == "default" => o.default = Some(v),
+            (
+                Expr::Atom(Atom {
+                    value: TypeValue::Keyword(k),
+                }),
+                Expr::Atom(Atom {
+                    value: TypeValue::String(s),
+                }),
+            ) if k == "doc" => o.doc = Some(s),
+            (
+                Expr::Atom(Atom {
+                    value: TypeValue::Keyword(k),
+                }),
+                Expr::Atom(Atom {
+                    value: TypeValue::String(s),
+                }),
+            ) if k == "rust-name" => o.rust_name = Some(s),
+            (
+                Expr::Atom(Atom {
+                    value: TypeValue::Keyword(k),
+                }),
+                Expr::Atom(Atom {
+                    value: TypeValue::String(s),
+                }),
+            ) if k == "deprecated" => o.deprecated = Some(s),
+            _ => return None,
+        }
+    }
+
+    Some(o)
+}
+
+/// one keyword-type pair of the msg or the rpc request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FieldSpec<'a> {
+    key: &'a str,
+    ty: &'a Expr,
+}
+
+impl<'a> FieldSpec<'a> {
+    /// the keyword without the colon
+    pub fn key(&self) -> &'a str {
+        self.key
+    }
+
+    /// the type expr as it is in the spec, with the options
+    pub fn ty(&self) -> &'a Expr {
+        self.ty
+    }
+
+    /// the type without the options, like string or (list book-info)
+    pub fn type_key(&self) -> String {
+        crate::diff::type_key(self.ty)
+    }
+
+    /// the field of (optional 'type) can be missing or nil
+    pub fn is_optional(&self) -> bool {
+        optional_type(self.ty).is_some()
+    }
+
+    pub fn default(&self) -> Option<&'a Expr> {
+        type_options(self.ty).and_then(|o| o.default)
+    }
+
+    pub fn doc(&self) -> Option<&'a str> {
+        type_options(self.ty).and_then(|o| o.doc)
+    }
+
+    /// the note of the deprecated field
+    pub fn deprecated(&self) -> Option<&'a str> {
+        type_options(self.ty).and_then(|o| o.deprecated)
+    }
+}
+
+/// the fields of the keyword-type pairs
+pub(crate) fn field_specs(pairs: &[Expr]) -> Vec<FieldSpec<'_>> {
+    pairs
+        .iter()
+        .array_chunks()
+        .filter_map(|[k, ty]| match k {
+            Expr::Atom(Atom {
+                value: TypeValue::Keyword(key),
+            }) => Some(FieldSpec { key, ty }),
+            _ => None,
+        })
+        .collect()
+}
+
+/// the lisp string literal of the text, the `"` and the `\` are escaped
+pub(crate) fn quote_string(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// the struct-level `:rust-name "Name"` right after the name and the
+/// docstring, and the rest exprs after it
+pub(crate) fn rust_name_option(rest: &[Expr]) -> Result<(Option<String>, &[Expr])> {
+    match rest {
+        [
+            Expr::Atom(Atom {
+                value: TypeValue::Keyword(k),
+            }),
+            Expr::Atom(Atom {
+                value: TypeValue::String(n),
+            }),
+            rest @ ..,
+        ] if k == "rust-name" => {
+            if !is_rust_ident(n) {
+                anyhow::bail!("the rust name {:?} is not an identifier", n)
+            }
+            Ok((Some(n.to_string()), rest))
+        }
+        _ => Ok((None, rest)),
+    }
+}
+
+/// the versions of the spec having the msg or the rpc, `:since 2 :until 4`
+/// are both inclusive
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct VersionRange {
+    pub since: Option<i64>,
+    pub until: Option<i64>,
+}
+
+impl VersionRange {
+    pub fn contains(&self, version: i64) -> bool {
+        self.since.is_none_or(|s| s <= version) && self.until.is_none_or(|u| version <= u)
+    }
+}
+
+/// the struct-level `:since n` and `:until n` after the rust name, and the
+/// rest exprs after them
+pub(crate) fn version_range_option(mut rest: &[Expr]) -> Result<(VersionRange, &[Expr])> {
+    let mut range = VersionRange::default();
+    while let [
+        Expr::Atom(Atom {
+            value: TypeValue::Keyword(k),
+        }),
+        Expr::Atom(Atom {
+            value: TypeValue::Number(n),
+        }),
+        tail @ ..,
+    ] = rest
+    {
+        let slot = match k.as_str() {
+            "since" => &mut range.since,
+            "until" => &mut range.until,
+            _ => break,
+        };
+        if slot.replace(*n).is_some() {
+            anyhow::bail!("the :{} is given twice", k)
+        }
+        rest = tail;
+    }
+    Ok((range, rest))
+}
+
+/// take the `(reserved :key ...)` out of the keyword-type pairs, return the
+/// rest pairs and the reserved keys. The reserved keys can't be the fields
+pub(crate) fn split_reserved(exprs: &[Expr]) -> Result<(Vec<Expr>, Vec<String>)> {
+    let mut rest = vec![];
+    let mut reserved = vec![];
+    let mut exprs = exprs.iter();
+    while let Some(e) = exprs.next() {
+        match e {
+            Expr::List(inner)
+                if matches!(inner.first(), Some(Expr::Atom(Atom {
+                    value: TypeValue::Symbol(r),
+                })) if r == "reserved") =>
+            {
+                for k in &inner[1..] {
+                    match k {
+                        Expr::Atom(Atom {
+                            value: TypeValue::Keyword(k),
+                        }) => reserved.push(k.to_string()),
+                        _ => anyhow::bail!("the reserved keys have to be keywords"),
+                    }
+                }
+            }
+            _ => {
+                rest.push(e.clone());
+                rest.extend(exprs.next().cloned());
+            }
+        }
+    }
+
+    for [k, _] in rest.iter().array_chunks() {
+        if let Expr::Atom(Atom {
+            value: TypeValue::Keyword(k),
+        }) = k
+            && reserved.contains(k)
+        {
+            anyhow::bail!("the keyword {} is reserved", k)
+        }
+    }
+
+    Ok((rest, reserved))
+}
+
+/// the first line of the written def- form, the name, the docstring, and the
+/// header options in the parsing order, like `(def-msg book "the book" :since 2`
+pub(crate) fn spec_head(
+    form: &str,
+    name: &str,
+    doc: Option<&str>,
+    rust_name: Option<&str>,
+    versions: VersionRange,
+) -> String {
+    let mut head = format!("({} {}", form, name);
+    if let Some(d) = doc {
+        head += &format!(" {}", quote_string(d));
+    }
+    if let Some(n) = rust_name {
+        head += &format!(" :rust-name {}", quote_string(n));
+    }
+    if let Some(v) = versions.since {
+        head += &format!(" :since {}", v);
+    }
+    if let Some(v) = versions.until {
+        head += &format!(" :until {}", v);
+    }
+    head
+}
+
+/// the written `:key type` lines of the fields, then the `(reserved ...)`
+/// line if any key is reserved. The lines have no indent
+pub(crate) fn spec_field_lines(fields: &[FieldSpec], reserved: &[String]) -> Vec<String> {
+    let mut lines: Vec<String> = fields
+        .iter()
+        .map(|f| format!(":{} {}", f.key(), f.ty()))
+        .collect();
+    if !reserved.is_empty() {
+        lines.push(format!(
+            "(reserved {})",
+            reserved
+                .iter()
+                .map(|r| format!(":{}", r))
+                .collect::<Vec<_>>()
+                .join(" ")
+        ));
+    }
+    lines
+}
+
+/// check the name can be the rust identifier, the raw identifier like r#type is ok
+fn is_rust_ident(n: &str) -> bool {
+    let n = n.strip_prefix("r#").unwrap_or(n);
+    n.chars()
+        .enumerate()
+        .all(|(i, c)| c == '_' || c.is_ascii_alphabetic() || (i > 0 && c.is_ascii_digit()))
+        && !n.is_empty()
+}
+
+/// replace the renamed struct names inside the type, like Vec<BookInfo>
+pub fn rename_type(ty: &str, renames: &BTreeMap<String, String>) -> String {
+    let mut res = String::new();
+    let mut ident = String::new();
+    for c in ty.chars().chain(std::iter::once(' ')) {
+        if c.is_ascii_alphanumeric() || c == '_' {
+            ident.push(c);
+            continue;
+        }
+        res += renames.get(&ident).unwrap_or(&ident);
+        ident.clear();
+        res.push(c);
+    }
+    res.pop();
+    res
+}
+
+/// rename the structs and the types of the fields referring them. It is
+/// after the boxing because the recursive groups are the default names
+pub fn rename_structs(
+    structs: Vec<GeneratedStruct>,
+    renames: &BTreeMap<String, String>,
+) -> Vec<GeneratedStruct> {
+    structs
+        .into_iter()
+        .map(|mut s| {
+            s.name = rename_type(&s.name, renames);
+            for f in &mut s.fields {
+                f.field_type = rename_type(&f.field_type, renames);
+            }
+            s
+        })
+        .collect()
+}
+
+/// convert the default values of the fields of the aliases, the newtype of
+/// the alias isn't its primary type
+pub fn alias_defaults(
+    structs: Vec<GeneratedStruct>,
+    aliases: &BTreeSet<String>,
+) -> Vec<GeneratedStruct> {
+    structs
+        .into_iter()
+        .map(|mut s| {
+            for f in &mut s.fields {
+                if let Some(d) = &f.default
+                    && aliases.contains(&f.spec_type)
+                {
+                    f.default = Some(format!("{}::from({})", type_translate(&f.spec_type), d));
+                }
+            }
+            s
+        })
+        .collect()
+}
+
+/// make the rustdoc comment of the docstring, every line starts with `///`
+pub fn doc_comment(doc: &str) -> String {
+    doc.lines()
+        .map(|l| format!("/// {}", l.trim()).trim_end().to_string())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// render the server dispatching to the handler of the rpcs, None if there
+/// is no rpc or the templates don't have the server.rs
+pub fn render_server(
+    templates: &Tera,
+    methods: &[RPCMethod],
+    options: &GenOptions,
+) -> Result<Option<String>> {
+    if methods.is_empty() || !templates.get_template_names().any(|n| n == SERVER_TEMPLATE) {
+        return Ok(None);
+    }
+
+    let mut context = Context::new();
+    options.insert_template(&mut context);
+    context.insert(
+        "rpcs",
+        &methods
+            .iter()
+            .map(|m| m.clone().renamed(&options.renames))
+            .collect::<Vec<_>>(),
+    );
+    Ok(Some(templates.render(SERVER_TEMPLATE, &context)?))
+}
+
+/// render the enum of the methods of the rpcs, None if there is no rpc or
+/// the templates don't have the rpc_method.rs
+pub fn render_methods(
+    templates: &Tera,
+    methods: &[RPCMethod],
+    options: &GenOptions,
+) -> Result<Option<String>> {
+    if methods.is_empty() || !templates.get_template_names().any(|n| n == METHOD_TEMPLATE) {
+        return Ok(None);
+    }
+
+    let mut context = Context::new();
+    options.insert_template(&mut context);
+    context.insert(
+        "variants",
+        &methods
+            .iter()
+            .map(|m| {
+                BTreeMap::from([
+                    ("name", kebab_to_pascal_case(&m.data_name)),
+                    ("symbol", m.data_name.clone()),
+                ])
+            })
+            .collect::<Vec<_>>(),
+    );
+    Ok(Some(templates.render(METHOD_TEMPLATE, &context)?))
+}
+
+/// the groups of the struct names referring each other directly (not through
+/// Vec), include the struct referring itself. The deps are the struct names
+/// and the struct names of their fields
+pub fn recursive_groups(deps: &[(String, Vec<String>)]) -> Vec<BTreeSet<String>> {
+    let reachable = |from: &str| {
+        let mut seen = BTreeSet::new();
+        let mut stack = vec![from];
+        while let Some(n) = stack.pop() {
+            for (_, refs) in deps.iter().filter(|(name, _)| name == n) {
+                for r in refs {
+                    if seen.insert(r.as_str()) {
+                        stack.push(r)
+                    }
+                }
+            }
+        }
+        seen
+    };
+
+    let mut groups: Vec<BTreeSet<String>> = vec![];
+    for (name, _) in deps {
+        let reach = reachable(name);
+        if !reach.contains(name.as_str()) || groups.iter().any(|g| g.contains(name)) {
+            continue;
+        }
+
+        groups.push(
+            reach
+                .into_iter()
+                .filter(|r| reachable(r).contains(name.as_str()))
+                .map(|r| r.to_string())
+                .collect(),
+        );
+    }
+    groups
+}
+
+/// box the fields of the structs referring the struct in the same recursive
+/// group, the groups of these structs themselves are included
+pub fn box_recursive(
+    structs: Vec<GeneratedStruct>,
+    recursive: &[BTreeSet<String>],
+) -> Vec<GeneratedStruct> {
+    let mut groups = recursive_groups(&structs.iter().map(|s| s.deps()).collect::<Vec<_>>());
+    groups.extend(recursive.iter().cloned());
+
+    structs
+        .into_iter()
+        .map(|mut s| {
+            for f in &mut s.fields {
+                let inner = f.struct_type();
+                if groups
+                    .iter()
+                    .any(|g| g.contains(&s.name) && g.contains(inner))
+                {
+                    f.field_type = match f.optional {
+                        true => format!("Option<Box<{}>>", inner),
+                        false => format!("Box<{}>", inner),
+                    };
+                }
+            }
+            s
+        })
+        .collect()
 }
 
 /// the GeneratedStruct is the middle layer between render and rpc spec (msg and rpc)
 /// def pkg is too simple, no need this
-#[derive(Debug, Serialize, PartialEq, Eq)]
+///
+/// the rust code is rendered from it, and the CodegenBackends of the other
+/// languages use the data name, the doc, and the spec-level fields of it
+#[derive(Debug, Serialize, PartialEq, Eq, Clone)]
 pub struct GeneratedStruct {
     pub name: String,
     pub derived_traits: Option<Vec<String>>,
     pub fields: Vec<GeneratedField>,
     pub comment: Option<String>,
 
+    /// the docstring of the spec, the comment is the rust doc comment of it
+    pub doc: Option<String>,
+
     /// the original data name
     /// for insert the impl block of gen_data
     data_name: String,
@@ -48,6 +831,9 @@ pub struct GeneratedStruct {
     /// different types have different data format
     /// this for detect which is which
     rpc_type: RPCDataType,
+
+    /// the versions of the msg or the rpc, they are the constants of the struct
+    pub versions: VersionRange,
 }
 
 impl GeneratedStruct {
@@ -63,16 +849,53 @@ impl GeneratedStruct {
             derived_traits,
             fields,
             comment,
+            doc: None,
 
             data_name: data_name.to_string(),
 
             rpc_type: ty,
+            versions: VersionRange::default(),
         }
     }
 
+    pub fn with_versions(mut self, versions: VersionRange) -> Self {
+        self.versions = versions;
+        self
+    }
+
+    /// give this struct the docstring, it is the rust doc comment too
+    pub fn with_doc(mut self, doc: Option<&str>) -> Self {
+        self.comment = doc.map(doc_comment);
+        self.doc = doc.map(|d| d.to_string());
+        self
+    }
+
+    /// the original data name
+    pub fn data_name(&self) -> &str {
+        &self.data_name
+    }
+
+    /// the anonymous map is encoded as '(:k v) instead of the data
+    pub fn rpc_type(&self) -> &RPCDataType {
+        &self.rpc_type
+    }
+
+    /// the name of this struct and the types of its fields
+    pub fn deps(&self) -> (String, Vec<String>) {
+        (
+            self.name.clone(),
+            self.fields
+                .iter()
+                .map(|f| f.struct_type().to_string())
+                .collect(),
+        )
+    }
+
     pub fn insert_template(&self, ctx: &mut Context) {
         ctx.insert("name", &self.name);
+        ctx.insert("comment", &self.comment);
         ctx.insert("fields", &self.fields);
+        ctx.insert("versions", &self.versions);
 
         match self.rpc_type {
             RPCDataType::Map => {
@@ -87,12 +910,33 @@ impl GeneratedStruct {
             }
         }
     }
+
+    /// render the struct, the ToRPCData impl, and the FromRPCData impl if
+    /// the templates have the from_rpc_impl
+    pub fn render(&self, templates: &Tera, ctx: &mut Context) -> Result<Vec<String>> {
+        self.insert_template(ctx);
+        let mut res = vec![
+            templates.render("def_struct.rs", ctx)?,
+            templates.render("rpc_impl", ctx)?,
+        ];
+        if templates
+            .get_template_names()
+            .any(|n| n == FROM_RPC_TEMPLATE)
+        {
+            res.push(templates.render(FROM_RPC_TEMPLATE, ctx)?);
+        }
+
+        Ok(res)
+    }
 }
 
 #[cfg(test)]
 mod tests {
 
+    use std::io::Cursor;
+
     use super::*;
+    use lisp_rpc_rust_parser::Parser;
     use tera::{Context, Tera};
 
     #[test]
@@ -112,8 +956,10 @@ mod tests {
                 GeneratedField::new("a", "number", None),
             ],
             comment: None,
+            doc: None,
             data_name: "name".to_string(),
             rpc_type: RPCDataType::Data,
+            versions: VersionRange::default(),
         };
 
         context.insert("name", &s.name);
@@ -126,6 +972,23 @@ mod tests {
 pub struct name {
     a: String,
     a: i64,
+}
+
+impl name {
+    pub fn new(a: String, a: i64) -> Self {
+        Self {
+            a,
+            a,
+        }
+    }
+
+    pub fn a(&self) -> &String {
+        &self.a
+    }
+
+    pub fn a(&self) -> &i64 {
+        &self.a
+    }
 }"#
         );
 
@@ -135,8 +998,10 @@ pub struct name {
             derived_traits: None,
             fields: vec![],
             comment: None,
+            doc: None,
             data_name: "name".to_string(),
             rpc_type: RPCDataType::Data,
+            versions: VersionRange::default(),
         };
 
         context.insert("name", &s.name);
@@ -147,6 +1012,128 @@ pub struct name {
             tera.render("test", &context).unwrap(),
             r#"#[derive(Debug)]
 pub struct name {
+}
+
+impl name {
+    pub fn new() -> Self {
+        Self {
+        }
+    }
+}"#
+        );
+
+        // pub fields, no getters
+        let s = GeneratedStruct::new(
+            "name",
+            None,
+            vec![GeneratedField::new("book-id", "number", None)],
+            None,
+            RPCDataType::Data,
+        );
+        let mut context = Context::new();
+        s.insert_template(&mut context);
+        GenOptions {
+            pub_fields: true,
+            ..Default::default()
+        }
+        .insert_template(&mut context);
+        assert_eq!(
+            tera.render("test", &context).unwrap(),
+            r#"#[derive(Debug)]
+pub struct Name {
+    pub book_id: i64,
+}
+
+impl Name {
+    pub fn new(book_id: i64) -> Self {
+        Self {
+            book_id,
+        }
+    }
+}"#
+        );
+
+        GenOptions {
+            pub_fields: true,
+            serde: true,
+            ..Default::default()
+        }
+        .insert_template(&mut context);
+        assert!(tera.render("test", &context).unwrap().starts_with(
+            r#"#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct Name {"#
+        ));
+    }
+
+    #[test]
+    fn test_generate_builder() {
+        let mut tera = Tera::default();
+        tera.add_raw_template("test", include_str!("../templates/def_struct.rs.template"))
+            .unwrap();
+
+        let s = GeneratedStruct::new(
+            "book-info",
+            None,
+            vec![
+                GeneratedField::new("title", "string", None),
+                GeneratedField::new("book-id", "number", None),
+            ],
+            None,
+            RPCDataType::Data,
+        );
+        let mut context = Context::new();
+        s.insert_template(&mut context);
+        GenOptions {
+            pub_fields: true,
+            builders: true,
+            ..Default::default()
+        }
+        .insert_template(&mut context);
+        assert_eq!(
+            tera.render("test", &context).unwrap(),
+            r#"#[derive(Debug)]
+pub struct BookInfo {
+    pub title: String,
+    pub book_id: i64,
+}
+
+impl BookInfo {
+    pub fn new(title: String, book_id: i64) -> Self {
+        Self {
+            title,
+            book_id,
+        }
+    }
+
+    pub fn builder() -> BookInfoBuilder {
+        Default::default()
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct BookInfoBuilder {
+    title: Option<String>,
+    book_id: Option<i64>,
+}
+
+impl BookInfoBuilder {
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    pub fn book_id(mut self, book_id: impl Into<i64>) -> Self {
+        self.book_id = Some(book_id.into());
+        self
+    }
+
+    /// all fields are required
+    pub fn build(self) -> Result<BookInfo, String> {
+        Ok(BookInfo {
+            title: self.title.ok_or("missing title")?,
+            book_id: self.book_id.ok_or("missing book-id")?,
+        })
+    }
 }"#
         );
     }
@@ -168,8 +1155,10 @@ pub struct name {
                 GeneratedField::new("a", "number", None),
             ],
             comment: None,
+            doc: None,
             data_name: "name".to_string(),
             rpc_type: RPCDataType::Data,
+            versions: VersionRange::default(),
         };
 
         context.insert("name", &s.name);
@@ -205,6 +1194,550 @@ pub struct name {
             self.a.to_rpc()
         )
     }
+}"#
+        );
+    }
+
+    #[test]
+    fn test_generate_from_rpc() {
+        let mut tera = Tera::default();
+        tera.add_raw_templates(vec![
+            (
+                "def_struct.rs",
+                include_str!("../templates/def_struct.rs.template"),
+            ),
+            ("rpc_impl", include_str!("../templates/rpc_impl.template")),
+            (
+                FROM_RPC_TEMPLATE,
+                include_str!("../templates/from_rpc_impl.template"),
+            ),
+        ])
+        .unwrap();
+
+        let s = GeneratedStruct::new(
+            "book-info",
+            None,
+            vec![
+                GeneratedField::new("title", "string", None),
+                GeneratedField::new("book-id", "number", None),
+            ],
+            None,
+            RPCDataType::Data,
+        );
+        let res = s.render(&tera, &mut Context::new()).unwrap();
+        assert_eq!(res.len(), 3);
+        assert_eq!(
+            res[2],
+            r#"impl FromRPCData for BookInfo {
+    fn from_rpc(d: &Data) -> Result<Self, DataError> {
+        let d = d.check_tag("book-info")?;
+        let (title, book_id) = (
+            GetAbleData::try_get(d, "title").and_then(FromRPCData::from_rpc),
+            GetAbleData::try_get(d, "book-id").and_then(FromRPCData::from_rpc),
+        );
+        // all missing or mismatched fields are in the error
+        DataError::check_fields(&[
+            ("title", title.as_ref().err()),
+            ("book-id", book_id.as_ref().err()),
+        ])?;
+        Ok(Self {
+            title: title?,
+            book_id: book_id?,
+        })
+    }
+}"#
+        );
+
+        let s = GeneratedStruct::new(
+            "get-book-lang",
+            None,
+            vec![GeneratedField::new("lang", "string", None)],
+            None,
+            RPCDataType::Map,
+        );
+        assert_eq!(
+            s.render(&tera, &mut Context::new()).unwrap()[2],
+            r#"impl FromRPCData for GetBookLang {
+    fn from_rpc(d: &Data) -> Result<Self, DataError> {
+        let (lang,) = (
+            GetAbleData::try_get(d, "lang").and_then(FromRPCData::from_rpc),
+        );
+        // all missing or mismatched fields are in the error
+        DataError::check_fields(&[
+            ("lang", lang.as_ref().err()),
+        ])?;
+        Ok(Self {
+            lang: lang?,
+        })
+    }
+}"#
+        );
+
+        // the templates without from_rpc_impl still work
+        let mut tera = Tera::default();
+        tera.add_raw_templates(vec![
+            (
+                "def_struct.rs",
+                include_str!("../templates/def_struct.rs.template"),
+            ),
+            ("rpc_impl", include_str!("../templates/rpc_impl.template")),
+        ])
+        .unwrap();
+        assert_eq!(s.render(&tera, &mut Context::new()).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_recursive_groups() {
+        let deps = |d: &[(&str, &[&str])]| -> Vec<(String, Vec<String>)> {
+            d.iter()
+                .map(|(n, refs)| (n.to_string(), refs.iter().map(|r| r.to_string()).collect()))
+                .collect()
+        };
+
+        let groups = recursive_groups(&deps(&[
+            ("Node", &["i64", "Node"]),
+            ("A", &["B"]),
+            ("B", &["C", "String"]),
+            ("C", &["A"]),
+            ("D", &["A"]),
+        ]));
+        assert_eq!(
+            groups,
+            vec![
+                BTreeSet::from(["Node".to_string()]),
+                BTreeSet::from(["A".to_string(), "B".to_string(), "C".to_string()]),
+            ]
+        );
+        assert!(recursive_groups(&deps(&[("A", &["B"]), ("B", &["String"])])).is_empty());
+
+        let structs = box_recursive(
+            vec![
+                GeneratedStruct::new(
+                    "a",
+                    None,
+                    vec![
+                        GeneratedField::new("b", "b", None).optional(),
+                        GeneratedField::new("c", "c", None),
+                    ],
+                    None,
+                    RPCDataType::Data,
+                ),
+                GeneratedStruct::new(
+                    "node",
+                    None,
+                    vec![GeneratedField::new("next", "node", None)],
+                    None,
+                    RPCDataType::Data,
+                ),
+            ],
+            &[BTreeSet::from(["A".to_string(), "B".to_string()])],
+        );
+        assert_eq!(structs[0].fields[0].field_type, "Option<Box<B>>");
+        assert_eq!(structs[0].fields[1].field_type, "C");
+        assert_eq!(structs[1].fields[0].field_type, "Box<Node>");
+    }
+
+    #[test]
+    fn test_rename_structs() {
+        let renames = BTreeMap::from([("Node".to_string(), "TreeNode".to_string())]);
+        assert_eq!(
+            rename_type("Option<Box<Node>>", &renames),
+            "Option<Box<TreeNode>>"
+        );
+        assert_eq!(
+            rename_type("std::collections::HashMap<String, Node>", &renames),
+            "std::collections::HashMap<String, TreeNode>"
+        );
+        assert_eq!(rename_type("NodeInfo", &renames), "NodeInfo");
+
+        let structs = rename_structs(
+            box_recursive(
+                vec![GeneratedStruct::new(
+                    "node",
+                    None,
+                    vec![GeneratedField::new_list("children", "node", None)],
+                    None,
+                    RPCDataType::Data,
+                )],
+                &[],
+            ),
+            &renames,
+        );
+        assert_eq!(structs[0].name, "TreeNode");
+        assert_eq!(structs[0].data_name, "node");
+        assert_eq!(structs[0].fields[0].field_type, "Vec<TreeNode>");
+
+        assert!(is_rust_ident("r#type"));
+        assert!(is_rust_ident("Book2"));
+        assert!(!is_rust_ident("2book"));
+        assert!(!is_rust_ident("book-info"));
+        assert!(!is_rust_ident(""));
+    }
+
+    #[test]
+    fn test_generate_server() {
+        let mut tera = Tera::default();
+        tera.add_raw_template(
+            SERVER_TEMPLATE,
+            include_str!("../templates/server.rs.template"),
+        )
+        .unwrap();
+
+        let methods: Vec<RPCMethod> = [
+            "(def-rpc get-book '(:title 'string) 'book-info)",
+            "(def-rpc return-book '(:id 'string))",
+        ]
+        .iter()
+        .map(|s| {
+            DefRPC::from_expr(&Parser::new().parse_root_one(Cursor::new(s)).unwrap())
+                .unwrap()
+                .method()
+        })
+        .collect();
+
+        let server = render_server(&tera, &methods, &GenOptions::default())
+            .unwrap()
+            .unwrap();
+        assert!(server.starts_with(
+            r#"/// the handler of all rpcs, the server calls it
+pub trait Handler {
+    fn get_book(&self, req: GetBook) -> BookInfo;
+    fn return_book(&self, req: ReturnBook);
+}"#
+        ));
+        assert!(server.contains(
+            r#"            "get-book" => match GetBook::from_rpc(d) {
+                Ok(req) => encode_response(self.handler.get_book(req)),
+                Err(e) => Data::error("bad-request", e.msg(), None),
+            },
+            "return-book" => match ReturnBook::from_rpc(d) {
+                Ok(req) => {
+                    self.handler.return_book(req);
+                    Data::ok(Data::Nil)
+                }
+                Err(e) => Data::error("bad-request", e.msg(), None),
+            },"#
+        ));
+
+        let server = render_server(
+            &tera,
+            &methods,
+            &GenOptions {
+                async_api: true,
+                ..Default::default()
+            },
+        )
+        .unwrap()
+        .unwrap();
+        assert!(server.starts_with(
+            r#"/// the handler of all rpcs, the server calls it
+pub trait Handler: Send + Sync {
+    fn get_book(&self, req: GetBook) -> impl std::future::Future<Output = BookInfo> + Send;
+    fn return_book(&self, req: ReturnBook) -> impl std::future::Future<Output = ()> + Send;
+}"#
+        ));
+        assert!(server.contains("    pub async fn dispatch(&self, d: &Data) -> Data {"));
+        assert!(server.contains("encode_response(self.handler.get_book(req).await)"));
+        assert!(server.contains(
+            r#"            (Some(id), Some(req)) => match self.dispatch(&req).await {
+                Data::Nil => Data::Nil,
+                resp => Data::reply(id, resp),
+            },"#
+        ));
+
+        assert!(
+            render_server(&tera, &[], &GenOptions::default())
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            render_server(&Tera::default(), &methods, &GenOptions::default())
+                .unwrap()
+                .is_none()
+        );
+
+        // the handler returns the Result of the declared errors
+        let lend = DefRPC::from_expr(
+            &Parser::new()
+                .parse_root_one(Cursor::new(
+                    "(def-rpc lend-book '(:id 'string) :errors ('not-found))",
+                ))
+                .unwrap(),
+        )
+        .unwrap()
+        .method();
+        let server = render_server(&tera, &[lend], &GenOptions::default())
+            .unwrap()
+            .unwrap();
+        assert!(
+            server.contains("    fn lend_book(&self, req: LendBook) -> Result<(), LendBookError>;")
+        );
+        assert!(server.contains(
+            r#"                Ok(req) => match self.handler.lend_book(req) {
+                    Ok(()) => Data::ok(Data::Nil),
+                    Err(e) => e.to_error_data(),
+                },"#
+        ));
+        assert!(!server.contains("The notifications respond nil"));
+
+        // the notification calls the hook and responds nil
+        let added = DefNotify::from_str("(def-notify book-added '(:id 'string))", None)
+            .unwrap()
+            .method();
+        let server = render_server(&tera, &[added], &GenOptions::default())
+            .unwrap()
+            .unwrap();
+        assert!(server.contains(
+            "    /// the hook of the book-added notification, it does nothing by default
+    fn on_book_added(&self, _msg: BookAdded) {}"
+        ));
+        assert!(server.contains(
+            r#"            "book-added" => {
+                if let Ok(msg) = BookAdded::from_rpc(d) {
+                    self.handler.on_book_added(msg);
+                }
+                Data::Nil
+            }"#
+        ));
+        assert!(
+            server.contains(
+                "    /// The notifications respond nil even they fail, the nil isn't sent"
+            )
+        );
+    }
+
+    #[test]
+    fn test_generate_methods() {
+        let mut tera = Tera::default();
+        tera.add_raw_template(
+            METHOD_TEMPLATE,
+            include_str!("../templates/rpc_method.rs.template"),
+        )
+        .unwrap();
+
+        let methods = vec![
+            DefRPC::from_expr(
+                &Parser::new()
+                    .parse_root_one(Cursor::new(
+                        "(def-rpc get-book '(:title 'string) 'book-info)",
+                    ))
+                    .unwrap(),
+            )
+            .unwrap()
+            .method(),
+            DefNotify::from_str("(def-notify book-added '(:id 'string))", None)
+                .unwrap()
+                .method(),
+        ];
+        assert_eq!(
+            render_methods(&tera, &methods, &GenOptions::default())
+                .unwrap()
+                .unwrap(),
+            r#"/// the methods of all rpcs, the symbols are the data names of their requests
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RpcMethod {
+    GetBook,
+    BookAdded,
+}
+
+impl RpcMethod {
+    /// the data name of the request of this method
+    pub fn as_symbol(&self) -> &'static str {
+        match self {
+            Self::GetBook => "get-book",
+            Self::BookAdded => "book-added",
+        }
+    }
+
+    /// the method of the data name, None if no rpc has it
+    pub fn from_symbol(s: &str) -> Option<Self> {
+        match s {
+            "get-book" => Some(Self::GetBook),
+            "book-added" => Some(Self::BookAdded),
+            _ => None,
+        }
+    }
+}"#
+        );
+        assert!(
+            render_methods(&tera, &[], &GenOptions::default())
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_generate_default() {
+        let mut tera = Tera::default();
+        tera.add_raw_templates(vec![
+            (
+                "def_struct.rs",
+                include_str!("../templates/def_struct.rs.template"),
+            ),
+            ("rpc_impl", include_str!("../templates/rpc_impl.template")),
+            (
+                FROM_RPC_TEMPLATE,
+                include_str!("../templates/from_rpc_impl.template"),
+            ),
+        ])
+        .unwrap();
+
+        let s = GeneratedStruct::new(
+            "language-perfer",
+            None,
+            vec![
+                GeneratedField::new("lang", "string", None),
+                GeneratedField::new("encoding", "number", None).with_default("8"),
+            ],
+            None,
+            RPCDataType::Data,
+        );
+        let res = s.render(&tera, &mut Context::new()).unwrap();
+        assert!(res[0].ends_with(
+            r#"
+impl Default for LanguagePerfer {
+    fn default() -> Self {
+        Self {
+            lang: Default::default(),
+            encoding: 8,
+        }
+    }
+}"#
+        ));
+        assert_eq!(
+            res[2],
+            r#"impl FromRPCData for LanguagePerfer {
+    fn from_rpc(d: &Data) -> Result<Self, DataError> {
+        let d = d.check_tag("language-perfer")?;
+        let (lang, encoding) = (
+            GetAbleData::try_get(d, "lang").and_then(FromRPCData::from_rpc),
+            GetAbleData::get(d, "encoding")
+                .map(FromRPCData::from_rpc)
+                .transpose()
+                .map(|v| v.unwrap_or_else(|| 8)),
+        );
+        // all missing or mismatched fields are in the error
+        DataError::check_fields(&[
+            ("lang", lang.as_ref().err()),
+            ("encoding", encoding.as_ref().err()),
+        ])?;
+        Ok(Self {
+            lang: lang?,
+            encoding: encoding?,
+        })
+    }
+}"#
+        );
+
+        let mut context = Context::new();
+        GenOptions {
+            builders: true,
+            ..Default::default()
+        }
+        .insert_template(&mut context);
+        let res = s.render(&tera, &mut context).unwrap();
+        assert!(res[0].contains("            encoding: self.encoding.unwrap_or_else(|| 8),\n"));
+
+        // no Default impl without the default values
+        let s = GeneratedStruct::new(
+            "language-perfer",
+            None,
+            vec![GeneratedField::new("lang", "string", None)],
+            None,
+            RPCDataType::Data,
+        );
+        assert!(!s.render(&tera, &mut Context::new()).unwrap()[0].contains("impl Default"));
+    }
+
+    #[test]
+    fn test_generate_list() {
+        let mut tera = Tera::default();
+        tera.add_raw_template("rpc_impl", include_str!("../templates/rpc_impl.template"))
+            .unwrap();
+
+        let field = GeneratedField::new_list("books", "book-info", None);
+        assert_eq!(field.field_type, "Vec<BookInfo>");
+
+        let mut context = Context::new();
+        context.insert("name", "Shelf");
+        context.insert("data_name", "shelf");
+        context.insert("fields", &vec![field]);
+        context.insert("ty", "data");
+        assert_eq!(
+            tera.render("rpc_impl", &context).unwrap(),
+            r#"impl ToRPCData for Shelf {
+    fn to_rpc(&self) -> String {
+        format!(
+            "(shelf :books '({}))",
+            self.books
+                .iter()
+                .map(|e| e.to_rpc())
+                .collect::<Vec<_>>()
+                .join(" ")
+        )
+    }
+}"#
+        );
+    }
+
+    #[test]
+    fn test_generate_optional() {
+        let mut tera = Tera::default();
+        tera.add_raw_templates(vec![
+            ("rpc_impl", include_str!("../templates/rpc_impl.template")),
+            (
+                FROM_RPC_TEMPLATE,
+                include_str!("../templates/from_rpc_impl.template"),
+            ),
+        ])
+        .unwrap();
+
+        let field = GeneratedField::new("sub-title", "string", None).optional();
+        assert_eq!(field.field_type, "Option<String>");
+
+        let mut context = Context::new();
+        context.insert("name", "BookInfo");
+        context.insert("data_name", "book-info");
+        context.insert(
+            "fields",
+            &vec![GeneratedField::new("title", "string", None), field],
+        );
+        context.insert("ty", "data");
+        assert_eq!(
+            tera.render("rpc_impl", &context).unwrap(),
+            r#"impl ToRPCData for BookInfo {
+    fn to_rpc(&self) -> String {
+        let mut args: Vec<String> = vec!["book-info".to_string()];
+        args.push(format!(":title {}", self.title.to_rpc()));
+        if let Some(v) = &self.sub_title {
+            args.push(format!(":sub-title {}", v.to_rpc()));
+        }
+        format!("({})", args.join(" "))
+    }
+}"#
+        );
+        assert_eq!(
+            tera.render(FROM_RPC_TEMPLATE, &context).unwrap(),
+            r#"impl FromRPCData for BookInfo {
+    fn from_rpc(d: &Data) -> Result<Self, DataError> {
+        let d = d.check_tag("book-info")?;
+        let (title, sub_title) = (
+            GetAbleData::try_get(d, "title").and_then(FromRPCData::from_rpc),
+            GetAbleData::get(d, "sub-title")
+                .filter(|v| !v.is_nil())
+                .map(FromRPCData::from_rpc)
+                .transpose(),
+        );
+        // all missing or mismatched fields are in the error
+        DataError::check_fields(&[
+            ("title", title.as_ref().err()),
+            ("sub-title", sub_title.as_ref().err()),
+        ])?;
+        Ok(Self {
+            title: title?,
+            sub_title: sub_title?,
+        })
+    }
 }"#
         );
     }
@@ -0,0 +1,260 @@
+//! the json schemas of the msgs, one document per def-msg. They validate the
+//! data bridged by the Data::to_json of the parser:
+//!
+//! + the msg => object with the `"__name"` field
+//! + the anonymous map and the (map 'k 'v) => object
+//! + the keyword => `{"__keyword": "kw"}`, the bytes => `{"__bytes": "base64"}`
+//! + the optional field can be missing or null
+
+use lisp_rpc_rust_parser::{Atom, Expr, TypeValue};
+use serde_json::{Map, Value, json};
+
+use crate::{GENERATED_MARKER, SpecFields, map_type, optional_type, type_options};
+
+/// the json schema dialect of the documents
+pub const JSON_SCHEMA_DIALECT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+/// the file name of the schema of the msg, the other schemas $ref to it
+pub fn schema_file_name(msg: &str) -> String {
+    format!("{}.schema.json", msg)
+}
+
+/// the schema document of the msg, the msg types are the $ref to their own
+/// documents
+pub(crate) fn msg_schema(f: &SpecFields<'_>) -> Value {
+    let mut s = Map::new();
+    // the first key, the generator checks it before overwriting the file
+    s.insert("$comment".into(), GENERATED_MARKER.into());
+    s.insert("$schema".into(), JSON_SCHEMA_DIALECT.into());
+    s.insert("$id".into(), schema_file_name(f.name).into());
+    s.insert("title".into(), f.name.into());
+    if let Some(d) = f.doc {
+        s.insert("description".into(), d.into());
+    }
+
+    if let Value::Object(o) = object_schema(&f.fields, Some(f.name)) {
+        s.extend(o)
+    }
+    Value::Object(s)
+}
+
+/// the object of the keyword-type pairs, the data name is the `"__name"` field
+fn object_schema(fields: &[Expr], data_name: Option<&str>) -> Value {
+    let mut properties = Map::new();
+    let mut required = vec![];
+    if let Some(n) = data_name {
+        properties.insert("__name".into(), json!({ "const": n }));
+        required.push("__name".to_string());
+    }
+
+    for [k, ty] in fields.iter().array_chunks() {
+        let Expr::Atom(Atom {
+            value: TypeValue::Keyword(k),
+        }) = k
+        else {
+            continue;
+        };
+
+        let options = type_options(ty);
+        let mut field = field_schema(options.as_ref().map_or(ty, |o| o.inner));
+        if let Some(o) = &options
+            && let Value::Object(field) = &mut field
+        {
+            if let Some(d) = o.doc {
+                field.insert("description".into(), d.into());
+            }
+            if let Some(v) = o.default.and_then(default_value) {
+                field.insert("default".into(), v);
+            }
+            if o.deprecated.is_some() {
+                field.insert("deprecated".into(), true.into());
+            }
+        }
+
+        if optional_type(ty).is_none() && options.as_ref().is_none_or(|o| o.default.is_none()) {
+            required.push(k.to_string())
+        }
+        properties.insert(k.to_string(), field);
+    }
+
+    json!({
+        "type": "object",
+        "properties": properties,
+        "required": required,
+    })
+}
+
+/// the schema of the field type, without the options
+fn field_schema(ty: &Expr) -> Value {
+    if let Some(t) = optional_type(ty) {
+        return json!({ "anyOf": [type_schema(t), { "type": "null" }] });
+    }
+    if let Some((_, v)) = map_type(ty) {
+        return json!({ "type": "object", "additionalProperties": type_schema(v) });
+    }
+
+    match ty {
+        Expr::Quote(box Expr::Atom(Atom {
+            value: TypeValue::Symbol(t),
+        })) => type_schema(t),
+        Expr::Quote(box Expr::List(inner)) | Expr::List(inner) => match inner.as_slice() {
+            [
+                Expr::Atom(Atom {
+                    value: TypeValue::Symbol(l),
+                }),
+                ele,
+            ] if l == "list" => json!({ "type": "array", "items": field_schema(ele) }),
+            [
+                Expr::Atom(Atom {
+                    value: TypeValue::Keyword(_),
+                }),
+                ..,
+            ] => object_schema(inner, None),
+            _ => json!({}),
+        },
+        _ => json!({}),
+    }
+}
+
+/// the schema of the primary type, or the $ref to the msg
+fn type_schema(t: &str) -> Value {
+    match t {
+        "string" => json!({ "type": "string" }),
+        "number" => json!({ "type": "integer" }),
+        "uuid" => json!({ "type": "string", "format": "uuid" }),
+        "keyword" => json!({
+            "type": "object",
+            "properties": { "__keyword": { "type": "string" } },
+            "required": ["__keyword"],
+        }),
+        "bytes" => json!({
+            "type": "object",
+            "properties": { "__bytes": { "type": "string", "contentEncoding": "base64" } },
+            "required": ["__bytes"],
+        }),
+        // (timestamp seconds [nanoseconds]) or the RFC3339 string
+        "timestamp" => json!({
+            "anyOf": [
+                { "type": "string", "format": "date-time" },
+                {
+                    "type": "object",
+                    "properties": {
+                        "__name": { "const": "timestamp" },
+                        "__args": {
+                            "type": "array",
+                            "items": { "type": "integer" },
+                            "minItems": 1,
+                            "maxItems": 2,
+                        },
+                    },
+                    "required": ["__name", "__args"],
+                },
+            ],
+        }),
+        msg => json!({ "$ref": schema_file_name(msg) }),
+    }
+}
+
+/// the json of the literal default value, None for the rust exprs
+fn default_value(v: &Expr) -> Option<Value> {
+    match v {
+        Expr::Atom(Atom {
+            value: TypeValue::String(s),
+        }) => Some(s.as_str().into()),
+        Expr::Atom(Atom {
+            value: TypeValue::Number(n),
+        }) => Some((*n).into()),
+        Expr::Atom(Atom {
+            value: TypeValue::Symbol(s),
+        }) if s == "t" => Some(true.into()),
+        Expr::Atom(Atom {
+            value: TypeValue::Symbol(s),
+        }) if s == "nil" => Some(Value::Null),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use lisp_rpc_rust_parser::Parser;
+
+    use super::*;
+    use crate::SpecFile;
+
+    #[test]
+    fn test_msg_schema() {
+        let mut specs = SpecFile::new();
+        for e in Parser::new()
+            .parse_root(Cursor::new(
+                r#"(def-msg book-info "the book"
+    :title ('string :doc "the title")
+    :id 'number
+    :tags '(list 'string)
+    :lang (optional 'language-perfer)
+    :prices '(map 'string 'number)
+    :extra '(:kind 'keyword :cover ('bytes :deprecated "no cover")))
+(def-msg language-perfer :lang ('string :default "en"))
+(def-rpc get-book '(:title 'string) 'book-info)"#,
+            ))
+            .unwrap()
+        {
+            specs.record_expr(&e).unwrap();
+        }
+
+        let schemas = specs.gen_json_schemas();
+        assert_eq!(
+            schemas.iter().map(|(n, _)| n.as_str()).collect::<Vec<_>>(),
+            vec!["book-info.schema.json", "language-perfer.schema.json"]
+        );
+
+        assert_eq!(
+            schemas[0].1,
+            json!({
+                "$schema": JSON_SCHEMA_DIALECT,
+                "$id": "book-info.schema.json",
+                "$comment": GENERATED_MARKER,
+                "title": "book-info",
+                "description": "the book",
+                "type": "object",
+                "properties": {
+                    "__name": { "const": "book-info" },
+                    "title": { "type": "string", "description": "the title" },
+                    "id": { "type": "integer" },
+                    "tags": { "type": "array", "items": { "type": "string" } },
+                    "lang": {
+                        "anyOf": [{ "$ref": "language-perfer.schema.json" }, { "type": "null" }]
+                    },
+                    "prices": { "type": "object", "additionalProperties": { "type": "integer" } },
+                    "extra": {
+                        "type": "object",
+                        "properties": {
+                            "kind": {
+                                "type": "object",
+                                "properties": { "__keyword": { "type": "string" } },
+                                "required": ["__keyword"],
+                            },
+                            "cover": {
+                                "type": "object",
+                                "properties": {
+                                    "__bytes": { "type": "string", "contentEncoding": "base64" }
+                                },
+                                "required": ["__bytes"],
+                                "deprecated": true,
+                            },
+                        },
+                        "required": ["kind", "cover"],
+                    },
+                },
+                "required": ["__name", "title", "id", "tags", "prices", "extra"],
+            })
+        );
+
+        assert_eq!(
+            schemas[1].1["properties"]["lang"],
+            json!({ "type": "string", "default": "en" })
+        );
+        assert_eq!(schemas[1].1["required"], json!(["__name"]));
+    }
+}
@@ -1,25 +1,65 @@
 #![feature(iter_array_chunks)]
 #![feature(box_patterns)]
 
+pub mod backend;
+pub mod def_alias;
+pub mod def_const;
 pub mod def_msg;
+pub mod def_notify;
 pub mod def_package;
 pub mod def_rpc;
+pub mod def_service;
+pub mod def_version;
+pub mod default_templates;
+pub mod diff;
+pub mod docs;
+mod filters;
+pub mod formatter;
+pub mod generate;
 pub mod generater;
+pub mod json_schema;
+pub mod lisp;
+pub mod proto;
+pub mod proto_import;
+pub mod python;
+mod resolve;
+pub mod rust_tokens;
+pub mod template_set;
+mod validate;
 
 use anyhow::{Context, Result};
-use std::collections::HashMap;
+use lisp_rpc_rust_parser::{Atom, Expr, TypeValue, data::Violation};
+use rayon::prelude::*;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
 use std::error::Error;
-use std::fs::{File, OpenOptions};
-use std::io::Write;
+use std::io::{Cursor, Read};
 use std::path::{Path, PathBuf};
 use std::{default, env, fs};
 use tera::Tera;
 use url::Url;
 
+pub use backend::{CodegenBackend, CodegenUnit, GeneratedRPC};
+pub use def_alias::*;
+pub use def_const::*;
 pub use def_msg::*;
+pub use def_notify::*;
 pub use def_package::*;
 pub use def_rpc::*;
+pub use def_service::*;
+pub use def_version::*;
+pub use default_templates::DEFAULT_TEMPLATES;
+pub use diff::{ChangeKind, SpecChange, SpecDiff};
+pub use docs::DocFormat;
+pub use filters::register_filters;
+pub use formatter::RustFormatter;
+pub use generate::{GenerateOptions, GenerateTarget, GeneratedOutput, generate};
 pub use generater::*;
+pub use proto_import::import_proto;
+pub use resolve::SpecFields;
+pub use rust_tokens::{RustTokensBackend, format_rust};
+pub use template_set::{default_template_cache, fetch_template_set};
+use validate::*;
 
 #[derive(Debug)]
 enum SpecErrorType {
@@ -40,20 +80,101 @@ impl std::fmt::Display for SpecError {
 
 impl Error for SpecError {}
 
+/// the first line of the generated files, the files with it are overwritten
+/// when the generator runs again
+pub const GENERATED_MARKER: &str = "generated by lisp-rpc-rust-generator, do not edit";
+
 pub enum TargetFile {
     Lib,
     Cargo,
 }
 
-/// the trait for all spec
-pub trait RPCSpec {
+/// the trait for all spec, the specs are rendered in parallel
+pub trait RPCSpec: Send + Sync {
     fn symbol_name(&self) -> String;
 
-    fn gen_code_with_temp_files(&self, temp_file_paths: &[String]) -> Result<String>;
+    /// load the template files and generate the code of this spec. The
+    /// templates are compiled for every call
+    #[deprecated(note = "load the templates once by load_templates, and use gen_code_with_tera")]
+    fn gen_code_with_temp_files(&self, temp_file_paths: &[String]) -> Result<String> {
+        self.gen_code_with_tera(&load_templates(temp_file_paths)?, &GenOptions::default())
+    }
 
-    fn gen_code_with_tera(&self, templates: &Tera) -> Result<String>;
+    fn gen_code_with_tera(&self, templates: &Tera, options: &GenOptions) -> Result<String>;
 
     fn file_target(&self) -> TargetFile;
+
+    /// the formatted lisp-rpc source of this spec, reading it gives the same
+    /// spec again
+    fn to_spec_string(&self) -> String;
+
+    /// the names and the field types of the generated structs, for finding
+    /// the recursive structs
+    fn struct_deps(&self) -> Result<Vec<(String, Vec<String>)>> {
+        Ok(vec![])
+    }
+
+    /// the generated structs of the msgs and the rpcs in this spec, before
+    /// the boxing and the renaming of rust. The other languages use them
+    fn gen_structs(&self) -> Result<Vec<GeneratedStruct>> {
+        Ok(vec![])
+    }
+
+    /// the rpcs handled by the generated server, empty if this spec has none
+    fn rpc_methods(&self) -> Vec<RPCMethod> {
+        vec![]
+    }
+
+    /// the renamed structs, the default names to the rust names
+    fn renames(&self) -> Vec<(String, String)> {
+        vec![]
+    }
+
+    /// the keyword-type pairs of the msgs and the rpcs in this spec, for
+    /// checking the spec before generating
+    fn spec_fields(&self) -> Vec<SpecFields<'_>> {
+        vec![]
+    }
+
+    /// the version of (def-version n), None if this spec isn't it
+    fn spec_version(&self) -> Option<i64> {
+        None
+    }
+
+    /// the name and the docstring of def-service, None if this spec isn't it
+    fn service(&self) -> Option<(&str, Option<&str>)> {
+        None
+    }
+
+    /// the def-const, None if this spec isn't it
+    fn constant(&self) -> Option<&DefConst> {
+        None
+    }
+
+    /// the def-alias, None if this spec isn't it
+    fn alias(&self) -> Option<&DefAlias> {
+        None
+    }
+
+    /// the msgs of this spec, include the anonymous return types of the rpcs
+    fn msgs(&self) -> Vec<&DefMsg> {
+        vec![]
+    }
+
+    /// the def-rpc, or the rpcs of the def-service
+    fn rpcs(&self) -> Vec<&DefRPC> {
+        vec![]
+    }
+
+    /// the def-notify, None if this spec isn't it
+    fn notification(&self) -> Option<&DefNotify> {
+        None
+    }
+
+    /// the def-rpc-package, None if this spec isn't it
+    fn package(&self) -> Option<&DefPkg> {
+        None
+    }
 }
 
 /// SpecFile struct for keep the status/states whiling parsing the spec file
@@ -62,8 +183,48 @@ pub trait RPCSpec {
 pub struct SpecFile {
     specs: Vec<Box<dyn RPCSpec>>,
 
-    /// the cache table for checking the duplication symbol
-    sym_table: HashMap<String, bool>,
+    /// the cache table for checking the duplication symbol, the symbols to
+    /// the sites of their definitions. The symbol is keyed with its package
+    /// when the shadowing across the packages is allowed
+    sym_table: HashMap<(Option<String>, String), String>,
+
+    /// the options of the generated code
+    options: GenOptions,
+
+    /// the spec files have been read, the file included twice is read once
+    sources: Vec<String>,
+
+    /// the spec files of the specs, None if the spec isn't read from file
+    origins: Vec<Option<String>>,
+
+    /// the spec file is being read
+    reading: Option<String>,
+
+    /// the package of the specs are being recorded
+    package: Option<String>,
+
+    /// the hash of the recorded specs, it is in the header of the generated
+    /// files
+    hash: SpecHash,
+}
+
+/// the FNV-1a hash of the spec exprs, it is same across the runs and the
+/// platforms. The comments and the spaces of the spec file aren't hashed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct SpecHash(u64);
+
+impl Default for SpecHash {
+    fn default() -> Self {
+        Self(0xcbf29ce484222325)
+    }
+}
+
+impl SpecHash {
+    fn update(&mut self, bytes: &[u8]) {
+        for b in bytes {
+            self.0 = (self.0 ^ *b as u64).wrapping_mul(0x100000001b3);
+        }
+    }
 }
 
 impl<'s> IntoIterator for &'s SpecFile {
@@ -81,85 +242,776 @@ impl SpecFile {
         Default::default()
     }
 
+    pub fn config_options(mut self, options: GenOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// read the spec file (path or url) and the spec files it includes with
+    /// (include "path-or-url"). The relative path is relative to the file
+    /// including it
+    pub fn read_spec(source: &str) -> Result<Self> {
+        Self::new().read(source)
+    }
+
+    /// same as read_spec, with the options configured before. The options
+    /// like allow_shadowing are used when reading
+    pub fn read(mut self, source: &str) -> Result<Self> {
+        self.read_source(source, &mut vec![])?;
+        Ok(self)
+    }
+
+    /// the including files are in the stack, meet them again is the cycle
+    fn read_source(&mut self, source: &str, stack: &mut Vec<String>) -> Result<()> {
+        let source = canonical_source(source);
+        if stack.contains(&source) {
+            anyhow::bail!("include cycle: {} -> {}", stack.join(" -> "), source)
+        }
+        if self.sources.contains(&source) {
+            return Ok(());
+        }
+        self.sources.push(source.clone());
+
+        let content = read_single_template_content(&source)?;
+        stack.push(source.clone());
+        self.record_source(Cursor::new(content), Some(&source), stack)?;
+        stack.pop();
+
+        Ok(())
+    }
+
+    /// same as read, the spec is from the reader instead of the file. The
+    /// includes of it are relative to the current folder
+    pub fn read_from(mut self, reader: impl Read) -> Result<Self> {
+        self.record_source(reader, None, &mut vec![])?;
+        Ok(self)
+    }
+
+    /// record the specs of the source code, the includes are relative to the
+    /// source file
+    fn record_source(
+        &mut self,
+        code: impl Read,
+        source: Option<&str>,
+        stack: &mut Vec<String>,
+    ) -> Result<()> {
+        let exprs = lisp_rpc_rust_parser::Parser::new()
+            .parse_root_with_source(code, source)
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+
+        for expr in &exprs {
+            match include_path(expr) {
+                Some(p) => {
+                    self.read_source(&resolve_include(source.unwrap_or_default(), p)?, stack)?
+                }
+                None => {
+                    self.reading = source.map(|s| s.to_string());
+                    self.record_expr(expr)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// record the spec of this expr, the expr has to be one of the def- exprs
+    pub fn record_expr(&mut self, expr: &Expr) -> Result<()> {
+        self.hash.update(format!("{}\n", expr).as_bytes());
+        if DefRPC::if_def_rpc_expr(expr) {
+            self.record_one(Box::new(DefRPC::from_expr(expr)?))
+        } else if DefMsg::if_def_msg_expr(expr) {
+            self.record_one(Box::new(DefMsg::from_expr(expr)?))
+        } else if DefService::if_def_service_expr(expr) {
+            self.record_one(Box::new(DefService::from_expr(expr)?))
+        } else if DefPkg::if_def_pkg_expr(expr) {
+            self.record_one(Box::new(DefPkg::from_expr(expr)?))
+        } else if DefVersion::if_def_version_expr(expr) {
+            self.record_one(Box::new(DefVersion::from_expr(expr)?))
+        } else if DefConst::if_def_const_expr(expr) {
+            self.record_one(Box::new(DefConst::from_expr(expr)?))
+        } else if DefAlias::if_def_alias_expr(expr) {
+            self.record_one(Box::new(DefAlias::from_expr(expr)?))
+        } else if DefNotify::if_def_notify_expr(expr) {
+            self.record_one(Box::new(DefNotify::from_expr(expr)?))
+        } else {
+            anyhow::bail!("unknown expr: {expr}")
+        }
+    }
+
+    /// record the spec, its symbol and the rpcs inside it can't be defined
+    /// before. The specs in different packages can have the same name if
+    /// the shadowing is allowed
     pub fn record_one(&mut self, spec: Box<dyn RPCSpec>) -> Result<()> {
-        let sym_name = spec.symbol_name();
-        self.specs.push(spec);
-        if self.sym_table.get(&sym_name).is_some() {
-            anyhow::bail!("sym {} already have", sym_name)
+        let is_pkg = matches!(spec.file_target(), TargetFile::Cargo);
+        let scope = match is_pkg || !self.options.allow_shadowing {
+            true => None,
+            false => self.package.clone(),
+        };
+
+        // the nth spec of the file
+        let nth = self.origins.iter().filter(|o| **o == self.reading).count() + 1;
+        let site = match &self.reading {
+            Some(f) => format!("the spec #{} of {}", nth, f),
+            None => format!("the spec #{}", nth),
+        };
+
+        let mut syms = vec![spec.symbol_name()];
+        for f in spec.spec_fields() {
+            if f.name != syms[0] {
+                syms.push(f.name.to_string())
+            }
+        }
+        for (i, sym) in syms.iter().enumerate() {
+            if let Some(first) = self.sym_table.get(&(scope.clone(), sym.clone())) {
+                anyhow::bail!("sym {} is defined twice, {} and {}", sym, first, site)
+            }
+            if syms[..i].contains(sym) {
+                anyhow::bail!("sym {} is defined twice in {}", sym, site)
+            }
         }
 
-        self.sym_table.insert(sym_name, true);
+        for sym in syms {
+            self.sym_table.insert((scope.clone(), sym), site.clone());
+        }
+        if is_pkg {
+            self.package = Some(spec.symbol_name());
+        }
+        self.origins.push(self.reading.clone());
+        self.specs.push(spec);
         Ok(())
     }
 
-    /// write the cargo toml and the lib file
+    /// merge the specs of the other spec file after the specs of this one,
+    /// same as reading the files one after another. The specs of the files
+    /// read by both are merged once. All symbols defined by both are reported
+    /// with their two sites, and nothing is merged then
+    pub fn merge(mut self, other: SpecFile) -> Result<Self> {
+        let reading = self.reading.take();
+        let mut merged = Self {
+            specs: vec![],
+            sym_table: self.sym_table.clone(),
+            origins: self.origins.clone(),
+            package: self.package.clone(),
+            options: self.options.clone(),
+            ..Default::default()
+        };
+
+        let mut conflicts = vec![];
+        for (spec, origin) in other.specs.into_iter().zip(other.origins) {
+            if origin.as_ref().is_some_and(|o| self.sources.contains(o)) {
+                continue;
+            }
+            merged.reading = origin;
+            if let Err(e) = merged.record_one(spec) {
+                conflicts.push(e.to_string());
+                // count the conflicting spec for the sites of the next specs,
+                // nothing is merged anyway
+                merged.origins.push(merged.reading.clone());
+            }
+        }
+        if !conflicts.is_empty() {
+            anyhow::bail!(
+                "the spec files have {} conflicts:\n{}",
+                conflicts.len(),
+                conflicts.join("\n")
+            )
+        }
+
+        self.specs.extend(merged.specs);
+        self.sym_table = merged.sym_table;
+        self.origins = merged.origins;
+        self.package = merged.package;
+        for source in other.sources {
+            if !self.sources.contains(&source) {
+                self.sources.push(source);
+            }
+        }
+        self.hash.update(format!("{}\n", other.hash.0).as_bytes());
+        self.reading = reading;
+        Ok(self)
+    }
+
+    /// check the specs before generating, the undefined types, the duplicate
+    /// keywords, and the type cycles. Each finding has the spec file and the
+    /// keyword path of the spec
+    pub fn check(&self) -> Vec<Violation> {
+        resolve::resolve(
+            self.specs
+                .iter()
+                .zip(&self.origins)
+                .map(|(s, o)| (s.as_ref(), o.as_deref())),
+        )
+    }
+
+    /// the changes from the old spec to the new spec, the breaking changes
+    /// fail the clients or the servers of the old spec
+    pub fn diff(old: &SpecFile, new: &SpecFile) -> SpecDiff {
+        diff::diff(
+            old.specs
+                .iter()
+                .flat_map(|s| old.unaliased_fields(s.as_ref())),
+            new.specs
+                .iter()
+                .flat_map(|s| new.unaliased_fields(s.as_ref())),
+        )
+    }
+
+    /// the generated files of the target in the memory, with the generated
+    /// marker and the stamp. They are relative to the folder of the first
+    /// package
+    pub fn generate(
+        &self,
+        target: GenerateTarget,
+        templates: &[impl AsRef<Path>],
+    ) -> Result<GeneratedOutput> {
+        self.check_or_bail()?;
+        let package = self.lib_name()?;
+        let tera = || self.load_templates(templates);
+        let files = match target {
+            GenerateTarget::Rust => self.gen_code(&tera()?)?,
+            GenerateTarget::Tokens => self.gen_backend(&RustTokensBackend)?,
+            GenerateTarget::Docs(format) => vec![(
+                PathBuf::from(format.file_name()),
+                self.gen_docs(&tera()?, format)?,
+            )],
+            GenerateTarget::JsonSchema => self
+                .gen_json_schemas()
+                .into_iter()
+                .map(|(name, schema)| {
+                    Ok((
+                        PathBuf::from("schemas").join(name),
+                        serde_json::to_string_pretty(&schema)?,
+                    ))
+                })
+                .collect::<Result<_>>()?,
+            GenerateTarget::Proto => self
+                .gen_protos(&tera()?)?
+                .into_iter()
+                .map(|(name, content)| (PathBuf::from("proto").join(name), content))
+                .collect(),
+            GenerateTarget::Lisp => vec![(
+                PathBuf::from("lisp").join(format!("{}.lisp", package)),
+                self.gen_lisp(&tera()?)?,
+            )],
+            GenerateTarget::Python => self.gen_backend(&python::PythonBackend::new(tera()?))?,
+        };
+
+        Ok(GeneratedOutput {
+            files: self.stamp_files(files)?,
+            package,
+        })
+    }
+
+    /// write the cargo toml and the lib file. The files generated before are
+    /// overwritten, the other files are kept unless the force option is on
     pub fn gen_code_to_file(
         &self,
         output_path: PathBuf,
         templates: &[impl AsRef<Path>],
     ) -> Result<()> {
-        let mut tera = Tera::default();
-        let mut all_temps = vec![];
-        for p in templates {
-            match p.as_ref().file_stem().map(|n| n.to_str()) {
-                Some(n) => {
-                    all_temps.push((p, n));
-                }
-                None => (),
-            }
+        self.generate(GenerateTarget::Rust, templates)?
+            .write_to(&output_path, self.options.force)
+    }
+
+    /// the cargo toml and the lib files of the rust crate, relative to the
+    /// crate folder
+    pub fn gen_code(&self, tera: &Tera) -> Result<Vec<(PathBuf, String)>> {
+        // the structs can refer each other across the specs
+        let mut deps = vec![];
+        for s in &self.specs {
+            deps.extend(s.struct_deps()?);
         }
+        let options = GenOptions {
+            recursive: recursive_groups(&deps),
+            renames: self.specs.iter().flat_map(|s| s.renames()).collect(),
+            version: self.specs.iter().find_map(|s| s.spec_version()),
+            aliases: self
+                .specs
+                .iter()
+                .filter_map(|s| s.alias())
+                .map(|a| a.name().to_string())
+                .collect(),
+            ..self.options.clone()
+        };
 
-        tera.add_template_files(all_temps)?;
+        // several packages are the modules of the crate of the first package,
+        // the specs after the package are in its module
+        let packages: Vec<String> = self
+            .specs
+            .iter()
+            .filter(|s| matches!(s.file_target(), TargetFile::Cargo))
+            .map(|s| kebab_to_snake_case(&s.symbol_name()))
+            .collect();
+        let multi_packages = packages.len() > 1;
 
         let mut lib_name = None;
         let mut cargo_content = String::new();
-        let mut lib_content = String::new();
+        // the symbol names and the codes of the specs
+        let mut root_codes: Vec<(String, String)> = vec![];
+        let mut modules: Vec<(String, Vec<(String, String)>)> = vec![];
+        // the struct names to their paths in the modules, for the server
+        let mut qualified: BTreeMap<String, BTreeMap<String, String>> = BTreeMap::new();
+        // the rpcs of the server and their modules
+        let mut methods = vec![];
+        // the specs are rendered in parallel, and assembled in the spec order
+        let rendered = self
+            .specs
+            .par_iter()
+            .map(|s| match s.file_target() {
+                TargetFile::Lib => s.gen_code_with_tera(tera, &options).map(Some),
+                TargetFile::Cargo => Ok(None),
+            })
+            .collect::<Result<Vec<_>>>()?;
         // file targets
-        for s in &self.specs {
+        for (s, code) in self.specs.iter().zip(rendered) {
             match s.file_target() {
                 TargetFile::Lib => {
-                    lib_content += s.gen_code_with_tera(&tera)?.as_str();
+                    let code = (
+                        kebab_to_snake_case(&s.symbol_name()),
+                        code.unwrap_or_default(),
+                    );
+                    match modules.last_mut() {
+                        Some((m, codes)) => {
+                            let errors = s.rpc_methods().into_iter().filter_map(|r| r.error_type);
+                            for name in s.struct_deps()?.into_iter().map(|(n, _)| n).chain(errors) {
+                                let rust_name = rename_type(&name, &options.renames);
+                                qualified
+                                    .entry(m.clone())
+                                    .or_default()
+                                    .insert(name, format!("{}::{}", m, rust_name));
+                            }
+                            codes.push(code)
+                        }
+                        None => root_codes.push(code),
+                    }
+                    let module = modules.last().map(|(m, _)| m.clone());
+                    methods.extend(s.rpc_methods().into_iter().map(|r| (module.clone(), r)));
                 }
                 TargetFile::Cargo => {
-                    lib_name = Some(s.symbol_name());
-                    cargo_content += s.gen_code_with_tera(&tera)?.as_str();
+                    if lib_name.is_none() {
+                        lib_name = Some(s.symbol_name());
+                        cargo_content += s.gen_code_with_tera(tera, &options)?.as_str();
+                    }
+                    if multi_packages {
+                        modules.push((kebab_to_snake_case(&s.symbol_name()), vec![]));
+                    }
+                }
+            }
+        }
+
+        // the structs of the rpc's own package shadow the ones of the other
+        // packages, same as the modules
+        let all_qualified: Vec<(String, String)> = qualified
+            .values()
+            .flatten()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+        let methods: Vec<RPCMethod> = methods
+            .into_iter()
+            .map(|(m, r)| {
+                let mut renames = options.renames_with(all_qualified.clone());
+                if let Some(own) = m.and_then(|m| qualified.get(&m)) {
+                    renames.extend(own.clone());
+                }
+                r.renamed(&renames)
+            })
+            .collect();
+        // the methods are renamed already
+        let server_options = GenOptions {
+            renames: BTreeMap::new(),
+            ..options.clone()
+        };
+        if let Some(code) = render_methods(tera, &methods, &server_options)? {
+            root_codes.push(("rpc_method".to_string(), code));
+        }
+        if let Some(server) = render_server(tera, &methods, &server_options)? {
+            root_codes.push(("server".to_string(), server));
+        }
+
+        // the files in the crate and their contents
+        let mut files = vec![];
+        let mut lib_codes = vec![];
+        if self.options.split_files {
+            for (m, codes) in &modules {
+                let mut mod_lines = module_imports(m, &packages);
+                for (sym, code) in codes {
+                    mod_lines.push(format!("mod {};\npub use {}::*;", sym, sym));
+                    files.push((
+                        PathBuf::from(format!("src/{}/{}.rs", m, sym)),
+                        format!("use super::*;\n\n{}", code),
+                    ));
+                }
+                files.push((
+                    PathBuf::from(format!("src/{}/mod.rs", m)),
+                    mod_lines.join("\n"),
+                ));
+                lib_codes.push(format!("pub mod {};", m));
+            }
+            for (sym, code) in &root_codes {
+                lib_codes.push(format!("mod {};\npub use {}::*;", sym, sym));
+                files.push((
+                    PathBuf::from(format!("src/{}.rs", sym)),
+                    format!("use super::*;\n\n{}", code),
+                ));
+            }
+            files.push((PathBuf::from("src/lib.rs"), lib_codes.join("\n")));
+        } else {
+            for (m, codes) in &modules {
+                let codes: Vec<&str> = codes.iter().map(|(_, c)| c.as_str()).collect();
+                lib_codes.push(render_module(m, &packages, &codes));
+            }
+            lib_codes.extend(root_codes.into_iter().map(|(_, c)| c));
+            files.push((PathBuf::from("src/lib.rs"), lib_codes.join("\n\n")));
+        }
+        files.push((PathBuf::from("Cargo.toml"), cargo_content));
+
+        Ok(files)
+    }
+
+    /// the api document of all specs in the format, grouped by the packages
+    pub fn gen_docs(&self, templates: &Tera, format: DocFormat) -> Result<String> {
+        let mut packages = vec![docs::DocPackage::new(None)];
+        for s in &self.specs {
+            match s.file_target() {
+                TargetFile::Cargo => packages.push(docs::DocPackage::new(Some(s.symbol_name()))),
+                TargetFile::Lib => {
+                    let pkg = packages.last_mut().context("no package")?;
+                    for f in self.unaliased_fields(s.as_ref()) {
+                        pkg.push(f)
+                    }
                 }
             }
         }
 
-        // start to create files
-        let lib_file_path = output_path
-            .join(lib_name.as_ref().context("no lib name")?)
-            .join("src/lib.rs");
+        let title = self
+            .specs
+            .iter()
+            .find(|s| matches!(s.file_target(), TargetFile::Cargo))
+            .map(|s| s.symbol_name())
+            .unwrap_or_else(|| "API".to_string());
+        docs::render_docs(
+            templates,
+            &title,
+            self.specs.iter().find_map(|s| s.spec_version()),
+            packages,
+            format,
+        )
+    }
+
+    /// write the api document to the crate folder, API.md or API.html
+    pub fn gen_docs_to_file(
+        &self,
+        output_path: PathBuf,
+        templates: &[impl AsRef<Path>],
+        format: DocFormat,
+    ) -> Result<()> {
+        self.generate(GenerateTarget::Docs(format), templates)?
+            .write_to(&output_path, self.options.force)
+    }
+
+    /// the json schema documents of all msgs, the file names and the schemas
+    pub fn gen_json_schemas(&self) -> Vec<(String, serde_json::Value)> {
+        self.specs
+            .iter()
+            .flat_map(|s| self.unaliased_fields(s.as_ref()))
+            .filter(|f| !f.is_rpc)
+            .map(|f| {
+                (
+                    json_schema::schema_file_name(f.name),
+                    json_schema::msg_schema(&f),
+                )
+            })
+            .collect()
+    }
+
+    /// write the json schemas to the schemas folder of the crate folder
+    pub fn gen_json_schemas_to_file(&self, output_path: PathBuf) -> Result<()> {
+        self.generate(GenerateTarget::JsonSchema, &[] as &[PathBuf])?
+            .write_to(&output_path, self.options.force)
+    }
+
+    /// the proto files of all packages, the file names and the contents
+    pub fn gen_protos(&self, templates: &Tera) -> Result<Vec<(String, String)>> {
+        let mut packages = vec![proto::ProtoPackage::new(&self.lib_name()?)];
+        for s in &self.specs {
+            match s.file_target() {
+                TargetFile::Cargo if packages.iter().any(|p| p.spec_count() > 0) => {
+                    packages.push(proto::ProtoPackage::new(&s.symbol_name()))
+                }
+                TargetFile::Cargo => (),
+                TargetFile::Lib => packages
+                    .last_mut()
+                    .context("no package")?
+                    .push(self.unaliased_fields(s.as_ref()), s.service()),
+            }
+        }
+        proto::render_protos(templates, &packages)
+    }
+
+    /// write the proto files to the proto folder of the crate folder
+    pub fn gen_protos_to_file(
+        &self,
+        output_path: PathBuf,
+        templates: &[impl AsRef<Path>],
+    ) -> Result<()> {
+        self.generate(GenerateTarget::Proto, templates)?
+            .write_to(&output_path, self.options.force)
+    }
+
+    /// the common lisp package of all specs, named after the first package
+    pub fn gen_lisp(&self, templates: &Tera) -> Result<String> {
+        let specs: Vec<SpecFields> = self
+            .specs
+            .iter()
+            .flat_map(|s| self.unaliased_fields(s.as_ref()))
+            .collect();
+        lisp::render_lisp(
+            templates,
+            &self.lib_name()?,
+            self.specs.iter().find_map(|s| s.spec_version()),
+            &specs,
+        )
+    }
+
+    /// write the lisp file to the lisp folder of the crate folder
+    pub fn gen_lisp_to_file(
+        &self,
+        output_path: PathBuf,
+        templates: &[impl AsRef<Path>],
+    ) -> Result<()> {
+        self.generate(GenerateTarget::Lisp, templates)?
+            .write_to(&output_path, self.options.force)
+    }
+
+    /// the language-agnostic structs and rpcs of all specs for the backends
+    pub fn codegen_unit(&self) -> Result<CodegenUnit> {
+        let mut structs = vec![];
+        let mut rpcs = vec![];
+        for s in &self.specs {
+            structs.extend(s.gen_structs()?);
+            let service = s.service().map(|(n, _)| n.to_string());
+            rpcs.extend(
+                s.spec_fields()
+                    .into_iter()
+                    .filter(|f| f.is_rpc)
+                    .map(|f| GeneratedRPC {
+                        data_name: f.name.to_string(),
+                        doc: f.doc.map(|d| d.to_string()),
+                        returns: f.returns.map(|r| r.to_string()),
+                        versions: f.versions,
+                        service: service.clone(),
+                        errors: f.errors.to_vec(),
+                        notify: f.notify,
+                    }),
+            );
+        }
+
+        Ok(CodegenUnit {
+            package: self.lib_name()?,
+            version: self.specs.iter().find_map(|s| s.spec_version()),
+            consts: self
+                .specs
+                .iter()
+                .filter_map(|s| s.constant())
+                .cloned()
+                .collect(),
+            aliases: self
+                .specs
+                .iter()
+                .filter_map(|s| s.alias())
+                .cloned()
+                .collect(),
+            structs,
+            rpcs,
+            renames: self
+                .options
+                .renames_with(self.specs.iter().flat_map(|s| s.renames()).collect()),
+        })
+    }
+
+    /// the files of the backend, relative to the folder of the package
+    pub fn gen_backend(&self, backend: &dyn CodegenBackend) -> Result<Vec<(PathBuf, String)>> {
+        backend.generate(&self.codegen_unit()?)
+    }
 
-        // create the parents
-        if let Some(parent) = lib_file_path.parent() {
-            fs::create_dir_all(parent)
-                .with_context(|| format!("Failed to create directory: {:?}", parent))?;
+    /// write the files of the backend to the crate folder
+    pub fn gen_backend_to_file(
+        &self,
+        output_path: PathBuf,
+        backend: &dyn CodegenBackend,
+    ) -> Result<()> {
+        self.check_or_bail()?;
+        GeneratedOutput {
+            package: self.lib_name()?,
+            files: self.stamp_files(self.gen_backend(backend)?)?,
         }
+        .write_to(&output_path, self.options.force)
+    }
 
-        let mut lib_file = OpenOptions::new()
-            .append(true)
-            .create(true)
-            .open(&lib_file_path)
-            .with_context(|| format!("Failed to open file: {:?}", lib_file_path))?;
+    /// the python module of all specs, named after the first package
+    pub fn gen_python(&self, templates: &Tera) -> Result<String> {
+        python::render_python(templates, &self.codegen_unit()?)
+    }
 
-        let cargo_file_path = output_path
-            .join(lib_name.as_ref().context("no lib name")?)
-            .join("Cargo.toml");
+    /// write the python file to the python folder of the crate folder
+    pub fn gen_python_to_file(
+        &self,
+        output_path: PathBuf,
+        templates: &[impl AsRef<Path>],
+    ) -> Result<()> {
+        self.generate(GenerateTarget::Python, templates)?
+            .write_to(&output_path, self.options.force)
+    }
 
-        let mut cargo_file = OpenOptions::new()
-            .append(true)
-            .create(true)
-            .open(&cargo_file_path)
-            .with_context(|| format!("Failed to open file: {:?}", cargo_file_path))?;
+    /// the keyword-type pairs of the spec with the aliases replaced by their
+    /// primary types, for the targets without the newtypes
+    fn unaliased_fields<'a>(&'a self, spec: &'a dyn RPCSpec) -> Vec<SpecFields<'a>> {
+        let aliases: BTreeMap<&str, &str> = self
+            .specs
+            .iter()
+            .filter_map(|s| s.alias())
+            .map(|a| (a.name(), a.alias_type()))
+            .collect();
+        spec.spec_fields()
+            .into_iter()
+            .map(|f| match aliases.is_empty() {
+                true => f,
+                false => SpecFields {
+                    fields: Cow::Owned(
+                        f.fields.iter().map(|e| unalias_type(e, &aliases)).collect(),
+                    ),
+                    returns: f.returns.map(|r| aliases.get(r).copied().unwrap_or(r)),
+                    ..f
+                },
+            })
+            .collect()
+    }
 
-        // write the file
-        write!(lib_file, "{}", lib_content)?;
-        write!(cargo_file, "{}", cargo_content)?;
+    /// the tera of the templates, they are over the stock templates if the
+    /// default_templates option is on
+    fn load_templates(&self, templates: &[impl AsRef<Path>]) -> Result<Tera> {
+        match self.options.default_templates {
+            true => load_templates_over(DEFAULT_TEMPLATES, templates),
+            false => load_templates(templates),
+        }
+    }
 
+    /// the name of the first package, it is the generated crate
+    fn lib_name(&self) -> Result<String> {
+        self.specs
+            .iter()
+            .find(|s| matches!(s.file_target(), TargetFile::Cargo))
+            .map(|s| s.symbol_name())
+            .context("no lib name")
+    }
+
+    /// bail with all findings of the check
+    fn check_or_bail(&self) -> Result<()> {
+        let findings = self.check();
+        if !findings.is_empty() {
+            anyhow::bail!(
+                "the spec has {} problems:\n{}",
+                findings.len(),
+                findings
+                    .iter()
+                    .map(|v| v.to_string())
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            )
+        }
         Ok(())
     }
+
+    /// the msgs in the spec order, include the anonymous return types of the
+    /// rpcs
+    pub fn messages(&self) -> Vec<&DefMsg> {
+        self.specs.iter().flat_map(|s| s.msgs()).collect()
+    }
+
+    /// the rpcs in the spec order, include the rpcs of the services
+    pub fn rpcs(&self) -> Vec<&DefRPC> {
+        self.specs.iter().flat_map(|s| s.rpcs()).collect()
+    }
+
+    pub fn notifications(&self) -> Vec<&DefNotify> {
+        self.specs.iter().filter_map(|s| s.notification()).collect()
+    }
+
+    /// the first package, None if the specs have no def-rpc-package
+    pub fn package(&self) -> Option<&DefPkg> {
+        self.packages().into_iter().next()
+    }
+
+    /// all packages, the specs after one package belong to it
+    pub fn packages(&self) -> Vec<&DefPkg> {
+        self.specs.iter().filter_map(|s| s.package()).collect()
+    }
+
+    /// the msg of the name, the first one if the packages shadow it
+    pub fn find_message(&self, name: &str) -> Option<&DefMsg> {
+        self.messages().into_iter().find(|m| m.name() == name)
+    }
+
+    /// the rpc of the name, the first one if the packages shadow it
+    pub fn find_rpc(&self, name: &str) -> Option<&DefRPC> {
+        self.rpcs().into_iter().find(|r| r.rpc_name() == name)
+    }
+
+    /// the spec files (paths or urls) have been read, with the included ones
+    pub fn sources(&self) -> &[String] {
+        &self.sources
+    }
+
+    /// the formatted lisp-rpc source of the specs in the spec order, the
+    /// specs of the included files are inside it
+    pub fn to_spec_string(&self) -> String {
+        self.specs
+            .iter()
+            .map(|s| s.to_spec_string())
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+
+    /// the hash of the specs, the specs with the same exprs have the same
+    /// hash
+    pub fn spec_hash(&self) -> String {
+        format!("{:016x}", self.hash.0)
+    }
+
+    /// the second line of the generated files, after the marker
+    fn generated_stamp(&self) -> String {
+        format!(
+            "lisp-rpc-rust-generator {}, spec {}",
+            env!("CARGO_PKG_VERSION"),
+            self.spec_hash()
+        )
+    }
+
+    /// format the files and put the generated marker and the stamp on them
+    fn stamp_files(&self, files: Vec<(PathBuf, String)>) -> Result<Vec<(PathBuf, String)>> {
+        let stamp = self.generated_stamp();
+        // the rust files are formatted before writing any
+        files
+            .into_iter()
+            .map(|(p, c)| {
+                let c = match self.options.format {
+                    Some(f) if p.extension() == Some("rs".as_ref()) => f
+                        .format(&c)
+                        .with_context(|| format!("Failed to format file: {:?}", p))?,
+                    _ => c,
+                };
+                let c = match p.extension().and_then(|e| e.to_str()) {
+                    Some("json") => c,
+                    Some("toml" | "py") => format!("# {}\n# {}\n\n{}", GENERATED_MARKER, stamp, c),
+                    Some("lisp") => format!(";;;; {}\n;;;; {}\n\n{}", GENERATED_MARKER, stamp, c),
+                    Some("md" | "html") => {
+                        format!("<!-- {} -->\n<!-- {} -->\n\n{}", GENERATED_MARKER, stamp, c)
+                    }
+                    _ => format!("// {}\n// {}\n\n{}", GENERATED_MARKER, stamp, c),
+                };
+                Ok((p, c))
+            })
+            .collect()
+    }
 }
 
 pub struct SpecFileIter<'s> {
@@ -194,6 +1046,147 @@ pub fn kebab_to_pascal_case(s: &str) -> String {
         .collect()
 }
 
+/// the camelCase of the kebab case name
+pub fn kebab_to_camel_case(s: &str) -> String {
+    let pascal = kebab_to_pascal_case(s);
+    let mut chars = pascal.chars();
+    match chars.next() {
+        None => String::new(),
+        Some(first_char) => first_char.to_ascii_lowercase().to_string() + chars.as_str(),
+    }
+}
+
+/// the kebab case of the PascalCase, the camelCase, or the snake_case name
+pub fn to_kebab_case(s: &str) -> String {
+    let chars: Vec<char> = s.chars().collect();
+    let mut out = String::new();
+    for (i, c) in chars.iter().enumerate() {
+        if *c == '_' || *c == '.' {
+            out.push('-');
+            continue;
+        }
+        if c.is_uppercase() && i > 0 {
+            let prev = chars[i - 1];
+            let next_lower = chars.get(i + 1).is_some_and(|n| n.is_lowercase());
+            if prev.is_lowercase() || prev.is_ascii_digit() || (prev.is_uppercase() && next_lower) {
+                out.push('-');
+            }
+        }
+        out.extend(c.to_lowercase());
+    }
+    out
+}
+
+/// the tera of the template files and the template folders, see the
+/// template_names. The templates are compiled once for all specs
+pub fn load_templates(templates: &[impl AsRef<Path>]) -> Result<Tera> {
+    load_templates_over(&[], templates)
+}
+
+/// where the template is from
+enum TemplateSource {
+    Embedded(&'static str),
+    File(PathBuf),
+}
+
+/// the tera of the templates over the embedded templates like the
+/// DEFAULT_TEMPLATES, the templates override the embedded ones
+fn load_templates_over(
+    embedded: &[(&str, &'static str)],
+    templates: &[impl AsRef<Path>],
+) -> Result<Tera> {
+    let mut sources = BTreeMap::new();
+    stack_templates(
+        &mut sources,
+        embedded
+            .iter()
+            .map(|(n, c)| (n.to_string(), TemplateSource::Embedded(c)))
+            .collect(),
+    );
+    for t in templates {
+        stack_templates(
+            &mut sources,
+            template_files(t.as_ref())?
+                .into_iter()
+                .map(|(n, p)| (n, TemplateSource::File(p)))
+                .collect(),
+        );
+    }
+
+    let mut tera = Tera::default();
+    register_filters(&mut tera);
+    let (mut raws, mut files) = (vec![], vec![]);
+    for (name, source) in sources {
+        match source {
+            TemplateSource::Embedded(c) => raws.push((name, c)),
+            TemplateSource::File(p) => files.push((p, Some(name))),
+        }
+    }
+    tera.add_raw_templates(raws)?;
+    tera.add_template_files(files)?;
+    Ok(tera)
+}
+
+/// the template names and their files. The name of the template file is its
+/// file stem, the templates in the folder are found recursively and named
+/// by their paths in the folder, like `partials/fields` of
+/// `partials/fields.template`
+///
+/// the later templates override the earlier ones of the same names, the
+/// overridden template is `base/<name>` and the new one can extend it
+pub fn template_names(templates: &[impl AsRef<Path>]) -> Result<BTreeMap<String, PathBuf>> {
+    let mut names = BTreeMap::new();
+    for t in templates {
+        stack_templates(&mut names, template_files(t.as_ref())?);
+    }
+    Ok(names)
+}
+
+/// the names and the files of the template file or the template folder
+fn template_files(t: &Path) -> Result<Vec<(String, PathBuf)>> {
+    if !t.is_dir() {
+        return Ok(match t.file_stem().and_then(|n| n.to_str()) {
+            Some(n) => vec![(n.to_string(), t.to_path_buf())],
+            None => vec![],
+        });
+    }
+
+    // the hidden files like .DS_Store aren't templates
+    let mut files: Vec<PathBuf> = get_all_file_paths_in_folder(t)?
+        .into_iter()
+        .filter(|f| {
+            f.strip_prefix(t)
+                .is_ok_and(|rel| !rel.iter().any(|c| c.to_string_lossy().starts_with('.')))
+        })
+        .collect();
+    files.sort();
+    files
+        .into_iter()
+        .map(|f| {
+            let rel = f.strip_prefix(t)?.with_extension("");
+            let name = rel
+                .iter()
+                .map(|c| c.to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            Ok((name, f))
+        })
+        .collect()
+}
+
+/// put the layer of the templates over the names, the overridden templates
+/// are moved to `base/<name>`
+fn stack_templates<T>(names: &mut BTreeMap<String, T>, layer: Vec<(String, T)>) {
+    for (name, t) in layer {
+        let mut base = name.clone();
+        let mut t = t;
+        while let Some(old) = names.insert(base.clone(), t) {
+            t = old;
+            base = format!("base/{}", base);
+        }
+    }
+}
+
 /// helper function
 pub fn kebab_to_snake_case(s: &str) -> String {
     s.replace('-', "_")
@@ -203,10 +1196,92 @@ pub fn kebab_to_snake_case(s: &str) -> String {
 pub fn type_translate(sym: &str) -> String {
     match kebab_to_pascal_case(sym).as_str() {
         "Number" => "i64".to_string(),
+        "Timestamp" => "std::time::SystemTime".to_string(),
+        "Bytes" => "Vec<u8>".to_string(),
+        // needs the uuid feature of the parser
+        "Uuid" => "uuid::Uuid".to_string(),
         s @ _ => s.to_string(),
     }
 }
 
+/// the imports of the module of the package, it uses the items of the other
+/// packages
+fn module_imports(name: &str, packages: &[String]) -> Vec<String> {
+    let mut lines = vec!["use super::*;".to_string()];
+    for p in packages.iter().filter(|p| *p != name) {
+        lines.push("#[allow(unused_imports)]".to_string());
+        lines.push(format!("use super::{}::*;", p));
+    }
+    lines.push(String::new());
+    lines
+}
+
+/// the inline module of the package
+fn render_module(name: &str, packages: &[String], codes: &[&str]) -> String {
+    let mut lines: Vec<String> = module_imports(name, packages)
+        .into_iter()
+        .map(|l| match l.is_empty() {
+            true => l,
+            false => format!("    {}", l),
+        })
+        .collect();
+
+    for l in codes.join("\n\n").lines() {
+        lines.push(match l.is_empty() {
+            true => String::new(),
+            false => format!("    {}", l),
+        });
+    }
+
+    format!("pub mod {} {{\n{}\n}}", name, lines.join("\n"))
+}
+
+/// the path of (include "path-or-url")
+pub fn include_path(expr: &Expr) -> Option<&str> {
+    match expr {
+        Expr::List(e) => match e.as_slice() {
+            [
+                Expr::Atom(Atom {
+                    value: TypeValue::Symbol(s),
+                }),
+                Expr::Atom(Atom {
+                    value: TypeValue::String(p),
+                }),
+            ] if s == "include" => Some(p),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// the include path relative to the including file, or the url
+fn resolve_include(including: &str, path: &str) -> Result<String> {
+    if Url::parse(path).is_ok_and(|u| u.scheme() == "http" || u.scheme() == "https") {
+        return Ok(path.to_string());
+    }
+
+    if let Ok(base) = Url::parse(including)
+        && (base.scheme() == "http" || base.scheme() == "https")
+    {
+        return Ok(base.join(path)?.to_string());
+    }
+
+    Ok(Path::new(including)
+        .parent()
+        .unwrap_or(Path::new(""))
+        .join(path)
+        .to_string_lossy()
+        .to_string())
+}
+
+/// the same file has the same name for finding the cycle, the url is kept
+fn canonical_source(source: &str) -> String {
+    match fs::canonicalize(source) {
+        Ok(p) => p.to_string_lossy().to_string(),
+        Err(_) => source.to_string(),
+    }
+}
+
 /// read from file or url
 pub fn read_single_template_content(source: &str) -> Result<String> {
     if let Ok(url) = Url::parse(source) {
@@ -290,3 +1365,552 @@ fn copy_recursive(source: &Path, destination: &Path) -> Result<()> {
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_spec_with_include() {
+        let dir = env::temp_dir().join(format!("lisp-rpc-include-{}", std::process::id()));
+        fs::create_dir_all(dir.join("common")).unwrap();
+        fs::write(
+            dir.join("common/types.lisp"),
+            r#"(def-msg book-info :title 'string)"#,
+        )
+        .unwrap();
+        fs::write(
+            dir.join("common/lang.lisp"),
+            r#"(include "types.lisp")
+(def-msg language-perfer :lang 'string)"#,
+        )
+        .unwrap();
+        fs::write(
+            dir.join("main.lisp"),
+            r#"(def-rpc-package demo)
+(include "common/types.lisp")
+(include "common/lang.lisp")
+(def-rpc get-book '(:title 'string :lang 'language-perfer) 'book-info)"#,
+        )
+        .unwrap();
+
+        // types.lisp is included twice but read once
+        let specs = SpecFile::read_spec(&dir.join("main.lisp").to_string_lossy()).unwrap();
+        assert_eq!(
+            specs
+                .into_iter()
+                .map(|s| s.symbol_name())
+                .collect::<Vec<_>>(),
+            vec!["demo", "book-info", "language-perfer", "get-book"]
+        );
+
+        fs::write(dir.join("a.lisp"), r#"(include "b.lisp")"#).unwrap();
+        fs::write(dir.join("b.lisp"), r#"(include "a.lisp")"#).unwrap();
+        match SpecFile::read_spec(&dir.join("a.lisp").to_string_lossy()) {
+            Err(e) => assert!(e.to_string().starts_with("include cycle")),
+            Ok(_) => panic!("the cycle should be found"),
+        }
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_spec_introspection() {
+        let mut sf = SpecFile::new();
+        for spec in [
+            "(def-rpc-package demo)",
+            r#"(def-msg book-info "the book" :title 'string :sub (optional 'string) :lang ('string :default "en" :doc "the language"))"#,
+            "(def-rpc get-book '(:title 'string) '(:book 'book-info))",
+            "(def-service shelf (put-book '(:title 'string)))",
+            "(def-notify book-added '(:id 'string))",
+        ] {
+            sf.record_expr(
+                &lisp_rpc_rust_parser::Parser::new()
+                    .parse_root_one(Cursor::new(spec))
+                    .unwrap(),
+            )
+            .unwrap();
+        }
+
+        assert_eq!(sf.package().map(|p| p.name()), Some("demo"));
+        assert_eq!(
+            sf.messages().iter().map(|m| m.name()).collect::<Vec<_>>(),
+            vec!["book-info", "get-book-response"]
+        );
+        assert_eq!(
+            sf.rpcs().iter().map(|r| r.rpc_name()).collect::<Vec<_>>(),
+            vec!["get-book", "put-book"]
+        );
+        assert_eq!(sf.notifications()[0].name(), "book-added");
+        assert_eq!(
+            sf.find_rpc("get-book").unwrap().return_value(),
+            Some("get-book-response")
+        );
+        assert!(sf.find_message("nope").is_none());
+
+        let book = sf.find_message("book-info").unwrap();
+        assert_eq!(book.doc(), Some("the book"));
+        let fields = book.fields();
+        assert_eq!(
+            fields
+                .iter()
+                .map(|f| (f.key(), f.type_key(), f.is_optional()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("title", "string".to_string(), false),
+                ("sub", "(optional string)".to_string(), true),
+                ("lang", "string".to_string(), false),
+            ]
+        );
+        assert_eq!(
+            fields[2].default().map(|d| d.to_string()),
+            Some(r#""en""#.to_string())
+        );
+        assert_eq!(fields[2].doc(), Some("the language"));
+        assert_eq!(fields[0].doc(), None);
+    }
+
+    #[test]
+    fn test_to_spec_string() {
+        let source = r#"(def-rpc-package demo :version "0.2.0" :edition 2021
+    :deps '(:tokio ("1" :features '("rt")) :uuid "1") :features '(:default '("serde")))
+(def-version 2)
+(def-const max-page "the \"max\" page" 100)
+(def-alias isbn 'string)
+(def-msg book-info "the book" :rust-name "Book" :since 2
+  :id 'isbn :title ('string :doc "the title") :tags '(list 'string)
+  (reserved :old-id))
+(def-msg ping)
+(def-rpc get-book :until 3 '(:id 'isbn (reserved :lang)) '(:book (optional 'book-info)) :errors ('not-found))
+(def-rpc sync)
+(def-service shelf "the shelf" (put-book '(:book 'book-info)) (count-books '() 'number))
+(def-notify book-added '(:id 'isbn))"#;
+        let read = |source: &str| {
+            let mut sf = SpecFile::new();
+            for e in lisp_rpc_rust_parser::Parser::new()
+                .parse_root(Cursor::new(source))
+                .unwrap()
+            {
+                sf.record_expr(&e).unwrap();
+            }
+            sf
+        };
+
+        let sf = read(source);
+        let written = sf.to_spec_string();
+        assert_eq!(
+            written,
+            r#"(def-rpc-package demo :version "0.2.0" :edition 2021
+  :deps '(:tokio ("1" :features '("rt")) :uuid "1")
+  :features '(:default '("serde")))
+
+(def-version 2)
+
+(def-const max-page "the \"max\" page" 100)
+
+(def-alias isbn 'string)
+
+(def-msg book-info "the book" :rust-name "Book" :since 2
+  :id 'isbn
+  :title ('string :doc "the title")
+  :tags '(list 'string)
+  (reserved :old-id))
+
+(def-msg ping)
+
+(def-rpc get-book :until 3
+  '(:id 'isbn
+    (reserved :lang))
+  '(:book (optional 'book-info))
+  :errors ('not-found))
+
+(def-rpc sync)
+
+(def-service shelf "the shelf"
+  (put-book
+    '(:book 'book-info))
+  (count-books
+    '()
+    'number))
+
+(def-notify book-added
+  '(:id 'isbn))"#
+        );
+
+        // reading the written specs gives the same specs
+        let again = read(&written);
+        assert_eq!(again.messages(), sf.messages());
+        assert_eq!(again.rpcs(), sf.rpcs());
+        assert_eq!(again.to_spec_string(), written);
+    }
+
+    #[test]
+    fn test_merge() {
+        let dir = env::temp_dir().join(format!("lisp-rpc-merge-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("types.lisp"),
+            "(def-msg language-perfer :lang 'string)",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("a.lisp"),
+            r#"(def-rpc-package demo)
+(include "types.lisp")
+(def-msg book-info :title 'string)"#,
+        )
+        .unwrap();
+        fs::write(
+            dir.join("b.lisp"),
+            r#"(include "types.lisp")
+(def-rpc get-book '(:title 'string) 'book-info)"#,
+        )
+        .unwrap();
+        fs::write(
+            dir.join("c.lisp"),
+            r#"(def-msg book-info :title 'string :price 'number)
+(def-rpc get-book '(:title 'string) 'book-info)"#,
+        )
+        .unwrap();
+        let path = |f: &str| dir.join(f).to_string_lossy().to_string();
+
+        // the types.lisp included by both is merged once
+        let specs = SpecFile::read_spec(&path("a.lisp"))
+            .unwrap()
+            .merge(SpecFile::read_spec(&path("b.lisp")).unwrap())
+            .unwrap();
+        assert_eq!(specs.into_iter().count(), 4);
+        assert!(specs.find_rpc("get-book").is_some());
+        assert!(specs.check().is_empty());
+
+        // all conflicts are reported with both sites
+        match specs.merge(SpecFile::read_spec(&path("c.lisp")).unwrap()) {
+            Err(e) => assert_eq!(
+                e.to_string(),
+                format!(
+                    "the spec files have 2 conflicts:
+sym book-info is defined twice, the spec #2 of {a} and the spec #1 of {c}
+sym get-book is defined twice, the spec #1 of {b} and the spec #2 of {c}",
+                    a = path("a.lisp"),
+                    b = path("b.lisp"),
+                    c = path("c.lisp"),
+                )
+            ),
+            Ok(_) => panic!("the conflicts should be found"),
+        }
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_record_duplicate() {
+        let dir = env::temp_dir().join(format!("lisp-rpc-duplicate-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("types.lisp"),
+            r#"(def-msg language-perfer :lang 'string)
+(def-msg book-info :title 'string)"#,
+        )
+        .unwrap();
+        fs::write(
+            dir.join("main.lisp"),
+            r#"(def-rpc-package demo)
+(include "types.lisp")
+(def-rpc-package store)
+(def-msg book-info :title 'string :price 'number)"#,
+        )
+        .unwrap();
+        let main = dir.join("main.lisp").to_string_lossy().to_string();
+
+        // both definitions are reported, and nothing is recorded
+        match SpecFile::read_spec(&main) {
+            Err(e) => assert_eq!(
+                e.to_string(),
+                format!(
+                    "sym book-info is defined twice, the spec #2 of {} and the spec #3 of {}",
+                    dir.join("types.lisp").to_string_lossy(),
+                    main
+                )
+            ),
+            Ok(_) => panic!("the duplicate should be found"),
+        }
+
+        // the packages are different modules
+        let specs = SpecFile::new()
+            .config_options(GenOptions {
+                allow_shadowing: true,
+                ..Default::default()
+            })
+            .read(&main)
+            .unwrap();
+        assert_eq!(specs.into_iter().count(), 5);
+
+        // the rpc of the service is the symbol too
+        let mut specs = SpecFile::new();
+        let mut p = lisp_rpc_rust_parser::Parser::new();
+        for e in p
+            .parse_root(Cursor::new(
+                r#"(def-rpc get-book-info '(:title 'string))
+(def-service library (get-book-info '(:title 'string)))"#,
+            ))
+            .unwrap()
+        {
+            if let Err(e) = specs.record_expr(&e) {
+                assert_eq!(
+                    e.to_string(),
+                    "sym get-book-info is defined twice, the spec #1 and the spec #2"
+                );
+            }
+        }
+        assert_eq!(specs.into_iter().count(), 1);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_gen_code_with_packages() {
+        let templates = get_all_file_paths_in_folder(
+            &PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("templates"),
+        )
+        .unwrap();
+        let gen_lib = |spec: &str| {
+            let dir = env::temp_dir().join(format!(
+                "lisp-rpc-packages-{}-{}",
+                std::process::id(),
+                spec.len()
+            ));
+            let mut specs = SpecFile::new();
+            for e in lisp_rpc_rust_parser::Parser::new()
+                .parse_root(Cursor::new(spec))
+                .unwrap()
+            {
+                specs.record_expr(&e).unwrap();
+            }
+            specs.gen_code_to_file(dir.clone(), &templates).unwrap();
+            let lib = fs::read_to_string(dir.join("demo/src/lib.rs")).unwrap();
+            fs::remove_dir_all(dir).unwrap();
+            lib.strip_prefix(&format!(
+                "// {}\n// {}\n\n",
+                GENERATED_MARKER,
+                specs.generated_stamp()
+            ))
+            .unwrap()
+            .to_string()
+        };
+
+        // one package is flat
+        let lib = gen_lib("(def-rpc-package demo) (def-msg book-info :title 'string)");
+        assert!(lib.starts_with("#[derive(Debug)]\npub struct BookInfo {"));
+
+        let lib = gen_lib(
+            "(def-rpc-package demo) (def-msg book-info :title 'string)
+(def-rpc-package store) (def-rpc get-book '(:title 'string) 'book-info)",
+        );
+        assert!(lib.starts_with(
+            "pub mod demo {
+    use super::*;
+    #[allow(unused_imports)]
+    use super::store::*;
+
+    #[derive(Debug)]
+    pub struct BookInfo {"
+        ));
+        assert!(lib.contains(
+            "pub mod store {
+    use super::*;
+    #[allow(unused_imports)]
+    use super::demo::*;"
+        ));
+        assert!(lib.contains("fn get_book(&self, req: store::GetBook) -> demo::BookInfo;"));
+    }
+
+    #[test]
+    fn test_gen_code_split_files() {
+        let templates = get_all_file_paths_in_folder(
+            &PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("templates"),
+        )
+        .unwrap();
+        let dir = env::temp_dir().join(format!("lisp-rpc-split-{}", std::process::id()));
+
+        let mut specs = SpecFile::new().config_options(GenOptions {
+            split_files: true,
+            ..Default::default()
+        });
+        for e in lisp_rpc_rust_parser::Parser::new()
+            .parse_root(Cursor::new(
+                "(def-rpc-package demo) (def-msg book-info :title 'string)
+(def-rpc-package store) (def-rpc get-book '(:title 'string) 'book-info)",
+            ))
+            .unwrap()
+        {
+            specs.record_expr(&e).unwrap();
+        }
+        specs.gen_code_to_file(dir.clone(), &templates).unwrap();
+
+        let read = |p: &str| {
+            let content = fs::read_to_string(dir.join("demo").join(p)).unwrap();
+            content.split_once("\n\n").unwrap().1.to_string()
+        };
+        assert_eq!(
+            read("src/lib.rs"),
+            "pub mod demo;\npub mod store;\nmod rpc_method;\npub use rpc_method::*;\nmod server;\npub use server::*;"
+        );
+        assert_eq!(
+            read("src/store/mod.rs"),
+            "use super::*;\n#[allow(unused_imports)]\nuse super::demo::*;\n\nmod get_book;\npub use get_book::*;"
+        );
+        assert!(
+            read("src/demo/book_info.rs")
+                .starts_with("use super::*;\n\n#[derive(Debug)]\npub struct BookInfo {")
+        );
+        assert!(read("src/server.rs").starts_with("use super::*;\n\n"));
+        assert!(read("Cargo.toml").starts_with("[package]\nname = \"demo\""));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_gen_code_again() {
+        let templates = get_all_file_paths_in_folder(
+            &PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("templates"),
+        )
+        .unwrap();
+        let dir = env::temp_dir().join(format!("lisp-rpc-again-{}", std::process::id()));
+
+        let mut specs = SpecFile::new();
+        for e in lisp_rpc_rust_parser::Parser::new()
+            .parse_root(Cursor::new(
+                "(def-rpc-package demo) (def-msg book-info :title 'string)",
+            ))
+            .unwrap()
+        {
+            specs.record_expr(&e).unwrap();
+        }
+
+        // the generated files are overwritten, not appended. The unchanged
+        // files aren't written again
+        specs.gen_code_to_file(dir.clone(), &templates).unwrap();
+        let lib = fs::read_to_string(dir.join("demo/src/lib.rs")).unwrap();
+        assert!(lib.starts_with(&format!(
+            "// {}\n// lisp-rpc-rust-generator {}, spec {}\n\n",
+            GENERATED_MARKER,
+            env!("CARGO_PKG_VERSION"),
+            specs.spec_hash()
+        )));
+        let modified = || {
+            fs::metadata(dir.join("demo/src/lib.rs"))
+                .unwrap()
+                .modified()
+                .unwrap()
+        };
+        let before = modified();
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        specs.gen_code_to_file(dir.clone(), &templates).unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.join("demo/src/lib.rs")).unwrap(),
+            lib
+        );
+        assert_eq!(modified(), before);
+
+        // the same exprs have the same hash, the spaces don't matter
+        let mut same = SpecFile::new();
+        for e in lisp_rpc_rust_parser::Parser::new()
+            .parse_root(Cursor::new(
+                "(def-rpc-package demo)\n\n(def-msg book-info\n    :title 'string)",
+            ))
+            .unwrap()
+        {
+            same.record_expr(&e).unwrap();
+        }
+        assert_eq!(same.spec_hash(), specs.spec_hash());
+
+        // the file written by hand is kept, unless it is forced. The other
+        // files aren't written either
+        fs::write(dir.join("demo/Cargo.toml"), "[package]").unwrap();
+        fs::write(dir.join("demo/src/lib.rs"), lib.clone() + "\n").unwrap();
+        assert!(specs.gen_code_to_file(dir.clone(), &templates).is_err());
+        assert_eq!(
+            fs::read_to_string(dir.join("demo/src/lib.rs")).unwrap(),
+            lib.clone() + "\n"
+        );
+        assert_eq!(
+            fs::read_to_string(dir.join("demo/Cargo.toml")).unwrap(),
+            "[package]"
+        );
+        let specs = specs.config_options(GenOptions {
+            force: true,
+            ..Default::default()
+        });
+        specs.gen_code_to_file(dir.clone(), &templates).unwrap();
+        assert!(
+            fs::read_to_string(dir.join("demo/Cargo.toml"))
+                .unwrap()
+                .starts_with(&format!(
+                    "# {}\n# {}\n\n[package]",
+                    GENERATED_MARKER,
+                    specs.generated_stamp()
+                ))
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_resolve_include() {
+        assert_eq!(
+            resolve_include("specs/main.lisp", "common.lisp").unwrap(),
+            "specs/common.lisp"
+        );
+        assert_eq!(
+            resolve_include("https://example.com/specs/main.lisp", "common.lisp").unwrap(),
+            "https://example.com/specs/common.lisp"
+        );
+        assert_eq!(
+            resolve_include("specs/main.lisp", "https://example.com/common.lisp").unwrap(),
+            "https://example.com/common.lisp"
+        );
+    }
+
+    #[test]
+    fn test_load_templates_layers() {
+        let dir = env::temp_dir().join(format!("lisp-rpc-templates-{}", std::process::id()));
+        let (stock, mine) = (dir.join("stock"), dir.join("mine"));
+        fs::create_dir_all(stock.join("partials")).unwrap();
+        fs::create_dir_all(&mine).unwrap();
+        fs::write(
+            stock.join("def_struct.rs.template"),
+            "struct {% block body %}{{ name }}{% endblock %}",
+        )
+        .unwrap();
+        fs::write(stock.join("rpc_impl.template"), "impl {{ name }}").unwrap();
+        fs::write(stock.join("partials/head.template"), "// {{ name }}").unwrap();
+        fs::write(
+            mine.join("def_struct.rs.template"),
+            r#"{% extends "base/def_struct.rs" %}{% block body %}{% include "partials/head" %} {{ super() }}{% endblock %}"#,
+        )
+        .unwrap();
+        fs::write(mine.join(".notes"), "not a template").unwrap();
+
+        let names = template_names(&[&stock, &mine]).unwrap();
+        assert_eq!(
+            names.keys().collect::<Vec<_>>(),
+            vec![
+                "base/def_struct.rs",
+                "def_struct.rs",
+                "partials/head",
+                "rpc_impl"
+            ]
+        );
+
+        let tera = load_templates(&[&stock, &mine]).unwrap();
+        let mut context = tera::Context::new();
+        context.insert("name", "Book");
+        assert_eq!(
+            tera.render("def_struct.rs", &context).unwrap(),
+            "struct // Book Book"
+        );
+        assert_eq!(tera.render("rpc_impl", &context).unwrap(), "impl Book");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
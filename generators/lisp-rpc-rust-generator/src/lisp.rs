@@ -0,0 +1,340 @@
+//! the common lisp code of the specs, one package of the generated crate.
+//! The msgs and the rpc requests are the classes with the to-rpc and the
+//! from-rpc methods, the rpcs are the generic functions of the handler, and
+//! the dispatch function is the server
+//!
+//! the slot values are the lisp values: the string, the integer, the keyword,
+//! the instance of the msg, the list, the alist of the (map 'string 'v), the
+//! seconds of the timestamp, and the base64 string of the bytes
+
+use std::collections::BTreeSet;
+
+use anyhow::Result;
+use lisp_rpc_rust_parser::{Atom, Expr, TypeValue};
+use serde::Serialize;
+use tera::{Context, Tera};
+
+use crate::{SpecFields, VersionRange, map_type, optional_type, quote_string, type_options};
+
+/// the name of the template of the lisp file
+pub const LISP_TEMPLATE: &str = "package.lisp";
+
+#[derive(Debug, Serialize)]
+struct LispSlot {
+    name: String,
+    doc: Option<String>,
+    /// the lisp literal of the initform, the required slot has none
+    initform: Option<String>,
+    /// the lisp form of the encoded value `v`
+    encode: String,
+    /// the lisp form of the decoded data `d`
+    decode: String,
+    /// the lisp form when the keyword is missing
+    missing: String,
+    optional: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct LispClass {
+    name: String,
+    doc: Option<String>,
+    /// the anonymous map, it is encoded as '(:k v) instead of (name :k v)
+    map: bool,
+    slots: Vec<LispSlot>,
+}
+
+#[derive(Debug, Serialize)]
+struct LispRPC {
+    name: String,
+    doc: Option<String>,
+    returns: bool,
+    versions: VersionRange,
+
+    /// the notification, the server calls the method without the response
+    notify: bool,
+}
+
+/// render the lisp package of the specs
+pub(crate) fn render_lisp(
+    templates: &Tera,
+    package: &str,
+    version: Option<i64>,
+    specs: &[SpecFields<'_>],
+) -> Result<String> {
+    let mut names = BTreeSet::new();
+    if let Some(dup) = specs.iter().find(|f| !names.insert(f.name)) {
+        anyhow::bail!(
+            "{} is defined twice, the lisp package needs the unique names",
+            dup.name
+        )
+    }
+
+    let mut classes = vec![];
+    for f in specs {
+        class(
+            f.name,
+            f.doc.filter(|_| !f.is_rpc),
+            &f.fields,
+            false,
+            &mut classes,
+        )?;
+    }
+
+    let rpcs: Vec<LispRPC> = specs
+        .iter()
+        .filter(|f| f.is_rpc)
+        .map(|f| LispRPC {
+            name: f.name.to_string(),
+            doc: f.doc.map(quote_string),
+            returns: f.returns.is_some(),
+            versions: f.versions,
+            notify: f.notify,
+        })
+        .collect();
+
+    // the classes and the accessors, the accessor can be the class of the
+    // anonymous map, like book-info-extra
+    let mut exports = vec![];
+    for c in &classes {
+        for n in std::iter::once(c.name.clone())
+            .chain(c.slots.iter().map(|s| format!("{}-{}", c.name, s.name)))
+        {
+            if !exports.contains(&n) {
+                exports.push(n)
+            }
+        }
+    }
+
+    let mut context = Context::new();
+    context.insert("package", package);
+    context.insert("exports", &exports);
+    context.insert("version", &version);
+    context.insert("classes", &classes);
+    context.insert("rpcs", &rpcs);
+    Ok(templates.render(LISP_TEMPLATE, &context)?)
+}
+
+/// add the class of the keyword-type pairs, and the classes of its anonymous
+/// maps after it
+fn class(
+    name: &str,
+    doc: Option<&str>,
+    fields: &[Expr],
+    map: bool,
+    classes: &mut Vec<LispClass>,
+) -> Result<()> {
+    let at = classes.len();
+    let mut c = LispClass {
+        name: name.to_string(),
+        doc: doc.map(quote_string),
+        map,
+        slots: vec![],
+    };
+
+    for [k, ty] in fields.iter().array_chunks() {
+        let Expr::Atom(Atom {
+            value: TypeValue::Keyword(k),
+        }) = k
+        else {
+            anyhow::bail!("{}: spec has to be keyword-type pairs", name)
+        };
+
+        let options = type_options(ty);
+        let inner = options.as_ref().map_or(ty, |o| o.inner);
+        let optional = optional_type(inner).is_some();
+        let default = options
+            .as_ref()
+            .and_then(|o| o.default)
+            .map(|d| d.to_string());
+        let (encode, decode) = codec(name, k, inner, classes)?;
+
+        c.slots.push(LispSlot {
+            name: k.to_string(),
+            doc: options.as_ref().and_then(|o| o.doc).map(quote_string),
+            initform: match (&default, optional) {
+                (Some(d), _) => Some(d.clone()),
+                (None, true) => Some("nil".to_string()),
+                (None, false) => None,
+            },
+            encode,
+            decode,
+            missing: match (default, optional) {
+                (Some(d), _) => d,
+                (None, true) => "nil".to_string(),
+                (None, false) => format!(
+                    "(error 'rpc-decode-error :message {})",
+                    quote_string(&format!("missing {} of {}", k, name))
+                ),
+            },
+            optional,
+        })
+    }
+
+    classes.insert(at, c);
+    Ok(())
+}
+
+/// the lisp forms encoding the value `v` and decoding the data `d` of the type
+fn codec(
+    owner: &str,
+    k: &str,
+    ty: &Expr,
+    classes: &mut Vec<LispClass>,
+) -> Result<(String, String)> {
+    if let Some(t) = optional_type(ty) {
+        return Ok(type_codec(t));
+    }
+    if let Some((_, v)) = map_type(ty) {
+        let (enc, dec) = type_codec(v);
+        return Ok((
+            format!(
+                "(list 'quote (loop for (k . x) in v append (let ((v x)) (list (intern (string-upcase k) :keyword) {}))))",
+                enc
+            ),
+            format!(
+                "(loop for (k x) on (unquote d) by #'cddr collect (let ((d x)) (cons (string-downcase (symbol-name k)) {})))",
+                dec
+            ),
+        ));
+    }
+
+    match ty {
+        Expr::Quote(box Expr::Atom(Atom {
+            value: TypeValue::Symbol(t),
+        })) => Ok(type_codec(t)),
+        Expr::Quote(box Expr::List(inner)) | Expr::List(inner) => match inner.as_slice() {
+            [
+                Expr::Atom(Atom {
+                    value: TypeValue::Symbol(l),
+                }),
+                ele,
+            ] if l == "list" => {
+                let (enc, dec) = codec(owner, k, ele, classes)?;
+                Ok((
+                    format!("(list 'quote (mapcar (lambda (v) {}) v))", enc),
+                    format!("(mapcar (lambda (d) {}) (unquote d))", dec),
+                ))
+            }
+            [
+                Expr::Atom(Atom {
+                    value: TypeValue::Keyword(_),
+                }),
+                ..,
+            ] => {
+                let name = format!("{}-{}", owner, k);
+                class(&name, None, inner, true, classes)?;
+                Ok(type_codec(&name))
+            }
+            _ => anyhow::bail!("{}.{}: unknown spec type {}", owner, k, ty),
+        },
+        _ => anyhow::bail!("{}.{}: unknown spec type {}", owner, k, ty),
+    }
+}
+
+fn type_codec(t: &str) -> (String, String) {
+    match t {
+        "string" | "number" | "keyword" | "uuid" => ("v".to_string(), "d".to_string()),
+        "timestamp" | "bytes" => (format!("(list '{} v)", t), "(second d)".to_string()),
+        msg => ("(to-rpc v)".to_string(), format!("(from-rpc '{} d)", msg)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use lisp_rpc_rust_parser::Parser;
+
+    use super::*;
+    use crate::SpecFile;
+
+    /// the parens out of the strings and the chars like #\( are balanced
+    fn balanced(code: &str) -> bool {
+        let (mut depth, mut in_str, mut escaped) = (0, false, false);
+        let code = code.replace("#\\(", "").replace("#\\)", "");
+        for c in code.chars() {
+            match (in_str, c) {
+                (true, '\\') if !escaped => {
+                    escaped = true;
+                    continue;
+                }
+                (true, '"') if !escaped => in_str = false,
+                (false, '"') => in_str = true,
+                (false, '(') => depth += 1,
+                (false, ')') => depth -= 1,
+                _ => (),
+            }
+            if depth < 0 {
+                return false;
+            }
+            escaped = false;
+        }
+        depth == 0 && !in_str
+    }
+
+    #[test]
+    fn test_render_lisp() {
+        let mut specs = SpecFile::new();
+        for e in Parser::new()
+            .parse_root(Cursor::new(
+                r#"(def-rpc-package library)
+(def-version 2)
+(def-msg book-info "the \"book\""
+    :title ('string :doc "the title")
+    :tags '(list 'string)
+    :lang (optional 'language-perfer)
+    :prices '(map 'string 'number)
+    :published 'timestamp
+    :extra '(:cover 'bytes))
+(def-msg language-perfer :lang ('string :default "en"))
+(def-rpc get-book "find the book" :since 2 '(:title 'string) 'book-info)
+(def-rpc return-book '(:books '(list 'book-info)))"#,
+            ))
+            .unwrap()
+        {
+            specs.record_expr(&e).unwrap();
+        }
+
+        let mut tera = Tera::default();
+        tera.add_raw_template(
+            LISP_TEMPLATE,
+            include_str!("../templates/package.lisp.template"),
+        )
+        .unwrap();
+        let code = specs.gen_lisp(&tera).unwrap();
+        assert!(balanced(&code), "{}", code);
+
+        for part in [
+            "#:book-info #:book-info-title #:book-info-tags #:book-info-lang #:book-info-prices
+           #:book-info-published #:book-info-extra #:book-info-extra-cover #:language-perfer",
+            r#"(defclass book-info ()
+  ((title :initarg :title :accessor book-info-title :documentation "the title")
+   (tags :initarg :tags :accessor book-info-tags)
+   (lang :initarg :lang :accessor book-info-lang :initform nil)
+   (prices :initarg :prices :accessor book-info-prices)
+   (published :initarg :published :accessor book-info-published)
+   (extra :initarg :extra :accessor book-info-extra))
+  (:documentation "the \"book\""))"#,
+            r#"(defmethod to-rpc ((obj book-info))
+  (append (list 'book-info)
+          (let ((v (book-info-title obj))) (list :title v))
+          (let ((v (book-info-tags obj))) (list :tags (list 'quote (mapcar (lambda (v) v) v))))
+          (let ((v (book-info-lang obj))) (when v (list :lang (to-rpc v))))"#,
+            r#"(defmethod to-rpc ((obj book-info-extra))
+  (list 'quote (append
+          (let ((v (book-info-extra-cover obj))) (list :cover (list 'bytes v))))))"#,
+            r#"(defmethod from-rpc ((type (eql 'language-perfer)) d)
+  (let ((plist (rpc-fields "language-perfer" d)))
+    (make-instance 'language-perfer
+      :lang (multiple-value-bind (k d) (get-properties plist '(:lang)) (if k d "en")))))"#,
+            r#":title (multiple-value-bind (k d) (get-properties plist '(:title)) (if k d (error 'rpc-decode-error :message "missing title of book-info")))"#,
+            r#"(defgeneric get-book (handler req)
+  (:documentation "find the book"))"#,
+            r#"((and (string= name "get-book") (not (<= 2 version 2)))"#,
+            r#"(list 'response :status "ok" :result (to-rpc (get-book handler (from-rpc 'get-book d)))))"#,
+            r#"(progn (return-book handler (from-rpc 'return-book d))"#,
+        ] {
+            assert!(code.contains(part), "{}\n\nnot in\n\n{}", part, code);
+        }
+    }
+}
@@ -1,81 +1,225 @@
 use anyhow::{Context, Result};
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use lisp_rpc_rust_generator::*;
-use std::fs::{self, File};
-use std::io;
+use std::fs;
 use std::path::PathBuf;
 
 #[derive(Parser, Debug)]
-#[command(author, version, about, long_about = None)]
+#[command(author, version, about, long_about = None, subcommand_negates_reqs = true)]
 struct Args {
-    #[arg(short, long, value_name = "spec-file")]
-    input_file: PathBuf,
+    #[command(subcommand)]
+    command: Option<Command>,
 
+    /// the spec files, the specs of several files are merged in order
+    #[arg(short, long, value_name = "spec-file", required = true)]
+    input_file: Vec<PathBuf>,
+
+    /// the templates folder, the templates in the nested folders are found
+    /// too. The templates of the later folder override the same ones of the
+    /// earlier folders and the stock templates. It can be the git url or the
+    /// http directory index of the shared template set
     #[arg(short, long, value_name = "templates-path")]
-    templates_path: PathBuf,
+    templates_path: Vec<PathBuf>,
+
+    /// the folder caching the remote template sets
+    #[arg(long, value_name = "cache-path")]
+    template_cache: Option<PathBuf>,
+
+    /// use the templates of the folders only, without the stock templates
+    #[arg(long)]
+    no_default_templates: bool,
+
+    #[arg(short, long, value_name = "output-path", required = true)]
+    output_path: Option<PathBuf>,
+
+    /// make the fields of the generated structs pub, instead of the getters
+    #[arg(long)]
+    pub_fields: bool,
+
+    /// generate the builders of the generated structs
+    #[arg(long)]
+    builders: bool,
+
+    /// generate the async client and server instead of the sync ones
+    #[arg(long = "async")]
+    async_api: bool,
+
+    /// derive the serde traits of the generated structs
+    #[arg(long)]
+    serde: bool,
+
+    /// generate the def-alias as the type alias instead of the newtype
+    #[arg(long)]
+    type_aliases: bool,
+
+    /// write one file per msg, rpc, and service instead of the single lib.rs
+    #[arg(long)]
+    split_files: bool,
+
+    /// overwrite the output files even they aren't generated by this generator
+    #[arg(long)]
+    force: bool,
+
+    /// allow the specs in different packages to have the same name
+    #[arg(long)]
+    allow_shadowing: bool,
+
+    /// compare the spec with the old spec before generating, fail if there
+    /// are breaking changes
+    #[arg(long, value_name = "old-spec-file")]
+    breaking_against: Option<PathBuf>,
+
+    /// generate the api document of the specs instead of the code, markdown
+    /// or html
+    #[arg(long, value_name = "format")]
+    docs: Option<DocFormat>,
+
+    /// generate the json schemas of the msgs instead of the code
+    #[arg(long)]
+    json_schema: bool,
+
+    /// generate the proto3 files of the specs instead of the code
+    #[arg(long)]
+    proto: bool,
+
+    /// generate the common lisp classes and server instead of the rust code
+    #[arg(long)]
+    lisp: bool,
 
-    #[arg(short, long, value_name = "output-path")]
-    output_path: PathBuf,
+    /// generate the python dataclasses and client instead of the rust code
+    #[arg(long)]
+    python: bool,
+
+    /// build the rust code as the syn tokens instead of the templates, the
+    /// options of the rust code are ignored
+    #[arg(long)]
+    tokens: bool,
+
+    /// format the generated rust files by rustfmt or prettyplease, the
+    /// invalid rust fails the generating
+    #[arg(long, value_name = "formatter")]
+    format: Option<RustFormatter>,
 }
 
-fn parse_spec_file(file: File) -> Result<SpecFile> {
-    let mut parser: lisp_rpc_rust_parser::Parser = Default::default();
-
-    let exprs = parser
-        .parse_root(file)
-        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
-
-    let mut specs = SpecFile::new();
-    for expr in &exprs {
-        if DefRPC::if_def_rpc_expr(expr) {
-            specs.record_one(Box::new(DefRPC::from_expr(expr)?))?;
-        } else if DefMsg::if_def_msg_expr(expr) {
-            specs.record_one(Box::new(DefMsg::from_expr(expr)?))?
-        } else if DefPkg::if_def_pkg_expr(expr) {
-            specs.record_one(Box::new(DefPkg::from_expr(expr)?))?
-        } else {
-            anyhow::bail!("unknown expr: {expr}");
-        }
-    }
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// convert the messages of the .proto file to the def-msg specs
+    ProtoImport {
+        #[arg(value_name = "proto-file")]
+        proto_file: PathBuf,
 
-    Ok(specs)
+        /// write the specs to the file instead of the stdout
+        #[arg(short, long, value_name = "spec-file")]
+        output: Option<PathBuf>,
+
+        /// overwrite the existing spec file
+        #[arg(long)]
+        force: bool,
+    },
 }
 
 fn main() -> Result<()> {
     let args = Args::parse();
 
-    let input_path = &args.input_file;
+    if let Some(Command::ProtoImport {
+        proto_file,
+        output,
+        force,
+    }) = &args.command
+    {
+        let specs = import_proto(
+            &fs::read_to_string(proto_file)
+                .with_context(|| format!("Failed to read file: {:?}", proto_file))?,
+        )?;
+        return match output {
+            Some(o) if o.exists() && !force => {
+                anyhow::bail!("{:?} exists, use --force to overwrite it", o)
+            }
+            Some(o) => Ok(fs::write(o, specs + "\n")?),
+            None => {
+                println!("{}", specs);
+                Ok(())
+            }
+        };
+    }
+
+    let output_path = args.output_path.clone().context("no output path")?;
+
+    for input_path in &args.input_file {
+        if !input_path.exists() {
+            eprintln!("Error: Input file does not exist at {:?}", input_path);
+            anyhow::bail!("Input file not found");
+        }
 
-    if !input_path.exists() {
-        eprintln!("Error: Input file does not exist at {:?}", input_path);
-        anyhow::bail!("Input file not found");
+        if !input_path.is_file() {
+            eprintln!("Error: Path {:?} is not a file.", input_path);
+            anyhow::bail!("Path is not a file");
+        }
     }
 
-    if !input_path.is_file() {
-        eprintln!("Error: Path {:?} is not a file.", input_path);
-        anyhow::bail!("Path is not a file");
+    let options = GenOptions {
+        pub_fields: args.pub_fields,
+        builders: args.builders,
+        async_api: args.async_api,
+        serde: args.serde,
+        type_aliases: args.type_aliases,
+        split_files: args.split_files,
+        force: args.force,
+        allow_shadowing: args.allow_shadowing,
+        default_templates: !args.no_default_templates,
+        format: args.format,
+        ..Default::default()
+    };
+    // the spec files and the files they include, the symbols defined by
+    // several files are reported with their sites
+    let mut specs = SpecFile::new().config_options(options.clone());
+    for input_path in &args.input_file {
+        let file = SpecFile::new()
+            .config_options(options.clone())
+            .read(&input_path.to_string_lossy())?;
+        specs = specs.merge(file)?;
     }
 
-    let file = File::open(input_path)?;
-    let specs = parse_spec_file(file)?;
-
-    // read all template file
-    let mut templates = vec![];
-    if args.templates_path.is_dir() {
-        for entry in fs::read_dir(args.templates_path)? {
-            let entry_path = entry?.path();
-            if entry_path.is_file() {
-                templates.push(
-                    entry_path
-                        .to_str()
-                        .context("cannot convert to string")?
-                        .to_string(),
-                );
-            }
+    if let Some(old_path) = &args.breaking_against {
+        let old = SpecFile::new()
+            .config_options(GenOptions {
+                allow_shadowing: args.allow_shadowing,
+                ..Default::default()
+            })
+            .read(&old_path.to_string_lossy())?;
+        let diff = SpecFile::diff(&old, &specs);
+        print!("{}", diff);
+        if diff.is_breaking() {
+            anyhow::bail!("the spec has breaking changes against {:?}", old_path)
         }
-    } else {
-        anyhow::bail!("templates_path has to be dir")
     }
 
-    specs.gen_code_to_file(args.output_path, &templates)
+    let cache = args
+        .template_cache
+        .clone()
+        .unwrap_or_else(default_template_cache);
+    let templates = &args
+        .templates_path
+        .iter()
+        .map(|p| fetch_template_set(&p.to_string_lossy(), &cache))
+        .collect::<Result<Vec<_>>>()?;
+    if let Some(p) = templates.iter().find(|p| !p.is_dir()) {
+        anyhow::bail!("templates_path {:?} has to be dir", p)
+    }
+    if templates.is_empty() && args.no_default_templates {
+        anyhow::bail!("--no-default-templates needs the templates path")
+    }
+
+    let target = match args.docs {
+        Some(format) => GenerateTarget::Docs(format),
+        None if args.json_schema => GenerateTarget::JsonSchema,
+        None if args.proto => GenerateTarget::Proto,
+        None if args.lisp => GenerateTarget::Lisp,
+        None if args.python => GenerateTarget::Python,
+        None if args.tokens => GenerateTarget::Tokens,
+        None => GenerateTarget::Rust,
+    };
+    specs
+        .generate(target, templates)?
+        .write_to(&output_path, args.force)
 }
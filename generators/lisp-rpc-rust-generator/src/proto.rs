@@ -0,0 +1,415 @@
+//! the protobuf (proto3) files of the specs, one file per package. The msgs
+//! and the rpc requests are the messages, the anonymous maps are the messages
+//! named after the owner and the keyword, and the rpcs are the services
+//!
+//! the field numbers are the positions of the keywords, inserting or removing
+//! the keyword in the middle changes the numbers of the fields after it
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use anyhow::Result;
+use lisp_rpc_rust_parser::{Atom, Expr, TypeValue};
+use serde::Serialize;
+use tera::{Context, Tera};
+
+use crate::{
+    SpecFields, kebab_to_pascal_case, kebab_to_snake_case, map_type, optional_type, type_options,
+};
+
+/// the name of the template of the proto file
+pub const PROTO_TEMPLATE: &str = "package.proto";
+
+#[derive(Debug, Serialize)]
+struct ProtoField {
+    comments: Vec<String>,
+    /// the "optional " or the "repeated ", empty for the plain field
+    label: &'static str,
+    ty: String,
+    name: String,
+    number: usize,
+    deprecated: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct ProtoMessage {
+    comments: Vec<String>,
+    name: String,
+    fields: Vec<ProtoField>,
+}
+
+#[derive(Debug, Serialize)]
+struct ProtoRPC {
+    comments: Vec<String>,
+    name: String,
+    req: String,
+    resp: String,
+}
+
+#[derive(Debug, Serialize)]
+struct ProtoService {
+    comments: Vec<String>,
+    name: String,
+    rpcs: Vec<ProtoRPC>,
+}
+
+/// the specs of the package, and the services of the rpcs. The def-rpcs
+/// outside the def-service are in the service named after the package
+pub(crate) struct ProtoPackage<'a> {
+    name: String,
+    specs: Vec<SpecFields<'a>>,
+
+    /// the service names and docs, and the rpc names of them
+    services: Vec<(String, Option<&'a str>, Vec<&'a str>)>,
+}
+
+impl<'a> ProtoPackage<'a> {
+    pub(crate) fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            specs: vec![],
+            services: vec![],
+        }
+    }
+
+    /// add the specs, the rpcs are in the service if it is given
+    pub(crate) fn push(
+        &mut self,
+        fields: Vec<SpecFields<'a>>,
+        service: Option<(&str, Option<&'a str>)>,
+    ) {
+        let rpcs: Vec<&str> = fields.iter().filter(|f| f.is_rpc).map(|f| f.name).collect();
+        if !rpcs.is_empty() {
+            let (name, doc) = match service {
+                Some((n, d)) => (n.to_string(), d),
+                None => (self.name.clone(), None),
+            };
+            match self.services.iter_mut().find(|(n, _, _)| *n == name) {
+                Some((_, _, s)) => s.extend(rpcs),
+                None => self.services.push((name, doc, rpcs)),
+            }
+        }
+        self.specs.extend(fields);
+    }
+
+    pub(crate) fn spec_count(&self) -> usize {
+        self.specs.len()
+    }
+
+    /// the proto package name
+    pub(crate) fn proto_name(&self) -> String {
+        kebab_to_snake_case(&self.name)
+    }
+
+    /// the file name of the proto file
+    pub(crate) fn file_name(&self) -> String {
+        format!("{}.proto", self.proto_name())
+    }
+}
+
+/// the state of rendering one package
+struct Builder<'a> {
+    /// the msg names to the proto packages defining them
+    owners: &'a BTreeMap<&'a str, String>,
+    package: String,
+    imports: BTreeSet<String>,
+    messages: Vec<ProtoMessage>,
+}
+
+/// render the proto files of the packages, the file names and the contents.
+/// The msgs of the other packages are imported
+pub(crate) fn render_protos(
+    templates: &Tera,
+    packages: &[ProtoPackage<'_>],
+) -> Result<Vec<(String, String)>> {
+    let owners: BTreeMap<&str, String> = packages
+        .iter()
+        .flat_map(|p| {
+            p.specs
+                .iter()
+                .filter(|f| !f.is_rpc)
+                .map(|f| (f.name, p.proto_name()))
+        })
+        .collect();
+
+    let mut files = vec![];
+    for p in packages {
+        let mut b = Builder {
+            owners: &owners,
+            package: p.proto_name(),
+            imports: BTreeSet::new(),
+            messages: vec![],
+        };
+
+        // the docs of the rpcs are on the rpcs of the services
+        for f in &p.specs {
+            b.message(f.name, f.doc.filter(|_| !f.is_rpc), &f.fields)?;
+        }
+
+        let mut services = vec![];
+        for (name, doc, rpcs) in &p.services {
+            let mut s = ProtoService {
+                comments: comment_lines(*doc),
+                name: kebab_to_pascal_case(name),
+                rpcs: vec![],
+            };
+            for f in p.specs.iter().filter(|f| rpcs.contains(&f.name)) {
+                s.rpcs.push(ProtoRPC {
+                    comments: comment_lines(f.doc),
+                    name: kebab_to_pascal_case(f.name),
+                    req: kebab_to_pascal_case(f.name),
+                    resp: match f.returns {
+                        Some(r) => b.type_name(r),
+                        None => {
+                            b.imports.insert("google/protobuf/empty.proto".to_string());
+                            "google.protobuf.Empty".to_string()
+                        }
+                    },
+                })
+            }
+            services.push(s);
+        }
+
+        let mut context = Context::new();
+        context.insert("package", &b.package);
+        context.insert("imports", &b.imports);
+        context.insert("messages", &b.messages);
+        context.insert("services", &services);
+        files.push((p.file_name(), templates.render(PROTO_TEMPLATE, &context)?));
+    }
+
+    Ok(files)
+}
+
+impl Builder<'_> {
+    /// add the message of the keyword-type pairs, and the messages of its
+    /// anonymous maps
+    fn message(&mut self, name: &str, doc: Option<&str>, fields: &[Expr]) -> Result<()> {
+        // the messages of the anonymous maps are after this message
+        let at = self.messages.len();
+        let mut m = ProtoMessage {
+            comments: comment_lines(doc),
+            name: kebab_to_pascal_case(name),
+            fields: vec![],
+        };
+
+        for (i, [k, ty]) in fields.iter().array_chunks().enumerate() {
+            let Expr::Atom(Atom {
+                value: TypeValue::Keyword(k),
+            }) = k
+            else {
+                anyhow::bail!("{}: spec has to be keyword-type pairs", name)
+            };
+
+            let options = type_options(ty);
+            let (label, proto_ty) =
+                self.field_type(name, k, options.as_ref().map_or(ty, |o| o.inner))?;
+
+            let mut comments = comment_lines(options.as_ref().and_then(|o| o.doc));
+            if let Some(d) = options.as_ref().and_then(|o| o.default) {
+                comments.push(format!("default {}", d));
+            }
+            if let Some(n) = options.as_ref().and_then(|o| o.deprecated) {
+                comments.push(format!("deprecated: {}", n));
+            }
+
+            m.fields.push(ProtoField {
+                comments,
+                label,
+                ty: proto_ty,
+                name: kebab_to_snake_case(k),
+                number: i + 1,
+                deprecated: options.as_ref().is_some_and(|o| o.deprecated.is_some()),
+            })
+        }
+
+        self.messages.insert(at, m);
+        Ok(())
+    }
+
+    /// the label and the proto type of the field
+    fn field_type(&mut self, owner: &str, k: &str, ty: &Expr) -> Result<(&'static str, String)> {
+        if let Some(t) = optional_type(ty) {
+            return Ok(("optional ", self.type_name(t)));
+        }
+        if let Some((_, v)) = map_type(ty) {
+            return Ok(("", format!("map<string, {}>", self.type_name(v))));
+        }
+
+        match ty {
+            Expr::Quote(box Expr::Atom(Atom {
+                value: TypeValue::Symbol(t),
+            })) => Ok(("", self.type_name(t))),
+            Expr::Quote(box Expr::List(inner)) | Expr::List(inner) => match inner.as_slice() {
+                [
+                    Expr::Atom(Atom {
+                        value: TypeValue::Symbol(l),
+                    }),
+                    ele,
+                ] if l == "list" => match self.field_type(owner, k, ele)? {
+                    ("", t) if !t.starts_with("map<") => Ok(("repeated ", t)),
+                    _ => anyhow::bail!(
+                        "{}.{}: the list of the optional, list, or map can't be the proto field",
+                        owner,
+                        k
+                    ),
+                },
+                [
+                    Expr::Atom(Atom {
+                        value: TypeValue::Keyword(_),
+                    }),
+                    ..,
+                ] => {
+                    let name = format!("{}-{}", owner, k);
+                    self.message(&name, None, inner)?;
+                    Ok(("", kebab_to_pascal_case(&name)))
+                }
+                _ => anyhow::bail!("{}.{}: unknown spec type {}", owner, k, ty),
+            },
+            _ => anyhow::bail!("{}.{}: unknown spec type {}", owner, k, ty),
+        }
+    }
+
+    /// the proto type of the primary type or the msg, the msg of the other
+    /// package is imported
+    fn type_name(&mut self, t: &str) -> String {
+        match t {
+            "string" | "keyword" | "uuid" => "string".to_string(),
+            "number" => "int64".to_string(),
+            "bytes" => "bytes".to_string(),
+            "timestamp" => {
+                self.imports
+                    .insert("google/protobuf/timestamp.proto".to_string());
+                "google.protobuf.Timestamp".to_string()
+            }
+            msg => match self.owners.get(msg) {
+                Some(p) if *p != self.package => {
+                    self.imports.insert(format!("{}.proto", p));
+                    format!("{}.{}", p, kebab_to_pascal_case(msg))
+                }
+                _ => kebab_to_pascal_case(msg),
+            },
+        }
+    }
+}
+
+fn comment_lines(doc: Option<&str>) -> Vec<String> {
+    doc.map(|d| d.lines().map(|l| l.trim().to_string()).collect())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use lisp_rpc_rust_parser::Parser;
+
+    use super::*;
+    use crate::SpecFile;
+
+    #[test]
+    fn test_render_protos() {
+        let mut specs = SpecFile::new();
+        for e in Parser::new()
+            .parse_root(Cursor::new(
+                r#"(def-rpc-package library)
+(def-msg book-info "the book"
+    :title ('string :doc "the title")
+    :tags '(list 'string)
+    :lang (optional 'language-perfer)
+    :prices '(map 'string 'number)
+    :published 'timestamp
+    :extra '(:kind 'keyword :cover ('bytes :deprecated "no cover")))
+(def-msg language-perfer :lang ('string :default "en"))
+(def-rpc get-book "find the book" '(:title 'string) 'book-info)
+(def-service shelf "the shelf"
+    (put-book '(:book 'book-info)))
+(def-rpc-package store)
+(def-rpc buy-book '(:book 'book-info :count 'number))"#,
+            ))
+            .unwrap()
+        {
+            specs.record_expr(&e).unwrap();
+        }
+
+        let mut tera = Tera::default();
+        tera.add_raw_template(
+            PROTO_TEMPLATE,
+            include_str!("../templates/package.proto.template"),
+        )
+        .unwrap();
+        let files = specs.gen_protos(&tera).unwrap();
+        assert_eq!(
+            files.iter().map(|(n, _)| n.as_str()).collect::<Vec<_>>(),
+            vec!["library.proto", "store.proto"]
+        );
+
+        assert_eq!(
+            files[0].1,
+            r#"syntax = "proto3";
+
+package library;
+
+import "google/protobuf/empty.proto";
+import "google/protobuf/timestamp.proto";
+
+// the book
+message BookInfo {
+  // the title
+  string title = 1;
+  repeated string tags = 2;
+  optional LanguagePerfer lang = 3;
+  map<string, int64> prices = 4;
+  google.protobuf.Timestamp published = 5;
+  BookInfoExtra extra = 6;
+}
+
+message BookInfoExtra {
+  string kind = 1;
+  // deprecated: no cover
+  bytes cover = 2 [deprecated = true];
+}
+
+message LanguagePerfer {
+  // default "en"
+  string lang = 1;
+}
+
+message GetBook {
+  string title = 1;
+}
+
+message PutBook {
+  BookInfo book = 1;
+}
+
+service Library {
+  // find the book
+  rpc GetBook(GetBook) returns (BookInfo);
+}
+
+// the shelf
+service Shelf {
+  rpc PutBook(PutBook) returns (google.protobuf.Empty);
+}"#
+        );
+
+        assert_eq!(
+            files[1].1,
+            r#"syntax = "proto3";
+
+package store;
+
+import "google/protobuf/empty.proto";
+import "library.proto";
+
+message BuyBook {
+  library.BookInfo book = 1;
+  int64 count = 2;
+}
+
+service Store {
+  rpc BuyBook(BuyBook) returns (google.protobuf.Empty);
+}"#
+        );
+    }
+}
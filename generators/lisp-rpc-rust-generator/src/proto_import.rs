@@ -0,0 +1,570 @@
+//! convert the messages of the .proto file to the def-msg specs, the reverse
+//! of the proto export:
+//!
+//! + the message => def-msg, the nested message is named after its parents,
+//!   like `book-info-extra`
+//! + the int and the fixed types => 'number, the enum => 'keyword, the
+//!   google.protobuf.Timestamp => 'timestamp
+//! + the repeated field => '(list 'type), the map<string, V> => '(map 'string 'V)
+//! + the optional field and the field of the oneof => (optional 'type)
+//! + the leading comments => the docstrings, the reserved names => (reserved ...)
+//!
+//! the services are skipped, the bool, the float, and the double have no spec
+//! type and fail
+
+use anyhow::Result;
+
+use crate::{quote_string, to_kebab_case};
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    /// the names, the numbers, and the keywords of proto
+    Word(String),
+    Str(String),
+    Punct(char),
+}
+
+/// the token and the comments on the lines before it
+#[derive(Debug)]
+struct Lexeme {
+    token: Token,
+    comments: Vec<String>,
+}
+
+fn lex(source: &str) -> Result<Vec<Lexeme>> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut out = vec![];
+    let mut comments = vec![];
+    let (mut i, mut line, mut last_line) = (0, 1, 0);
+
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '\n' => {
+                line += 1;
+                i += 1;
+            }
+            c if c.is_whitespace() => i += 1,
+            '/' if chars.get(i + 1) == Some(&'/') => {
+                let end = chars[i..]
+                    .iter()
+                    .position(|c| *c == '\n')
+                    .map_or(chars.len(), |p| i + p);
+                // the comment after the token on the same line isn't the doc
+                if line != last_line {
+                    comments.push(
+                        chars[i + 2..end]
+                            .iter()
+                            .collect::<String>()
+                            .trim()
+                            .to_string(),
+                    );
+                }
+                i = end;
+            }
+            '/' if chars.get(i + 1) == Some(&'*') => {
+                let end = (i + 2..chars.len().saturating_sub(1))
+                    .find(|j| chars[*j] == '*' && chars[j + 1] == '/')
+                    .ok_or_else(|| anyhow::anyhow!("line {}: unclosed comment", line))?;
+                let text: String = chars[i + 2..end].iter().collect();
+                if line != last_line {
+                    comments.extend(
+                        text.lines()
+                            .map(|l| l.trim().trim_start_matches('*').trim().to_string())
+                            .filter(|l| !l.is_empty()),
+                    );
+                }
+                line += text.matches('\n').count();
+                i = end + 2;
+            }
+            '"' | '\'' => {
+                let end = (i + 1..chars.len())
+                    .find(|j| chars[*j] == c && chars[j - 1] != '\\')
+                    .ok_or_else(|| anyhow::anyhow!("line {}: unclosed string", line))?;
+                out.push(Lexeme {
+                    token: Token::Str(chars[i + 1..end].iter().collect()),
+                    comments: std::mem::take(&mut comments),
+                });
+                last_line = line;
+                i = end + 1;
+            }
+            c if c.is_alphanumeric() || "_.-+".contains(c) => {
+                let end = (i..chars.len())
+                    .find(|j| !(chars[*j].is_alphanumeric() || "_.-+".contains(chars[*j])))
+                    .unwrap_or(chars.len());
+                out.push(Lexeme {
+                    token: Token::Word(chars[i..end].iter().collect()),
+                    comments: std::mem::take(&mut comments),
+                });
+                last_line = line;
+                i = end;
+            }
+            c => {
+                out.push(Lexeme {
+                    token: Token::Punct(c),
+                    comments: std::mem::take(&mut comments),
+                });
+                last_line = line;
+                i += 1;
+            }
+        }
+    }
+
+    Ok(out)
+}
+
+#[derive(Debug, PartialEq)]
+enum Label {
+    Plain,
+    Optional,
+    Repeated,
+}
+
+#[derive(Debug)]
+struct ProtoField {
+    doc: Option<String>,
+    label: Label,
+    /// the type, the value type of the map
+    ty: String,
+    map_key: Option<String>,
+    name: String,
+    deprecated: bool,
+}
+
+#[derive(Debug)]
+struct ProtoMessage {
+    /// the names of the parents and this message
+    path: Vec<String>,
+    doc: Option<String>,
+    fields: Vec<ProtoField>,
+    reserved: Vec<String>,
+}
+
+/// the messages and the enums of the file
+#[derive(Debug, Default)]
+struct ProtoFile {
+    package: Option<String>,
+    messages: Vec<ProtoMessage>,
+    enums: Vec<Vec<String>>,
+}
+
+struct ProtoParser {
+    lexemes: Vec<Lexeme>,
+    pos: usize,
+}
+
+impl ProtoParser {
+    fn peek(&self) -> Option<&Token> {
+        self.lexemes.get(self.pos).map(|l| &l.token)
+    }
+
+    fn next(&mut self) -> Result<Token> {
+        let t = self
+            .lexemes
+            .get(self.pos)
+            .map(|l| l.token.clone())
+            .ok_or_else(|| anyhow::anyhow!("unexpected end of the proto file"))?;
+        self.pos += 1;
+        Ok(t)
+    }
+
+    /// the doc of the declaration starting at the current token
+    fn doc(&self) -> Option<String> {
+        self.lexemes
+            .get(self.pos)
+            .filter(|l| !l.comments.is_empty())
+            .map(|l| l.comments.join("\n"))
+    }
+
+    fn word(&mut self) -> Result<String> {
+        match self.next()? {
+            Token::Word(w) => Ok(w),
+            t => anyhow::bail!("expect the name, got {:?}", t),
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<()> {
+        match self.next()? {
+            Token::Punct(p) if p == c => Ok(()),
+            t => anyhow::bail!("expect {:?}, got {:?}", c, t),
+        }
+    }
+
+    /// skip to the end of the statement, the ';' or the block
+    fn skip_statement(&mut self) -> Result<()> {
+        let mut depth = 0;
+        loop {
+            match self.next()? {
+                Token::Punct('{') => depth += 1,
+                Token::Punct('}') if depth == 1 => return Ok(()),
+                Token::Punct('}') => depth -= 1,
+                Token::Punct(';') if depth == 0 => return Ok(()),
+                _ => (),
+            }
+        }
+    }
+
+    fn parse_file(&mut self) -> Result<ProtoFile> {
+        let mut file = ProtoFile::default();
+        while let Some(t) = self.peek() {
+            match t {
+                Token::Word(w) if w == "package" => {
+                    self.next()?;
+                    file.package = Some(self.word()?);
+                    self.expect(';')?;
+                }
+                Token::Word(w) if w == "message" => self.parse_message(&[], &mut file)?,
+                Token::Word(w) if w == "enum" => self.parse_enum(&[], &mut file)?,
+                // syntax, import, option, service, extend
+                _ => self.skip_statement()?,
+            }
+        }
+        Ok(file)
+    }
+
+    fn parse_enum(&mut self, parents: &[String], file: &mut ProtoFile) -> Result<()> {
+        self.next()?;
+        let mut path = parents.to_vec();
+        path.push(self.word()?);
+        file.enums.push(path);
+        self.skip_statement()
+    }
+
+    fn parse_message(&mut self, parents: &[String], file: &mut ProtoFile) -> Result<()> {
+        let doc = self.doc();
+        self.next()?;
+        let mut path = parents.to_vec();
+        path.push(self.word()?);
+        self.expect('{')?;
+
+        // the nested messages are after this one
+        let at = file.messages.len();
+        let mut m = ProtoMessage {
+            path,
+            doc,
+            fields: vec![],
+            reserved: vec![],
+        };
+        loop {
+            match self.peek() {
+                Some(Token::Punct('}')) => {
+                    self.next()?;
+                    break;
+                }
+                Some(Token::Punct(';')) => {
+                    self.next()?;
+                }
+                Some(Token::Word(w)) => match w.as_str() {
+                    "message" => self.parse_message(&m.path, file)?,
+                    "enum" => self.parse_enum(&m.path, file)?,
+                    "reserved" => {
+                        self.next()?;
+                        while !matches!(self.peek(), Some(Token::Punct(';'))) {
+                            if let Token::Str(s) = self.next()? {
+                                m.reserved.push(s)
+                            }
+                        }
+                        self.next()?;
+                    }
+                    "oneof" => {
+                        self.next()?;
+                        self.word()?;
+                        self.expect('{')?;
+                        while !matches!(self.peek(), Some(Token::Punct('}'))) {
+                            let mut f = self.parse_field()?;
+                            f.label = Label::Optional;
+                            m.fields.push(f);
+                        }
+                        self.next()?;
+                    }
+                    "option" | "extensions" | "extend" => self.skip_statement()?,
+                    _ => m.fields.push(self.parse_field()?),
+                },
+                t => anyhow::bail!("{}: unexpected {:?}", m.path.join("."), t),
+            }
+        }
+
+        file.messages.insert(at, m);
+        Ok(())
+    }
+
+    /// [label] type name = number [options];
+    fn parse_field(&mut self) -> Result<ProtoField> {
+        let doc = self.doc();
+        let mut label = Label::Plain;
+        let mut ty = self.word()?;
+        match ty.as_str() {
+            "optional" => label = Label::Optional,
+            "repeated" => label = Label::Repeated,
+            // proto2
+            "required" => label = Label::Plain,
+            _ => (),
+        }
+        if ty == "optional" || ty == "repeated" || ty == "required" {
+            ty = self.word()?;
+        }
+
+        let mut map_key = None;
+        if ty == "map" && self.peek() == Some(&Token::Punct('<')) {
+            self.next()?;
+            map_key = Some(self.word()?);
+            self.expect(',')?;
+            ty = self.word()?;
+            self.expect('>')?;
+        }
+
+        let name = self.word()?;
+        self.expect('=')?;
+        self.word()?;
+
+        let mut deprecated = false;
+        if self.peek() == Some(&Token::Punct('[')) {
+            self.next()?;
+            loop {
+                match self.next()? {
+                    Token::Punct(']') => break,
+                    Token::Word(w) if w == "deprecated" => {
+                        self.expect('=')?;
+                        deprecated = self.word()? == "true";
+                    }
+                    _ => (),
+                }
+            }
+        }
+        self.expect(';')?;
+
+        Ok(ProtoField {
+            doc,
+            label,
+            ty,
+            map_key,
+            name,
+            deprecated,
+        })
+    }
+}
+
+fn spec_name(path: &[String]) -> String {
+    path.iter()
+        .map(|p| to_kebab_case(p))
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+impl ProtoFile {
+    /// the spec type of the proto type in the message
+    fn spec_type(&self, scope: &[String], ty: &str) -> Result<String> {
+        let t = match ty {
+            "string" => return Ok("string".to_string()),
+            "bytes" => return Ok("bytes".to_string()),
+            "int32" | "int64" | "uint32" | "uint64" | "sint32" | "sint64" | "fixed32"
+            | "fixed64" | "sfixed32" | "sfixed64" => return Ok("number".to_string()),
+            "google.protobuf.Timestamp" | ".google.protobuf.Timestamp" => {
+                return Ok("timestamp".to_string());
+            }
+            "bool" | "float" | "double" => anyhow::bail!("the proto type {} has no spec type", ty),
+            t => t.trim_start_matches('.'),
+        };
+        let t = match &self.package {
+            Some(p) => t.strip_prefix(&format!("{}.", p)).unwrap_or(t),
+            None => t,
+        };
+        let name: Vec<String> = t.split('.').map(|s| s.to_string()).collect();
+
+        // the inner scopes first
+        for i in (0..=scope.len()).rev() {
+            let mut path = scope[..i].to_vec();
+            path.extend(name.iter().cloned());
+            if self.messages.iter().any(|m| m.path == path) {
+                return Ok(spec_name(&path));
+            }
+            if self.enums.contains(&path) {
+                return Ok("keyword".to_string());
+            }
+        }
+        anyhow::bail!("undefined proto type {}", ty)
+    }
+
+    fn field_spec(&self, m: &ProtoMessage, f: &ProtoField) -> Result<String> {
+        let ty = self.spec_type(&m.path, &f.ty)?;
+        if let Some(k) = &f.map_key {
+            if k != "string" {
+                anyhow::bail!("the map key {} has to be string", k)
+            }
+            return Ok(format!("'(map 'string '{})", ty));
+        }
+
+        Ok(match f.label {
+            Label::Repeated => format!("'(list '{})", ty),
+            Label::Optional => format!("(optional '{})", ty),
+            Label::Plain => {
+                let mut options = vec![];
+                if let Some(d) = &f.doc {
+                    options.push(format!(":doc {}", quote_string(d)));
+                }
+                if f.deprecated {
+                    options.push(":deprecated \"deprecated\"".to_string());
+                }
+                match options.is_empty() {
+                    true => format!("'{}", ty),
+                    false => format!("('{} {})", ty, options.join(" ")),
+                }
+            }
+        })
+    }
+}
+
+/// convert the messages of the .proto source to the def-msg specs, with the
+/// (def-rpc-package ...) of the proto package
+pub fn import_proto(source: &str) -> Result<String> {
+    let file = ProtoParser {
+        lexemes: lex(source)?,
+        pos: 0,
+    }
+    .parse_file()?;
+
+    let mut forms = vec![];
+    if let Some(p) = &file.package {
+        forms.push(format!("(def-rpc-package {})", to_kebab_case(p)));
+    }
+
+    let mut problems = vec![];
+    for m in &file.messages {
+        let mut lines = vec![format!("(def-msg {}", spec_name(&m.path))];
+        if let Some(d) = &m.doc {
+            lines[0].push_str(&format!(" {}", quote_string(d)));
+        }
+        for f in &m.fields {
+            match file.field_spec(m, f) {
+                Ok(s) => lines.push(format!("  :{} {}", to_kebab_case(&f.name), s)),
+                Err(e) => problems.push(format!("{}.{}: {}", m.path.join("."), f.name, e)),
+            }
+        }
+        if !m.reserved.is_empty() {
+            lines.push(format!(
+                "  (reserved {})",
+                m.reserved
+                    .iter()
+                    .map(|r| format!(":{}", to_kebab_case(r)))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            ));
+        }
+        forms.push(lines.join("\n") + ")");
+    }
+
+    if !problems.is_empty() {
+        anyhow::bail!(
+            "the proto file has {} problems:\n{}",
+            problems.len(),
+            problems.join("\n")
+        )
+    }
+    Ok(forms.join("\n\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use lisp_rpc_rust_parser::Parser;
+
+    use super::*;
+    use crate::SpecFile;
+
+    #[test]
+    fn test_import_proto() {
+        let specs = import_proto(
+            r#"syntax = "proto3";
+
+package library;
+
+import "google/protobuf/timestamp.proto";
+
+// the book
+message BookInfo {
+  // the title
+  string title = 1;
+  repeated string tags = 2; // not the doc
+  optional LanguagePerfer lang = 3;
+  map<string, int64> prices = 4;
+  google.protobuf.Timestamp published = 5;
+  Extra extra = 6;
+  Kind kind = 7 [deprecated = true];
+  reserved 8, 9;
+  reserved "old_id";
+
+  message Extra {
+    bytes cover = 1;
+  }
+
+  oneof shelf {
+    string room = 10;
+    library.Shelf shelf_info = 11;
+  }
+}
+
+/* the language */
+message LanguagePerfer {
+  string lang = 1;
+}
+
+message Shelf { uint32 floor = 1; }
+
+enum Kind {
+  KIND_UNSPECIFIED = 0;
+}
+
+service Library {
+  rpc GetBook(BookInfo) returns (BookInfo);
+}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            specs,
+            r#"(def-rpc-package library)
+
+(def-msg book-info "the book"
+  :title ('string :doc "the title")
+  :tags '(list 'string)
+  :lang (optional 'language-perfer)
+  :prices '(map 'string 'number)
+  :published 'timestamp
+  :extra 'book-info-extra
+  :kind ('keyword :deprecated "deprecated")
+  :room (optional 'string)
+  :shelf-info (optional 'shelf)
+  (reserved :old-id))
+
+(def-msg book-info-extra
+  :cover 'bytes)
+
+(def-msg language-perfer "the language"
+  :lang 'string)
+
+(def-msg shelf
+  :floor 'number)"#
+        );
+
+        // the specs are valid
+        let mut spec_file = SpecFile::new();
+        for e in Parser::new().parse_root(Cursor::new(&specs)).unwrap() {
+            spec_file.record_expr(&e).unwrap();
+        }
+        assert!(spec_file.check().is_empty());
+
+        let err = import_proto(r#"message A { bool ok = 1; map<int32, string> m = 2; B b = 3; }"#)
+            .unwrap_err()
+            .to_string();
+        assert_eq!(
+            err,
+            "the proto file has 3 problems:
+A.ok: the proto type bool has no spec type
+A.m: the map key int32 has to be string
+A.b: undefined proto type B"
+        );
+
+        assert_eq!(to_kebab_case("HTTPServer2Config"), "http-server2-config");
+    }
+}
@@ -0,0 +1,351 @@
+//! the python module of the specs, it is the CodegenBackend of python. The
+//! msgs and the rpc requests are the dataclasses with the to_rpc and the
+//! from_rpc methods, and the rpcs are the methods of the socket client
+//!
+//! the field values are the python values: the str, the int, the str of the
+//! keyword, the uuid.UUID, the datetime of the timestamp, the bytes, the
+//! instance of the msg, the list, and the dict of the (map 'string 'v)
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+
+use anyhow::Result;
+use serde::Serialize;
+use tera::{Context, Tera};
+
+use crate::{
+    CodegenBackend, CodegenUnit, ConstValue, DefaultValue, GeneratedField, RPCDataType,
+    kebab_to_pascal_case, kebab_to_snake_case,
+};
+
+/// the name of the template of the python file
+pub const PYTHON_TEMPLATE: &str = "package.py";
+
+/// the names can't be the python attributes, they are suffixed with `_`
+const RESERVED_NAMES: &[&str] = &[
+    "False", "None", "True", "and", "as", "assert", "async", "await", "break", "class", "continue",
+    "def", "del", "elif", "else", "except", "finally", "for", "from", "global", "if", "import",
+    "in", "is", "lambda", "nonlocal", "not", "or", "pass", "raise", "return", "try", "while",
+    "with", "yield", "to_rpc", "from_rpc",
+];
+
+/// the classes of the python module itself
+const MODULE_NAMES: &[&str] = &["RPCDecodeError", "RPCError", "Symbol", "Keyword", "Client"];
+
+#[derive(Debug, Serialize)]
+struct PyField {
+    name: String,
+    key: String,
+    ty: String,
+    doc: Option<String>,
+    /// the python literal of the default value, the required field has none
+    default: Option<String>,
+    /// the function encoding the value to the rpc text
+    encode: String,
+    /// the function decoding the rpc data to the value
+    decode: String,
+    optional: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct PyClass {
+    name: String,
+    data_name: String,
+    doc: Option<String>,
+    /// the anonymous map, it is encoded as '(:k v) instead of (name :k v)
+    map: bool,
+    fields: Vec<PyField>,
+}
+
+#[derive(Debug, Serialize)]
+struct PyConst {
+    name: String,
+    /// the python literal of the value
+    value: String,
+    doc: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct PyRPC {
+    method: String,
+    req: String,
+    /// the annotation and the decoding function of the result, None if the
+    /// rpc returns nothing
+    resp: Option<(String, String)>,
+    doc: Option<String>,
+
+    /// the notification is sent without waiting for the response
+    notify: bool,
+}
+
+/// the python module `python/<package>.py` rendered by the package.py
+/// template
+pub struct PythonBackend {
+    templates: Tera,
+}
+
+impl PythonBackend {
+    pub fn new(templates: Tera) -> Self {
+        Self { templates }
+    }
+}
+
+impl CodegenBackend for PythonBackend {
+    fn generate(&self, unit: &CodegenUnit) -> Result<Vec<(PathBuf, String)>> {
+        Ok(vec![(
+            PathBuf::from("python").join(format!("{}.py", kebab_to_snake_case(&unit.package))),
+            render_python(&self.templates, unit)?,
+        )])
+    }
+}
+
+/// render the python module of the generated structs and the rpcs
+pub(crate) fn render_python(templates: &Tera, unit: &CodegenUnit) -> Result<String> {
+    let structs = &unit.structs;
+    let mut names = BTreeSet::new();
+    if let Some(dup) = structs.iter().find(|s| !names.insert(&s.name)) {
+        anyhow::bail!(
+            "{} is defined twice, the python module needs the unique names",
+            dup.data_name()
+        )
+    }
+    if let Some(s) = structs
+        .iter()
+        .find(|s| MODULE_NAMES.contains(&s.name.as_str()))
+    {
+        anyhow::bail!(
+            "{} is the class of the python module, rename the spec",
+            s.data_name()
+        )
+    }
+
+    // python has no newtypes, the aliases are their primary types
+    let aliases: BTreeMap<&str, &str> = unit
+        .aliases
+        .iter()
+        .map(|a| (a.name(), a.alias_type()))
+        .collect();
+    let unalias = |t: &str| aliases.get(t).copied().unwrap_or(t).to_string();
+
+    let classes: Vec<PyClass> = structs
+        .iter()
+        .map(|s| PyClass {
+            name: s.name.clone(),
+            data_name: s.data_name().to_string(),
+            doc: s.doc.as_deref().map(docstring),
+            map: *s.rpc_type() == RPCDataType::Map,
+            fields: s
+                .fields
+                .iter()
+                .map(|f| {
+                    let mut f = f.clone();
+                    f.spec_type = unalias(&f.spec_type);
+                    field(&f)
+                })
+                .collect(),
+        })
+        .collect();
+
+    let consts = unit
+        .consts
+        .iter()
+        .map(|c| {
+            Ok(PyConst {
+                name: c.rust_name(),
+                value: match c.value() {
+                    ConstValue::Number(n) => n.to_string(),
+                    ConstValue::String(s) => serde_json::to_string(s)?,
+                },
+                doc: c.doc().map(|d| {
+                    d.lines()
+                        .map(|l| format!("# {}", l.trim()).trim_end().to_string())
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                }),
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let rpcs: Vec<PyRPC> = unit
+        .rpcs
+        .iter()
+        .map(|r| PyRPC {
+            method: attr_name(&r.data_name),
+            req: kebab_to_pascal_case(&r.data_name),
+            resp: r.returns.as_deref().map(|t| {
+                let (ty, _, decode) = type_codec(&unalias(t));
+                (ty, decode)
+            }),
+            doc: r.doc.as_deref().map(docstring),
+            notify: r.notify,
+        })
+        .collect();
+
+    let mut context = Context::new();
+    context.insert("package", &unit.package);
+    context.insert("version", &unit.version);
+    context.insert("consts", &consts);
+    context.insert("classes", &classes);
+    context.insert("rpcs", &rpcs);
+    Ok(templates.render(PYTHON_TEMPLATE, &context)?)
+}
+
+fn field(f: &GeneratedField) -> PyField {
+    let (mut ty, mut encode, mut decode) = type_codec(&f.spec_type);
+    if f.list {
+        ty = format!("List[{}]", ty);
+        encode = format!("_encode_list({})", encode);
+        decode = format!("_decode_list({})", decode);
+    }
+    if f.map {
+        ty = format!("Dict[str, {}]", ty);
+        encode = format!("_encode_map({})", encode);
+        decode = format!("_decode_map({})", decode);
+    }
+    if f.optional {
+        ty = format!("Optional[{}]", ty);
+        decode = format!("_decode_optional({})", decode);
+    }
+
+    PyField {
+        name: attr_name(f.key_name()),
+        key: f.key_name().to_string(),
+        ty,
+        doc: f.doc.as_deref().map(docstring),
+        default: match (&f.default_value, f.optional) {
+            (Some(DefaultValue::Number(n)), _) => Some(n.to_string()),
+            (Some(DefaultValue::String(s)), _) => Some(serde_json::to_string(s).unwrap()),
+            (None, true) => Some("None".to_string()),
+            (None, false) => None,
+        },
+        encode,
+        decode,
+        optional: f.optional,
+    }
+}
+
+/// the annotation, the encoding function, and the decoding function of the
+/// primary type or the msg
+fn type_codec(t: &str) -> (String, String, String) {
+    let (ty, codec) = match t {
+        "string" => ("str", "string"),
+        "number" => ("int", "number"),
+        "keyword" => ("str", "keyword"),
+        "uuid" => ("uuid.UUID", "uuid"),
+        "timestamp" => ("datetime.datetime", "timestamp"),
+        "bytes" => ("bytes", "bytes"),
+        msg => {
+            let name = kebab_to_pascal_case(msg);
+            return (
+                format!("\"{}\"", name),
+                "_encode_msg".to_string(),
+                format!("{}.from_rpc", name),
+            );
+        }
+    };
+    (
+        ty.to_string(),
+        format!("_encode_{}", codec),
+        format!("_decode_{}", codec),
+    )
+}
+
+/// the python attribute of the keyword, the reserved names end with `_`
+fn attr_name(key: &str) -> String {
+    let name = kebab_to_snake_case(key);
+    match RESERVED_NAMES.contains(&name.as_str()) {
+        true => name + "_",
+        false => name,
+    }
+}
+
+/// the python docstring of the doc
+fn docstring(doc: &str) -> String {
+    format!(
+        "\"\"\"{}\"\"\"",
+        doc.replace('\\', "\\\\").replace('"', "\\\"")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use lisp_rpc_rust_parser::Parser;
+
+    use super::*;
+    use crate::SpecFile;
+
+    #[test]
+    fn test_render_python() {
+        let mut specs = SpecFile::new();
+        for e in Parser::new()
+            .parse_root(Cursor::new(
+                r#"(def-rpc-package library)
+(def-version 2)
+(def-const max-page-size 100)
+(def-const protocol-magic "the magic" "LRPC")
+(def-alias isbn 'string)
+(def-msg book-info "the \"book\""
+    :title ('string :doc "the title")
+    :isbn 'isbn
+    :tags '(list 'string)
+    :lang (optional 'language-perfer)
+    :prices '(map 'string 'number)
+    :class 'keyword
+    :extra '(:cover 'bytes))
+(def-msg language-perfer :lang ('string :default "en"))
+(def-rpc get-book "find the book" :since 2 '(:title 'string) 'book-info)
+(def-rpc return-book '(:books '(list 'book-info)))"#,
+            ))
+            .unwrap()
+        {
+            specs.record_expr(&e).unwrap();
+        }
+
+        let mut tera = Tera::default();
+        tera.add_raw_template(
+            PYTHON_TEMPLATE,
+            include_str!("../templates/package.py.template"),
+        )
+        .unwrap();
+        let code = specs.gen_python(&tera).unwrap();
+
+        for part in [
+            "SPEC_VERSION = 2",
+            "MAX_PAGE_SIZE = 100\n# the magic\nPROTOCOL_MAGIC = \"LRPC\"\n",
+            r#"@dataclass(kw_only=True)
+class BookInfoExtra:
+    cover: bytes
+
+    def to_rpc(self) -> str:
+        parts = []
+        parts.append(":cover " + _encode_bytes(self.cover))
+        return "'(" + " ".join(parts) + ")""#,
+            r#"@dataclass(kw_only=True)
+class BookInfo:
+    """the \"book\""""
+
+    title: str
+    """the title"""
+    isbn: str
+    tags: List[str]
+    lang: Optional["LanguagePerfer"] = None
+    prices: Dict[str, int]
+    class_: str
+    extra: "BookInfoExtra""#,
+            r#"        if self.lang is not None:
+            parts.append(":lang " + _encode_msg(self.lang))"#,
+            r#"            lang=_field(kw, "book-info", "lang", _decode_optional(LanguagePerfer.from_rpc), None),
+            prices=_field(kw, "book-info", "prices", _decode_map(_decode_number)),"#,
+            r#"    lang: str = "en""#,
+            r#"    def get_book(self, req: GetBook) -> "BookInfo":
+        """find the book"""
+        return BookInfo.from_rpc(self.call(req.to_rpc()))"#,
+            r#"    def return_book(self, req: ReturnBook) -> None:
+        self.call(req.to_rpc())"#,
+        ] {
+            assert!(code.contains(part), "{}\n\nnot in\n\n{}", part, code);
+        }
+    }
+}
@@ -0,0 +1,307 @@
+//! check the specs before generating code, instead of generating the code
+//! failing to compile. All findings are reported, not only the first one
+
+use std::borrow::Cow;
+use std::collections::{BTreeSet, HashMap};
+
+use lisp_rpc_rust_parser::{Atom, Expr, TypeValue, data::Violation};
+
+use crate::{RPCSpec, VersionRange, map_type, optional_type, recursive_groups, type_options};
+
+/// the types without the def-msg
+pub(crate) const PRIMARY_TYPES: &[&str] =
+    &["string", "number", "keyword", "timestamp", "bytes", "uuid"];
+
+/// the keyword-type pairs of the msg or the rpc, and the return type of the rpc.
+/// The fields are owned after the aliases are replaced
+pub struct SpecFields<'a> {
+    pub name: &'a str,
+    pub fields: Cow<'a, [Expr]>,
+    pub returns: Option<&'a str>,
+    pub versions: VersionRange,
+    pub doc: Option<&'a str>,
+    pub is_rpc: bool,
+
+    /// the rpc is the one-way notification of def-notify
+    pub notify: bool,
+
+    /// the declared errors of the rpc
+    pub errors: &'a [String],
+}
+
+/// the state of one checking
+struct Resolver<'a> {
+    /// the msgs, the rpcs, and the aliases
+    defined: BTreeSet<&'a str>,
+
+    /// the struct names and the struct names of their required fields, the
+    /// fields can't be nil or empty
+    deps: Vec<(String, Vec<String>)>,
+
+    /// the struct names to the paths of their specs, for the cycles
+    paths: HashMap<String, String>,
+
+    out: Vec<Violation>,
+}
+
+/// check the specs, the source is the spec file of the spec:
+///
+/// + the referenced types are defined
+/// + no duplicate keywords in the msg or the rpc
+/// + no cycles of the required fields, no value of them can be made
+/// + the :since and the :until are in the spec version
+pub(crate) fn resolve<'a>(
+    specs: impl IntoIterator<Item = (&'a dyn RPCSpec, Option<&'a str>)> + Clone,
+) -> Vec<Violation> {
+    let mut r = Resolver {
+        defined: specs
+            .clone()
+            .into_iter()
+            .flat_map(|(s, _)| s.spec_fields())
+            .map(|f| f.name)
+            .chain(
+                specs
+                    .clone()
+                    .into_iter()
+                    .filter_map(|(s, _)| s.alias().map(|a| a.name())),
+            )
+            .chain(PRIMARY_TYPES.iter().copied())
+            .collect(),
+        deps: vec![],
+        paths: HashMap::new(),
+        out: vec![],
+    };
+
+    let version = specs
+        .clone()
+        .into_iter()
+        .find_map(|(s, _)| s.spec_version());
+
+    for (s, source) in specs {
+        for f in s.spec_fields() {
+            let path = match source {
+                Some(src) => format!("{}: {}", src, f.name),
+                None => f.name.to_string(),
+            };
+            r.fields(&path, f.name, &f.fields);
+            if let Some(ret) = f.returns
+                && !r.defined.contains(ret)
+            {
+                r.out.push(Violation::new(
+                    &path,
+                    format!("undefined return type {}", ret),
+                ));
+            }
+            r.versions(&path, f.versions, version);
+        }
+    }
+
+    for g in recursive_groups(&r.deps) {
+        let names: Vec<&str> = g.iter().map(|n| n.as_str()).collect();
+        r.out.push(Violation::new(
+            &r.paths[names[0]],
+            format!(
+                "type cycle of the required fields, no value can be made: {}",
+                names.join(", ")
+            ),
+        ));
+    }
+
+    r.out
+}
+
+impl Resolver<'_> {
+    /// check the keyword-type pairs of the struct
+    fn fields(&mut self, path: &str, owner: &str, fields: &[Expr]) {
+        self.paths.insert(owner.to_string(), path.to_string());
+        self.deps.push((owner.to_string(), vec![]));
+
+        let mut seen = BTreeSet::new();
+        for [k, ty] in fields.iter().array_chunks() {
+            let k = match k {
+                Expr::Atom(Atom {
+                    value: TypeValue::Keyword(k),
+                }) => k,
+                _ => {
+                    self.out
+                        .push(Violation::new(path, "spec has to be keyword-type pairs"));
+                    return;
+                }
+            };
+
+            let field_path = Violation::join_path(path, k);
+            if !seen.insert(k) {
+                self.out
+                    .push(Violation::new(&field_path, "duplicate keyword"));
+            }
+            self.field_type(&field_path, owner, k, ty, true);
+        }
+    }
+
+    /// check the type of the field, the required type is a dep of the owner
+    fn field_type(&mut self, path: &str, owner: &str, k: &str, ty: &Expr, required: bool) {
+        if let Some(o) = type_options(ty) {
+            return self.field_type(path, owner, k, o.inner, required);
+        }
+        if let Some(t) = optional_type(ty) {
+            return self.type_name(path, owner, t, false);
+        }
+        if let Some((_, v)) = map_type(ty) {
+            return self.type_name(path, owner, v, false);
+        }
+
+        match ty {
+            Expr::Quote(box Expr::Atom(Atom {
+                value: TypeValue::Symbol(t),
+            })) => self.type_name(path, owner, t, required),
+
+            Expr::Quote(box Expr::List(inner)) | Expr::List(inner) => match inner.as_slice() {
+                // anonymous map, it is the struct named after the owner and the keyword
+                [
+                    Expr::Atom(Atom {
+                        value: TypeValue::Keyword(_),
+                    }),
+                    ..,
+                ] => {
+                    let name = format!("{}-{}", owner, k);
+                    if required {
+                        self.add_dep(owner, &name);
+                    }
+                    self.fields(path, &name, inner)
+                }
+
+                [
+                    Expr::Atom(Atom {
+                        value: TypeValue::Symbol(l),
+                    }),
+                    ele_ty,
+                ] if l == "list" => self.field_type(path, owner, k, ele_ty, false),
+
+                _ => self.out.push(Violation::new(path, "unknown spec type")),
+            },
+
+            _ => self.out.push(Violation::new(path, "unknown spec type")),
+        }
+    }
+
+    /// the range is in the versions up to the spec version
+    fn versions(&mut self, path: &str, range: VersionRange, version: Option<i64>) {
+        let bounds = [("since", range.since), ("until", range.until)];
+        for (k, v) in bounds.iter().filter_map(|(k, v)| Some((k, (*v)?))) {
+            match version {
+                None => self.out.push(Violation::new(
+                    path,
+                    format!(":{} needs the (def-version n)", k),
+                )),
+                Some(version) if v < 1 || v > version => self.out.push(Violation::new(
+                    path,
+                    format!(":{} {} is not in the versions 1 to {}", k, v, version),
+                )),
+                _ => (),
+            }
+        }
+        if let (Some(s), Some(u)) = (range.since, range.until)
+            && s > u
+        {
+            self.out.push(Violation::new(
+                path,
+                format!(":since {} is after :until {}", s, u),
+            ));
+        }
+    }
+
+    fn type_name(&mut self, path: &str, owner: &str, t: &str, required: bool) {
+        if !self.defined.contains(t) {
+            self.out
+                .push(Violation::new(path, format!("undefined type {}", t)));
+        } else if required && !PRIMARY_TYPES.contains(&t) {
+            self.add_dep(owner, t);
+        }
+    }
+
+    fn add_dep(&mut self, owner: &str, t: &str) {
+        if let Some((_, refs)) = self.deps.iter_mut().find(|(n, _)| n == owner) {
+            refs.push(t.to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use lisp_rpc_rust_parser::Parser;
+
+    use crate::SpecFile;
+
+    fn findings(spec: &str) -> Vec<String> {
+        let mut specs = SpecFile::new();
+        for e in Parser::new().parse_root(Cursor::new(spec)).unwrap() {
+            specs.record_expr(&e).unwrap();
+        }
+        specs.check().iter().map(|v| v.to_string()).collect()
+    }
+
+    #[test]
+    fn test_resolve() {
+        assert!(
+            findings(
+                r#"(def-msg language-perfer :lang 'string)
+(def-msg node :value 'number :next (optional 'node) :children '(list 'node))
+(def-msg book-info :lang 'language-perfer :tags '(map 'string 'node))
+(def-rpc get-book-info '(:title 'string :lang 'language-perfer) 'book-info)"#
+            )
+            .is_empty()
+        );
+
+        assert_eq!(
+            findings(
+                r#"(def-msg book-info
+    :lang 'language-perfer
+    :title 'string
+    :title 'number
+    :extra '(:a (optional 'author) :a 'string))
+(def-rpc get-book-info '(:title 'string) 'book)"#
+            ),
+            vec![
+                "book-info.lang: undefined type language-perfer",
+                "book-info.title: duplicate keyword",
+                "book-info.extra.a: undefined type author",
+                "book-info.extra.a: duplicate keyword",
+                "get-book-info: undefined return type book",
+            ]
+        );
+
+        assert_eq!(
+            findings(
+                r#"(def-msg a :since 2 :b 'string)
+(def-version 3)
+(def-msg b :since 3 :until 2 :a 'a)
+(def-rpc get-a :until 4 '(:id 'number) 'a)"#
+            ),
+            vec![
+                "b: :since 3 is after :until 2",
+                "get-a: :until 4 is not in the versions 1 to 3",
+            ]
+        );
+        assert_eq!(
+            findings(r#"(def-msg a :since 2 :b 'string)"#),
+            vec!["a: :since needs the (def-version n)"]
+        );
+
+        // the cycles through the optional, list, or map fields are fine
+        assert_eq!(
+            findings(
+                r#"(def-msg a :b 'b :c (optional 'c))
+(def-msg b :a 'a)
+(def-msg c :c '(:c 'c))
+(def-msg d :d ('d :doc "itself"))"#
+            ),
+            vec![
+                "a: type cycle of the required fields, no value can be made: a, b",
+                "c: type cycle of the required fields, no value can be made: c, c-c",
+                "d: type cycle of the required fields, no value can be made: d",
+            ]
+        );
+    }
+}
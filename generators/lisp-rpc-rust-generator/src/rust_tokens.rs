@@ -0,0 +1,1078 @@
+//! the rust code built as the syn tokens instead of the templates, and
+//! formatted by prettyplease. The RustTokensBackend generates the same crate
+//! as the stock templates without the options, the code is always valid rust
+//!
+//! format_rust parses and formats the rust code rendered by the templates,
+//! the template rendering the broken rust fails with the line of it
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use proc_macro2::{Literal, TokenStream};
+use quote::{format_ident, quote};
+
+use crate::{
+    CodegenBackend, CodegenUnit, DefAlias, GeneratedField, GeneratedRPC, GeneratedStruct,
+    RPCDataType, alias_defaults, box_recursive, kebab_to_pascal_case, kebab_to_snake_case,
+    rename_structs, rename_type, type_translate,
+};
+
+/// the rust crate of the specs, the Cargo.toml and the src/lib.rs
+pub struct RustTokensBackend;
+
+impl CodegenBackend for RustTokensBackend {
+    fn generate(&self, unit: &CodegenUnit) -> Result<Vec<(PathBuf, String)>> {
+        Ok(vec![
+            (
+                PathBuf::from("src/lib.rs"),
+                format_tokens(lib_tokens(unit)?)?,
+            ),
+            (
+                PathBuf::from("Cargo.toml"),
+                format!(
+                    "[package]\nname = \"{}\"\nversion = \"0.1.0\"\nedition = \"2024\"\n\n[dependencies]\nlisp-rpc-rust-parser = \"0\"\n",
+                    unit.package
+                ),
+            ),
+        ])
+    }
+}
+
+/// parse the rust code and format it by prettyplease, the normal comments
+/// are dropped and the doc comments are kept
+pub fn format_rust(code: &str) -> Result<String> {
+    let file = syn::parse_file(code).map_err(|e| {
+        let at = e.span().start();
+        anyhow::anyhow!(
+            "the generated rust is invalid, {} at line {} column {}:\n{}",
+            e,
+            at.line,
+            at.column + 1,
+            code.lines().nth(at.line.saturating_sub(1)).unwrap_or("")
+        )
+    })?;
+    Ok(prettyplease::unparse(&file))
+}
+
+fn format_tokens(tokens: TokenStream) -> Result<String> {
+    Ok(prettyplease::unparse(&syn::parse2(tokens)?))
+}
+
+fn lib_tokens(unit: &CodegenUnit) -> Result<TokenStream> {
+    let mut items = vec![];
+    if let Some(v) = unit.version.map(Literal::i64_unsuffixed) {
+        items.push(quote! {
+            /// the version of the spec, the client sends it in the (request :version ...)
+            /// envelope
+            pub const SPEC_VERSION: i64 = #v;
+        });
+    }
+    for c in &unit.consts {
+        let docs = doc_attrs(c.doc());
+        let name = ident(&c.rust_name())?;
+        let ty = rust_type(c.value().rust_type())?;
+        let value = rust_expr(&c.value().rust_literal())?;
+        items.push(quote! {
+            #(#docs)*
+            pub const #name: #ty = #value;
+        });
+    }
+
+    for a in &unit.aliases {
+        items.push(alias_tokens(a)?);
+    }
+
+    let aliases = unit.aliases.iter().map(|a| a.name().to_string()).collect();
+    let structs = rename_structs(box_recursive(unit.structs.clone(), &[]), &unit.renames);
+    for s in alias_defaults(structs, &aliases) {
+        items.push(struct_tokens(&s)?);
+    }
+    for r in &unit.rpcs {
+        items.extend(error_tokens(r, &unit.renames)?);
+    }
+
+    let (rpcs, services): (Vec<&GeneratedRPC>, Vec<&GeneratedRPC>) =
+        unit.rpcs.iter().partition(|r| r.service.is_none());
+    if !unit.rpcs.is_empty() {
+        items.push(client_tokens(&unit.rpcs, unit.version, &unit.renames)?);
+    }
+    if !rpcs.is_empty() {
+        items.push(method_tokens(&rpcs)?);
+        items.push(server_tokens(&rpcs, unit.version, &unit.renames)?);
+    }
+
+    let mut names: Vec<&str> = vec![];
+    for s in services.iter().filter_map(|r| r.service.as_deref()) {
+        if !names.contains(&s) {
+            names.push(s)
+        }
+    }
+    for n in names {
+        let rpcs: Vec<&GeneratedRPC> = services
+            .iter()
+            .filter(|r| r.service.as_deref() == Some(n))
+            .copied()
+            .collect();
+        items.push(service_tokens(n, &rpcs, &unit.renames)?);
+    }
+
+    Ok(quote! { #(#items)* })
+}
+
+/// the newtype of the alias and its conversions
+fn alias_tokens(a: &DefAlias) -> Result<TokenStream> {
+    let docs = doc_attrs(a.doc());
+    let name = ident(&kebab_to_pascal_case(a.name()))?;
+    let ty = rust_type(&type_translate(a.alias_type()))?;
+    let derives = match a.has_default() {
+        true => quote! { #[derive(Debug, Clone, PartialEq, Default)] },
+        false => quote! { #[derive(Debug, Clone, PartialEq)] },
+    };
+    Ok(quote! {
+        #(#docs)*
+        #derives
+        pub struct #name(pub #ty);
+
+        impl From<#ty> for #name {
+            fn from(v: #ty) -> Self {
+                Self(v)
+            }
+        }
+
+        impl ToRPCData for #name {
+            fn to_rpc(&self) -> String {
+                self.0.to_rpc()
+            }
+        }
+
+        impl FromRPCData for #name {
+            fn from_rpc(d: &Data) -> Result<Self, DataError> {
+                FromRPCData::from_rpc(d).map(Self)
+            }
+        }
+    })
+}
+
+/// the struct, its constructor and getters, the default, and the rpc data
+/// impls
+fn struct_tokens(s: &GeneratedStruct) -> Result<TokenStream> {
+    let name = ident(&s.name)?;
+    let data_name = s.data_name();
+    let docs = doc_attrs(s.doc.as_deref());
+    let deprecated = match s.fields.iter().any(|f| f.deprecated.is_some()) {
+        true => quote! { #[allow(deprecated)] },
+        false => quote! {},
+    };
+
+    let mut defs = vec![];
+    let mut args = vec![];
+    let mut getters = vec![];
+    let mut names = vec![];
+    let mut defaults = vec![];
+    for f in &s.fields {
+        let (n, ty) = (ident(&f.name)?, rust_type(&f.field_type)?);
+        let field_docs = doc_attrs(f.doc.as_deref());
+        let note = deprecated_attr(f)?;
+        defs.push(quote! { #(#field_docs)* #note #n: #ty });
+        args.push(quote! { #n: #ty });
+        getters.push(quote! {
+            #note
+            pub fn #n(&self) -> &#ty {
+                &self.#n
+            }
+        });
+        defaults.push(match &f.default {
+            Some(d) => {
+                let d = rust_expr(d)?;
+                quote! { #n: #d }
+            }
+            None => quote! { #n: Default::default() },
+        });
+        names.push(n);
+    }
+
+    let mut consts = vec![];
+    if let Some(v) = s.versions.since.map(Literal::i64_unsuffixed) {
+        consts.push(quote! {
+            /// the first spec version having this struct
+            pub const SINCE_VERSION: i64 = #v;
+        });
+    }
+    if let Some(v) = s.versions.until.map(Literal::i64_unsuffixed) {
+        consts.push(quote! {
+            /// the last spec version having this struct
+            pub const UNTIL_VERSION: i64 = #v;
+        });
+    }
+
+    let default_impl = match s.fields.iter().any(|f| f.default.is_some()) {
+        true => quote! {
+            #deprecated
+            impl Default for #name {
+                fn default() -> Self {
+                    Self { #(#defaults),* }
+                }
+            }
+        },
+        false => quote! {},
+    };
+
+    let to_rpc = to_rpc_tokens(s)?;
+    let from_rpc = from_rpc_tokens(s)?;
+    let check_tag = match s.rpc_type() {
+        RPCDataType::Data => quote! { let d = d.check_tag(#data_name)?; },
+        _ => quote! {},
+    };
+
+    Ok(quote! {
+        #(#docs)*
+        #[derive(Debug)]
+        pub struct #name {
+            #(#defs),*
+        }
+
+        #deprecated
+        impl #name {
+            #(#consts)*
+
+            pub fn new(#(#args),*) -> Self {
+                Self { #(#names),* }
+            }
+
+            #(#getters)*
+        }
+
+        #default_impl
+
+        #deprecated
+        impl ToRPCData for #name {
+            fn to_rpc(&self) -> String {
+                #to_rpc
+            }
+        }
+
+        #deprecated
+        impl FromRPCData for #name {
+            fn from_rpc(d: &Data) -> Result<Self, DataError> {
+                #check_tag
+                #from_rpc
+            }
+        }
+    })
+}
+
+/// the body of to_rpc, the optional fields are skipped when they are None
+fn to_rpc_tokens(s: &GeneratedStruct) -> Result<TokenStream> {
+    let mut pushes = vec![];
+    for f in &s.fields {
+        let n = ident(&f.name)?;
+        let key = f.key_name();
+        pushes.push(if f.optional {
+            let fmt = format!(":{} {{}}", key);
+            quote! {
+                if let Some(v) = &self.#n {
+                    args.push(format!(#fmt, v.to_rpc()));
+                }
+            }
+        } else if f.list {
+            let fmt = format!(":{} '({{}})", key);
+            // prettyplease keeps the macro args as they are, the elements
+            // are joined out of the format!
+            quote! {
+                let #n = self.#n.iter().map(|e| e.to_rpc()).collect::<Vec<_>>().join(" ");
+                args.push(format!(#fmt, #n));
+            }
+        } else {
+            let fmt = format!(":{} {{}}", key);
+            quote! { args.push(format!(#fmt, self.#n.to_rpc())); }
+        });
+    }
+
+    let (head, fmt) = match s.rpc_type() {
+        RPCDataType::Data => {
+            let data_name = s.data_name();
+            (quote! { #data_name.to_string() }, "({})")
+        }
+        _ => (quote! {}, "'({})"),
+    };
+    Ok(match pushes.is_empty() {
+        true => quote! {
+            let args: Vec<String> = vec![#head];
+            format!(#fmt, args.join(" "))
+        },
+        false => quote! {
+            let mut args: Vec<String> = vec![#head];
+            #(#pushes)*
+            format!(#fmt, args.join(" "))
+        },
+    })
+}
+
+/// the body of from_rpc, all fields are decoded before checking, so the
+/// error lists every missing or mismatched field
+fn from_rpc_tokens(s: &GeneratedStruct) -> Result<TokenStream> {
+    let mut names = vec![];
+    let mut decodes = vec![];
+    let mut keys = vec![];
+    for f in &s.fields {
+        let key = f.key_name();
+        decodes.push(if f.optional {
+            quote! {
+                GetAbleData::get(d, #key)
+                    .filter(|v| !v.is_nil())
+                    .map(FromRPCData::from_rpc)
+                    .transpose()
+            }
+        } else if let Some(default) = &f.default {
+            let default = rust_expr(default)?;
+            quote! {
+                GetAbleData::get(d, #key)
+                    .map(FromRPCData::from_rpc)
+                    .transpose()
+                    .map(|v| v.unwrap_or_else(|| #default))
+            }
+        } else {
+            quote! { GetAbleData::try_get(d, #key).and_then(FromRPCData::from_rpc) }
+        });
+        names.push(ident(&f.name)?);
+        keys.push(key);
+    }
+
+    if names.is_empty() {
+        return Ok(quote! { Ok(Self {}) });
+    }
+    Ok(quote! {
+        let (#(#names,)*) = (#(#decodes,)*);
+        DataError::check_fields(&[#((#keys, #names.as_ref().err())),*])?;
+        Ok(Self { #(#names: #names?),* })
+    })
+}
+
+/// the enum of the declared errors of the rpc and its conversions of the
+/// (error ...) envelope, None if the rpc declares no error
+fn error_tokens(
+    r: &GeneratedRPC,
+    renames: &BTreeMap<String, String>,
+) -> Result<Option<TokenStream>> {
+    let Some(name) = error_type(r, renames)? else {
+        return Ok(None);
+    };
+    let doc = format!(
+        " the declared errors of {}, they are the codes of the\n (error :code ... :message ...) envelope",
+        r.data_name
+    );
+    let variants = r
+        .errors
+        .iter()
+        .map(|e| ident(&kebab_to_pascal_case(e)))
+        .collect::<Result<Vec<_>>>()?;
+    let codes = &r.errors;
+
+    Ok(Some(quote! {
+        #[doc = #doc]
+        #[derive(Debug, Clone, PartialEq)]
+        pub enum #name {
+            #(#variants(String),)*
+        }
+
+        impl #name {
+            /// the code of the (error ...) envelope
+            pub fn code(&self) -> &'static str {
+                match self {
+                    #(Self::#variants(_) => #codes,)*
+                }
+            }
+
+            pub fn message(&self) -> &str {
+                match self {
+                    #(Self::#variants(m))|* => m,
+                }
+            }
+
+            /// the (error :code ... :message ...) envelope of this error
+            pub fn to_error_data(&self) -> Data {
+                Data::error(self.code(), self.message(), None)
+            }
+
+            /// the declared error of the (error ...) envelope, None if the data isn't
+            /// the envelope of the declared codes
+            pub fn from_error_data(d: &Data) -> Option<Self> {
+                let message = d.error_message().unwrap_or_default().to_string();
+                match d.error_code()? {
+                    #(#codes => Some(Self::#variants(message)),)*
+                    _ => None,
+                }
+            }
+        }
+
+        impl std::fmt::Display for #name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                write!(f, "{}: {}", self.code(), self.message())
+            }
+        }
+
+        impl std::error::Error for #name {}
+    }))
+}
+
+/// the typed methods of the client, one per rpc
+fn client_tokens(
+    rpcs: &[GeneratedRPC],
+    version: Option<i64>,
+    renames: &BTreeMap<String, String>,
+) -> Result<TokenStream> {
+    let (send, notify) = match version {
+        Some(v) => {
+            let fmt = format!("(request :version {} :body {{}})", v);
+            (
+                quote! { &format!(#fmt, req.to_rpc()) },
+                quote! { &format!(#fmt, msg.to_rpc()) },
+            )
+        }
+        None => (quote! { &req.to_rpc() }, quote! { &msg.to_rpc() }),
+    };
+
+    let mut methods = vec![];
+    for r in rpcs {
+        let (method, req) = rpc_names(r, renames)?;
+        let docs = doc_attrs(r.doc.as_deref());
+        if r.notify {
+            methods.push(quote! {
+                #(#docs)*
+                pub fn #method(&self, msg: #req) -> Result<(), Box<dyn std::error::Error>> {
+                    self.transport().notify(#notify)
+                }
+            });
+            continue;
+        }
+        methods.push(match (resp_type(r, renames)?, error_type(r, renames)?) {
+            (Some(resp), Some(err)) => quote! {
+                #(#docs)*
+                pub fn #method(&self, req: #req) -> Result<#resp, Box<dyn std::error::Error>> {
+                    let resp = self.transport().send(#send)?;
+                    let d = Data::from_root_str(&resp, None)?;
+                    if let Some(e) = #err::from_error_data(&d) {
+                        return Err(Box::new(e));
+                    }
+                    Ok(FromRPCData::from_rpc(&d)?)
+                }
+            },
+            (Some(resp), None) => quote! {
+                #(#docs)*
+                pub fn #method(&self, req: #req) -> Result<#resp, Box<dyn std::error::Error>> {
+                    let resp = self.transport().send(#send)?;
+                    Ok(FromRPCData::from_rpc(&Data::from_root_str(&resp, None)?)?)
+                }
+            },
+            (None, Some(err)) => quote! {
+                #(#docs)*
+                pub fn #method(&self, req: #req) -> Result<(), Box<dyn std::error::Error>> {
+                    let resp = self.transport().send(#send)?;
+                    match Data::from_root_str(&resp, None).ok().and_then(|d| #err::from_error_data(&d)) {
+                        Some(e) => Err(Box::new(e)),
+                        None => Ok(()),
+                    }
+                }
+            },
+            (None, None) => quote! {
+                #(#docs)*
+                pub fn #method(&self, req: #req) -> Result<(), Box<dyn std::error::Error>> {
+                    self.transport().send(#send)?;
+                    Ok(())
+                }
+            },
+        });
+    }
+
+    Ok(quote! {
+        impl<T: Transport> Client<T> {
+            #(#methods)*
+        }
+    })
+}
+
+/// the handler of the rpcs and the server dispatching the requests to it
+fn server_tokens(
+    rpcs: &[&GeneratedRPC],
+    version: Option<i64>,
+    renames: &BTreeMap<String, String>,
+) -> Result<TokenStream> {
+    let mut handler = vec![];
+    let mut arms = vec![];
+    for r in rpcs {
+        let (method, req) = rpc_names(r, renames)?;
+        let data_name = &r.data_name;
+        let resp = resp_type(r, renames)?;
+        let err = error_type(r, renames)?;
+        let hook = format_ident!("on_{}", method);
+        handler.push(match r.notify {
+            true => {
+                let doc = format!(
+                    " the hook of the {} notification, it does nothing by default",
+                    data_name
+                );
+                quote! {
+                    #[doc = #doc]
+                    fn #hook(&self, _msg: #req) {}
+                }
+            }
+            false => handler_method(&method, &req, resp.as_ref(), err.as_ref()),
+        });
+
+        if let Some(v) = version
+            && (r.versions.since.is_some() || r.versions.until.is_some())
+        {
+            let since = r.versions.since.unwrap_or(1);
+            let until = r.versions.until.unwrap_or(v);
+            let msg = format!(
+                "{} is in the versions {} to {}, got {{}}",
+                data_name, since, until
+            );
+            let (since, until) = (
+                Literal::i64_unsuffixed(since),
+                Literal::i64_unsuffixed(until),
+            );
+            arms.push(match r.notify {
+                true => quote! {
+                    #data_name if !(#since..=#until).contains(&version) => Data::Nil,
+                },
+                false => quote! {
+                    #data_name if !(#since..=#until).contains(&version) => Data::error(
+                        "unsupported-version",
+                        &format!(#msg, version),
+                        None,
+                    ),
+                },
+            });
+        }
+        if r.notify {
+            arms.push(quote! {
+                #data_name => {
+                    if let Ok(msg) = #req::from_rpc(d) {
+                        self.handler.#hook(msg);
+                    }
+                    Data::Nil
+                }
+            });
+            continue;
+        }
+        let ok = match (resp, err) {
+            (Some(_), Some(_)) => quote! {
+                Ok(req) => match self.handler.#method(req) {
+                    Ok(resp) => encode_response(resp),
+                    Err(e) => e.to_error_data(),
+                },
+            },
+            (None, Some(_)) => quote! {
+                Ok(req) => match self.handler.#method(req) {
+                    Ok(()) => Data::ok(Data::Nil),
+                    Err(e) => e.to_error_data(),
+                },
+            },
+            (Some(_), None) => quote! { Ok(req) => encode_response(self.handler.#method(req)), },
+            (None, None) => quote! {
+                Ok(req) => {
+                    self.handler.#method(req);
+                    Data::ok(Data::Nil)
+                }
+            },
+        };
+        arms.push(quote! {
+            #data_name => match #req::from_rpc(d) {
+                #ok
+                Err(e) => Data::error("bad-request", e.msg(), None),
+            },
+        });
+    }
+
+    let envelope = match version {
+        Some(v) => {
+            let msg = format!("the versions up to {} are supported, got {{}}", v);
+            let v = Literal::i64_unsuffixed(v);
+            quote! {
+                let (version, d) = match d.request_body() {
+                    Some(body) => (d.request_version().unwrap_or(#v), body),
+                    None => (#v, d),
+                };
+                if version > #v {
+                    return Data::error("unsupported-version", &format!(#msg, version), None);
+                }
+            }
+        }
+        None => quote! {},
+    };
+    let notify_doc = rpcs.iter().any(|r| r.notify).then(|| {
+        quote! { #[doc = " The notifications respond nil even they fail, the nil isn't sent"] }
+    });
+
+    Ok(quote! {
+        /// the handler of all rpcs, the server calls it
+        pub trait Handler {
+            #(#handler)*
+        }
+
+        /// the server dispatches the request data to the handler by the data name
+        pub struct Server<H: Handler> {
+            handler: H,
+        }
+
+        impl<H: Handler> Server<H> {
+            pub fn new(handler: H) -> Self {
+                Self { handler }
+            }
+
+            /// decode the request, call the handler, and encode the response in the
+            /// (response ...) envelope, or the (error ...) envelope if it fails
+            #notify_doc
+            pub fn dispatch(&self, d: &Data) -> Data {
+                #envelope
+                let name = match d.untagged() {
+                    Data::Data(e) => e.get_name(),
+                    _ => return Data::error("bad-request", "the request has to be data", None),
+                };
+
+                match name {
+                    #(#arms)*
+                    _ => Data::error("unknown-rpc", &format!("unknown rpc {}", name), None),
+                }
+            }
+
+            /// dispatch the request in the (call :id n ...) and wrap its response in
+            /// the (reply :id n ...), the other data are dispatched as usual
+            pub fn dispatch_call(&self, d: &Data) -> Data {
+                if !d.is_call() {
+                    return self.dispatch(d);
+                }
+
+                match (d.call_id(), d.call_request()) {
+                    (Some(id), Some(req)) => match self.dispatch(&req) {
+                        Data::Nil => Data::Nil,
+                        resp => Data::reply(id, resp),
+                    },
+                    _ => Data::error("bad-request", "the call needs the id and the method", None),
+                }
+            }
+        }
+
+        fn encode_response(resp: impl ToRPCData) -> Data {
+            match Data::from_root_str(&resp.to_rpc(), None) {
+                Ok(d) => Data::ok(d),
+                Err(e) => Data::error("internal", &e.to_string(), None),
+            }
+        }
+
+        /// wrap the request in the (call :id n ...), the transports sending the
+        /// concurrent requests over one connection correlate the replies by the id
+        pub fn encode_call(
+            id: i64,
+            req: &str,
+        ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+            let d = Data::from_root_str(req, None).map_err(|e| e.to_string())?;
+            Ok(Data::call(id, &d)?.to_string())
+        }
+
+        /// the id and the body of the (reply :id n :body ...)
+        pub fn decode_reply(
+            reply: &str,
+        ) -> Result<(i64, String), Box<dyn std::error::Error + Send + Sync>> {
+            let d = Data::from_root_str(reply, None).map_err(|e| e.to_string())?;
+            match (d.reply_id(), d.reply_body()) {
+                (Some(id), Some(body)) => Ok((id, body.to_string())),
+                _ => Err(format!("{} isn't the reply", reply).into()),
+            }
+        }
+    })
+}
+
+/// the enum of the methods of the rpcs and its conversions of the data names
+fn method_tokens(rpcs: &[&GeneratedRPC]) -> Result<TokenStream> {
+    let names = rpcs
+        .iter()
+        .map(|r| ident(&kebab_to_pascal_case(&r.data_name)))
+        .collect::<Result<Vec<_>>>()?;
+    let symbols: Vec<&str> = rpcs.iter().map(|r| r.data_name.as_str()).collect();
+
+    Ok(quote! {
+        /// the methods of all rpcs, the symbols are the data names of their requests
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub enum RpcMethod {
+            #(#names),*
+        }
+
+        impl RpcMethod {
+            /// the data name of the request of this method
+            pub fn as_symbol(&self) -> &'static str {
+                match self {
+                    #(Self::#names => #symbols,)*
+                }
+            }
+
+            /// the method of the data name, None if no rpc has it
+            pub fn from_symbol(s: &str) -> Option<Self> {
+                match s {
+                    #(#symbols => Some(Self::#names),)*
+                    _ => None,
+                }
+            }
+        }
+    })
+}
+
+/// the trait of the def-service and the function dispatching to it
+fn service_tokens(
+    service: &str,
+    rpcs: &[&GeneratedRPC],
+    renames: &BTreeMap<String, String>,
+) -> Result<TokenStream> {
+    let name = ident(&kebab_to_pascal_case(service))?;
+    let dispatch = format_ident!("dispatch_{}", kebab_to_snake_case(service));
+    let doc = format!(
+        " route the request data to the method of {} by the data name, and\n return the response. None if no rpc of {} has the name",
+        name, name
+    );
+
+    let mut methods = vec![];
+    let mut arms = vec![];
+    for r in rpcs {
+        let (method, req) = rpc_names(r, renames)?;
+        let data_name = &r.data_name;
+        let resp = resp_type(r, renames)?;
+        let err = error_type(r, renames)?;
+        methods.push(handler_method(&method, &req, resp.as_ref(), err.as_ref()));
+        match (resp, err) {
+            (resp, Some(_)) => {
+                let ok = match resp {
+                    Some(_) => quote! { Ok(resp) => resp.to_rpc(), },
+                    None => quote! { Ok(()) => "nil".to_string(), },
+                };
+                arms.push(quote! {
+                    #data_name => Some(FromRPCData::from_rpc(d).map(|req: #req| {
+                        match service.#method(req) {
+                            #ok
+                            Err(e) => e.to_error_data().to_string(),
+                        }
+                    })),
+                });
+            }
+            (Some(_), None) => {
+                arms.push(quote! {
+                    #data_name => Some(FromRPCData::from_rpc(d).map(|req: #req| {
+                        service.#method(req).to_rpc()
+                    })),
+                });
+            }
+            (None, None) => {
+                arms.push(quote! {
+                    #data_name => Some(FromRPCData::from_rpc(d).map(|req: #req| {
+                        service.#method(req);
+                        "nil".to_string()
+                    })),
+                });
+            }
+        }
+    }
+
+    Ok(quote! {
+        pub trait #name {
+            #(#methods)*
+        }
+
+        #[doc = #doc]
+        pub fn #dispatch(service: &impl #name, d: &Data) -> Option<Result<String, DataError>> {
+            let name = match d.untagged() {
+                Data::Data(e) => e.get_name(),
+                _ => return None,
+            };
+
+            match name {
+                #(#arms)*
+                _ => None,
+            }
+        }
+    })
+}
+
+/// the method and the request struct of the rpc, the struct is renamed
+fn rpc_names(
+    r: &GeneratedRPC,
+    renames: &BTreeMap<String, String>,
+) -> Result<(syn::Ident, syn::Ident)> {
+    Ok((
+        ident(&kebab_to_snake_case(&r.data_name))?,
+        ident(&rename_type(&kebab_to_pascal_case(&r.data_name), renames))?,
+    ))
+}
+
+/// the method of the handler trait, it returns the Result of the declared
+/// errors if the rpc has them
+fn handler_method(
+    method: &syn::Ident,
+    req: &syn::Ident,
+    resp: Option<&syn::Type>,
+    err: Option<&syn::Ident>,
+) -> TokenStream {
+    match (resp, err) {
+        (Some(resp), Some(err)) => quote! { fn #method(&self, req: #req) -> Result<#resp, #err>; },
+        (None, Some(err)) => quote! { fn #method(&self, req: #req) -> Result<(), #err>; },
+        (Some(resp), None) => quote! { fn #method(&self, req: #req) -> #resp; },
+        (None, None) => quote! { fn #method(&self, req: #req); },
+    }
+}
+
+/// the enum of the declared errors, None if the rpc declares no error
+fn error_type(r: &GeneratedRPC, renames: &BTreeMap<String, String>) -> Result<Option<syn::Ident>> {
+    match r.errors.is_empty() {
+        true => Ok(None),
+        false => Ok(Some(ident(&rename_type(
+            &(kebab_to_pascal_case(&r.data_name) + "Error"),
+            renames,
+        ))?)),
+    }
+}
+
+fn resp_type(r: &GeneratedRPC, renames: &BTreeMap<String, String>) -> Result<Option<syn::Type>> {
+    r.returns
+        .as_deref()
+        .map(|t| rust_type(&rename_type(&type_translate(t), renames)))
+        .transpose()
+}
+
+/// the doc attributes of the docstring, one per line like the `///`
+fn doc_attrs(doc: Option<&str>) -> Vec<TokenStream> {
+    doc.into_iter()
+        .flat_map(|d| d.lines())
+        .map(|l| {
+            let l = format!(" {}", l.trim()).trim_end().to_string();
+            quote! { #[doc = #l] }
+        })
+        .collect()
+}
+
+fn deprecated_attr(f: &GeneratedField) -> Result<TokenStream> {
+    Ok(match &f.deprecated {
+        Some(note) => {
+            let note: syn::LitStr = syn::parse_str(note)?;
+            quote! { #[deprecated(note = #note)] }
+        }
+        None => quote! {},
+    })
+}
+
+fn ident(name: &str) -> Result<syn::Ident> {
+    syn::parse_str(name).with_context(|| format!("{} can't be the rust name", name))
+}
+
+fn rust_type(ty: &str) -> Result<syn::Type> {
+    syn::parse_str(ty).with_context(|| format!("{} isn't the rust type", ty))
+}
+
+fn rust_expr(expr: &str) -> Result<syn::Expr> {
+    syn::parse_str(expr).with_context(|| format!("{} isn't the rust expr", expr))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use lisp_rpc_rust_parser::Parser;
+
+    use super::*;
+    use crate::{GenOptions, SpecFile};
+
+    #[test]
+    fn test_rust_tokens() {
+        let spec = r#"(def-rpc-package library)
+(def-version 2)
+(def-const max-page-size "the page limit" 100)
+(def-const min-offset -1)
+(def-alias isbn "the book id" 'string)
+(def-msg book-info "the \"book\""
+    :title ('string :doc "the title")
+    :isbn ('isbn :default "0")
+    :tags '(list 'string)
+    :lang (optional 'language-perfer)
+    :prices '(map 'string 'number)
+    :old ('string :deprecated "use the title")
+    :extra '(:cover 'bytes))
+(def-msg language-perfer :lang ('string :default "en"))
+(def-msg node :next (optional 'node))
+(def-rpc get-book "find the book" :since 2 '(:title 'string) 'book-info)
+(def-rpc return-book '(:books '(list 'book-info)) :errors ('not-found))
+(def-notify book-added '(:id 'string))
+(def-service shelf (put-book '(:book 'book-info)))"#;
+        let read = |options: GenOptions| {
+            let mut specs = SpecFile::new().config_options(options);
+            for e in Parser::new().parse_root(Cursor::new(spec)).unwrap() {
+                specs.record_expr(&e).unwrap();
+            }
+            specs
+        };
+
+        let files = read(GenOptions::default())
+            .gen_backend(&RustTokensBackend)
+            .unwrap();
+        assert_eq!(files[0].0, PathBuf::from("src/lib.rs"));
+        let code = &files[0].1;
+        for part in [
+            "pub const SPEC_VERSION: i64 = 2;",
+            "/// the page limit\npub const MAX_PAGE_SIZE: i64 = 100;\npub const MIN_OFFSET: i64 = -1;",
+            r#"/// the "book"
+#[derive(Debug)]
+pub struct BookInfo {
+    /// the title
+    title: String,
+    isbn: Isbn,
+    tags: Vec<String>,
+    lang: Option<LanguagePerfer>,
+    prices: std::collections::HashMap<String, i64>,
+    #[deprecated(note = "use the title")]
+    old: String,
+    extra: BookInfoExtra,
+}"#,
+            "next: Option<Box<Node>>,",
+            "/// the book id\n#[derive(Debug, Clone, PartialEq, Default)]\npub struct Isbn(pub String);",
+            "            GetAbleData::get(d, \"isbn\")
+                .map(FromRPCData::from_rpc)
+                .transpose()
+                .map(|v| v.unwrap_or_else(|| Isbn::from(\"0\".to_string()))),",
+            "                (\"title\", title.as_ref().err()),",
+            "            title: title?,",
+            r#"        let mut args: Vec<String> = vec!["book-info".to_string()];
+        args.push(format!(":title {}", self.title.to_rpc()));
+        args.push(format!(":isbn {}", self.isbn.to_rpc()));
+        let tags = self.tags.iter().map(|e| e.to_rpc()).collect::<Vec<_>>().join(" ");
+        args.push(format!(":tags '({})", tags));
+        if let Some(v) = &self.lang {"#,
+            r#"        let mut args: Vec<String> = vec![];
+        args.push(format!(":cover {}", self.cover.to_rpc()));
+        format!("'({})", args.join(" "))"#,
+            r#"                .map(|v| v.unwrap_or_else(|| "en".to_string())),"#,
+            "pub const SINCE_VERSION: i64 = 2;",
+            r#"        let resp = self
+            .transport()
+            .send(&format!("(request :version 2 :body {})", req.to_rpc()))?;"#,
+            r#""get-book" if !(2..=2).contains(&version) => {"#,
+            "fn return_book(&self, req: ReturnBook) -> Result<(), ReturnBookError>;",
+            "pub enum ReturnBookError {\n    NotFound(String),\n}",
+            "    /// the hook of the book-added notification, it does nothing by default
+    fn on_book_added(&self, _msg: BookAdded) {}",
+            r#"    pub fn book_added(&self, msg: BookAdded) -> Result<(), Box<dyn std::error::Error>> {
+        self.transport().notify(&format!("(request :version 2 :body {})", msg.to_rpc()))
+    }"#,
+            r#"            "book-added" => {
+                if let Ok(msg) = BookAdded::from_rpc(d) {
+                    self.handler.on_book_added(msg);
+                }
+                Data::Nil
+            }"#,
+            r#"                    Ok(req) => {
+                        match self.handler.return_book(req) {
+                            Ok(()) => Data::ok(Data::Nil),
+                            Err(e) => e.to_error_data(),
+                        }
+                    }"#,
+            "pub trait Shelf {\n    fn put_book(&self, req: PutBook);\n}",
+            "pub fn dispatch_shelf(",
+            "                match self.dispatch(&req) {
+                    Data::Nil => Data::Nil,
+                    resp => Data::reply(id, resp),
+                }",
+            "pub enum RpcMethod {\n    GetBook,",
+            r#"            "get-book" => Some(Self::GetBook),"#,
+            "pub fn encode_call(",
+            "pub fn decode_reply(",
+        ] {
+            assert!(code.contains(part), "{}\n\nnot in\n\n{}", part, code);
+        }
+        assert!(format_rust(code).is_ok());
+
+        // the stock templates render the valid rust with the options
+        for (i, options) in [
+            GenOptions::default(),
+            GenOptions {
+                async_api: true,
+                serde: true,
+                ..Default::default()
+            },
+            GenOptions {
+                pub_fields: true,
+                builders: true,
+                split_files: true,
+                ..Default::default()
+            },
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let out =
+                std::env::temp_dir().join(format!("lisp-rpc-tokens-{}-{}", std::process::id(), i));
+            read(GenOptions {
+                default_templates: true,
+                ..options
+            })
+            .gen_code_to_file(out.clone(), &[] as &[PathBuf])
+            .unwrap();
+            for f in crate::get_all_file_paths_in_folder(&out).unwrap() {
+                if f.extension().is_some_and(|e| e == "rs") {
+                    let code = std::fs::read_to_string(&f).unwrap();
+                    if let Err(e) = format_rust(&code) {
+                        panic!("{:?}: {}", f, e)
+                    }
+                }
+            }
+            std::fs::remove_dir_all(out).unwrap();
+        }
+
+        assert!(
+            format_rust("pub struct A {\n    a: i64\n    b: i64,\n}")
+                .unwrap_err()
+                .to_string()
+                .ends_with("at line 3 column 5:\n    b: i64,")
+        );
+    }
+
+    #[test]
+    fn test_rust_tokens_renames() {
+        let spec = r#"(def-rpc-package library)
+(def-msg result :rust-name "BookResult" :kind 'string)
+(def-msg shelf :books '(list 'result))
+(def-rpc find-book :rust-name "Fetch" '(:title 'string) 'result :errors ('not-found))"#;
+        let specs = SpecFile::new()
+            .config_options(GenOptions {
+                default_templates: true,
+                ..Default::default()
+            })
+            .read_from(spec.as_bytes())
+            .unwrap();
+
+        let tokens = specs.gen_backend(&RustTokensBackend).unwrap();
+        let templates = specs
+            .generate(crate::GenerateTarget::Rust, &[] as &[PathBuf])
+            .unwrap();
+        let structs = |code: &str| {
+            let mut names: Vec<String> = code
+                .lines()
+                .filter_map(|l| l.strip_prefix("pub struct "))
+                .map(|l| l.trim_end_matches(" {").to_string())
+                // the structs of the specs, not the server
+                .filter(|n| !n.contains('<'))
+                .collect();
+            names.sort();
+            names
+        };
+
+        // the structs are named same as the templates name them
+        let code = &tokens[0].1;
+        assert_eq!(
+            structs(code),
+            structs(templates.file("src/lib.rs").unwrap())
+        );
+        assert_eq!(structs(code), vec!["BookResult", "Fetch", "Shelf"]);
+        assert!(code.contains("books: Vec<BookResult>,"));
+        assert!(
+            code.contains("fn find_book(&self, req: Fetch) -> Result<BookResult, FindBookError>;")
+        );
+        assert!(code.contains("Fetch::from_rpc"));
+    }
+}
@@ -0,0 +1,221 @@
+//! the template sets shared by the git repos or the http directory indexes.
+//! The remote set is downloaded to the cache folder, and the folder is used
+//! as the local templates folder
+//!
+//! the git url is the url ending with `.git`, or starts with `git@`, `git://`,
+//! `ssh://` or `git+`. The `#<ref>` after the url is the branch or the tag.
+//! The other http urls are the directory indexes, the `.template` files of the
+//! index and its sub-folders are downloaded
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use anyhow::{Context, Result};
+use url::Url;
+
+use crate::read_single_template_content;
+
+/// the folder of the template set, the remote set is downloaded to the cache
+/// folder first. The local path is returned as it is
+pub fn fetch_template_set(source: &str, cache: &Path) -> Result<PathBuf> {
+    if is_git_source(source) {
+        let (repo, rev) = match source.split_once('#') {
+            Some((repo, rev)) => (repo.strip_prefix("git+").unwrap_or(repo), Some(rev)),
+            None => (source.strip_prefix("git+").unwrap_or(source), None),
+        };
+        let dir = cache.join(cache_name(source));
+        fetch_git(repo, rev, &dir)?;
+        return Ok(dir);
+    }
+
+    match Url::parse(source) {
+        Ok(url) if url.scheme() == "http" || url.scheme() == "https" => {
+            let dir = cache.join(cache_name(source));
+            fetch_index(&url, &dir)?;
+            Ok(dir)
+        }
+        _ => Ok(PathBuf::from(source)),
+    }
+}
+
+/// the default cache folder of the template sets
+pub fn default_template_cache() -> PathBuf {
+    std::env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".cache")))
+        .unwrap_or_else(std::env::temp_dir)
+        .join("lisp-rpc")
+        .join("templates")
+}
+
+fn is_git_source(source: &str) -> bool {
+    let repo = source.split('#').next().unwrap_or(source);
+    repo.ends_with(".git")
+        || ["git@", "git://", "ssh://", "git+"]
+            .iter()
+            .any(|p| repo.starts_with(p))
+}
+
+/// the folder name of the source in the cache folder
+fn cache_name(source: &str) -> String {
+    source
+        .split_once("://")
+        .map_or(source, |(_, rest)| rest)
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '.' | '-' => c,
+            _ => '_',
+        })
+        .collect()
+}
+
+/// clone the repo to the folder, or update the cloned one
+fn fetch_git(repo: &str, rev: Option<&str>, dir: &Path) -> Result<()> {
+    let mut cmd = Command::new("git");
+    if dir.join(".git").exists() {
+        println!("Attempting to update template set: {}", repo);
+        cmd.arg("-C").arg(dir).args(["pull", "--ff-only"]);
+    } else {
+        println!("Attempting to clone template set: {}", repo);
+        if let Some(p) = dir.parent() {
+            fs::create_dir_all(p)?;
+        }
+        cmd.args(["clone", "--depth", "1"]);
+        if let Some(rev) = rev {
+            cmd.args(["--branch", rev]);
+        }
+        cmd.arg(repo).arg(dir);
+    }
+
+    let status = cmd
+        .status()
+        .with_context(|| format!("Failed to run git for {}", repo))?;
+    if !status.success() {
+        anyhow::bail!("git failed to fetch the template set {}", repo)
+    }
+    Ok(())
+}
+
+/// download the templates of the index and its sub-folders to the folder
+fn fetch_index(url: &Url, dir: &Path) -> Result<()> {
+    // the index is the folder, the links are relative to it
+    let mut base = url.clone();
+    if !base.path().ends_with('/') {
+        base.set_path(&format!("{}/", base.path()));
+    }
+
+    let mut indexes = vec![base.clone()];
+    while let Some(index) = indexes.pop() {
+        let html = read_single_template_content(index.as_str())?;
+        for link in index_links(&index, &html) {
+            let Some(rel) = base.make_relative(&link) else {
+                continue;
+            };
+            if rel.ends_with('/') {
+                indexes.push(link);
+            } else if rel.ends_with(".template") {
+                let path = dir.join(&rel);
+                if let Some(p) = path.parent() {
+                    fs::create_dir_all(p)?;
+                }
+                fs::write(&path, read_single_template_content(link.as_str())?)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// the links of the index inside the index folder, the parents, the sort
+/// links and the hidden files are skipped
+fn index_links(index: &Url, html: &str) -> Vec<Url> {
+    html.split("href=")
+        .skip(1)
+        .filter_map(|s| {
+            let quote = s.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+            s[1..].split(quote).next()
+        })
+        .filter(|href| !href.starts_with('?') && !href.starts_with('#'))
+        .filter_map(|href| index.join(href).ok())
+        .filter(|link| {
+            link.query().is_none()
+                && link.as_str() != index.as_str()
+                && link.as_str().starts_with(index.as_str())
+                && !link.as_str()[index.as_str().len()..]
+                    .split('/')
+                    .any(|c| c.starts_with('.'))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fetch_template_set() {
+        let index = Url::parse("http://example.com/templates/").unwrap();
+        assert_eq!(
+            index_links(
+                &index,
+                r#"<a href="?C=N;O=D">Name</a> <a href="../">Parent</a>
+<a href="def_msg.rs.template">def_msg.rs.template</a>
+<a href='partials/'>partials/</a> <a href=".hidden">.hidden</a>
+<a href="/templates/def_rpc.rs.template">def_rpc.rs.template</a>
+<a href="http://other.com/x.template">x</a>"#
+            )
+            .iter()
+            .map(|u| u.as_str())
+            .collect::<Vec<_>>(),
+            vec![
+                "http://example.com/templates/def_msg.rs.template",
+                "http://example.com/templates/partials/",
+                "http://example.com/templates/def_rpc.rs.template",
+            ]
+        );
+
+        assert!(is_git_source("https://github.com/org/templates.git#v1"));
+        assert!(is_git_source("git@github.com:org/templates"));
+        assert!(!is_git_source("https://example.com/templates/"));
+        assert_eq!(
+            cache_name("https://github.com/org/templates.git#v1"),
+            "github.com_org_templates.git_v1"
+        );
+
+        // the local git repo is cloned to the cache
+        let root = std::env::temp_dir().join(format!("lisp-rpc-set-{}", std::process::id()));
+        let repo = root.join("set.git");
+        fs::create_dir_all(repo.join("partials")).unwrap();
+        fs::write(repo.join("partials/head.template"), "// head").unwrap();
+        let git = |args: &[&str]| {
+            assert!(
+                Command::new("git")
+                    .arg("-C")
+                    .arg(&repo)
+                    .args(["-c", "user.name=t", "-c", "user.email=t@t"])
+                    .args(args)
+                    .output()
+                    .unwrap()
+                    .status
+                    .success()
+            )
+        };
+        git(&["init", "-q"]);
+        git(&["add", "."]);
+        git(&["commit", "-qm", "init"]);
+
+        let source = format!("file://{}", repo.display());
+        let cache = root.join("cache");
+        let dir = fetch_template_set(&source, &cache).unwrap();
+        assert_eq!(
+            crate::template_names(&[&dir])
+                .unwrap()
+                .keys()
+                .collect::<Vec<_>>(),
+            vec!["partials/head"]
+        );
+        // the second fetch updates the cloned one
+        assert_eq!(fetch_template_set(&source, &cache).unwrap(), dir);
+        fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,487 @@
+//! validate the Data against the specs, servers can reject the malformed
+//! requests before dispatch
+
+use std::time::SystemTime;
+
+use lisp_rpc_rust_parser::{
+    Atom, Expr, TypeValue,
+    data::{Data, FromData, Violation, check_uuid},
+};
+
+/// the keyword-value pairs of the expr data or the map data
+fn pairs_of(d: &Data) -> Option<Vec<(&str, &Data)>> {
+    match d {
+        Data::Data(e) => Some(e.iter().map(|(k, v)| (k.as_str(), v)).collect()),
+        Data::Map(m) => Some(m.iter().map(|(k, v)| (k.as_str(), v)).collect()),
+        Data::Tagged(t) => pairs_of(t.data()),
+        _ => None,
+    }
+}
+
+pub(crate) fn kind_of(d: &Data) -> &'static str {
+    match d {
+        Data::Data(_) => "data",
+        Data::List(_) => "list",
+        Data::Map(_) => "map",
+        Data::Value(TypeValue::String(_)) => "string",
+        Data::Value(TypeValue::Number(_)) => "number",
+        Data::Value(TypeValue::Keyword(_)) => "keyword",
+        Data::Value(TypeValue::Symbol(_)) => "symbol",
+        Data::Tagged(_) => "tagged",
+        Data::Nil => "nil",
+        Data::Bytes(_) => "bytes",
+    }
+}
+
+/// check the keyword-value pairs of data against the keyword-type pairs of
+/// spec. All keywords of spec are required except the optional ones and the
+/// ones with the default value, and no unknown keyword. The reserved keys
+/// are ignored
+pub(crate) fn validate_fields(
+    path: &str,
+    fields: &[Expr],
+    reserved: &[String],
+    d: &Data,
+    out: &mut Vec<Violation>,
+) {
+    let pairs = match pairs_of(d) {
+        Some(p) => p,
+        None => {
+            out.push(Violation::new(
+                path,
+                format!("expect data or map, got {}", kind_of(d)),
+            ));
+            return;
+        }
+    };
+
+    let mut spec_keys = vec![];
+    for [k, ty] in fields.iter().array_chunks() {
+        let k = match k {
+            Expr::Atom(Atom {
+                value: TypeValue::Keyword(k),
+            }) => k,
+            _ => {
+                out.push(Violation::new(path, "spec has to be keyword-type pairs"));
+                return;
+            }
+        };
+        spec_keys.push(k.as_str());
+
+        let field_path = Violation::join_path(path, k);
+        match pairs.iter().find(|(kk, _)| kk == k) {
+            Some((_, v)) => validate_type(&field_path, ty, v, out),
+            None if crate::optional_type(ty).is_some()
+                || crate::type_options(ty).is_some_and(|o| o.default.is_some()) => {}
+            None => out.push(Violation::new(&field_path, "missing keyword")),
+        }
+    }
+
+    for (k, _) in &pairs {
+        if !spec_keys.contains(k) && !reserved.iter().any(|r| r == k) {
+            out.push(Violation::new(
+                &Violation::join_path(path, k),
+                "unknown keyword",
+            ));
+        }
+    }
+}
+
+/// check the value against the type expr of the spec
+/// 'string, 'number, 'keyword, 'timestamp, 'bytes, 'uuid, 'other-msg, '(:a 'string), '(list 'string)
+/// (optional 'string) and ('number :default 8 :doc "comment")
+pub(crate) fn validate_type(path: &str, ty: &Expr, d: &Data, out: &mut Vec<Violation>) {
+    // the type with the options, like the default value
+    if let Some(o) = crate::type_options(ty) {
+        return validate_type(path, o.inner, d, out);
+    }
+
+    // the tag has to match the named type, then check the data inside
+    if let Data::Tagged(t) = d {
+        if let Expr::Quote(box Expr::Atom(Atom {
+            value: TypeValue::Symbol(ty_name),
+        })) = ty
+            && ty_name != t.type_name()
+        {
+            out.push(Violation::new(
+                path,
+                format!("expect {} type, got {} type", ty_name, t.type_name()),
+            ));
+            return;
+        }
+        return validate_type(path, ty, t.data(), out);
+    }
+
+    match ty {
+        Expr::Quote(box Expr::Atom(Atom {
+            value: TypeValue::Symbol(t),
+        })) => match (t.as_str(), d) {
+            ("string", Data::Value(TypeValue::String(_)))
+            | ("number", Data::Value(TypeValue::Number(_)))
+            | ("keyword", Data::Value(TypeValue::Keyword(_)))
+            | ("bytes", Data::Bytes(_)) => (),
+            ("string" | "number" | "keyword" | "bytes", _) => out.push(Violation::new(
+                path,
+                format!("expect {}, got {}", t, kind_of(d)),
+            )),
+            ("uuid", Data::Value(TypeValue::String(s))) => {
+                if let Err(e) = check_uuid(s) {
+                    out.push(Violation::new(path, e.msg()))
+                }
+            }
+            ("uuid", _) => out.push(Violation::new(
+                path,
+                format!("expect uuid string, got {}", kind_of(d)),
+            )),
+            // (timestamp seconds) or the RFC3339 string
+            ("timestamp", _) => {
+                if let Err(e) = SystemTime::from_rpc_data(d) {
+                    out.push(Violation::new(path, e.msg()))
+                }
+            }
+            // the other msg
+            (_, Data::Data(e)) if e.get_name() == t => (),
+            _ => out.push(Violation::new(
+                path,
+                format!("expect {} data, got {}", t, kind_of(d)),
+            )),
+        },
+
+        Expr::Quote(box Expr::List(inner)) | Expr::List(inner) => match inner.as_slice() {
+            // anonymous map
+            [
+                Expr::Atom(Atom {
+                    value: TypeValue::Keyword(_),
+                }),
+                ..,
+            ] => match d {
+                Data::Map(_) => validate_fields(path, inner, &[], d, out),
+                _ => out.push(Violation::new(
+                    path,
+                    format!("expect map, got {}", kind_of(d)),
+                )),
+            },
+
+            // optional, nil is the missing value
+            [
+                Expr::Atom(Atom {
+                    value: TypeValue::Symbol(o),
+                }),
+                inner_ty,
+            ] if o == "optional" => match d {
+                Data::Nil => (),
+                _ => validate_type(path, inner_ty, d, out),
+            },
+
+            // list
+            [
+                Expr::Atom(Atom {
+                    value: TypeValue::Symbol(l),
+                }),
+                ele_ty,
+            ] if l == "list" => match d {
+                Data::List(l) => {
+                    for (i, ele) in l.iter().enumerate() {
+                        validate_type(
+                            &Violation::join_path(path, &i.to_string()),
+                            ele_ty,
+                            ele,
+                            out,
+                        )
+                    }
+                }
+                _ => out.push(Violation::new(
+                    path,
+                    format!("expect list, got {}", kind_of(d)),
+                )),
+            },
+
+            // map, the keys are the keywords
+            [
+                Expr::Atom(Atom {
+                    value: TypeValue::Symbol(m),
+                }),
+                _,
+                val_ty,
+            ] if m == "map" => match d {
+                Data::Map(m) => {
+                    for (k, v) in m.iter() {
+                        validate_type(&Violation::join_path(path, k), val_ty, v, out)
+                    }
+                }
+                // the empty map '() is read as the empty list
+                Data::List(l) if l.is_empty() => (),
+                _ => out.push(Violation::new(
+                    path,
+                    format!("expect map, got {}", kind_of(d)),
+                )),
+            },
+
+            _ => out.push(Violation::new(path, "unknown spec type")),
+        },
+
+        _ => out.push(Violation::new(path, "unknown spec type")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use lisp_rpc_rust_parser::{
+        Parser,
+        data::{Data, FromStr},
+    };
+
+    use crate::{DefMsg, DefRPC};
+
+    fn data(s: &str) -> Data {
+        Data::from_str(&Parser::new(), s).unwrap()
+    }
+
+    #[test]
+    fn test_validate_def_msg() {
+        let spec = DefMsg::from_expr(
+            &Parser::new()
+                .parse_root_one(Cursor::new(
+                    r#"(def-msg book-info
+    :lang 'language-perfer
+    :title 'string
+    :id 'number
+    :tags '(list 'string)
+    :extra '(:a 'string :b 'number))"#,
+                ))
+                .unwrap(),
+        )
+        .unwrap();
+
+        let good = data(
+            r#"(book-info :lang (language-perfer :lang "en") :title "1984" :id 1 :tags '("a" "b") :extra '(:a "a" :b 2))"#,
+        );
+        assert!(spec.validate(&good).is_empty());
+
+        // the tags have to match the spec names
+        let tagged = data(
+            r#"(the book-info (book-info :lang (the language-perfer (language-perfer :lang "en")) :title "1984" :id 1 :tags '("a") :extra '(:a "a" :b 2)))"#,
+        );
+        assert!(spec.validate(&tagged).is_empty());
+        let tagged = data(
+            r#"(book-info :lang (the language (language-perfer :lang "en")) :title "1984" :id 1 :tags '("a") :extra '(:a "a" :b 2))"#,
+        );
+        assert_eq!(
+            spec.validate(&tagged)[0].to_string(),
+            "lang: expect language-perfer type, got language type"
+        );
+        assert_eq!(
+            spec.validate(&data(r#"(the author (book-info))"#))[0].to_string(),
+            "expect type book-info, got type author"
+        );
+
+        let bad = data(
+            r#"(book :lang '(:lang "en") :title 1 :tags '("a" 2) :extra '(:a "a" :c 2) :more 1)"#,
+        );
+        let violations: Vec<String> = spec.validate(&bad).iter().map(|v| v.to_string()).collect();
+        assert_eq!(
+            violations,
+            vec![
+                "expect book-info data, got book",
+                "lang: expect language-perfer data, got map",
+                "title: expect string, got number",
+                "id: missing keyword",
+                "tags.1: expect string, got number",
+                "extra.b: missing keyword",
+                "extra.c: unknown keyword",
+                "more: unknown keyword",
+            ]
+        );
+
+        assert_eq!(
+            spec.validate(&data(r#""book""#))[0].to_string(),
+            "expect book-info data, got string"
+        );
+    }
+
+    #[test]
+    fn test_validate_def_rpc() {
+        let spec = DefRPC::from_expr(
+            &Parser::new()
+                .parse_root_one(Cursor::new(
+                    r#"(def-rpc get-book '(:title 'string :lang '(:lang 'string)) 'book-info)"#,
+                ))
+                .unwrap(),
+        )
+        .unwrap();
+
+        assert!(
+            spec.validate(&data(r#"(get-book :title "1984" :lang '(:lang "en"))"#))
+                .is_empty()
+        );
+
+        let violations = spec.validate(&data(r#"(get-book :title "1984" :lang "en")"#));
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].path, "lang");
+    }
+
+    #[test]
+    fn test_validate_optional() {
+        let spec = DefMsg::from_expr(
+            &Parser::new()
+                .parse_root_one(Cursor::new(
+                    r#"(def-msg book :title 'string :subtitle (optional 'string))"#,
+                ))
+                .unwrap(),
+        )
+        .unwrap();
+
+        assert!(spec.validate(&data(r#"(book :title "a")"#)).is_empty());
+        assert!(
+            spec.validate(&data(r#"(book :title "a" :subtitle nil)"#))
+                .is_empty()
+        );
+        assert!(
+            spec.validate(&data(r#"(book :title "a" :subtitle "b")"#))
+                .is_empty()
+        );
+        assert_eq!(
+            spec.validate(&data(r#"(book :title "a" :subtitle 1)"#))[0].to_string(),
+            "subtitle: expect string, got number"
+        );
+        assert_eq!(
+            spec.validate(&data(r#"(book :subtitle "b")"#))[0].to_string(),
+            "title: missing keyword"
+        );
+    }
+
+    #[test]
+    fn test_validate_default() {
+        let spec = DefMsg::from_expr(
+            &Parser::new()
+                .parse_root_one(Cursor::new(
+                    r#"(def-msg language-perfer :lang 'string :encoding ('number :default 8))"#,
+                ))
+                .unwrap(),
+        )
+        .unwrap();
+
+        assert!(
+            spec.validate(&data(r#"(language-perfer :lang "en")"#))
+                .is_empty()
+        );
+        assert_eq!(
+            spec.validate(&data(r#"(language-perfer :lang "en" :encoding "utf8")"#))[0].to_string(),
+            "encoding: expect number, got string"
+        );
+    }
+
+    #[test]
+    fn test_validate_map() {
+        let spec = DefMsg::from_expr(
+            &Parser::new()
+                .parse_root_one(Cursor::new(
+                    r#"(def-msg word-count :counts (map 'string 'number))"#,
+                ))
+                .unwrap(),
+        )
+        .unwrap();
+
+        assert!(
+            spec.validate(&data(r#"(word-count :counts '(:a 1 :b 2))"#))
+                .is_empty()
+        );
+        assert!(
+            spec.validate(&data(r#"(word-count :counts '())"#))
+                .is_empty()
+        );
+        assert_eq!(
+            spec.validate(&data(r#"(word-count :counts '(:a 1 :b "2"))"#))[0].to_string(),
+            "counts.b: expect number, got string"
+        );
+        assert_eq!(
+            spec.validate(&data(r#"(word-count :counts '(1 2))"#))[0].to_string(),
+            "counts: expect map, got list"
+        );
+    }
+
+    #[test]
+    fn test_validate_reserved() {
+        let spec = DefMsg::from_expr(
+            &Parser::new()
+                .parse_root_one(Cursor::new(
+                    r#"(def-msg book :title 'string (reserved :legacy-id))"#,
+                ))
+                .unwrap(),
+        )
+        .unwrap();
+
+        assert!(
+            spec.validate(&data(r#"(book :title "a" :legacy-id 1)"#))
+                .is_empty()
+        );
+        assert_eq!(
+            spec.validate(&data(r#"(book :title "a" :isbn 1)"#))[0].to_string(),
+            "isbn: unknown keyword"
+        );
+    }
+
+    #[test]
+    fn test_validate_uuid() {
+        let spec = DefMsg::from_expr(
+            &Parser::new()
+                .parse_root_one(Cursor::new(r#"(def-msg user :id 'uuid)"#))
+                .unwrap(),
+        )
+        .unwrap();
+
+        assert!(
+            spec.validate(&data(
+                r#"(user :id "67e55044-10b1-426f-9247-bb680e5fe0c8")"#
+            ))
+            .is_empty()
+        );
+        assert_eq!(
+            spec.validate(&data(r#"(user :id "67e55044")"#))[0].to_string(),
+            r#"id: invalid uuid "67e55044""#
+        );
+        assert_eq!(
+            spec.validate(&data("(user :id 1)"))[0].to_string(),
+            "id: expect uuid string, got number"
+        );
+    }
+
+    #[test]
+    fn test_validate_timestamp_and_bytes() {
+        let spec = DefMsg::from_expr(
+            &Parser::new()
+                .parse_root_one(Cursor::new(
+                    r#"(def-msg event :at 'timestamp :body 'bytes)"#,
+                ))
+                .unwrap(),
+        )
+        .unwrap();
+
+        assert!(
+            spec.validate(&data(
+                r#"(event :at (timestamp 1710000000) :body (bytes "AAH/"))"#
+            ))
+            .is_empty()
+        );
+        assert!(
+            spec.validate(&data(
+                r#"(event :at "2024-03-09T16:00:00Z" :body (bytes ""))"#
+            ))
+            .is_empty()
+        );
+        assert_eq!(
+            spec.validate(&data(r#"(event :at "yesterday" :body (bytes ""))"#))[0].to_string(),
+            r#"at: invalid RFC3339 time "yesterday""#
+        );
+        assert_eq!(
+            spec.validate(&data(r#"(event :at 1710000000 :body (bytes ""))"#))[0].to_string(),
+            "at: expect timestamp, got 1710000000"
+        );
+        assert_eq!(
+            spec.validate(&data(r#"(event :at (timestamp 1) :body "AAH/")"#))[0].to_string(),
+            "body: expect bytes, got string"
+        );
+    }
+}
@@ -0,0 +1,190 @@
+//! The canonical binary form of Expr, like the Rivest canonical s-expression.
+//!
+//! every atom is length-prefixed, there is no whitespace, so one expr has exactly one encoding:
+//!
+//! + list: `(` exprs... `)`
+//! + quote: `'` expr
+//! + symbol: `3:abc`
+//! + string: `"5:hello`
+//! + keyword: `:5:title`
+//! + number: `#4:1984`
+
+use crate::{Atom, Expr, ParserError, TypeValue};
+
+impl Expr {
+    /// encode this expr to the canonical bytes
+    pub fn to_canonical_bytes(&self) -> Vec<u8> {
+        let mut buf = vec![];
+        write_canonical(self, &mut buf);
+        buf
+    }
+
+    /// decode the expr from the canonical bytes, the bytes have to be exactly one expr
+    pub fn from_canonical_bytes(bytes: &[u8]) -> Result<Self, ParserError> {
+        let mut ind = 0;
+        let expr = read_canonical(bytes, &mut ind)?;
+        if ind != bytes.len() {
+            return Err(ParserError::CorruptData(
+                "trailing bytes after canonical expr",
+            ));
+        }
+
+        Ok(expr)
+    }
+}
+
+fn write_atom_payload(payload: &[u8], buf: &mut Vec<u8>) {
+    buf.extend_from_slice(payload.len().to_string().as_bytes());
+    buf.push(b':');
+    buf.extend_from_slice(payload);
+}
+
+fn write_canonical(expr: &Expr, buf: &mut Vec<u8>) {
+    match expr {
+        Expr::Atom(Atom { value }) => match value {
+            TypeValue::Symbol(s) => write_atom_payload(s.as_bytes(), buf),
+            TypeValue::String(s) => {
+                buf.push(b'"');
+                write_atom_payload(s.as_bytes(), buf)
+            }
+            TypeValue::Keyword(s) => {
+                buf.push(b':');
+                write_atom_payload(s.as_bytes(), buf)
+            }
+            TypeValue::Number(n) => {
+                buf.push(b'#');
+                write_atom_payload(n.to_string().as_bytes(), buf)
+            }
+        },
+        Expr::List(exprs) => {
+            buf.push(b'(');
+            for e in exprs {
+                write_canonical(e, buf);
+            }
+            buf.push(b')');
+        }
+        Expr::Quote(e) => {
+            buf.push(b'\'');
+            write_canonical(e, buf);
+        }
+    }
+}
+
+/// read the `len:payload` part
+fn read_atom_payload<'b>(bytes: &'b [u8], ind: &mut usize) -> Result<&'b str, ParserError> {
+    let start = *ind;
+    while let Some(b) = bytes.get(*ind)
+        && b.is_ascii_digit()
+    {
+        *ind += 1;
+    }
+
+    let len_part = &bytes[start..*ind];
+    if len_part.is_empty() || (len_part.len() > 1 && len_part[0] == b'0') {
+        return Err(ParserError::CorruptData("invalid length prefix"));
+    }
+
+    if bytes.get(*ind) != Some(&b':') {
+        return Err(ParserError::CorruptData("length prefix has to end with :"));
+    }
+    *ind += 1;
+
+    let len: usize = std::str::from_utf8(len_part)
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .ok_or(ParserError::CorruptData("invalid length prefix"))?;
+
+    let payload = bytes.get(*ind..*ind + len).ok_or(ParserError::CorruptData(
+        "payload is shorter than its length",
+    ))?;
+    *ind += len;
+
+    std::str::from_utf8(payload).map_err(|_| ParserError::CorruptData("payload is not utf8"))
+}
+
+fn read_canonical(bytes: &[u8], ind: &mut usize) -> Result<Expr, ParserError> {
+    match bytes.get(*ind) {
+        Some(b'(') => {
+            *ind += 1;
+            let mut res = vec![];
+            loop {
+                match bytes.get(*ind) {
+                    Some(b')') => {
+                        *ind += 1;
+                        break;
+                    }
+                    Some(_) => res.push(read_canonical(bytes, ind)?),
+                    None => return Err(ParserError::CorruptData("list is not closed")),
+                }
+            }
+            Ok(Expr::List(res))
+        }
+        Some(b'\'') => {
+            *ind += 1;
+            Ok(Expr::Quote(Box::new(read_canonical(bytes, ind)?)))
+        }
+        Some(b'"') => {
+            *ind += 1;
+            Ok(Expr::Atom(Atom::read_string(read_atom_payload(
+                bytes, ind,
+            )?)))
+        }
+        Some(b':') => {
+            *ind += 1;
+            Ok(Expr::Atom(Atom::read_keyword(read_atom_payload(
+                bytes, ind,
+            )?)))
+        }
+        Some(b'#') => {
+            *ind += 1;
+            let token = read_atom_payload(bytes, ind)?;
+            let n = token
+                .parse::<i64>()
+                .map_err(|_| ParserError::CorruptData("invalid number payload"))?;
+            Ok(Expr::Atom(Atom::read_number(token, n)))
+        }
+        Some(b) if b.is_ascii_digit() => Ok(Expr::Atom(Atom::read(read_atom_payload(bytes, ind)?))),
+        Some(_) => Err(ParserError::CorruptData("unknown canonical tag")),
+        None => Err(ParserError::CorruptData("run out the bytes")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::Parser;
+
+    #[test]
+    fn test_canonical_bytes() {
+        let mut parser = Parser::new();
+        let expr = parser
+            .parse_root_one(Cursor::new(
+                r#"(get-book :title "hello world" :id 1984 :lang '(:lang 'string))"#,
+            ))
+            .unwrap();
+
+        let bytes = expr.to_canonical_bytes();
+        assert_eq!(
+            bytes,
+            br#"(8:get-book:5:title"11:hello world:2:id#4:1984:4:lang'(:4:lang'6:string))"#
+        );
+        assert_eq!(Expr::from_canonical_bytes(&bytes), Ok(expr));
+
+        assert!(Expr::from_canonical_bytes(b"(3:abc").is_err());
+        assert!(Expr::from_canonical_bytes(b"3:ab").is_err());
+        assert!(Expr::from_canonical_bytes(b"03:abc").is_err());
+        assert!(Expr::from_canonical_bytes(b"3:abc3:abc").is_err());
+        assert!(Expr::from_canonical_bytes(b"#3:abc").is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn test_canonical_round_trip(e in crate::testing::arb_expr()) {
+            prop_assert_eq!(Expr::from_canonical_bytes(&e.to_canonical_bytes()), Ok(e));
+        }
+    }
+}
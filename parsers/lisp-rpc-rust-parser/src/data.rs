@@ -2,23 +2,92 @@
 //!
 //! The first symbol is the name of data, and everything else are the "arguments"
 
-use std::{cell::OnceCell, collections::HashMap, env, error::Error, io::Cursor};
-
+use std::{
+    collections::{HashMap, VecDeque},
+    env,
+    error::Error,
+    hash::{Hash, Hasher},
+    io::Cursor,
+    sync::OnceLock,
+};
+
+use indexmap::IndexMap;
 use itertools::Itertools;
 use tracing::{debug, error};
 
-use crate::{Atom, Expr, Parser, TypeValue, impl_into_data_for_numbers};
+use crate::{
+    Atom, Expr, Parser, TypeValue, impl_into_data_for_numbers, impl_into_data_for_tuples,
+    impl_try_from_data,
+};
 
-#[derive(Debug, PartialEq, Eq, Clone)]
-enum DataErrorType {
+mod builder;
+pub use builder::*;
+
+mod binary;
+pub use binary::{FRAME_HEADER_LEN, peek_frame_len};
+
+mod canonical;
+
+mod stream;
+pub use stream::DataStream;
+
+mod entry;
+pub use entry::MapEntry;
+
+mod redact;
+pub use redact::REDACTED;
+
+mod shared;
+pub use shared::SharedData;
+
+mod tagged;
+pub use tagged::{TAG_SYMBOL, TaggedData};
+
+mod bytes;
+pub use bytes::BYTES_SYMBOL;
+
+mod uuid;
+pub use uuid::check_uuid;
+
+mod time;
+pub use time::{TIMESTAMP_NAME, from_rfc3339, to_rfc3339};
+
+mod envelope;
+pub use envelope::{CALL_NAME, ERROR_NAME, REPLY_NAME, REQUEST_NAME, RESPONSE_NAME};
+
+#[cfg(feature = "json")]
+mod json;
+#[cfg(feature = "json")]
+pub use json::{
+    JSON_ARGS_FIELD, JSON_BYTES_FIELD, JSON_DATA_FIELD, JSON_KEYWORD_FIELD, JSON_NAME_FIELD,
+    JSON_TYPE_FIELD,
+};
+
+mod validate;
+pub use validate::*;
+
+#[cfg(feature = "serde")]
+pub mod rpc_serde;
+
+/// the kind of the DataError
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum DataErrorType {
+    /// the input cannot be the data
     InvalidInput,
-    CorruptedData,
+    /// the keyword doesn't exist
+    MissingKey,
+    /// the value isn't the expected type
+    WrongType,
+    /// the data is too deep or too large
+    LimitExceeded,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct DataError {
     msg: String,
     err_type: DataErrorType,
+    /// the missing or mismatched fields of decoding the data
+    violations: Vec<Violation>,
 }
 
 impl std::fmt::Display for DataError {
@@ -29,42 +98,443 @@ impl std::fmt::Display for DataError {
 
 impl Error for DataError {}
 
+impl DataError {
+    /// the error of the keyword doesn't exist
+    pub fn missing_key(k: &str) -> Self {
+        Self {
+            msg: format!("missing key {}", k),
+            err_type: DataErrorType::MissingKey,
+            violations: vec![],
+        }
+    }
+
+    /// the error of the value of keyword isn't the expected type
+    pub fn wrong_type(k: &str, expected: &str) -> Self {
+        Self {
+            msg: format!("the value of key {} is not {}", k, expected),
+            err_type: DataErrorType::WrongType,
+            violations: vec![],
+        }
+    }
+
+    /// the error of the data exceeds the parser limits
+    pub fn limit_exceeded(msg: &str) -> Self {
+        Self {
+            msg: msg.to_string(),
+            err_type: DataErrorType::LimitExceeded,
+            violations: vec![],
+        }
+    }
+
+    pub fn err_type(&self) -> DataErrorType {
+        self.err_type
+    }
+
+    pub fn msg(&self) -> &str {
+        &self.msg
+    }
+
+    pub fn is_missing_key(&self) -> bool {
+        self.err_type == DataErrorType::MissingKey
+    }
+
+    pub fn is_wrong_type(&self) -> bool {
+        self.err_type == DataErrorType::WrongType
+    }
+
+    /// the missing or mismatched fields, the paths of the nested fields are
+    /// joined like "lang.encoding". Empty if the error isn't of the fields
+    pub fn violations(&self) -> &[Violation] {
+        &self.violations
+    }
+
+    /// check the decoded results of the fields together, the error lists
+    /// every missing or mismatched field instead of the first one. The type
+    /// of the error is the one of the first failed field
+    pub fn check_fields(fields: &[(&str, Option<&DataError>)]) -> Result<(), DataError> {
+        let mut err_type = None;
+        let mut violations = vec![];
+        for (k, e) in fields {
+            let Some(e) = e else { continue };
+            err_type.get_or_insert(e.err_type);
+            if e.violations.is_empty() {
+                let msg = match e.err_type {
+                    DataErrorType::MissingKey => "missing",
+                    _ => e.msg(),
+                };
+                violations.push(Violation::new(k, msg));
+            } else {
+                violations.extend(
+                    e.violations
+                        .iter()
+                        .map(|v| Violation::new(&Violation::join_path(k, &v.path), v.msg.clone())),
+                );
+            }
+        }
+
+        match err_type {
+            Some(err_type) => Err(DataError {
+                msg: format!(
+                    "invalid fields: {}",
+                    violations.iter().map(|v| v.to_string()).join(", ")
+                ),
+                err_type,
+                violations,
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
 pub trait FromExpr {
     fn from_expr(expr: &Expr) -> Result<Self, Box<dyn Error>>
     where
         Self: Sized;
 }
 
+/// check the nesting depth on the tokens, so the too deep data is rejected
+/// before the recursive reading
+fn check_depth_limit(p: &Parser, tokens: &VecDeque<String>) -> Result<(), DataError> {
+    let max = match p.max_data_depth {
+        Some(max) => max,
+        None => return Ok(()),
+    };
+
+    let mut depth = 0_usize;
+    let mut in_string = false;
+    let mut escaped = false;
+    for t in tokens {
+        if escaped {
+            escaped = false;
+            continue;
+        }
+
+        match t.as_str() {
+            "\\" if in_string => escaped = true,
+            "\"" => in_string = !in_string,
+            "(" if !in_string => {
+                depth += 1;
+                if depth > max {
+                    return Err(DataError::limit_exceeded(&format!(
+                        "data is deeper than {}",
+                        max
+                    )));
+                }
+            }
+            ")" if !in_string => depth = depth.saturating_sub(1),
+            _ => (),
+        }
+    }
+
+    Ok(())
+}
+
 pub trait FromStr: FromExpr {
     fn from_str(p: &Parser, s: &str) -> Result<Self, Box<dyn Error>>
     where
         Self: Sized,
     {
         let c = Cursor::new(s);
-        let mut tkn = p.tokenize(c);
+        let mut tkn = p.tokenize(c)?;
+        check_depth_limit(p, &tkn)?;
 
         let exp = p.read_router(tkn.get(0).ok_or(DataError {
             msg: "empty str".to_string(),
             err_type: DataErrorType::InvalidInput,
+            violations: vec![],
         })?)?(p, &mut tkn)?;
 
+        check_node_limit(p, &exp)?;
         Self::from_expr(&exp)
     }
 }
 
+fn check_node_limit(p: &Parser, exp: &Expr) -> Result<(), DataError> {
+    match p.max_data_nodes {
+        Some(max) if exp.node_count() > max => Err(DataError::limit_exceeded(&format!(
+            "data has more than {} nodes",
+            max
+        ))),
+        _ => Ok(()),
+    }
+}
+
 pub trait IntoData {
     fn into_rpc_data(&self) -> Data;
 }
 
 // impl the into data for several type
-impl_into_data_for_numbers!(i8, i16, i32, i64);
+// u8 is not here, the [u8] and Vec<u8> are the bytes
+impl_into_data_for_numbers!(i8, i16, i32, i64, u16, u32);
+
+impl IntoData for str {
+    fn into_rpc_data(&self) -> Data {
+        Data::Value(TypeValue::String(self.to_string()))
+    }
+}
+
+impl IntoData for String {
+    fn into_rpc_data(&self) -> Data {
+        Data::Value(TypeValue::String(self.clone()))
+    }
+}
+
+impl<T: IntoData + ?Sized> IntoData for &T {
+    fn into_rpc_data(&self) -> Data {
+        (**self).into_rpc_data()
+    }
+}
+
+/// true is t, false is nil
+impl IntoData for bool {
+    fn into_rpc_data(&self) -> Data {
+        if *self {
+            Data::Value(TypeValue::Symbol("t".to_string()))
+        } else {
+            Data::Nil
+        }
+    }
+}
+
+/// None is nil
+impl<T: IntoData> IntoData for Option<T> {
+    fn into_rpc_data(&self) -> Data {
+        match self {
+            Some(v) => v.into_rpc_data(),
+            None => Data::Nil,
+        }
+    }
+}
+
+impl<T: IntoData> IntoData for [T] {
+    fn into_rpc_data(&self) -> Data {
+        Data::List(ListData {
+            inner_data: self.iter().map(|v| v.into_rpc_data()).collect(),
+        })
+    }
+}
+
+impl<T: IntoData> IntoData for Vec<T> {
+    fn into_rpc_data(&self) -> Data {
+        self.as_slice().into_rpc_data()
+    }
+}
+
+/// the keys are sorted, so the output is stable
+impl<T: IntoData> IntoData for HashMap<String, T> {
+    fn into_rpc_data(&self) -> Data {
+        Data::Map(MapData::from_pairs(
+            self.iter()
+                .sorted_by(|a, b| a.0.cmp(b.0))
+                .map(|(k, v)| (k.clone(), v.into_rpc_data()))
+                .collect(),
+        ))
+    }
+}
+
+impl_into_data_for_tuples!((A 0), (A 0, B 1), (A 0, B 1, C 2), (A 0, B 1, C 2, D 3));
+
+/// The reverse of IntoData, make the rust value from the data
+pub trait FromData: Sized {
+    fn from_rpc_data(d: &Data) -> Result<Self, DataError>;
+}
+
+fn mismatch(expected: &str, d: &Data) -> DataError {
+    DataError {
+        msg: format!("expect {}, got {}", expected, d.to_string()),
+        err_type: DataErrorType::WrongType,
+        violations: vec![],
+    }
+}
+
+impl FromData for Data {
+    fn from_rpc_data(d: &Data) -> Result<Self, DataError> {
+        Ok(d.clone())
+    }
+}
+
+impl FromData for i64 {
+    fn from_rpc_data(d: &Data) -> Result<Self, DataError> {
+        match d {
+            Data::Value(TypeValue::Number(n)) => Ok(*n),
+            _ => Err(mismatch("number", d)),
+        }
+    }
+}
+
+impl FromData for String {
+    fn from_rpc_data(d: &Data) -> Result<Self, DataError> {
+        match d {
+            Data::Value(TypeValue::String(s)) => Ok(s.clone()),
+            _ => Err(mismatch("string", d)),
+        }
+    }
+}
+
+/// t is true, nil is false
+impl FromData for bool {
+    fn from_rpc_data(d: &Data) -> Result<Self, DataError> {
+        match d {
+            Data::Value(TypeValue::Symbol(s)) if s == "t" => Ok(true),
+            Data::Nil => Ok(false),
+            _ => Err(mismatch("t or nil", d)),
+        }
+    }
+}
+
+/// nil is None
+impl<T: FromData> FromData for Option<T> {
+    fn from_rpc_data(d: &Data) -> Result<Self, DataError> {
+        match d {
+            Data::Nil => Ok(None),
+            _ => Ok(Some(T::from_rpc_data(d)?)),
+        }
+    }
+}
+
+impl<T: FromData> FromData for Vec<T> {
+    fn from_rpc_data(d: &Data) -> Result<Self, DataError> {
+        match d {
+            Data::List(l) => l.inner_data.iter().map(T::from_rpc_data).collect(),
+            _ => Err(mismatch("list", d)),
+        }
+    }
+}
+
+impl<T: FromData> FromData for HashMap<String, T> {
+    fn from_rpc_data(d: &Data) -> Result<Self, DataError> {
+        match d {
+            Data::Map(m) => m
+                .iter()
+                .map(|(k, v)| Ok((k.clone(), T::from_rpc_data(v)?)))
+                .collect(),
+            // the empty map '() is read as the empty list
+            Data::List(l) if l.is_empty() => Ok(HashMap::new()),
+            _ => Err(mismatch("map", d)),
+        }
+    }
+}
+
+impl_try_from_data!(i64, String, bool);
+
+impl<T: FromData> TryFrom<&Data> for Option<T> {
+    type Error = DataError;
+
+    fn try_from(d: &Data) -> Result<Self, Self::Error> {
+        Self::from_rpc_data(d)
+    }
+}
+
+impl<T: FromData> TryFrom<&Data> for Vec<T> {
+    type Error = DataError;
+
+    fn try_from(d: &Data) -> Result<Self, Self::Error> {
+        Self::from_rpc_data(d)
+    }
+}
+
+impl<T: FromData> TryFrom<&Data> for HashMap<String, T> {
+    type Error = DataError;
+
+    fn try_from(d: &Data) -> Result<Self, Self::Error> {
+        Self::from_rpc_data(d)
+    }
+}
 
 pub trait GetAbleData {
     fn get<'s>(&'s self, k: &'_ str) -> Option<&'s Data>;
+
+    /// all keywords in order, empty if this data has no keywords
+    fn keys(&self) -> Vec<&str>;
+
+    /// all values in the same order of keys
+    fn values(&self) -> Vec<&Data> {
+        self.keys()
+            .into_iter()
+            .filter_map(|k| self.get(k))
+            .collect()
+    }
+
+    fn contains_key(&self, k: &'_ str) -> bool {
+        self.get(k).is_some()
+    }
+
+    /// the count of keywords
+    fn len(&self) -> usize {
+        self.keys().len()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// get the nested value by the dot separated keys, like "lang.encoding"
+    fn get_path<'s>(&'s self, path: &'_ str) -> Option<&'s Data> {
+        self.get_path_keys(&path.split('.').collect::<Vec<_>>())
+    }
+
+    /// get the nested value by the keys, go through the data and map values
+    fn get_path_keys<'s>(&'s self, keys: &[&str]) -> Option<&'s Data> {
+        let (first, rest) = keys.split_first()?;
+        let mut res = self.get(first)?;
+        for k in rest {
+            res = res.get(k)?;
+        }
+
+        Some(res)
+    }
+
+    /// get the value, return the missing key error if the key doesn't exist
+    fn try_get<'s>(&'s self, k: &'_ str) -> Result<&'s Data, DataError> {
+        self.get(k).ok_or_else(|| DataError::missing_key(k))
+    }
+
+    fn get_str<'s>(&'s self, k: &'_ str) -> Result<&'s str, DataError> {
+        match self.try_get(k)? {
+            Data::Value(TypeValue::String(s)) => Ok(s),
+            _ => Err(DataError::wrong_type(k, "string")),
+        }
+    }
+
+    fn get_i64(&self, k: &'_ str) -> Result<i64, DataError> {
+        match self.try_get(k)? {
+            Data::Value(TypeValue::Number(n)) => Ok(*n),
+            _ => Err(DataError::wrong_type(k, "number")),
+        }
+    }
+
+    fn get_map<'s>(&'s self, k: &'_ str) -> Result<&'s MapData, DataError> {
+        match self.try_get(k)? {
+            Data::Map(m) => Ok(m),
+            _ => Err(DataError::wrong_type(k, "map")),
+        }
+    }
+
+    fn get_list<'s>(&'s self, k: &'_ str) -> Result<&'s ListData, DataError> {
+        match self.try_get(k)? {
+            Data::List(l) => Ok(l),
+            _ => Err(DataError::wrong_type(k, "list")),
+        }
+    }
+
+    fn get_data<'s>(&'s self, k: &'_ str) -> Result<&'s ExprData, DataError> {
+        match self.try_get(k)? {
+            Data::Data(d) => Ok(d),
+            _ => Err(DataError::wrong_type(k, "data")),
+        }
+    }
+
+    /// get the value of keyword and convert it to T
+    fn get_as<T: FromData>(&self, k: &'_ str) -> Result<T, DataError>
+    where
+        Self: Sized,
+    {
+        T::from_rpc_data(self.try_get(k)?)
+    }
 }
 
 /// define all the data, list, and map type that can be treat as Data
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
 pub enum Data {
     /// Data is (data-name keyword-data-pairs...)
     Data(ExprData),
@@ -78,45 +548,62 @@ pub enum Data {
     /// Everything else is value
     Value(TypeValue),
 
-    /// error if something happen
-    Error(DataError),
+    /// Tagged is (the type-name data)
+    Tagged(TaggedData),
+
+    /// Nil is nil, the cleared value. It is different from the missing keyword
+    Nil,
+
+    /// Bytes is (bytes "base64"), the binary attachment
+    Bytes(Vec<u8>),
 }
 
 impl Data {
     fn from_expr(e: &Expr) -> Result<Self, Box<dyn Error>> {
         match e {
-            Expr::List(_) => Ok(Self::Data(ExprData::from_expr(e)?)),
+            Expr::List(exprs) => {
+                if let Some(b) = bytes::bytes_from_exprs(exprs) {
+                    return b;
+                }
+                match TaggedData::from_exprs(exprs) {
+                    Some(t) => Ok(Self::Tagged(t?)),
+                    None => Ok(Self::Data(ExprData::from_expr(e)?)),
+                }
+            }
             Expr::Quote(expr) => {
                 // list or map
                 match expr.as_ref() {
-                    Expr::List(exprs) => match &exprs[0] {
+                    Expr::List(exprs) => match exprs.first() {
+                        // empty list
+                        None => Ok(Self::List(ListData { inner_data: vec![] })),
+
                         // Map data
-                        Expr::Atom(Atom {
+                        Some(Expr::Atom(Atom {
                             value: crate::TypeValue::Keyword(_),
                             ..
-                        }) => Ok(Self::Map(MapData::from_expr(e)?)),
+                        })) => Ok(Self::Map(MapData::from_expr(e)?)),
 
                         // List data
-                        Expr::Atom(Atom { .. }) => Ok(Self::List(ListData::from_expr(e)?)),
-
-                        _ => Err(Box::new(DataError {
-                            msg: format!("cannot generate Data from the expr {:?}", e),
-                            err_type: DataErrorType::InvalidInput,
-                        })),
+                        Some(_) => Ok(Self::List(ListData::from_expr(e)?)),
                     },
                     Expr::Atom(Atom { value }) => Ok(Self::Value(value.clone())),
                     _ => Err(Box::new(DataError {
                         msg: format!("cannot generate Data from the expr {:?}", e),
                         err_type: DataErrorType::InvalidInput,
+                        violations: vec![],
                     })),
                 }
             }
             Expr::Atom(a) => match &a.value {
+                // t and nil are the boolean values
+                TypeValue::Symbol(s) if s == "t" => Ok(Self::Value(a.value.clone())),
+                TypeValue::Symbol(s) if s == "nil" => Ok(Self::Nil),
                 TypeValue::Symbol(_) => {
                     error!("symbol cannot be data");
                     Err(Box::new(DataError {
                         msg: format!("cannot generate Data from the symbol {:?}", a),
                         err_type: DataErrorType::InvalidInput,
+                        violations: vec![],
                     }))
                 }
                 vv @ _ => Ok(Self::Value(vv.clone())),
@@ -130,27 +617,34 @@ impl Data {
             Data::List(list_data) => list_data.to_string(),
             Data::Map(map_data) => map_data.to_string(),
             Data::Value(type_value) => type_value.to_string(),
-            Data::Error(data_error) => format!("{:?}", data_error),
+            Data::Tagged(tagged_data) => tagged_data.to_string(),
+            Data::Nil => "nil".to_string(),
+            Data::Bytes(b) => format!("({} \"{}\")", BYTES_SYMBOL, bytes::encode_bytes(b)),
         }
     }
 
+    /// is this the nil
+    pub fn is_nil(&self) -> bool {
+        matches!(self, Data::Nil)
+    }
+
     /// generate the root data.
     /// root data has to be expr
     pub fn new<'a>(
         name: &str,
         kv_pairs: impl Iterator<Item = (&'a str, &'a dyn IntoData)>,
     ) -> Result<Self, Box<dyn Error>> {
-        Ok(Data::Data(ExprData::new(
-            name,
-            kv_pairs.map(|(s, x)| {
-                (
-                    Expr::Atom(Atom {
-                        value: TypeValue::Keyword(s.to_string()),
-                    }),
-                    x.into_rpc_data(),
-                )
-            }),
-        )?))
+        let mut args = vec![];
+        for (s, x) in kv_pairs {
+            args.push((
+                Expr::Atom(Atom {
+                    value: TypeValue::make_keyword(s)?,
+                }),
+                x.into_rpc_data(),
+            ));
+        }
+
+        Ok(Data::Data(ExprData::new(name, args.into_iter())?))
     }
 
     /// read the root data.
@@ -160,16 +654,40 @@ impl Data {
             None => &Default::default(),
         };
 
-        match Self::from_str(&p, s) {
-            Ok(d) => match d {
-                Data::Data(expr_data) => Ok(Self::Data(expr_data)),
-                Data::Error(data_error) => Err(Box::new(data_error)),
-                _ => Err(Box::new(DataError {
-                    msg: "root data has to be expr data".to_string(),
-                    err_type: DataErrorType::InvalidInput,
-                })),
-            },
-            e @ Err(_) => e,
+        Self::from_str(&p, s).and_then(Self::check_root)
+    }
+
+    /// read all root data of the str, like the file of recorded traffic
+    pub fn from_root_str_many(
+        s: &str,
+        parser: Option<&Parser>,
+    ) -> Result<Vec<Self>, Box<dyn Error>> {
+        let p = match parser {
+            Some(p) => p,
+            None => &Default::default(),
+        };
+
+        let mut tkn = p.tokenize(Cursor::new(s))?;
+        check_depth_limit(p, &tkn)?;
+
+        p.read_roots(&mut tkn)?
+            .iter()
+            .map(|exp| {
+                check_node_limit(p, exp)?;
+                Self::from_expr(exp).and_then(Self::check_root)
+            })
+            .collect()
+    }
+
+    fn check_root(d: Self) -> Result<Self, Box<dyn Error>> {
+        match d {
+            Data::Data(_) => Ok(d),
+            Data::Tagged(ref t) if matches!(t.data(), Data::Data(_)) => Ok(d),
+            _ => Err(Box::new(DataError {
+                msg: "root data has to be expr data".to_string(),
+                err_type: DataErrorType::InvalidInput,
+                violations: vec![],
+            })),
         }
     }
 }
@@ -185,6 +703,57 @@ impl FromExpr for Data {
 
 impl FromStr for Data {}
 
+impl TryFrom<&Expr> for Data {
+    type Error = Box<dyn Error>;
+
+    fn try_from(expr: &Expr) -> Result<Self, Self::Error> {
+        Self::from_expr(expr)
+    }
+}
+
+/// the expr of the data, reading it back gives the same data
+impl From<&Data> for Expr {
+    fn from(d: &Data) -> Self {
+        match d {
+            Data::Data(e) => Expr::List(
+                std::iter::once(Expr::Atom(Atom::read(&e.name)))
+                    .chain(e.positional.iter().map(Expr::from))
+                    .chain(
+                        e.rest_args
+                            .iter()
+                            .flat_map(|(k, v)| [k.clone(), Expr::from(v)]),
+                    )
+                    .collect(),
+            ),
+            Data::List(l) => Expr::Quote(Box::new(Expr::List(
+                l.inner_data.iter().map(Expr::from).collect(),
+            ))),
+            Data::Map(m) => Expr::Quote(Box::new(Expr::List(
+                m.iter()
+                    .flat_map(|(k, v)| [Expr::Atom(Atom::read_keyword(k)), Expr::from(v)])
+                    .collect(),
+            ))),
+            // the symbols other than t are quoted, like 'string
+            Data::Value(v @ TypeValue::Symbol(s)) if s != "t" => {
+                Expr::Quote(Box::new(Expr::Atom(Atom { value: v.clone() })))
+            }
+            Data::Value(v) => Expr::Atom(Atom { value: v.clone() }),
+            Data::Tagged(t) => Expr::List(vec![
+                Expr::Atom(Atom::read(TAG_SYMBOL)),
+                Expr::Atom(Atom::read(t.type_name())),
+                Expr::from(t.data()),
+            ]),
+            Data::Nil => Expr::Atom(Atom::read("nil")),
+            Data::Bytes(b) => Expr::List(vec![
+                Expr::Atom(Atom::read(BYTES_SYMBOL)),
+                Expr::Atom(Atom {
+                    value: TypeValue::String(bytes::encode_bytes(b)),
+                }),
+            ]),
+        }
+    }
+}
+
 impl std::fmt::Display for Data {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", self.to_string())
@@ -202,16 +771,29 @@ impl GetAbleData for Data {
         match self {
             Data::Data(expr_data) => <ExprData as GetAbleData>::get(expr_data, k),
             Data::Map(map_data) => <MapData as GetAbleData>::get(map_data, k),
+            Data::Tagged(t) => t.data().get(k),
             _ => None,
         }
     }
+
+    fn keys(&self) -> Vec<&str> {
+        match self {
+            Data::Data(expr_data) => <ExprData as GetAbleData>::keys(expr_data),
+            Data::Map(map_data) => <MapData as GetAbleData>::keys(map_data),
+            Data::Tagged(t) => t.data().keys(),
+            _ => vec![],
+        }
+    }
 }
 
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, Clone)]
 pub struct ExprData {
     name: String,
+    /// the values before the first keyword, like 1 and 2 in (add 1 2 :mode "fast")
+    positional: Vec<Data>,
     rest_args: Vec<(Expr, Data)>,
-    inner_map: OnceCell<DataMap>,
+    /// made and checked when the data is made, so reading never fails
+    inner_map: DataMap,
 }
 
 impl ExprData {
@@ -222,6 +804,7 @@ impl ExprData {
                 return Err(Box::new(DataError {
                     msg: "cannot generate ExprData from this expr".to_string(),
                     err_type: DataErrorType::InvalidInput,
+                    violations: vec![],
                 }));
             }
         };
@@ -230,13 +813,7 @@ impl ExprData {
             return Err(Box::new(DataError {
                 msg: "empty data".to_string(),
                 err_type: DataErrorType::InvalidInput,
-            }));
-        }
-
-        if exprs.len() % 2 != 1 {
-            return Err(Box::new(DataError {
-                msg: "rest data has to be odd length elements".to_string(),
-                err_type: DataErrorType::InvalidInput,
+                violations: vec![],
             }));
         }
 
@@ -248,12 +825,38 @@ impl ExprData {
                 return Err(Box::new(DataError {
                     msg: "data's first element has to be symbol".to_string(),
                     err_type: DataErrorType::InvalidInput,
+                    violations: vec![],
                 }));
             }
         };
 
+        // the values before the first keyword are positional
+        let first_kw = exprs[1..]
+            .iter()
+            .position(|e| {
+                matches!(
+                    e,
+                    Expr::Atom(Atom {
+                        value: crate::TypeValue::Keyword(_),
+                    })
+                )
+            })
+            .map_or(exprs.len(), |i| i + 1);
+        let positional = exprs[1..first_kw]
+            .iter()
+            .map(Data::from_expr)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if (exprs.len() - first_kw) % 2 != 0 {
+            return Err(Box::new(DataError {
+                msg: "rest data has to be keyword value pairs".to_string(),
+                err_type: DataErrorType::InvalidInput,
+                violations: vec![],
+            }));
+        }
+
         let mut rest_a = vec![];
-        for [k, v] in exprs[1..].into_iter().array_chunks() {
+        for [k, v] in exprs[first_kw..].iter().array_chunks() {
             match (k, v) {
                 (
                     Expr::Atom(Atom {
@@ -265,15 +868,20 @@ impl ExprData {
                     return Err(Box::new(DataError {
                         msg: "has to be keyword value pairs".to_string(),
                         err_type: DataErrorType::InvalidInput,
+                        violations: vec![],
                     }));
                 }
             }
         }
 
+        // check the duplicate keywords
+        let inner_map = DataMap::new(&rest_a)?;
+
         Ok(Self {
             name: name.to_string(),
+            positional,
             rest_args: rest_a,
-            inner_map: OnceCell::new(), // generate when get method called
+            inner_map,
         })
     }
 
@@ -283,10 +891,14 @@ impl ExprData {
         rest_args: impl Iterator<Item = (Expr, Data)>,
     ) -> Result<Self, Box<dyn Error>> {
         let _ = TypeValue::make_symbol(name)?;
+        let rest_args: Vec<_> = rest_args.collect();
+        let inner_map = DataMap::new(&rest_args)?;
+
         Ok(Self {
             name: name.to_string(),
-            rest_args: rest_args.collect(),
-            inner_map: OnceCell::new(),
+            positional: vec![],
+            rest_args,
+            inner_map,
         })
     }
 
@@ -300,18 +912,44 @@ impl ExprData {
         format!(
             "({} {})",
             self.name,
-            self.rest_args
+            self.positional
                 .iter()
-                .map(|(k, v)| format!("{} {}", k.to_string(), v.to_string()))
+                .map(|v| v.to_string())
+                .chain(self.rest_args.iter().map(|(k, v)| format!(
+                    "{} {}",
+                    k.to_string(),
+                    self.arg_to_string(k, v)
+                )))
                 .join(" ")
         )
     }
 
+    /// the positional values before the first keyword
+    pub fn positional(&self) -> &[Data] {
+        &self.positional
+    }
+
     pub fn get(&self, k: &str) -> Option<&Data> {
-        let m = self
-            .inner_map
-            .get_or_init(|| DataMap::new(&self.rest_args).unwrap());
-        m.get(k)
+        self.inner_map.get(k)
+    }
+
+    /// iterate the keyword-value pairs in order
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &Data)> {
+        self.inner_map.iter()
+    }
+
+    /// make the expr data from the pairs whose keywords are valid and unique
+    /// already, like the pairs of the other data
+    fn from_checked_pairs(name: String, positional: Vec<Data>, pairs: Vec<(String, Data)>) -> Self {
+        Self {
+            name,
+            positional,
+            rest_args: pairs
+                .iter()
+                .map(|(k, v)| (Expr::Atom(Atom::read_keyword(k)), v.clone()))
+                .collect(),
+            inner_map: pairs.into_iter().collect(),
+        }
     }
 }
 
@@ -332,13 +970,36 @@ impl IntoData for ExprData {
     }
 }
 
+/// the inner map is built from rest_args, only compare the rest_args
+impl PartialEq for ExprData {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+            && self.positional == other.positional
+            && self.rest_args == other.rest_args
+    }
+}
+
+impl Eq for ExprData {}
+
+impl Hash for ExprData {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.name.hash(state);
+        self.positional.hash(state);
+        self.rest_args.hash(state);
+    }
+}
+
 impl GetAbleData for ExprData {
     fn get<'s>(&'s self, k: &'_ str) -> Option<&'s Data> {
         self.get(k)
     }
+
+    fn keys(&self) -> Vec<&str> {
+        self.iter().map(|(k, _)| k.as_str()).collect()
+    }
 }
 
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
 pub struct ListData {
     inner_data: Vec<Data>,
 }
@@ -376,11 +1037,13 @@ impl ListData {
                     msg: "cannot generate ListData from this expr, not list after quote"
                         .to_string(),
                     err_type: DataErrorType::InvalidInput,
+                    violations: vec![],
                 })),
             },
             _ => Err(Box::new(DataError {
                 msg: "cannot generate ListData from this expr, need quoted".to_string(),
                 err_type: DataErrorType::InvalidInput,
+                violations: vec![],
             })),
         }
     }
@@ -393,41 +1056,71 @@ impl ListData {
     }
 }
 
+impl ListData {
+    pub fn iter(&self) -> impl Iterator<Item = &Data> {
+        self.inner_data.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner_data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inner_data.is_empty()
+    }
+
+    pub fn get(&self, i: usize) -> Option<&Data> {
+        self.inner_data.get(i)
+    }
+
+    pub fn push(&mut self, d: Data) {
+        self.inner_data.push(d)
+    }
+}
+
+impl From<Vec<Data>> for ListData {
+    fn from(inner_data: Vec<Data>) -> Self {
+        Self { inner_data }
+    }
+}
+
+impl<'a> IntoIterator for &'a ListData {
+    type Item = &'a Data;
+    type IntoIter = std::slice::Iter<'a, Data>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.inner_data.iter()
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct MapData {
-    kwrds: Vec<String>,
     map: DataMap,
 }
 
 impl MapData {
+    /// make the map data from the keyword-value pairs
+    fn from_pairs(pairs: Vec<(String, Data)>) -> Self {
+        Self {
+            map: pairs.into_iter().collect(),
+        }
+    }
+
     pub fn from_expr(expr: &Expr) -> Result<Self, Box<dyn Error>> {
-        let mut kwrds = vec![];
         let map = match expr {
             Expr::Quote(e2) => match e2.as_ref() {
-                Expr::List(ee) => {
-                    for [k, _] in ee.iter().array_chunks() {
-                        match k {
-                            Expr::Atom(Atom {
-                                value: crate::TypeValue::Keyword(k),
-                            }) => {
-                                kwrds.push(k.to_string());
-                            }
-                            _ => {
-                                return Err(Box::new(DataError {
-                                    msg: "MapData has to be keyword pairs like '(:a 1 :b 2)"
-                                        .to_string(),
-                                    err_type: DataErrorType::InvalidInput,
-                                }));
-                            }
-                        }
-                    }
-
-                    DataMap::from_exprs(&ee)?
-                }
+                Expr::List(ee) => DataMap::from_exprs(ee).map_err(|_| {
+                    Box::new(DataError {
+                        msg: "MapData has to be unique keyword pairs like '(:a 1 :b 2)".to_string(),
+                        err_type: DataErrorType::InvalidInput,
+                        violations: vec![],
+                    })
+                })?,
                 _ => {
                     return Err(Box::new(DataError {
                         msg: "MapData has to be quoted like '(:a 1 :b 2)".to_string(),
                         err_type: DataErrorType::InvalidInput,
+                        violations: vec![],
                     }));
                 }
             },
@@ -435,37 +1128,23 @@ impl MapData {
                 return Err(Box::new(DataError {
                     msg: "MapData has to be quoted like '(:a 1 :b 2)".to_string(),
                     err_type: DataErrorType::InvalidInput,
+                    violations: vec![],
                 }));
             }
         };
 
-        Ok(Self { kwrds, map })
+        Ok(Self { map })
     }
 
     pub fn to_string(&self) -> String {
-        format!(
-            "'({})",
-            self.kwrds
-                .iter()
-                .map(|k| [
-                    format!(":{}", k.to_string()),
-                    self.map
-                        .get(k)
-                        .unwrap_or(&Data::Error(DataError {
-                            msg: "corrupted data".to_string(),
-                            err_type: DataErrorType::CorruptedData
-                        }))
-                        .to_string()
-                ])
-                .flatten()
-                .join(" ")
-        )
+        format!("'({})", self.map.to_string())
     }
 
     fn get(&self, k: &str) -> Option<&Data> {
         self.map.get(k)
     }
 
+    /// iterate the keyword-value pairs in the insertion order
     pub fn iter(&self) -> impl Iterator<Item = (&String, &Data)> {
         self.map.iter()
     }
@@ -492,20 +1171,59 @@ impl IntoData for MapData {
     }
 }
 
+/// the map equals regardless of the keywords order, so is the hash
+impl Hash for MapData {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.len().hash(state);
+        for (k, v) in self.iter().sorted_by(|(a, _), (b, _)| a.cmp(b)) {
+            k.hash(state);
+            v.hash(state);
+        }
+    }
+}
+
 impl GetAbleData for MapData {
     fn get<'s>(&'s self, k: &'_ str) -> Option<&'s Data> {
         self.get(k)
     }
+
+    fn keys(&self) -> Vec<&str> {
+        self.iter().map(|(k, _)| k.as_str()).collect()
+    }
 }
 
+/// the keyword-value map keeps the insertion order
 #[derive(Debug, PartialEq, Eq, Clone)]
 struct DataMap {
-    hash_map: HashMap<String, Data>,
+    index_map: IndexMap<String, Data>,
 }
 
 impl DataMap {
+    fn insert_unique(&mut self, k: &str, v: Data) -> Result<(), Box<dyn Error>> {
+        if self.index_map.contains_key(k) {
+            return Err(Box::new(DataError {
+                msg: format!("duplicate keyword {}", k),
+                err_type: DataErrorType::InvalidInput,
+                violations: vec![],
+            }));
+        }
+
+        self.index_map.insert(k.to_string(), v);
+        Ok(())
+    }
+
     fn from_exprs(exprs: &[Expr]) -> Result<Self, Box<dyn Error>> {
-        let mut table = HashMap::new();
+        if !exprs.len().is_multiple_of(2) {
+            return Err(Box::new(DataError {
+                msg: "has to be keyword value pairs for making the data map".to_string(),
+                err_type: DataErrorType::InvalidInput,
+                violations: vec![],
+            }));
+        }
+
+        let mut table = Self {
+            index_map: IndexMap::new(),
+        };
         for [k, v] in exprs.iter().array_chunks() {
             match (k, v) {
                 (
@@ -514,22 +1232,25 @@ impl DataMap {
                     }),
                     _,
                 ) => {
-                    table.insert(k.to_string(), Data::from_expr(v)?);
+                    table.insert_unique(k, Data::from_expr(v)?)?;
                 }
                 _ => {
                     return Err(Box::new(DataError {
                         msg: "has to be keyword value pairs for making the data map".to_string(),
                         err_type: DataErrorType::InvalidInput,
+                        violations: vec![],
                     }));
                 }
             }
         }
 
-        Ok(Self { hash_map: table })
+        Ok(table)
     }
 
     fn new(kv: &[(Expr, Data)]) -> Result<Self, Box<dyn Error>> {
-        let mut table = HashMap::new();
+        let mut table = Self {
+            index_map: IndexMap::new(),
+        };
 
         for (e, d) in kv {
             match (e, d) {
@@ -538,53 +1259,51 @@ impl DataMap {
                         value: TypeValue::Keyword(k),
                     }),
                     dd,
-                ) => table.insert(k.to_string(), dd.clone()),
+                ) => table.insert_unique(k, dd.clone())?,
                 _ => {
                     return Err(Box::new(DataError {
                         msg: "has to be keyword value pairs for making the data map".to_string(),
                         err_type: DataErrorType::InvalidInput,
+                        violations: vec![],
                     }));
                 }
             };
         }
 
-        Ok(Self { hash_map: table })
+        Ok(table)
     }
 
     pub fn get(&self, k: &'_ str) -> Option<&Data> {
-        match self.hash_map.get(k) {
-            Some(vv) => Some(vv),
-            None => None,
-        }
+        self.index_map.get(k)
     }
 
     pub fn to_string(&self) -> String {
-        self.hash_map
+        self.index_map
             .iter()
             .map(|(k, v)| format!(":{} {}", k, v.to_string()))
             .join(" ")
     }
 
     pub fn iter(&self) -> impl Iterator<Item = (&String, &Data)> {
-        self.hash_map.iter()
+        self.index_map.iter()
     }
 
     pub fn len(&self) -> usize {
-        self.hash_map.len()
+        self.index_map.len()
     }
 }
 
 impl FromIterator<(String, Data)> for DataMap {
     fn from_iter<T: IntoIterator<Item = (String, Data)>>(iter: T) -> Self {
         Self {
-            hash_map: iter.into_iter().collect(),
+            index_map: iter.into_iter().collect(),
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::assert_matches::assert_matches;
+    use std::assert_matches;
 
     use super::*;
 
@@ -751,6 +1470,142 @@ mod tests {
         assert_eq!(e.unwrap().to_string(), "(a-b )")
     }
 
+    #[test]
+    fn test_new_data() {
+        let d = Data::new("get-book", [("id", &1_i32 as &dyn IntoData)].into_iter()).unwrap();
+        assert_eq!(d.to_string(), "(get-book :id 1)");
+
+        assert!(Data::new("get book", [("id", &1_i32 as &dyn IntoData)].into_iter()).is_err());
+        assert!(Data::new("get-book", [("i:d", &1_i32 as &dyn IntoData)].into_iter()).is_err());
+        assert!(Data::new("get-book", [("", &1_i32 as &dyn IntoData)].into_iter()).is_err());
+    }
+
+    #[test]
+    fn test_positional_data() {
+        let p = Parser::new();
+        let d = Data::from_str(&p, r#"(add 1 2 :mode "fast")"#).unwrap();
+        assert_eq!(d.to_string(), r#"(add 1 2 :mode "fast")"#);
+        let Data::Data(e) = &d else { panic!() };
+        assert_eq!(e.positional(), &[1.into_rpc_data(), 2.into_rpc_data()]);
+        assert_eq!(d.get_str("mode").unwrap(), "fast");
+
+        // only positional values, and the keyword can be the value of keyword
+        let d = Data::from_str(&p, r#"(add 1 '(2 3) nil)"#).unwrap();
+        assert_eq!(d.to_string(), "(add 1 '(2 3) nil)");
+        assert!(d.keys().is_empty());
+        let d = Data::from_str(&p, "(sort '(3 1) :order :desc)").unwrap();
+        assert_eq!(
+            d.get("order"),
+            Some(&Data::Value(TypeValue::Keyword("desc".to_string())))
+        );
+
+        assert!(Data::from_str(&p, "(add 1 :mode)").is_err());
+        assert!(Data::from_str(&p, "(add :mode 1 2)").is_err());
+
+        let b = DataBuilder::new("add")
+            .arg(1)
+            .kw("mode", "fast")
+            .arg(2)
+            .build()
+            .unwrap();
+        assert_eq!(b, Data::from_str(&p, r#"(add 1 2 :mode "fast")"#).unwrap());
+        assert!(
+            DataBuilder::new("add")
+                .arg(Data::Value(TypeValue::Keyword("a".to_string())))
+                .build()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_nil_data() {
+        let p = Parser::new();
+        let d = Data::from_str(&p, "(update-book :isbn nil :title \"1984\")").unwrap();
+
+        // cleared and untouched are different
+        assert_eq!(d.get("isbn"), Some(&Data::Nil));
+        assert!(d.get("isbn").unwrap().is_nil());
+        assert_eq!(d.get("author"), None);
+        assert!(d.contains_key("isbn"));
+        assert_eq!(d.to_string(), "(update-book :isbn nil :title \"1984\")");
+
+        assert_eq!(None::<i64>.into_rpc_data(), Data::Nil);
+        assert_eq!(false.into_rpc_data(), Data::Nil);
+        assert_eq!(d.get_as::<Option<String>>("isbn").unwrap(), None);
+        assert!(!d.get_as::<bool>("isbn").unwrap());
+        assert!(d.get_as::<String>("isbn").unwrap_err().is_wrong_type());
+        assert_eq!(Data::from_bytes(&Data::Nil.to_bytes()).unwrap(), Data::Nil);
+    }
+
+    #[test]
+    fn test_from_data_impls() {
+        let p = Parser::new();
+        let d = Data::from_str(
+            &p,
+            r#"(get-book :title "1984" :year 1949 :hardcover t :isbn nil :tags '("a" "b") :lang '(:lang "en") :extra '())"#,
+        )
+        .unwrap();
+
+        assert_eq!(d.get_as::<String>("title").unwrap(), "1984");
+        assert_eq!(d.get_as::<i64>("year").unwrap(), 1949);
+        assert!(d.get_as::<bool>("hardcover").unwrap());
+        assert_eq!(d.get_as::<Option<i64>>("isbn").unwrap(), None);
+        assert_eq!(d.get_as::<Option<i64>>("year").unwrap(), Some(1949));
+        assert_eq!(d.get_as::<Vec<String>>("tags").unwrap(), vec!["a", "b"]);
+        assert_eq!(
+            d.get_as::<HashMap<String, String>>("lang").unwrap(),
+            HashMap::from([("lang".to_string(), "en".to_string())])
+        );
+        assert!(
+            d.get_as::<HashMap<String, String>>("extra")
+                .unwrap()
+                .is_empty()
+        );
+
+        assert_eq!(i64::try_from(d.get("year").unwrap()).unwrap(), 1949);
+        assert!(
+            String::try_from(d.get("year").unwrap())
+                .unwrap_err()
+                .is_wrong_type()
+        );
+        assert!(d.get_as::<Vec<i64>>("tags").unwrap_err().is_wrong_type());
+        assert!(d.get_as::<i64>("nope").unwrap_err().is_missing_key());
+
+        let e = d.get_as::<i64>("title").unwrap_err();
+        assert_eq!(e.err_type(), DataErrorType::WrongType);
+        assert_eq!(e.msg(), r#"expect number, got "1984""#);
+    }
+
+    #[test]
+    fn test_into_data_impls() {
+        let tags = vec!["a".to_string(), "b".to_string()];
+        let lang = HashMap::from([("lang".to_string(), "en"), ("encoding".to_string(), "utf8")]);
+        let d = Data::new(
+            "get-book",
+            [
+                ("title", &"1984" as &dyn IntoData),
+                ("hardcover", &true),
+                ("ebook", &false),
+                ("isbn", &None::<i64>),
+                ("year", &Some(1949)),
+                ("tags", &tags),
+                ("empty", &Vec::<i32>::new()),
+                ("lang", &lang),
+                ("pair", &("a", 1)),
+            ]
+            .into_iter(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            d.to_string(),
+            r#"(get-book :title "1984" :hardcover t :ebook nil :isbn nil :year 1949 :tags '("a" "b") :empty '() :lang '(:encoding "utf8" :lang "en") :pair '("a" 1))"#
+        );
+
+        // round trip
+        assert_eq!(d, Data::from_str(&Parser::new(), &d.to_string()).unwrap());
+    }
+
     #[test]
     fn test_get_data() {
         let p = Parser::new();
@@ -763,6 +1618,295 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_typed_getters() {
+        let p = Parser::new();
+        let d = Data::from_str(
+            &p,
+            r#"(get-book :title "hello" :id 1984 :lang '(:lang "en") :tags '(1 2) :author (author :name "a"))"#,
+        )
+        .unwrap();
+
+        assert_eq!(d.get_str("title"), Ok("hello"));
+        assert_eq!(d.get_i64("id"), Ok(1984));
+        assert_eq!(d.get_map("lang").unwrap().get_str("lang"), Ok("en"));
+        assert!(d.get_list("tags").is_ok());
+        assert_eq!(d.get_data("author").unwrap().get_name(), "author");
+
+        let Data::Data(ed) = &d else { panic!() };
+        assert_eq!(ed.get_str("title"), Ok("hello"));
+
+        assert!(d.get_str("missing").unwrap_err().is_missing_key());
+        assert!(d.get_i64("title").unwrap_err().is_wrong_type());
+        assert!(d.get_str("id").unwrap_err().is_wrong_type());
+        assert!(d.get_map("tags").unwrap_err().is_wrong_type());
+        assert!(d.get_data("lang").unwrap_err().is_wrong_type());
+    }
+
+    #[test]
+    fn test_check_fields() {
+        let d = Data::from_root_str(r#"(get-book :id "1984" :lang '(:lang 1))"#, None).unwrap();
+        let title = d.get_str("title");
+        let id = d.get_i64("id");
+        let lang = d.get_map("lang").unwrap().get_str("lang");
+        let lang = DataError::check_fields(&[("lang", lang.as_ref().err())]);
+        assert_eq!(lang.as_ref().unwrap_err().violations().len(), 1);
+
+        let e = DataError::check_fields(&[
+            ("title", title.as_ref().err()),
+            ("id", id.as_ref().err()),
+            ("lang", lang.as_ref().err()),
+        ])
+        .unwrap_err();
+        assert!(e.is_missing_key());
+        assert_eq!(
+            e.violations()
+                .iter()
+                .map(|v| v.to_string())
+                .collect::<Vec<_>>(),
+            vec![
+                "title: missing",
+                "id: the value of key id is not number",
+                "lang.lang: the value of key lang is not string",
+            ]
+        );
+        assert_eq!(
+            e.msg(),
+            "invalid fields: title: missing, id: the value of key id is not number, lang.lang: the value of key lang is not string"
+        );
+
+        assert!(DataError::check_fields(&[("id", None)]).is_ok());
+        assert!(d.get_str("title").unwrap_err().violations().is_empty());
+    }
+
+    #[test]
+    fn test_data_macro() {
+        let title = "1984".to_string();
+        let tags = vec!["a", "b"];
+        let d = crate::data!((get-book
+            :title {title}
+            :book-id -12
+            :hardcover t
+            :isbn nil
+            :lang [:lang "en" :encoding {4 * 2}]
+            :tags {tags}
+            :matrix [[1 2] [] [:a-b 1]]
+            :author (author :name "orwell")));
+
+        assert_eq!(
+            d.to_string(),
+            r#"(get-book :title "1984" :book-id -12 :hardcover t :isbn nil :lang '(:lang "en" :encoding 8) :tags '("a" "b") :matrix '('(1 2) '() '(:a-b 1)) :author (author :name "orwell"))"#
+        );
+
+        assert_eq!(crate::data!((ping)).to_string(), "(ping )");
+        assert_eq!(crate::data!([1 -2 "c"]).to_string(), r#"'(1 -2 "c")"#);
+        assert_eq!(crate::data!({ d.clone() }), d);
+    }
+
+    #[test]
+    fn test_expr_data_conversions() {
+        let mut p = Parser::new();
+        let s = r#"(get-book 1 :title "1984" :isbn nil :hardcover t :kind :novel :spec '(:title 'string) :tags '("a" '(1 2)) :author (the author (author :name "orwell")))"#;
+        let expr = p.parse_root_one(Cursor::new(s)).unwrap();
+
+        let d = Data::try_from(&expr).unwrap();
+        assert_eq!(d.get_str("title").unwrap(), "1984");
+
+        let back = Expr::from(&d);
+        assert_eq!(back, expr);
+        assert_eq!(back.into_tokens(), s);
+        assert_eq!(Data::try_from(&back).unwrap(), d);
+
+        assert!(Data::try_from(&Expr::Atom(Atom::read("symbol"))).is_err());
+    }
+
+    #[test]
+    fn test_from_root_str_many() {
+        let all = Data::from_root_str_many(
+            r#"(ping :id 1)
+(get-book :title "a (b)")
+
+(the pong (pong))"#,
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            all.iter().map(|d| d.to_string()).collect::<Vec<_>>(),
+            vec![
+                "(ping :id 1)",
+                r#"(get-book :title "a (b)")"#,
+                "(the pong (pong ))"
+            ]
+        );
+        assert!(Data::from_root_str_many("", None).unwrap().is_empty());
+
+        assert!(Data::from_root_str_many("(ping) 1", None).is_err());
+        assert!(Data::from_root_str_many("(ping) (the pong 1)", None).is_err());
+        assert!(Data::from_root_str_many("(ping :id 1 :id 2)", None).is_err());
+
+        let p = Parser::new().config_max_data_depth(Some(2));
+        assert!(Data::from_root_str_many("(ping :a '(1)) (ping)", Some(&p)).is_ok());
+        assert!(Data::from_root_str_many("(ping) (ping :a '('(1)))", Some(&p)).is_err());
+        let p = Parser::new().config_max_data_nodes(Some(3));
+        assert!(Data::from_root_str_many("(ping :a 1) (ping :a 1 :b 2)", Some(&p)).is_err());
+    }
+
+    #[test]
+    fn test_data_limits() {
+        let s = r#"(get-book :lang '(:lang "en" :tags '("(((" 1)))"#;
+
+        let p = Parser::new()
+            .config_max_data_depth(Some(3))
+            .config_max_data_nodes(Some(12));
+        assert!(Data::from_str(&p, s).is_ok());
+
+        let p = Parser::new().config_max_data_depth(Some(2));
+        let e = Data::from_str(&p, s).unwrap_err();
+        assert_eq!(
+            e.downcast_ref::<DataError>().unwrap().err_type(),
+            DataErrorType::LimitExceeded
+        );
+
+        let p = Parser::new().config_max_data_nodes(Some(11));
+        let e = Data::from_str(&p, s).unwrap_err();
+        assert_eq!(
+            e.downcast_ref::<DataError>().unwrap().err_type(),
+            DataErrorType::LimitExceeded
+        );
+
+        // the adversarial deep data is rejected without reading
+        let deep = "(".repeat(100_000);
+        let p = Parser::new().config_max_data_depth(Some(64));
+        assert!(Data::from_str(&p, &deep).is_err());
+    }
+
+    #[test]
+    fn test_list_data_accessors() {
+        let p = Parser::new();
+        let mut l = ListData::from_str(&p, r#"'(1 "a" '(:b 2))"#).unwrap();
+        assert_eq!(l.len(), 3);
+        assert!(!l.is_empty());
+        assert_eq!(l.get(0), Some(&Data::Value(TypeValue::Number(1))));
+        assert_eq!(l.get(3), None);
+        assert_eq!(
+            l.get(2).unwrap().get_path("b"),
+            Some(&Data::Value(TypeValue::Number(2)))
+        );
+
+        l.push(true.into_rpc_data());
+        assert_eq!(l.to_string(), r#"'(1 "a" '(:b 2) t)"#);
+        assert_eq!(l.iter().count(), 4);
+        assert_eq!((&l).into_iter().count(), 4);
+
+        let l = ListData::from(vec![1.into_rpc_data(), "b".into_rpc_data()]);
+        assert_eq!(l.to_string(), r#"'(1 "b")"#);
+        assert!(ListData::from(vec![]).is_empty());
+    }
+
+    #[test]
+    fn test_map_data_order_and_duplicates() {
+        let p = Parser::new();
+        let s = "'(:z 1 :a 2 :m '(:y 3 :b 4))";
+        let d = Data::from_str(&p, s).unwrap();
+        assert_eq!(d.to_string(), s);
+
+        match &d {
+            Data::Map(m) => assert_eq!(
+                m.iter().map(|(k, _)| k.as_str()).collect::<Vec<_>>(),
+                vec!["z", "a", "m"]
+            ),
+            _ => panic!("should be map"),
+        }
+
+        assert!(Data::from_str(&p, "'(:a 1 :a 2)").is_err());
+        assert!(Data::from_str(&p, "(get-book :id 1 :id 2)").is_err());
+        // the crafted pairs are rejected when reading, not when getting
+        assert!(Data::from_str(&p, "(get-book :id 1 2 3)").is_err());
+        assert!(Data::from_str(&p, "(get-book :id 1 :title)").is_err());
+        assert!(
+            Data::new(
+                "get-book",
+                [("id", &1_i32 as &dyn IntoData), ("id", &2)].into_iter()
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_data_hash() {
+        use std::collections::HashSet;
+
+        let p = Parser::new();
+        let all = [
+            r#"(get-book :id 1 :lang '(:lang "en" :encoding 8))"#,
+            r#"(get-book :id 1 :lang '(:lang "en" :encoding 8))"#,
+            // the map equals in any order
+            r#"(get-book :id 1 :lang '(:encoding 8 :lang "en"))"#,
+            // the data doesn't
+            r#"(get-book :lang '(:lang "en" :encoding 8) :id 1)"#,
+            r#"(get-book :id 2 :lang '(:lang "en" :encoding 8))"#,
+        ]
+        .map(|s| Data::from_str(&p, s).unwrap());
+
+        assert_eq!(all[0], all[2]);
+        assert_ne!(all[0], all[3]);
+        assert!(all[0].eq_semantic(&all[3]));
+        assert!(!all[0].eq_semantic(&all[4]));
+
+        // the inner map isn't the part of hash and eq
+        let set: HashSet<&Data> = all.iter().collect();
+        assert_eq!(set.len(), 3);
+    }
+
+    #[test]
+    fn test_get_path() {
+        let p = Parser::new();
+        let d = Data::from_str(
+            &p,
+            r#"(get-book :title "hello" :lang '(:lang "en" :encoding 8) :author (author :info '(:name "a")))"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            d.get_path("lang.encoding"),
+            Some(&Data::Value(TypeValue::Number(8)))
+        );
+        assert_eq!(
+            d.get_path_keys(&["author", "info", "name"]),
+            Some(&Data::Value(TypeValue::String("a".to_string())))
+        );
+        assert_eq!(d.get_path("title"), d.get("title"));
+        assert_eq!(d.get_path("lang.missing"), None);
+        assert_eq!(d.get_path("title.deeper"), None);
+        assert_eq!(d.get_path_keys(&[]), None);
+    }
+
+    #[test]
+    fn test_get_able_introspect() {
+        let p = Parser::new();
+        let d = Data::from_str(&p, r#"(get-book :title "hello" :lang '(:lang "en"))"#).unwrap();
+
+        assert_eq!(d.keys(), vec!["title", "lang"]);
+        assert_eq!(d.values()[0], &"hello".into_rpc_data());
+        assert!(d.contains_key("lang"));
+        assert!(!d.contains_key("author"));
+        assert_eq!(GetAbleData::len(&d), 2);
+
+        let m = d.get_map("lang").unwrap();
+        assert_eq!(GetAbleData::keys(m), vec!["lang"]);
+
+        let e = Data::from_str(&p, "(ping)").unwrap();
+        assert!(e.is_empty());
+        // list and value have no keywords
+        let l = vec![1, 2].into_rpc_data();
+        assert!(l.keys().is_empty());
+        assert!(!l.contains_key("a"));
+
+        // works through the trait object too
+        let g: &dyn GetAbleData = &d;
+        assert_eq!(g.len(), 2);
+    }
+
     #[test]
     fn test_make_map_data() {
         let p = Parser::new();
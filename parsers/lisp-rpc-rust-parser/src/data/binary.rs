@@ -0,0 +1,305 @@
+//! The compact binary encoding of Data, for the hot paths without text parsing.
+//!
+//! one frame is the u32 big-endian length of the body, then the body is one value.
+//! every value is a type tag then its payload:
+//!
+//! + number: `0x01` i64 big-endian
+//! + string: `0x02` u32 length, utf8 bytes
+//! + keyword: `0x03` u32 length, utf8 bytes
+//! + symbol: `0x04` u32 length, utf8 bytes
+//! + nil: `0x05`
+//! + bytes: `0x06` u32 length, raw bytes
+//! + list: `0x10` u32 count, values...
+//! + map: `0x11` u32 count, (u32 length, keyword bytes, value)...
+//! + tagged: `0x13` u32 length, type name bytes, value
+//! + data: `0x12` u32 length, name bytes, u32 count, positional values...,
+//!   u32 count, (u32 length, keyword bytes, value)...
+
+use super::*;
+
+const TAG_NUMBER: u8 = 0x01;
+const TAG_STRING: u8 = 0x02;
+const TAG_KEYWORD: u8 = 0x03;
+const TAG_SYMBOL: u8 = 0x04;
+const TAG_NIL: u8 = 0x05;
+const TAG_BYTES: u8 = 0x06;
+const TAG_LIST: u8 = 0x10;
+const TAG_MAP: u8 = 0x11;
+const TAG_DATA: u8 = 0x12;
+const TAG_TAGGED: u8 = 0x13;
+
+/// the bytes of the frame length header
+pub const FRAME_HEADER_LEN: usize = 4;
+
+/// the whole frame length (header included) of the bytes, none if the
+/// header isn't complete yet. Transports can wait until this many bytes arrived.
+pub fn peek_frame_len(bytes: &[u8]) -> Option<usize> {
+    let header: [u8; FRAME_HEADER_LEN] = bytes.get(..FRAME_HEADER_LEN)?.try_into().ok()?;
+    Some(FRAME_HEADER_LEN + u32::from_be_bytes(header) as usize)
+}
+
+fn corrupted(msg: &str) -> DataError {
+    DataError {
+        msg: format!("corrupted binary data, {}", msg),
+        err_type: DataErrorType::InvalidInput,
+        violations: vec![],
+    }
+}
+
+impl Data {
+    /// encode this data to one binary frame
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = vec![0; FRAME_HEADER_LEN];
+        write_data(self, &mut buf);
+
+        let body_len = (buf.len() - FRAME_HEADER_LEN) as u32;
+        buf[..FRAME_HEADER_LEN].copy_from_slice(&body_len.to_be_bytes());
+        buf
+    }
+
+    /// decode the data from one binary frame, the bytes have to be exactly one frame
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, DataError> {
+        match peek_frame_len(bytes) {
+            Some(l) if l == bytes.len() => (),
+            Some(_) => return Err(corrupted("frame length mismatch")),
+            None => return Err(corrupted("incomplete frame header")),
+        }
+
+        let mut reader = Reader {
+            bytes: &bytes[FRAME_HEADER_LEN..],
+            ind: 0,
+        };
+        let d = reader.read_data()?;
+        if reader.ind != reader.bytes.len() {
+            return Err(corrupted("trailing bytes after data"));
+        }
+
+        Ok(d)
+    }
+}
+
+fn write_str(s: &str, buf: &mut Vec<u8>) {
+    buf.extend_from_slice(&(s.len() as u32).to_be_bytes());
+    buf.extend_from_slice(s.as_bytes());
+}
+
+fn write_data(d: &Data, buf: &mut Vec<u8>) {
+    match d {
+        Data::Value(TypeValue::Number(n)) => {
+            buf.push(TAG_NUMBER);
+            buf.extend_from_slice(&n.to_be_bytes());
+        }
+        Data::Value(TypeValue::String(s)) => {
+            buf.push(TAG_STRING);
+            write_str(s, buf);
+        }
+        Data::Value(TypeValue::Keyword(s)) => {
+            buf.push(TAG_KEYWORD);
+            write_str(s, buf);
+        }
+        Data::Value(TypeValue::Symbol(s)) => {
+            buf.push(TAG_SYMBOL);
+            write_str(s, buf);
+        }
+        Data::Nil => buf.push(TAG_NIL),
+        Data::Bytes(b) => {
+            buf.push(TAG_BYTES);
+            buf.extend_from_slice(&(b.len() as u32).to_be_bytes());
+            buf.extend_from_slice(b);
+        }
+        Data::List(l) => {
+            buf.push(TAG_LIST);
+            buf.extend_from_slice(&(l.len() as u32).to_be_bytes());
+            l.iter().for_each(|d| write_data(d, buf));
+        }
+        Data::Map(m) => {
+            buf.push(TAG_MAP);
+            buf.extend_from_slice(&(m.len() as u32).to_be_bytes());
+            for (k, v) in m.iter() {
+                write_str(k, buf);
+                write_data(v, buf);
+            }
+        }
+        Data::Tagged(t) => {
+            buf.push(TAG_TAGGED);
+            write_str(t.type_name(), buf);
+            write_data(t.data(), buf);
+        }
+        Data::Data(e) => {
+            buf.push(TAG_DATA);
+            write_str(e.get_name(), buf);
+            buf.extend_from_slice(&(e.positional.len() as u32).to_be_bytes());
+            e.positional.iter().for_each(|d| write_data(d, buf));
+            buf.extend_from_slice(&(e.rest_args.len() as u32).to_be_bytes());
+            for (k, v) in e.iter() {
+                write_str(k, buf);
+                write_data(v, buf);
+            }
+        }
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    ind: usize,
+}
+
+impl Reader<'_> {
+    fn take(&mut self, n: usize) -> Result<&[u8], DataError> {
+        let end = self
+            .ind
+            .checked_add(n)
+            .filter(|end| *end <= self.bytes.len())
+            .ok_or_else(|| corrupted("unexpected end of bytes"))?;
+        let res = &self.bytes[self.ind..end];
+        self.ind = end;
+        Ok(res)
+    }
+
+    fn read_u32(&mut self) -> Result<usize, DataError> {
+        Ok(u32::from_be_bytes(self.take(4)?.try_into().unwrap()) as usize)
+    }
+
+    fn read_str(&mut self) -> Result<String, DataError> {
+        let len = self.read_u32()?;
+        String::from_utf8(self.take(len)?.to_vec()).map_err(|_| corrupted("invalid utf8"))
+    }
+
+    fn read_keyword(&mut self) -> Result<String, DataError> {
+        let k = self.read_str()?;
+        if !TypeValue::is_valid_symbol(&k) {
+            return Err(corrupted(&format!("invalid keyword {:?}", k)));
+        }
+        Ok(k)
+    }
+
+    fn read_pairs(&mut self) -> Result<Vec<(String, Data)>, DataError> {
+        let count = self.read_u32()?;
+        let mut pairs: Vec<(String, Data)> = vec![];
+        for _ in 0..count {
+            let k = self.read_keyword()?;
+            if pairs.iter().any(|(kk, _)| *kk == k) {
+                return Err(corrupted(&format!("duplicate keyword {}", k)));
+            }
+            pairs.push((k, self.read_data()?));
+        }
+        Ok(pairs)
+    }
+
+    fn read_values(&mut self) -> Result<Vec<Data>, DataError> {
+        let count = self.read_u32()?;
+        // every value has one tag byte at least
+        let mut res = Vec::with_capacity(count.min(self.bytes.len() - self.ind));
+        for _ in 0..count {
+            res.push(self.read_data()?);
+        }
+        Ok(res)
+    }
+
+    fn read_data(&mut self) -> Result<Data, DataError> {
+        let tag = self.take(1)?[0];
+        match tag {
+            TAG_NUMBER => Ok(Data::Value(TypeValue::Number(i64::from_be_bytes(
+                self.take(8)?.try_into().unwrap(),
+            )))),
+            TAG_NIL => Ok(Data::Nil),
+            TAG_BYTES => {
+                let len = self.read_u32()?;
+                Ok(Data::Bytes(self.take(len)?.to_vec()))
+            }
+            TAG_STRING => Ok(Data::Value(TypeValue::String(self.read_str()?))),
+            TAG_KEYWORD => Ok(Data::Value(TypeValue::Keyword(self.read_keyword()?))),
+            TAG_SYMBOL => match self.read_str()? {
+                s if TypeValue::is_valid_symbol(&s) => Ok(Data::Value(TypeValue::Symbol(s))),
+                s => Err(corrupted(&format!("invalid symbol {:?}", s))),
+            },
+            TAG_LIST => Ok(Data::List(self.read_values()?.into())),
+            TAG_MAP => Ok(Data::Map(MapData::from_pairs(self.read_pairs()?))),
+            TAG_DATA => {
+                let name = self.read_str()?;
+                if !TypeValue::is_valid_symbol(&name) {
+                    return Err(corrupted(&format!("invalid data name {:?}", name)));
+                }
+
+                let positional = self.read_values()?;
+                if positional
+                    .iter()
+                    .any(|d| matches!(d, Data::Value(TypeValue::Keyword(_))))
+                {
+                    return Err(corrupted("positional value cannot be keyword"));
+                }
+
+                let pairs = self.read_pairs()?;
+                Ok(Data::Data(ExprData::from_checked_pairs(
+                    name, positional, pairs,
+                )))
+            }
+            TAG_TAGGED => {
+                let type_name = self.read_str()?;
+                self.read_data()?
+                    .tagged(&type_name)
+                    .map_err(|_| corrupted(&format!("invalid type name {:?}", type_name)))
+            }
+            t => Err(corrupted(&format!("unknown tag {:#x}", t))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_binary_round_trip() {
+        let p = Parser::new();
+        let d = Data::from_str(
+            &p,
+            r#"(get-book 7 '(1) :title "1984 \"x\"" :id -12 :hardcover t :kw :a :tags '() :langs '('(:lang "en") 2) :author (the author (author :name "orwell")))"#,
+        )
+        .unwrap();
+
+        let bytes = d.to_bytes();
+        assert_eq!(peek_frame_len(&bytes), Some(bytes.len()));
+        assert_eq!(peek_frame_len(&bytes[..3]), None);
+        assert_eq!(Data::from_bytes(&bytes).unwrap(), d);
+
+        // two frames in one buffer
+        let mut stream = bytes.clone();
+        stream.extend_from_slice(&"a".into_rpc_data().to_bytes());
+        let first = peek_frame_len(&stream).unwrap();
+        assert_eq!(Data::from_bytes(&stream[..first]).unwrap(), d);
+        assert_eq!(
+            Data::from_bytes(&stream[first..]).unwrap(),
+            "a".into_rpc_data()
+        );
+    }
+
+    #[test]
+    fn test_binary_symbol_round_trip() {
+        let p = Parser::new();
+        let d = Data::from_str(&p, "(book-info :type 'string)").unwrap();
+        assert_eq!(Data::from_bytes(&d.to_bytes()).unwrap(), d);
+    }
+
+    #[test]
+    fn test_binary_corrupted() {
+        let bytes = 1.into_rpc_data().to_bytes();
+        assert_eq!(bytes, vec![0, 0, 0, 9, TAG_NUMBER, 0, 0, 0, 0, 0, 0, 0, 1]);
+
+        assert!(Data::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(Data::from_bytes(&[]).is_err());
+        assert!(Data::from_bytes(&[0, 0, 0, 1, 0xff]).is_err());
+        // list claims more elements than there are
+        assert!(Data::from_bytes(&[0, 0, 0, 5, TAG_LIST, 0xff, 0xff, 0xff, 0xff]).is_err());
+        // invalid symbol
+        assert!(Data::from_bytes(&[0, 0, 0, 6, TAG_SYMBOL, 0, 0, 0, 1, b' ']).is_err());
+        // duplicate keywords
+        assert!(
+            Data::from_bytes(&[
+                0, 0, 0, 25, TAG_MAP, 0, 0, 0, 2, 0, 0, 0, 1, b'a', TAG_STRING, 0, 0, 0, 0, 0, 0,
+                0, 1, b'a', TAG_STRING, 0, 0, 0, 0,
+            ])
+            .is_err()
+        );
+    }
+}
@@ -0,0 +1,299 @@
+//! The fluent builders for making the nested data, like:
+//!
+//! `DataBuilder::new("get-book").kw("title", "1984").kw_map("lang", |m| m.kw("lang", "en")).build()`
+//!
+//! the invalid names and keywords are reported when build
+
+use super::*;
+
+fn check_keyword(k: &str, pairs: &[(String, Data)], error: &mut Option<DataError>) {
+    if error.is_some() {
+        return;
+    }
+
+    if !TypeValue::is_valid_symbol(k) {
+        *error = Some(DataError {
+            msg: format!("invalid keyword {:?}", k),
+            err_type: DataErrorType::InvalidInput,
+            violations: vec![],
+        })
+    } else if pairs.iter().any(|(kk, _)| kk == k) {
+        *error = Some(DataError {
+            msg: format!("duplicate keyword {:?}", k),
+            err_type: DataErrorType::InvalidInput,
+            violations: vec![],
+        })
+    }
+}
+
+/// the builder of the expr data `(name :keyword value...)`
+#[derive(Debug)]
+pub struct DataBuilder {
+    name: String,
+    positional: Vec<Data>,
+    args: Vec<(String, Data)>,
+    error: Option<DataError>,
+}
+
+impl DataBuilder {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            positional: vec![],
+            args: vec![],
+            error: None,
+        }
+    }
+
+    /// add the positional value, they always go before the keywords
+    pub fn arg(mut self, v: impl IntoData) -> Self {
+        let v = v.into_rpc_data();
+        if let (None, Data::Value(TypeValue::Keyword(k))) = (&self.error, &v) {
+            self.error = Some(DataError {
+                msg: format!("positional value cannot be keyword :{}", k),
+                err_type: DataErrorType::InvalidInput,
+                violations: vec![],
+            });
+        }
+        self.positional.push(v);
+        self
+    }
+
+    /// add the keyword-value pair
+    pub fn kw(mut self, k: &str, v: impl IntoData) -> Self {
+        check_keyword(k, &self.args, &mut self.error);
+        self.args.push((k.to_string(), v.into_rpc_data()));
+        self
+    }
+
+    /// add the keyword with the map value made by f
+    pub fn kw_map(mut self, k: &str, f: impl FnOnce(MapBuilder) -> MapBuilder) -> Self {
+        match f(MapBuilder::new()).build() {
+            Ok(m) => self.kw(k, m),
+            Err(e) => {
+                self.error.get_or_insert(e);
+                self
+            }
+        }
+    }
+
+    /// add the keyword with the list value made by f
+    pub fn kw_list(mut self, k: &str, f: impl FnOnce(ListBuilder) -> ListBuilder) -> Self {
+        match f(ListBuilder::new()).build() {
+            Ok(l) => self.kw(k, l),
+            Err(e) => {
+                self.error.get_or_insert(e);
+                self
+            }
+        }
+    }
+
+    /// add the keyword with the nested expr data made by f
+    pub fn kw_data(
+        mut self,
+        k: &str,
+        name: &str,
+        f: impl FnOnce(DataBuilder) -> DataBuilder,
+    ) -> Self {
+        match f(DataBuilder::new(name)).build_expr_data() {
+            Ok(d) => self.kw(k, d),
+            Err(e) => {
+                self.error.get_or_insert(e);
+                self
+            }
+        }
+    }
+
+    fn build_expr_data(self) -> Result<ExprData, DataError> {
+        if let Some(e) = self.error {
+            return Err(e);
+        }
+
+        if !TypeValue::is_valid_symbol(&self.name) {
+            return Err(DataError {
+                msg: format!("invalid data name {:?}", self.name),
+                err_type: DataErrorType::InvalidInput,
+                violations: vec![],
+            });
+        }
+
+        // the keywords are unique already
+        Ok(ExprData::from_checked_pairs(
+            self.name,
+            self.positional,
+            self.args,
+        ))
+    }
+
+    pub fn build(self) -> Result<Data, DataError> {
+        Ok(Data::Data(self.build_expr_data()?))
+    }
+}
+
+/// the builder of the map data `'(:keyword value...)`
+#[derive(Debug, Default)]
+pub struct MapBuilder {
+    pairs: Vec<(String, Data)>,
+    error: Option<DataError>,
+}
+
+impl MapBuilder {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// add the keyword-value pair
+    pub fn kw(mut self, k: &str, v: impl IntoData) -> Self {
+        check_keyword(k, &self.pairs, &mut self.error);
+        self.pairs.push((k.to_string(), v.into_rpc_data()));
+        self
+    }
+
+    pub fn kw_map(self, k: &str, f: impl FnOnce(MapBuilder) -> MapBuilder) -> Self {
+        match f(MapBuilder::new()).build() {
+            Ok(m) => self.kw(k, m),
+            Err(e) => self.with_error(e),
+        }
+    }
+
+    pub fn kw_list(self, k: &str, f: impl FnOnce(ListBuilder) -> ListBuilder) -> Self {
+        match f(ListBuilder::new()).build() {
+            Ok(l) => self.kw(k, l),
+            Err(e) => self.with_error(e),
+        }
+    }
+
+    pub fn kw_data(self, k: &str, name: &str, f: impl FnOnce(DataBuilder) -> DataBuilder) -> Self {
+        match f(DataBuilder::new(name)).build_expr_data() {
+            Ok(d) => self.kw(k, d),
+            Err(e) => self.with_error(e),
+        }
+    }
+
+    fn with_error(mut self, e: DataError) -> Self {
+        self.error.get_or_insert(e);
+        self
+    }
+
+    pub fn build(self) -> Result<MapData, DataError> {
+        match self.error {
+            Some(e) => Err(e),
+            None => Ok(MapData::from_pairs(self.pairs)),
+        }
+    }
+}
+
+/// the builder of the list data `'(value...)`
+#[derive(Debug, Default)]
+pub struct ListBuilder {
+    items: Vec<Data>,
+    error: Option<DataError>,
+}
+
+impl ListBuilder {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn item(mut self, v: impl IntoData) -> Self {
+        self.items.push(v.into_rpc_data());
+        self
+    }
+
+    pub fn item_map(self, f: impl FnOnce(MapBuilder) -> MapBuilder) -> Self {
+        match f(MapBuilder::new()).build() {
+            Ok(m) => self.item(m),
+            Err(e) => self.with_error(e),
+        }
+    }
+
+    pub fn item_list(self, f: impl FnOnce(ListBuilder) -> ListBuilder) -> Self {
+        match f(ListBuilder::new()).build() {
+            Ok(l) => self.item(l),
+            Err(e) => self.with_error(e),
+        }
+    }
+
+    pub fn item_data(self, name: &str, f: impl FnOnce(DataBuilder) -> DataBuilder) -> Self {
+        match f(DataBuilder::new(name)).build_expr_data() {
+            Ok(d) => self.item(d),
+            Err(e) => self.with_error(e),
+        }
+    }
+
+    fn with_error(mut self, e: DataError) -> Self {
+        self.error.get_or_insert(e);
+        self
+    }
+
+    pub fn build(self) -> Result<ListData, DataError> {
+        match self.error {
+            Some(e) => Err(e),
+            None => Ok(ListData {
+                inner_data: self.items,
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_data_builder() {
+        let d = DataBuilder::new("get-book")
+            .kw("title", "1984")
+            .kw("id", 12)
+            .kw_map("lang", |m| m.kw("lang", "en").kw("encoding", 8))
+            .kw_list("tags", |l| l.item("a").item_map(|m| m.kw("b", 1)))
+            .kw_data("author", "author", |d| d.kw("name", "orwell"))
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            d.to_string(),
+            r#"(get-book :title "1984" :id 12 :lang '(:lang "en" :encoding 8) :tags '("a" '(:b 1)) :author (author :name "orwell"))"#
+        );
+
+        assert_eq!(d, Data::from_str(&Parser::new(), &d.to_string()).unwrap());
+        assert_eq!(
+            d.get_path("lang.encoding"),
+            Some(&Data::Value(TypeValue::Number(8)))
+        );
+
+        // the invalid names
+        assert!(DataBuilder::new("get book").build().is_err());
+        assert!(
+            DataBuilder::new("get-book")
+                .kw("ti tle", 1)
+                .build()
+                .is_err()
+        );
+        assert!(
+            DataBuilder::new("get-book")
+                .kw_map("lang", |m| m.kw(":lang", "en"))
+                .build()
+                .is_err()
+        );
+        assert!(
+            DataBuilder::new("get-book")
+                .kw("id", 1)
+                .kw("id", 2)
+                .build()
+                .is_err()
+        );
+        assert!(
+            DataBuilder::new("get-book")
+                .kw_map("lang", |m| m.kw("lang", "en").kw("lang", "cn"))
+                .build()
+                .is_err()
+        );
+        assert!(
+            DataBuilder::new("get-book")
+                .kw_list("tags", |l| l.item_data("a b", |d| d))
+                .build()
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,130 @@
+//! The binary attachments. The bytes are written as `(bytes "base64")` in
+//! the text, and as the raw bytes in the binary frame.
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+
+use super::*;
+
+/// the symbol of the bytes form
+pub const BYTES_SYMBOL: &str = "bytes";
+
+/// the base64 text of the bytes
+pub(super) fn encode_bytes(b: &[u8]) -> String {
+    STANDARD.encode(b)
+}
+
+/// the bytes data of the base64 text
+pub(super) fn decode_bytes(s: &str) -> Result<Data, DataError> {
+    STANDARD.decode(s).map(Data::Bytes).map_err(|e| DataError {
+        msg: format!("invalid base64 of bytes: {}", e),
+        err_type: DataErrorType::InvalidInput,
+        violations: vec![],
+    })
+}
+
+/// read the (bytes "base64"), none if the exprs isn't the bytes form
+pub(super) fn bytes_from_exprs(exprs: &[Expr]) -> Option<Result<Data, Box<dyn Error>>> {
+    match exprs.first() {
+        Some(Expr::Atom(Atom {
+            value: TypeValue::Symbol(s),
+        })) if s == BYTES_SYMBOL => (),
+        _ => return None,
+    }
+
+    let res = match exprs {
+        [
+            _,
+            Expr::Atom(Atom {
+                value: TypeValue::String(s),
+            }),
+        ] => decode_bytes(s),
+        _ => Err(DataError {
+            msg: format!(
+                "({} \"base64\") has to wrap exactly one string",
+                BYTES_SYMBOL
+            ),
+            err_type: DataErrorType::InvalidInput,
+            violations: vec![],
+        }),
+    };
+    Some(res.map_err(|e| e.into()))
+}
+
+impl Data {
+    /// the bytes if this data is the bytes
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            Data::Bytes(b) => Some(b),
+            _ => None,
+        }
+    }
+}
+
+impl IntoData for [u8] {
+    fn into_rpc_data(&self) -> Data {
+        Data::Bytes(self.to_vec())
+    }
+}
+
+impl IntoData for Vec<u8> {
+    fn into_rpc_data(&self) -> Data {
+        Data::Bytes(self.clone())
+    }
+}
+
+impl FromData for Vec<u8> {
+    fn from_rpc_data(d: &Data) -> Result<Self, DataError> {
+        match d {
+            Data::Bytes(b) => Ok(b.clone()),
+            _ => Err(mismatch("bytes", d)),
+        }
+    }
+}
+
+impl TryFrom<&Data> for Vec<u8> {
+    type Error = DataError;
+
+    fn try_from(d: &Data) -> Result<Self, Self::Error> {
+        Self::from_rpc_data(d)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bytes_data() {
+        let p = Parser::new();
+        let d = Data::from_str(&p, r#"(upload :name "a.bin" :content (bytes "AAH/"))"#).unwrap();
+
+        assert_eq!(d.get("content").unwrap().as_bytes(), Some(&[0, 1, 255][..]));
+        assert_eq!(d.get_as::<Vec<u8>>("content").unwrap(), vec![0, 1, 255]);
+        assert_eq!(
+            DataBuilder::new("upload")
+                .kw("name", "a.bin")
+                .kw("content", &[0u8, 1, 255][..])
+                .build()
+                .unwrap(),
+            d
+        );
+        assert_eq!(
+            d.to_string(),
+            r#"(upload :name "a.bin" :content (bytes "AAH/"))"#
+        );
+        assert_eq!(
+            Vec::<u8>::new().into_rpc_data().to_string(),
+            r#"(bytes "")"#
+        );
+
+        // raw in the binary frame
+        let frame = d.get("content").unwrap().to_bytes();
+        assert!(frame.ends_with(&[0, 1, 255]));
+        assert_eq!(Data::from_bytes(&d.to_bytes()).unwrap(), d);
+
+        assert!(Vec::<u8>::from_rpc_data(&"AAH/".into_rpc_data()).is_err());
+        assert!(Data::from_str(&p, r#"(bytes "not base64!")"#).is_err());
+        assert!(Data::from_str(&p, r#"(bytes "AA==" "AA==")"#).is_err());
+        assert!(Data::from_str(&p, "(bytes :a 1)").is_err());
+    }
+}
@@ -0,0 +1,100 @@
+//! The canonical form of Data. Same data always has the same canonical form
+//! and digest, no matter the keywords order it was made with.
+
+use sha2::{Digest, Sha256};
+
+use super::*;
+
+impl Data {
+    /// the canonical form of this data, all keywords of data and map are
+    /// sorted recursively
+    pub fn canonicalize(&self) -> Data {
+        match self {
+            Data::Data(e) => Data::Data(ExprData::from_checked_pairs(
+                e.name.clone(),
+                e.positional.iter().map(|d| d.canonicalize()).collect(),
+                e.iter()
+                    .sorted_by(|(a, _), (b, _)| a.cmp(b))
+                    .map(|(k, v)| (k.clone(), v.canonicalize()))
+                    .collect(),
+            )),
+            Data::Map(m) => Data::Map(MapData::from_pairs(
+                m.iter()
+                    .sorted_by(|(a, _), (b, _)| a.cmp(b))
+                    .map(|(k, v)| (k.clone(), v.canonicalize()))
+                    .collect(),
+            )),
+            Data::List(l) => Data::List(
+                l.iter()
+                    .map(|d| d.canonicalize())
+                    .collect::<Vec<_>>()
+                    .into(),
+            ),
+            Data::Tagged(t) => t
+                .data()
+                .canonicalize()
+                .tagged(t.type_name())
+                .expect("type name is valid already"),
+            Data::Value(_) | Data::Nil | Data::Bytes(_) => self.clone(),
+        }
+    }
+
+    /// equal regardless of the keywords order of data and map
+    pub fn eq_semantic(&self, other: &Data) -> bool {
+        self.canonicalize() == other.canonicalize()
+    }
+
+    /// the sha256 of the canonical form, for signing, deduplication and
+    /// idempotency keys
+    pub fn digest(&self) -> [u8; 32] {
+        Sha256::digest(self.canonicalize().to_string().as_bytes()).into()
+    }
+
+    /// the digest in lowercase hex
+    pub fn digest_hex(&self) -> String {
+        self.digest().iter().map(|b| format!("{:02x}", b)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_canonicalize() {
+        let p = Parser::new();
+        let a = Data::from_str(
+            &p,
+            r#"(get-book :title "1984" :lang '(:lang "en" :encoding 8) :tags '('(:b 1 :a 2)))"#,
+        )
+        .unwrap();
+        let b = Data::from_str(
+            &p,
+            r#"(get-book :tags '('(:a 2 :b 1)) :lang '(:encoding 8 :lang "en") :title "1984")"#,
+        )
+        .unwrap();
+
+        assert_ne!(a.to_string(), b.to_string());
+        assert_eq!(a.canonicalize(), b.canonicalize());
+        assert_eq!(
+            a.canonicalize().to_string(),
+            r#"(get-book :lang '(:encoding 8 :lang "en") :tags '('(:a 2 :b 1)) :title "1984")"#
+        );
+        assert_eq!(
+            a.canonicalize().get_path("lang.encoding"),
+            a.get_path("lang.encoding")
+        );
+
+        assert_ne!(a, b);
+        assert!(a.eq_semantic(&b));
+        assert_eq!(a.digest(), b.digest());
+        assert_eq!(a.digest_hex().len(), 64);
+
+        let c = Data::from_str(
+            &p,
+            r#"(get-book :title "1985" :lang '(:lang "en" :encoding 8) :tags '('(:b 1 :a 2)))"#,
+        )
+        .unwrap();
+        assert_ne!(a.digest(), c.digest());
+    }
+}
@@ -0,0 +1,124 @@
+//! The in-place editing of the map data, like the entry API of HashMap.
+//! Handlers can accumulate the values into the response map without rebuilding it.
+
+use indexmap::map::Entry;
+
+use super::*;
+
+fn check_keyword(k: &str) -> Result<(), DataError> {
+    if !TypeValue::is_valid_symbol(k) {
+        return Err(DataError {
+            msg: format!("invalid keyword {:?}", k),
+            err_type: DataErrorType::InvalidInput,
+            violations: vec![],
+        });
+    }
+    Ok(())
+}
+
+/// the entry of one keyword in the map data
+pub struct MapEntry<'a> {
+    inner: Entry<'a, String, Data>,
+}
+
+impl<'a> MapEntry<'a> {
+    /// the keyword of this entry
+    pub fn key(&self) -> &str {
+        self.inner.key()
+    }
+
+    /// insert the value if the keyword doesn't exist, return the value
+    pub fn or_insert(self, v: impl IntoData) -> &'a mut Data {
+        self.inner.or_insert_with(|| v.into_rpc_data())
+    }
+
+    /// insert the value made by f if the keyword doesn't exist, return the value
+    pub fn or_insert_with<F: FnOnce() -> Data>(self, f: F) -> &'a mut Data {
+        self.inner.or_insert_with(f)
+    }
+
+    /// modify the value if the keyword exists
+    pub fn and_modify<F: FnOnce(&mut Data)>(self, f: F) -> Self {
+        Self {
+            inner: self.inner.and_modify(f),
+        }
+    }
+}
+
+impl MapData {
+    /// the empty map data '()
+    pub fn new() -> Self {
+        Self {
+            map: DataMap {
+                index_map: IndexMap::new(),
+            },
+        }
+    }
+
+    /// the entry of the keyword, the new keyword is appended in the end
+    pub fn entry(&mut self, k: &str) -> Result<MapEntry<'_>, DataError> {
+        check_keyword(k)?;
+        Ok(MapEntry {
+            inner: self.map.index_map.entry(k.to_string()),
+        })
+    }
+
+    pub fn get_mut(&mut self, k: &str) -> Option<&mut Data> {
+        self.map.index_map.get_mut(k)
+    }
+
+    /// insert the value, return the old value of the keyword
+    pub fn insert(&mut self, k: &str, v: impl IntoData) -> Result<Option<Data>, DataError> {
+        check_keyword(k)?;
+        Ok(self.map.index_map.insert(k.to_string(), v.into_rpc_data()))
+    }
+}
+
+impl Default for MapData {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_map_entry() {
+        let mut m = MapData::new();
+        assert_eq!(m.to_string(), "'()");
+
+        for book in ["1984", "animal farm", "1984"] {
+            match m.entry("books").unwrap().or_insert(Vec::<i64>::new()) {
+                Data::List(l) => l.push(book.into_rpc_data()),
+                _ => unreachable!(),
+            }
+
+            let count = m.entry("count").unwrap().or_insert(0);
+            if let Data::Value(TypeValue::Number(n)) = count {
+                *n += 1;
+            }
+        }
+        assert_eq!(
+            m.to_string(),
+            r#"'(:books '("1984" "animal farm" "1984") :count 3)"#
+        );
+
+        m.entry("count")
+            .unwrap()
+            .and_modify(|d| *d = 0.into_rpc_data())
+            .or_insert(10);
+        assert_eq!(m.get_i64("count").unwrap(), 0);
+        assert_eq!(m.entry("lang").unwrap().key(), "lang");
+
+        *m.get_mut("count").unwrap() = Data::Nil;
+        assert_eq!(m.get("count"), Some(&Data::Nil));
+        assert!(m.get_mut("nope").is_none());
+
+        assert!(m.entry("a b").is_err());
+        assert!(m.insert("", 1).is_err());
+        assert_eq!(m.insert("count", 2).unwrap(), Some(Data::Nil));
+        assert_eq!(m.insert("total", 2).unwrap(), None);
+    }
+}
@@ -0,0 +1,353 @@
+//! The conventional envelopes:
+//!
+//! `(request :version 3 :body (get-book :title "1984"))`
+//!
+//! `(response :status "ok" :result value)`
+//!
+//! `(error :code "not-found" :message "no such book" :details value)`
+//!
+//! `(call :id 42 :method 'get-book :params '(:title "1984"))`
+//!
+//! `(reply :id 42 :body (response :status "ok" :result value))`
+
+use super::*;
+
+pub const REQUEST_NAME: &str = "request";
+pub const RESPONSE_NAME: &str = "response";
+pub const ERROR_NAME: &str = "error";
+pub const CALL_NAME: &str = "call";
+pub const REPLY_NAME: &str = "reply";
+
+impl Data {
+    /// the request wrapping the body with the spec version of the client
+    pub fn request(version: i64, body: impl IntoData) -> Data {
+        DataBuilder::new(REQUEST_NAME)
+            .kw("version", version)
+            .kw("body", body)
+            .build()
+            .expect("request envelope should be valid")
+    }
+
+    /// the ok response wrapping the result
+    pub fn ok(result: impl IntoData) -> Data {
+        DataBuilder::new(RESPONSE_NAME)
+            .kw("status", "ok")
+            .kw("result", result)
+            .build()
+            .expect("response envelope should be valid")
+    }
+
+    /// the error response, the details is optional
+    pub fn error(code: &str, message: &str, details: Option<Data>) -> Data {
+        let b = DataBuilder::new(ERROR_NAME)
+            .kw("code", code)
+            .kw("message", message);
+        match details {
+            Some(d) => b.kw("details", d),
+            None => b,
+        }
+        .build()
+        .expect("error envelope should be valid")
+    }
+
+    /// the call of the request with the id correlating its reply, the method
+    /// is the quoted symbol of the request name and the params are its keyword-value pairs.
+    /// The version of the (request ...) stays in the call
+    pub fn call(id: i64, request: &Data) -> Result<Data, DataError> {
+        let (version, body) = match request.request_body() {
+            Some(body) => (request.request_version(), body),
+            None => (None, request),
+        };
+        let e = match body {
+            Data::Data(e) if e.positional().is_empty() => e,
+            _ => {
+                return Err(DataError {
+                    msg: format!("the call needs the request like (name :k v), not {}", body),
+                    err_type: DataErrorType::InvalidInput,
+                    violations: vec![],
+                });
+            }
+        };
+
+        let b = DataBuilder::new(CALL_NAME).kw("id", id);
+        let b = match version {
+            Some(v) => b.kw("version", v),
+            None => b,
+        }
+        .kw(
+            "method",
+            Data::Value(TypeValue::Symbol(e.get_name().to_string())),
+        );
+        let params: Vec<(String, Data)> = e.iter().map(|(k, v)| (k.clone(), v.clone())).collect();
+        match params.is_empty() {
+            true => b,
+            false => b.kw("params", MapData::from_pairs(params)),
+        }
+        .build()
+    }
+
+    /// the reply of the call with the same id, the body is the response or
+    /// the error
+    pub fn reply(id: i64, body: impl IntoData) -> Data {
+        DataBuilder::new(REPLY_NAME)
+            .kw("id", id)
+            .kw("body", body)
+            .build()
+            .expect("reply envelope should be valid")
+    }
+
+    fn envelope_name(&self) -> Option<&str> {
+        match self {
+            Data::Data(e) => Some(e.get_name()),
+            _ => None,
+        }
+    }
+
+    /// is this the (request ...)
+    pub fn is_request(&self) -> bool {
+        self.envelope_name() == Some(REQUEST_NAME)
+    }
+
+    /// the spec version of the request
+    pub fn request_version(&self) -> Option<i64> {
+        if self.is_request() {
+            self.get_i64("version").ok()
+        } else {
+            None
+        }
+    }
+
+    /// the body of the request
+    pub fn request_body(&self) -> Option<&Data> {
+        if self.is_request() {
+            self.get("body")
+        } else {
+            None
+        }
+    }
+
+    /// is this the (call ...)
+    pub fn is_call(&self) -> bool {
+        self.envelope_name() == Some(CALL_NAME)
+    }
+
+    /// the id of the call
+    pub fn call_id(&self) -> Option<i64> {
+        if self.is_call() {
+            self.get_i64("id").ok()
+        } else {
+            None
+        }
+    }
+
+    /// the method of the call, the quoted symbol or the string
+    pub fn call_method(&self) -> Option<&str> {
+        if !self.is_call() {
+            return None;
+        }
+        match self.get("method") {
+            Some(Data::Value(TypeValue::Symbol(s) | TypeValue::String(s))) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// the request of the call, it is wrapped in the (request ...) again if
+    /// the call has the version
+    pub fn call_request(&self) -> Option<Data> {
+        let method = self.call_method()?;
+        let params = match self.get("params") {
+            Some(Data::Map(m)) => m.iter().map(|(k, v)| (k.clone(), v.clone())).collect(),
+            Some(Data::List(l)) if l.is_empty() => vec![],
+            None => vec![],
+            _ => return None,
+        };
+        let body = Data::Data(ExprData::from_checked_pairs(
+            method.to_string(),
+            vec![],
+            params,
+        ));
+        match self.get_i64("version") {
+            Ok(v) => Some(Data::request(v, body)),
+            Err(_) => Some(body),
+        }
+    }
+
+    /// is this the (reply ...)
+    pub fn is_reply(&self) -> bool {
+        self.envelope_name() == Some(REPLY_NAME)
+    }
+
+    /// the id of the reply
+    pub fn reply_id(&self) -> Option<i64> {
+        if self.is_reply() {
+            self.get_i64("id").ok()
+        } else {
+            None
+        }
+    }
+
+    /// the body of the reply
+    pub fn reply_body(&self) -> Option<&Data> {
+        if self.is_reply() {
+            self.get("body")
+        } else {
+            None
+        }
+    }
+
+    /// is this the (response :status "ok" ...)
+    pub fn is_ok(&self) -> bool {
+        self.envelope_name() == Some(RESPONSE_NAME) && self.get_str("status").ok() == Some("ok")
+    }
+
+    /// is this the (error ...)
+    pub fn is_error(&self) -> bool {
+        self.envelope_name() == Some(ERROR_NAME)
+    }
+
+    /// the result of the ok response
+    pub fn result(&self) -> Option<&Data> {
+        if self.is_ok() {
+            self.get("result")
+        } else {
+            None
+        }
+    }
+
+    /// the code of the error response
+    pub fn error_code(&self) -> Option<&str> {
+        if self.is_error() {
+            self.get_str("code").ok()
+        } else {
+            None
+        }
+    }
+
+    /// the message of the error response
+    pub fn error_message(&self) -> Option<&str> {
+        if self.is_error() {
+            self.get_str("message").ok()
+        } else {
+            None
+        }
+    }
+
+    /// the details of the error response
+    pub fn error_details(&self) -> Option<&Data> {
+        if self.is_error() {
+            self.get("details")
+        } else {
+            None
+        }
+    }
+}
+
+impl ExprData {
+    /// the method of the call is written as the quoted symbol, like
+    /// :method 'get-book. The other values are written as they are
+    pub(super) fn arg_to_string(&self, k: &Expr, v: &Data) -> String {
+        match (k, v) {
+            (
+                Expr::Atom(Atom {
+                    value: TypeValue::Keyword(k),
+                }),
+                Data::Value(TypeValue::Symbol(s)),
+            ) if self.name == CALL_NAME && k == "method" => format!("'{}", s),
+            _ => v.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_envelopes() {
+        let body = Data::from_root_str(r#"(get-book :title "1984")"#, None).unwrap();
+        let req = Data::request(3, body.clone());
+        assert_eq!(
+            req.to_string(),
+            r#"(request :version 3 :body (get-book :title "1984"))"#
+        );
+        assert!(req.is_request());
+        assert_eq!(req.request_version(), Some(3));
+        assert_eq!(req.request_body(), Some(&body));
+        assert_eq!(body.request_body(), None);
+
+        let ok = Data::ok(vec![1, 2]);
+        assert_eq!(ok.to_string(), r#"(response :status "ok" :result '(1 2))"#);
+        assert!(ok.is_ok());
+        assert!(!ok.is_error());
+        assert_eq!(ok.result(), Some(&vec![1, 2].into_rpc_data()));
+        assert_eq!(ok.error_code(), None);
+
+        let err = Data::error("not-found", "no such book", Some("1984".into_rpc_data()));
+        assert_eq!(
+            err.to_string(),
+            r#"(error :code "not-found" :message "no such book" :details "1984")"#
+        );
+        assert!(err.is_error());
+        assert!(!err.is_ok());
+        assert_eq!(err.result(), None);
+        assert_eq!(err.error_code(), Some("not-found"));
+        assert_eq!(err.error_message(), Some("no such book"));
+        assert_eq!(err.error_details(), Some(&"1984".into_rpc_data()));
+
+        let err =
+            Data::from_root_str(r#"(error :code "bad" :message "bad request")"#, None).unwrap();
+        assert_eq!(err.error_code(), Some("bad"));
+        assert_eq!(err.error_details(), None);
+
+        assert!(
+            !Data::from_str(&Parser::new(), "(response :status \"failed\")")
+                .unwrap()
+                .is_ok()
+        );
+
+        let call = Data::call(42, &body).unwrap();
+        assert_eq!(
+            call.to_string(),
+            r#"(call :id 42 :method 'get-book :params '(:title "1984"))"#
+        );
+        assert_eq!(Data::from_root_str(&call.to_string(), None).unwrap(), call);
+        assert!(call.is_call());
+        assert_eq!(call.call_id(), Some(42));
+        assert_eq!(call.call_method(), Some("get-book"));
+        assert_eq!(call.call_request(), Some(body.clone()));
+        assert_eq!(body.call_id(), None);
+
+        let call = Data::call(7, &req).unwrap();
+        assert_eq!(
+            call.to_string(),
+            r#"(call :id 7 :version 3 :method 'get-book :params '(:title "1984"))"#
+        );
+        assert_eq!(call.call_request(), Some(req.clone()));
+
+        let call = Data::from_root_str("(call :id 1 :method 'ping)", None).unwrap();
+        assert_eq!(
+            call.call_request(),
+            Some(Data::from_root_str("(ping)", None).unwrap())
+        );
+        assert_eq!(Data::call(1, &call.call_request().unwrap()).unwrap(), call);
+        assert!(Data::call(1, &ok.result().unwrap().clone()).is_err());
+
+        // the method in string is read too
+        let call = Data::from_root_str("(call :id 1 :method \"ping\")", None).unwrap();
+        assert_eq!(call.call_method(), Some("ping"));
+        assert_eq!(
+            call.call_request(),
+            Some(Data::from_root_str("(ping)", None).unwrap())
+        );
+
+        let reply = Data::reply(42, ok.clone());
+        assert_eq!(
+            reply.to_string(),
+            r#"(reply :id 42 :body (response :status "ok" :result '(1 2)))"#
+        );
+        assert!(reply.is_reply());
+        assert_eq!(reply.reply_id(), Some(42));
+        assert_eq!(reply.reply_body(), Some(&ok));
+        assert_eq!(call.reply_body(), None);
+    }
+}
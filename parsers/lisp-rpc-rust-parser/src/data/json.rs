@@ -0,0 +1,252 @@
+//! The JSON bridge of Data, for the HTTP/JSON gateways.
+//!
+//! + expr data => object with the `"__name"` field, and the `"__args"` array
+//!   if it has positional values
+//! + map => object
+//! + list => array
+//! + number and string => number and string
+//! + keyword => `{"__keyword": "kw"}`
+//! + t / nil => true / null (false is nil too when reading)
+//! + tagged => `{"__the": "type-name", "__data": value}`
+//! + bytes => `{"__bytes": "base64"}`
+
+use serde_json::{Map, Number, Value};
+
+use super::*;
+
+/// the field name of the expr data name
+pub const JSON_NAME_FIELD: &str = "__name";
+
+/// the field name of the positional values of the expr data
+pub const JSON_ARGS_FIELD: &str = "__args";
+
+/// the field name of the declared type of the tagged data
+pub const JSON_TYPE_FIELD: &str = "__the";
+
+/// the field name of the data inside the tagged data
+pub const JSON_DATA_FIELD: &str = "__data";
+
+/// the field name of the keyword value
+pub const JSON_KEYWORD_FIELD: &str = "__keyword";
+
+/// the field name of the base64 of the bytes
+pub const JSON_BYTES_FIELD: &str = "__bytes";
+
+fn invalid_json(msg: String) -> DataError {
+    DataError {
+        msg,
+        err_type: DataErrorType::InvalidInput,
+        violations: vec![],
+    }
+}
+
+fn pairs_to_json<'a>(
+    pairs: impl Iterator<Item = (&'a String, &'a Data)>,
+    obj: &mut Map<String, Value>,
+) {
+    for (k, v) in pairs {
+        obj.insert(k.clone(), v.to_json());
+    }
+}
+
+impl Data {
+    pub fn to_json(&self) -> Value {
+        match self {
+            Data::Data(e) => {
+                let mut obj = Map::new();
+                obj.insert(JSON_NAME_FIELD.to_string(), Value::from(e.get_name()));
+                if !e.positional().is_empty() {
+                    obj.insert(
+                        JSON_ARGS_FIELD.to_string(),
+                        Value::Array(e.positional().iter().map(|d| d.to_json()).collect()),
+                    );
+                }
+                pairs_to_json(e.iter(), &mut obj);
+                Value::Object(obj)
+            }
+            Data::Map(m) => {
+                let mut obj = Map::new();
+                pairs_to_json(m.iter(), &mut obj);
+                Value::Object(obj)
+            }
+            Data::List(l) => Value::Array(l.iter().map(|d| d.to_json()).collect()),
+            Data::Value(TypeValue::Number(n)) => Value::Number(Number::from(*n)),
+            Data::Value(TypeValue::String(s)) => Value::String(s.clone()),
+            Data::Value(TypeValue::Keyword(k)) => {
+                let mut obj = Map::new();
+                obj.insert(JSON_KEYWORD_FIELD.to_string(), Value::String(k.clone()));
+                Value::Object(obj)
+            }
+            Data::Value(TypeValue::Symbol(s)) if s == "t" => Value::Bool(true),
+            Data::Value(TypeValue::Symbol(_)) | Data::Nil => Value::Null,
+            Data::Bytes(b) => {
+                let mut obj = Map::new();
+                obj.insert(
+                    JSON_BYTES_FIELD.to_string(),
+                    Value::String(bytes::encode_bytes(b)),
+                );
+                Value::Object(obj)
+            }
+            Data::Tagged(t) => {
+                let mut obj = Map::new();
+                obj.insert(JSON_TYPE_FIELD.to_string(), Value::from(t.type_name()));
+                obj.insert(JSON_DATA_FIELD.to_string(), t.data().to_json());
+                Value::Object(obj)
+            }
+        }
+    }
+
+    pub fn from_json(v: &Value) -> Result<Self, DataError> {
+        match v {
+            Value::Null | Value::Bool(false) => Ok(Data::Nil),
+            Value::Bool(true) => Ok(true.into_rpc_data()),
+            Value::Number(n) => n
+                .as_i64()
+                .map(|n| n.into_rpc_data())
+                .ok_or_else(|| invalid_json(format!("number {} has to be i64", n))),
+            Value::String(s) => Ok(s.into_rpc_data()),
+            Value::Array(vs) => Ok(Data::List(
+                vs.iter()
+                    .map(Data::from_json)
+                    .collect::<Result<Vec<_>, _>>()?
+                    .into(),
+            )),
+            Value::Object(obj) => {
+                if let (1, Some(k)) = (obj.len(), obj.get(JSON_KEYWORD_FIELD)) {
+                    return match k {
+                        Value::String(k) if TypeValue::is_valid_symbol(k) => {
+                            Ok(Data::Value(TypeValue::Keyword(k.clone())))
+                        }
+                        _ => Err(invalid_json(format!("invalid keyword {}", k))),
+                    };
+                }
+
+                if let (1, Some(b)) = (obj.len(), obj.get(JSON_BYTES_FIELD)) {
+                    return match b {
+                        Value::String(b) => bytes::decode_bytes(b),
+                        _ => Err(invalid_json(format!("invalid bytes {}", b))),
+                    };
+                }
+
+                if let (2, Some(ty), Some(d)) = (
+                    obj.len(),
+                    obj.get(JSON_TYPE_FIELD),
+                    obj.get(JSON_DATA_FIELD),
+                ) {
+                    return match ty {
+                        Value::String(ty) => Data::from_json(d)?.tagged(ty),
+                        _ => Err(invalid_json(format!("invalid type name {}", ty))),
+                    };
+                }
+
+                let name = match obj.get(JSON_NAME_FIELD) {
+                    Some(Value::String(name)) => Some(name.as_str()),
+                    Some(name) => return Err(invalid_json(format!("invalid data name {}", name))),
+                    None => None,
+                };
+
+                let positional = match (name, obj.get(JSON_ARGS_FIELD)) {
+                    (Some(_), Some(Value::Array(vs))) => vs
+                        .iter()
+                        .map(Data::from_json)
+                        .collect::<Result<Vec<_>, _>>()?,
+                    (Some(_), Some(args)) => {
+                        return Err(invalid_json(format!("invalid positional values {}", args)));
+                    }
+                    _ => vec![],
+                };
+
+                let mut pairs = vec![];
+                for (k, v) in obj
+                    .iter()
+                    .filter(|(k, _)| *k != JSON_NAME_FIELD)
+                    .filter(|(k, _)| name.is_none() || *k != JSON_ARGS_FIELD)
+                {
+                    pairs.push((k.as_str(), Data::from_json(v)?));
+                }
+
+                match name {
+                    Some(name) => pairs
+                        .into_iter()
+                        .fold(
+                            positional
+                                .into_iter()
+                                .fold(DataBuilder::new(name), |b, v| b.arg(v)),
+                            |b, (k, v)| b.kw(k, v),
+                        )
+                        .build(),
+                    None => Ok(Data::Map(
+                        pairs
+                            .into_iter()
+                            .fold(MapBuilder::new(), |b, (k, v)| b.kw(k, v))
+                            .build()?,
+                    )),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_json_bridge() {
+        let d = Data::from_str(
+            &Parser::new(),
+            r#"(get-book :title "1984" :id 1 :hardcover t :isbn nil :kind :novel :tags '("a") :lang '(:lang "en"))"#,
+        )
+        .unwrap();
+
+        let j = d.to_json();
+        assert_eq!(
+            j,
+            json!({
+                "__name": "get-book",
+                "title": "1984",
+                "id": 1,
+                "hardcover": true,
+                "isbn": null,
+                "kind": {"__keyword": "novel"},
+                "tags": ["a"],
+                "lang": {"lang": "en"},
+            })
+        );
+        assert_eq!(Data::from_json(&j).unwrap(), d);
+
+        assert_eq!(
+            Data::from_json(&json!({"a": false})).unwrap().to_string(),
+            "'(:a nil)"
+        );
+        let d = Data::from_str(&Parser::new(), r#"(add 1 "a" :mode :fast)"#).unwrap();
+        let j = d.to_json();
+        assert_eq!(
+            j,
+            json!({"__name": "add", "__args": [1, "a"], "mode": {"__keyword": "fast"}})
+        );
+        assert_eq!(Data::from_json(&j).unwrap(), d);
+        assert!(Data::from_json(&json!({"__name": "add", "__args": 1})).is_err());
+
+        let d = Data::from_str(&Parser::new(), "(the point (point :x 1))").unwrap();
+        let j = d.to_json();
+        assert_eq!(
+            j,
+            json!({"__the": "point", "__data": {"__name": "point", "x": 1}})
+        );
+        assert_eq!(Data::from_json(&j).unwrap(), d);
+        assert!(Data::from_json(&json!({"__the": 1, "__data": 1})).is_err());
+
+        let d = vec![0u8, 1, 255].into_rpc_data();
+        assert_eq!(d.to_json(), json!({"__bytes": "AAH/"}));
+        assert_eq!(Data::from_json(&d.to_json()).unwrap(), d);
+        assert!(Data::from_json(&json!({"__bytes": "!"})).is_err());
+
+        assert!(Data::from_json(&json!(1.5)).is_err());
+        assert!(Data::from_json(&json!({"a b": 1})).is_err());
+        assert!(Data::from_json(&json!({"__name": 1})).is_err());
+        assert!(Data::from_json(&json!({"__name": "get book"})).is_err());
+    }
+}
@@ -0,0 +1,98 @@
+//! Redact the sensitive values before logging the data.
+
+use super::*;
+
+/// the value replaces the redacted values
+pub const REDACTED: &str = "<redacted>";
+
+impl Data {
+    /// the copy of this data with the values of the keyword paths replaced
+    /// by `"<redacted>"`. The paths are dot separated like `get_path`,
+    /// and go through every element of the lists, so `"users.token"` redacts
+    /// the token of all users.
+    pub fn redacted(&self, paths: &[&str]) -> Data {
+        let paths: Vec<Vec<&str>> = paths.iter().map(|p| p.split('.').collect()).collect();
+        let paths: Vec<&[&str]> = paths.iter().map(|p| p.as_slice()).collect();
+        self.redact_keys(&paths)
+    }
+
+    fn redact_keys(&self, paths: &[&[&str]]) -> Data {
+        if paths.is_empty() {
+            return self.clone();
+        }
+
+        // the value of k, redacted or with the rest paths of k redacted
+        let redact_value = |k: &str, v: &Data| -> Data {
+            let mut rest = vec![];
+            for p in paths {
+                match p.split_first() {
+                    Some((first, [])) if *first == k => return REDACTED.into_rpc_data(),
+                    Some((first, r)) if *first == k => rest.push(r),
+                    _ => (),
+                }
+            }
+            v.redact_keys(&rest)
+        };
+
+        match self {
+            Data::Data(e) => Data::Data(ExprData::from_checked_pairs(
+                e.name.clone(),
+                e.positional.clone(),
+                e.iter()
+                    .map(|(k, v)| (k.clone(), redact_value(k, v)))
+                    .collect(),
+            )),
+            Data::Map(m) => Data::Map(MapData::from_pairs(
+                m.iter()
+                    .map(|(k, v)| (k.clone(), redact_value(k, v)))
+                    .collect(),
+            )),
+            Data::List(l) => Data::List(
+                l.iter()
+                    .map(|d| d.redact_keys(paths))
+                    .collect::<Vec<_>>()
+                    .into(),
+            ),
+            Data::Tagged(t) => t
+                .data()
+                .redact_keys(paths)
+                .tagged(t.type_name())
+                .expect("type name is valid already"),
+            Data::Value(_) | Data::Nil | Data::Bytes(_) => self.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redacted() {
+        let p = Parser::new();
+        let d = Data::from_str(
+            &p,
+            r#"(login :user "a" :password "123" :session '(:token "t1" :ttl 10) :devices '('(:token "t2") '(:id 1)))"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            d.redacted(&["password", "session.token", "devices.token", "missing.path"])
+                .to_string(),
+            r#"(login :user "a" :password "<redacted>" :session '(:token "<redacted>" :ttl 10) :devices '('(:token "<redacted>") '(:id 1)))"#
+        );
+
+        // the whole sub-tree
+        assert_eq!(
+            d.redacted(&["session"]).get_str("session").unwrap(),
+            REDACTED
+        );
+        assert_eq!(d.redacted(&[]), d);
+
+        let t = Data::from_str(&p, r#"(the login (login :password "123"))"#).unwrap();
+        assert_eq!(
+            t.redacted(&["password"]).to_string(),
+            r#"(the login (login :password "<redacted>"))"#
+        );
+    }
+}
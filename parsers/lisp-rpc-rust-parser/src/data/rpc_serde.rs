@@ -0,0 +1,691 @@
+//! The serde data format of lisp-rpc, like the serde_json. The rust value
+//! is serialized to Data first, then to the lisp-rpc text.
+//!
+//! struct => (Name :field value...)
+//! map => '(:key value...)
+//! seq and tuple => '(value...)
+//! unit variant => "Variant"
+//! other variants => '(:Variant value)
+//! bool => t / nil, None and unit => nil
+
+use ::serde::{
+    Deserialize, Serialize,
+    de::{
+        self, DeserializeOwned, IntoDeserializer, Visitor,
+        value::{BorrowedStrDeserializer, StrDeserializer},
+    },
+    forward_to_deserialize_any, ser,
+};
+
+use super::*;
+
+impl ser::Error for DataError {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        DataError {
+            msg: msg.to_string(),
+            err_type: DataErrorType::InvalidInput,
+            violations: vec![],
+        }
+    }
+}
+
+impl de::Error for DataError {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        DataError {
+            msg: msg.to_string(),
+            err_type: DataErrorType::WrongType,
+            violations: vec![],
+        }
+    }
+}
+
+/// serialize the value to Data
+pub fn to_data<T: Serialize + ?Sized>(v: &T) -> Result<Data, DataError> {
+    v.serialize(DataSerializer)
+}
+
+/// serialize the value to lisp-rpc text
+pub fn to_string<T: Serialize + ?Sized>(v: &T) -> Result<String, DataError> {
+    Ok(to_data(v)?.to_string())
+}
+
+/// deserialize the value from Data
+pub fn from_data<'de, T: Deserialize<'de>>(d: &'de Data) -> Result<T, DataError> {
+    T::deserialize(d)
+}
+
+/// deserialize the value from lisp-rpc text
+pub fn from_str<T: DeserializeOwned>(s: &str) -> Result<T, DataError> {
+    let d = Data::from_str(&Parser::new(), s).map_err(|e| DataError {
+        msg: e.to_string(),
+        err_type: DataErrorType::InvalidInput,
+        violations: vec![],
+    })?;
+    from_data(&d)
+}
+
+fn nil() -> Data {
+    Data::Nil
+}
+
+fn key_of(k: Data) -> Result<String, DataError> {
+    match k {
+        Data::Value(TypeValue::String(s)) | Data::Value(TypeValue::Keyword(s)) => Ok(s),
+        _ => Err(ser::Error::custom(format!(
+            "map key has to be string, got {}",
+            k
+        ))),
+    }
+}
+
+/// the serializer makes the Data
+pub struct DataSerializer;
+
+impl ser::Serializer for DataSerializer {
+    type Ok = Data;
+    type Error = DataError;
+
+    type SerializeSeq = SerializeList;
+    type SerializeTuple = SerializeList;
+    type SerializeTupleStruct = SerializeList;
+    type SerializeTupleVariant = SerializeTupleVariant;
+    type SerializeMap = SerializeMap;
+    type SerializeStruct = SerializeStruct;
+    type SerializeStructVariant = SerializeStructVariant;
+
+    fn serialize_bool(self, v: bool) -> Result<Data, DataError> {
+        Ok(v.into_rpc_data())
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Data, DataError> {
+        Ok(v.into_rpc_data())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Data, DataError> {
+        Ok(v.into_rpc_data())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Data, DataError> {
+        Ok(v.into_rpc_data())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Data, DataError> {
+        Ok(v.into_rpc_data())
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Data, DataError> {
+        Ok((v as i64).into_rpc_data())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Data, DataError> {
+        Ok(v.into_rpc_data())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Data, DataError> {
+        Ok(v.into_rpc_data())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Data, DataError> {
+        i64::try_from(v)
+            .map(|n| n.into_rpc_data())
+            .map_err(|_| ser::Error::custom(format!("{} overflows the number", v)))
+    }
+
+    fn serialize_f32(self, _: f32) -> Result<Data, DataError> {
+        Err(ser::Error::custom("float is not supported"))
+    }
+
+    fn serialize_f64(self, _: f64) -> Result<Data, DataError> {
+        Err(ser::Error::custom("float is not supported"))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Data, DataError> {
+        Ok(v.to_string().into_rpc_data())
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Data, DataError> {
+        Ok(v.into_rpc_data())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Data, DataError> {
+        Ok(v.into_rpc_data())
+    }
+
+    fn serialize_none(self) -> Result<Data, DataError> {
+        Ok(nil())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Data, DataError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Data, DataError> {
+        Ok(nil())
+    }
+
+    fn serialize_unit_struct(self, _: &'static str) -> Result<Data, DataError> {
+        Ok(nil())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+    ) -> Result<Data, DataError> {
+        Ok(variant.into_rpc_data())
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        value: &T,
+    ) -> Result<Data, DataError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Data, DataError> {
+        Ok(Data::Map(
+            MapBuilder::new().kw(variant, to_data(value)?).build()?,
+        ))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SerializeList, DataError> {
+        Ok(SerializeList {
+            items: Vec::with_capacity(len.unwrap_or(0)),
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SerializeList, DataError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _: &'static str,
+        len: usize,
+    ) -> Result<SerializeList, DataError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+        _: usize,
+    ) -> Result<SerializeTupleVariant, DataError> {
+        Ok(SerializeTupleVariant {
+            variant,
+            list: ListBuilder::new(),
+        })
+    }
+
+    fn serialize_map(self, _: Option<usize>) -> Result<SerializeMap, DataError> {
+        Ok(SerializeMap {
+            map: MapBuilder::new(),
+            next_key: None,
+        })
+    }
+
+    fn serialize_struct(self, name: &'static str, _: usize) -> Result<SerializeStruct, DataError> {
+        Ok(SerializeStruct {
+            data: DataBuilder::new(name),
+        })
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+        _: usize,
+    ) -> Result<SerializeStructVariant, DataError> {
+        Ok(SerializeStructVariant {
+            variant,
+            map: MapBuilder::new(),
+        })
+    }
+}
+
+pub struct SerializeList {
+    items: Vec<Data>,
+}
+
+impl SerializeList {
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), DataError> {
+        self.items.push(to_data(value)?);
+        Ok(())
+    }
+
+    fn finish(self) -> Data {
+        Data::List(ListData {
+            inner_data: self.items,
+        })
+    }
+}
+
+impl ser::SerializeSeq for SerializeList {
+    type Ok = Data;
+    type Error = DataError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), DataError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Data, DataError> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeTuple for SerializeList {
+    type Ok = Data;
+    type Error = DataError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), DataError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Data, DataError> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeTupleStruct for SerializeList {
+    type Ok = Data;
+    type Error = DataError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), DataError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Data, DataError> {
+        Ok(self.finish())
+    }
+}
+
+pub struct SerializeTupleVariant {
+    variant: &'static str,
+    list: ListBuilder,
+}
+
+impl ser::SerializeTupleVariant for SerializeTupleVariant {
+    type Ok = Data;
+    type Error = DataError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), DataError> {
+        self.list = std::mem::take(&mut self.list).item(to_data(value)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Data, DataError> {
+        Ok(Data::Map(
+            MapBuilder::new()
+                .kw(self.variant, self.list.build()?)
+                .build()?,
+        ))
+    }
+}
+
+pub struct SerializeMap {
+    map: MapBuilder,
+    next_key: Option<String>,
+}
+
+impl ser::SerializeMap for SerializeMap {
+    type Ok = Data;
+    type Error = DataError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), DataError> {
+        self.next_key = Some(key_of(to_data(key)?)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), DataError> {
+        let k = self
+            .next_key
+            .take()
+            .ok_or_else(|| ser::Error::custom("serialize value before key"))?;
+        self.map = std::mem::take(&mut self.map).kw(&k, to_data(value)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Data, DataError> {
+        Ok(Data::Map(self.map.build()?))
+    }
+}
+
+pub struct SerializeStruct {
+    data: DataBuilder,
+}
+
+impl ser::SerializeStruct for SerializeStruct {
+    type Ok = Data;
+    type Error = DataError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), DataError> {
+        let data = std::mem::replace(&mut self.data, DataBuilder::new(""));
+        self.data = data.kw(key, to_data(value)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Data, DataError> {
+        self.data.build()
+    }
+}
+
+pub struct SerializeStructVariant {
+    variant: &'static str,
+    map: MapBuilder,
+}
+
+impl ser::SerializeStructVariant for SerializeStructVariant {
+    type Ok = Data;
+    type Error = DataError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), DataError> {
+        self.map = std::mem::take(&mut self.map).kw(key, to_data(value)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Data, DataError> {
+        Ok(Data::Map(
+            MapBuilder::new()
+                .kw(self.variant, self.map.build()?)
+                .build()?,
+        ))
+    }
+}
+
+/// the keyword-value pairs of the map data or the expr data
+fn pairs_of(d: &Data) -> Option<Vec<(&str, &Data)>> {
+    match d {
+        Data::Map(m) => Some(m.iter().map(|(k, v)| (k.as_str(), v)).collect()),
+        Data::Data(e) => Some(
+            e.rest_args
+                .iter()
+                .filter_map(|(k, v)| match k {
+                    Expr::Atom(Atom {
+                        value: TypeValue::Keyword(k),
+                    }) => Some((k.as_str(), v)),
+                    _ => None,
+                })
+                .collect(),
+        ),
+        Data::List(l) if l.inner_data.is_empty() => Some(vec![]),
+        Data::Tagged(t) => pairs_of(t.data()),
+        _ => None,
+    }
+}
+
+impl<'de> de::Deserializer<'de> for &'de Data {
+    type Error = DataError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DataError> {
+        match self {
+            Data::Value(TypeValue::Number(n)) => visitor.visit_i64(*n),
+            Data::Value(TypeValue::String(s)) | Data::Value(TypeValue::Keyword(s)) => {
+                visitor.visit_borrowed_str(s)
+            }
+            Data::Value(TypeValue::Symbol(s)) if s == "t" => visitor.visit_bool(true),
+            Data::Nil => visitor.visit_unit(),
+            Data::Bytes(b) => visitor.visit_borrowed_bytes(b),
+            Data::List(l) => visitor.visit_seq(ListAccess(l.inner_data.iter())),
+            Data::Map(_) | Data::Data(_) => self.deserialize_map(visitor),
+            Data::Tagged(t) => t.data().deserialize_any(visitor),
+            _ => Err(de::Error::custom(format!("cannot deserialize {}", self))),
+        }
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DataError> {
+        match self {
+            Data::Nil => visitor.visit_bool(false),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DataError> {
+        match self {
+            Data::Nil => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, DataError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DataError> {
+        match pairs_of(self) {
+            Some(pairs) => visitor.visit_map(PairsAccess {
+                pairs: pairs.into_iter(),
+                value: None,
+            }),
+            None => Err(de::Error::custom(format!("expect map, got {}", self))),
+        }
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        _: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, DataError> {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        _: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, DataError> {
+        match self {
+            Data::Value(TypeValue::String(s)) => {
+                let d: StrDeserializer<DataError> = s.as_str().into_deserializer();
+                visitor.visit_enum(d)
+            }
+            _ => match pairs_of(self).as_deref() {
+                Some([(name, value)]) => visitor.visit_enum(VariantAccess { name, value }),
+                _ => Err(de::Error::custom(format!("expect enum, got {}", self))),
+            },
+        }
+    }
+
+    forward_to_deserialize_any! {
+        i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct identifier ignored_any
+    }
+}
+
+struct ListAccess<'de>(std::slice::Iter<'de, Data>);
+
+impl<'de> de::SeqAccess<'de> for ListAccess<'de> {
+    type Error = DataError;
+
+    fn next_element_seed<T: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, DataError> {
+        self.0.next().map(|d| seed.deserialize(d)).transpose()
+    }
+}
+
+struct PairsAccess<'de> {
+    pairs: std::vec::IntoIter<(&'de str, &'de Data)>,
+    value: Option<&'de Data>,
+}
+
+impl<'de> de::MapAccess<'de> for PairsAccess<'de> {
+    type Error = DataError;
+
+    fn next_key_seed<K: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, DataError> {
+        match self.pairs.next() {
+            Some((k, v)) => {
+                self.value = Some(v);
+                seed.deserialize(BorrowedStrDeserializer::new(k)).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, DataError> {
+        match self.value.take() {
+            Some(v) => seed.deserialize(v),
+            None => Err(de::Error::custom("deserialize value before key")),
+        }
+    }
+}
+
+struct VariantAccess<'de> {
+    name: &'de str,
+    value: &'de Data,
+}
+
+impl<'de> de::EnumAccess<'de> for VariantAccess<'de> {
+    type Error = DataError;
+    type Variant = Self;
+
+    fn variant_seed<V: de::DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, Self), DataError> {
+        Ok((
+            seed.deserialize(BorrowedStrDeserializer::new(self.name))?,
+            self,
+        ))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for VariantAccess<'de> {
+    type Error = DataError;
+
+    fn unit_variant(self) -> Result<(), DataError> {
+        Deserialize::deserialize(self.value)
+    }
+
+    fn newtype_variant_seed<T: de::DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> Result<T::Value, DataError> {
+        seed.deserialize(self.value)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _: usize, visitor: V) -> Result<V::Value, DataError> {
+        de::Deserializer::deserialize_seq(self.value, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, DataError> {
+        de::Deserializer::deserialize_map(self.value, visitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use ::serde::{Deserialize, Serialize};
+
+    use super::*;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum Cover {
+        Hard,
+        Soft(i64),
+        Sized(i64, i64),
+        Custom { color: String },
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Author {
+        name: String,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    #[serde(rename = "book-info", rename_all = "kebab-case")]
+    struct BookInfo {
+        title: String,
+        book_id: u32,
+        hardcover: bool,
+        isbn: Option<String>,
+        tags: Vec<String>,
+        authors: Vec<Author>,
+        lang: BTreeMap<String, String>,
+        covers: Vec<Cover>,
+        pair: (i64, String),
+    }
+
+    #[test]
+    fn test_serde_round_trip() {
+        let b = BookInfo {
+            title: "1984".to_string(),
+            book_id: 1,
+            hardcover: false,
+            isbn: None,
+            tags: vec![],
+            authors: vec![Author {
+                name: "orwell".to_string(),
+            }],
+            lang: BTreeMap::from([("lang".to_string(), "en".to_string())]),
+            covers: vec![
+                Cover::Hard,
+                Cover::Soft(1),
+                Cover::Sized(2, 3),
+                Cover::Custom {
+                    color: "red".to_string(),
+                },
+            ],
+            pair: (1, "a".to_string()),
+        };
+
+        let s = to_string(&b).unwrap();
+        assert_eq!(
+            s,
+            r#"(book-info :title "1984" :book-id 1 :hardcover nil :isbn nil :tags '() :authors '((Author :name "orwell")) :lang '(:lang "en") :covers '("Hard" '(:Soft 1) '(:Sized '(2 3)) '(:Custom '(:color "red"))) :pair '(1 "a"))"#
+        );
+        assert_eq!(from_str::<BookInfo>(&s).unwrap(), b);
+
+        assert_eq!(from_str::<Vec<i64>>("'(1 2)").unwrap(), vec![1, 2]);
+        assert_eq!(
+            from_str::<BTreeMap<String, i64>>("'()").unwrap(),
+            BTreeMap::new()
+        );
+    }
+
+    #[test]
+    fn test_serde_errors() {
+        assert!(to_string(&1.5_f64).is_err());
+        assert!(to_string(&u64::MAX).is_err());
+        assert!(to_string(&BTreeMap::from([(1, 1)])).is_err());
+        assert!(from_str::<Author>(r#"(Author :name 1)"#).is_err());
+        assert!(from_str::<Author>(r#"(Author :title "a")"#).is_err());
+    }
+}
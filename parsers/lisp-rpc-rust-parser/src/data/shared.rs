@@ -0,0 +1,144 @@
+//! The cheaply clonable data for the fan-out. The server broadcasting one
+//! payload to many subscribers clones the SharedData instead of the whole
+//! tree, and the text is generated once for all of them.
+
+use std::{ops::Deref, sync::Arc};
+
+use super::*;
+
+#[derive(Debug)]
+struct SharedInner {
+    data: Data,
+    text: OnceLock<String>,
+}
+
+/// the Arc-backed read-only data, clone is only one reference count
+#[derive(Debug, Clone)]
+pub struct SharedData {
+    inner: Arc<SharedInner>,
+}
+
+impl SharedData {
+    pub fn new(data: Data) -> Self {
+        Self {
+            inner: Arc::new(SharedInner {
+                data,
+                text: OnceLock::new(),
+            }),
+        }
+    }
+
+    /// the text of the data, generated at the first call and shared by all clones
+    pub fn as_text(&self) -> &str {
+        self.inner.text.get_or_init(|| self.inner.data.to_string())
+    }
+
+    /// the data can be edited, it is copied only if there are other clones (copy-on-write)
+    pub fn make_mut(&mut self) -> &mut Data {
+        let inner = Arc::make_mut(&mut self.inner);
+        // the cached text is stale after editing
+        inner.text = OnceLock::new();
+        &mut inner.data
+    }
+
+    /// take the data back, it is copied only if there are other clones
+    pub fn into_data(self) -> Data {
+        match Arc::try_unwrap(self.inner) {
+            Ok(inner) => inner.data,
+            Err(inner) => inner.data.clone(),
+        }
+    }
+
+    /// are the two shared data the same allocation
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.inner, &other.inner)
+    }
+}
+
+impl Clone for SharedInner {
+    fn clone(&self) -> Self {
+        Self {
+            data: self.data.clone(),
+            text: self.text.clone(),
+        }
+    }
+}
+
+impl Deref for SharedData {
+    type Target = Data;
+
+    fn deref(&self) -> &Data {
+        &self.inner.data
+    }
+}
+
+impl PartialEq for SharedData {
+    fn eq(&self, other: &Self) -> bool {
+        self.ptr_eq(other) || self.inner.data == other.inner.data
+    }
+}
+
+impl Eq for SharedData {}
+
+impl std::hash::Hash for SharedData {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.inner.data.hash(state)
+    }
+}
+
+impl From<Data> for SharedData {
+    fn from(data: Data) -> Self {
+        Self::new(data)
+    }
+}
+
+impl std::fmt::Display for SharedData {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_text())
+    }
+}
+
+impl Data {
+    /// move this data into the SharedData
+    pub fn into_shared(self) -> SharedData {
+        SharedData::new(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shared_data() {
+        let d = Data::from_str(&Parser::new(), r#"(news :title "hello" :tags '("a"))"#).unwrap();
+        let shared = d.clone().into_shared();
+
+        let subscribers: Vec<SharedData> = (0..100).map(|_| shared.clone()).collect();
+        assert!(subscribers.iter().all(|s| s.ptr_eq(&shared)));
+        assert_eq!(
+            subscribers[0].as_text(),
+            r#"(news :title "hello" :tags '("a"))"#
+        );
+        assert_eq!(subscribers[1].to_string(), subscribers[0].as_text());
+        // deref to the data
+        assert_eq!(subscribers[2].get_str("title").unwrap(), "hello");
+
+        // send to the other threads
+        let s = shared.clone();
+        let text = std::thread::spawn(move || s.as_text().to_string())
+            .join()
+            .unwrap();
+        assert_eq!(text, shared.as_text());
+
+        // copy on write, the others are untouched
+        let mut mine = shared.clone();
+        *mine.make_mut() = Data::from_str(&Parser::new(), "(news :title \"bye\")").unwrap();
+        assert!(!mine.ptr_eq(&shared));
+        assert_eq!(mine.as_text(), "(news :title \"bye\")");
+        assert_eq!(shared.get_str("title").unwrap(), "hello");
+
+        drop(subscribers);
+        assert_eq!(shared.into_data(), d);
+    }
+}
@@ -0,0 +1,203 @@
+//! Read the root data from the stream (like the socket) one by one, every
+//! root data is yielded as soon as its last `)` arrived.
+
+use std::io::{BufRead, BufReader, Read};
+
+use super::*;
+
+/// the iterator of the root data of the reader
+pub struct DataStream<'p, R: Read> {
+    parser: &'p Parser,
+    reader: BufReader<R>,
+    done: bool,
+}
+
+impl Data {
+    /// read the first root data from the reader
+    pub fn from_reader(p: &Parser, r: impl Read) -> Result<Self, Box<dyn Error>> {
+        match Self::stream(p, r).next() {
+            Some(d) => d,
+            None => Err(Box::new(DataError {
+                msg: "no data in reader".to_string(),
+                err_type: DataErrorType::InvalidInput,
+                violations: vec![],
+            })),
+        }
+    }
+
+    /// read all root data from the reader lazily
+    pub fn stream<R: Read>(p: &Parser, r: R) -> DataStream<'_, R> {
+        DataStream {
+            parser: p,
+            reader: BufReader::new(r),
+            done: false,
+        }
+    }
+}
+
+impl<R: Read> DataStream<'_, R> {
+    /// read the bytes of the next root data, none if the reader ends
+    fn next_message(&mut self) -> Result<Option<Vec<u8>>, Box<dyn Error>> {
+        let mut msg = vec![];
+        let mut depth = 0_usize;
+        let mut in_string = false;
+        let mut escaped = false;
+
+        loop {
+            let buf = self.reader.fill_buf()?;
+            if buf.is_empty() {
+                if msg.is_empty() {
+                    return Ok(None);
+                }
+                return Err(Box::new(DataError {
+                    msg: "reader ends inside the data".to_string(),
+                    err_type: DataErrorType::InvalidInput,
+                    violations: vec![],
+                }));
+            }
+
+            let mut used = 0;
+            let mut complete = false;
+            for &b in buf {
+                used += 1;
+
+                if msg.is_empty() {
+                    match b {
+                        b' ' | b'\n' | b'\r' | b'\t' => continue,
+                        b'(' => (),
+                        _ => {
+                            return Err(Box::new(DataError {
+                                msg: format!("root data has to start with (, got {:?}", b as char),
+                                err_type: DataErrorType::InvalidInput,
+                                violations: vec![],
+                            }));
+                        }
+                    }
+                }
+
+                msg.push(b);
+                if escaped {
+                    escaped = false;
+                } else if in_string {
+                    match b {
+                        b'\\' => escaped = true,
+                        b'"' => in_string = false,
+                        _ => (),
+                    }
+                } else {
+                    match b {
+                        b'"' => in_string = true,
+                        b'(' => depth += 1,
+                        b')' => depth -= 1,
+                        _ => (),
+                    }
+                }
+
+                if depth == 0 {
+                    complete = true;
+                    break;
+                }
+            }
+            self.reader.consume(used);
+
+            if complete {
+                return Ok(Some(msg));
+            }
+
+            if self
+                .parser
+                .max_message_bytes
+                .is_some_and(|max| msg.len() > max)
+            {
+                return Err(Box::new(DataError::limit_exceeded("message is too large")));
+            }
+        }
+    }
+}
+
+impl<R: Read> Iterator for DataStream<'_, R> {
+    type Item = Result<Data, Box<dyn Error>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let res = match self.next_message() {
+            Ok(Some(msg)) => match String::from_utf8(msg) {
+                Ok(s) => Data::from_root_str(&s, Some(self.parser)),
+                Err(e) => Err(e.into()),
+            },
+            Ok(None) => {
+                self.done = true;
+                return None;
+            }
+            Err(e) => Err(e),
+        };
+
+        // stop at the first error, the rest of the stream cannot be trusted
+        if res.is_err() {
+            self.done = true;
+        }
+        Some(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    /// the reader returns one byte every read, like the slow socket
+    struct SlowReader(Cursor<Vec<u8>>);
+
+    impl Read for SlowReader {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let len = buf.len().min(1);
+            self.0.read(&mut buf[..len])
+        }
+    }
+
+    #[test]
+    fn test_data_stream() {
+        let p = Parser::new();
+        let source = r#"
+(ping :id 1)
+(get-book :title "a \")(\" b" :lang '(:lang "en"))(pong)
+"#;
+
+        let all: Vec<Data> = Data::stream(&p, SlowReader(Cursor::new(source.as_bytes().to_vec())))
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(
+            all.iter().map(|d| d.to_string()).collect::<Vec<_>>(),
+            vec![
+                "(ping :id 1)",
+                r#"(get-book :title "a \")(\" b" :lang '(:lang "en"))"#,
+                "(pong )",
+            ]
+        );
+
+        assert_eq!(
+            Data::from_reader(&p, source.as_bytes())
+                .unwrap()
+                .to_string(),
+            "(ping :id 1)"
+        );
+        assert!(Data::from_reader(&p, "  ".as_bytes()).is_err());
+
+        // the error stops the stream
+        let mut s = Data::stream(&p, "(ping) x (pong)".as_bytes());
+        assert!(s.next().unwrap().is_ok());
+        assert!(s.next().unwrap().is_err());
+        assert!(s.next().is_none());
+
+        let mut s = Data::stream(&p, "(ping :id".as_bytes());
+        assert!(s.next().unwrap().is_err());
+
+        let p = Parser::new().config_max_message_bytes(Some(8));
+        let mut s = Data::stream(&p, SlowReader(Cursor::new(b"(ping :id 12345)".to_vec())));
+        assert!(s.next().unwrap().is_err());
+    }
+}
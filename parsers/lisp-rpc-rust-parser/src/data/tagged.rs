@@ -0,0 +1,149 @@
+//! The typed tagging form `(the type-name data)`, the data carries its
+//! declared type across the channels, and the receiver checks it.
+
+use super::*;
+
+/// the symbol of the tagging form
+pub const TAG_SYMBOL: &str = "the";
+
+/// the data wrapped by `(the type-name data)`
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub struct TaggedData {
+    type_name: String,
+    data: Box<Data>,
+}
+
+impl TaggedData {
+    /// read the (the type-name data), none if the exprs isn't the tagging form
+    pub(super) fn from_exprs(exprs: &[Expr]) -> Option<Result<Self, Box<dyn Error>>> {
+        let type_name = match exprs {
+            [
+                Expr::Atom(Atom {
+                    value: TypeValue::Symbol(the),
+                }),
+                Expr::Atom(Atom {
+                    value: TypeValue::Symbol(ty),
+                }),
+                ..,
+            ] if the == TAG_SYMBOL => ty,
+            _ => return None,
+        };
+
+        if exprs.len() != 3 {
+            return Some(Err(Box::new(DataError {
+                msg: format!(
+                    "({} {} data) has to wrap exactly one data",
+                    TAG_SYMBOL, type_name
+                ),
+                err_type: DataErrorType::InvalidInput,
+                violations: vec![],
+            })));
+        }
+
+        Some(Data::from_expr(&exprs[2]).map(|d| Self {
+            type_name: type_name.to_string(),
+            data: Box::new(d),
+        }))
+    }
+
+    /// the declared type name
+    pub fn type_name(&self) -> &str {
+        &self.type_name
+    }
+
+    /// the data inside
+    pub fn data(&self) -> &Data {
+        &self.data
+    }
+}
+
+impl std::fmt::Display for TaggedData {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "({} {} {})", TAG_SYMBOL, self.type_name, self.data)
+    }
+}
+
+impl Data {
+    /// wrap this data with the declared type name
+    pub fn tagged(self, type_name: &str) -> Result<Data, DataError> {
+        if !TypeValue::is_valid_symbol(type_name) {
+            return Err(DataError {
+                msg: format!("invalid type name {:?}", type_name),
+                err_type: DataErrorType::InvalidInput,
+                violations: vec![],
+            });
+        }
+
+        Ok(Data::Tagged(TaggedData {
+            type_name: type_name.to_string(),
+            data: Box::new(self),
+        }))
+    }
+
+    /// the declared type name if this data is tagged
+    pub fn type_tag(&self) -> Option<&str> {
+        match self {
+            Data::Tagged(t) => Some(t.type_name()),
+            _ => None,
+        }
+    }
+
+    /// the data without the tag
+    pub fn untagged(&self) -> &Data {
+        match self {
+            Data::Tagged(t) => t.data(),
+            _ => self,
+        }
+    }
+
+    /// check the declared type if this data is tagged, and return the data
+    /// without the tag. The untagged data always passes.
+    pub fn check_tag(&self, type_name: &str) -> Result<&Data, DataError> {
+        match self.type_tag() {
+            Some(ty) if ty != type_name => Err(DataError {
+                msg: format!("expect type {}, got type {}", type_name, ty),
+                err_type: DataErrorType::WrongType,
+                violations: vec![],
+            }),
+            _ => Ok(self.untagged()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tagged_data() {
+        let p = Parser::new();
+        let d = Data::from_str(
+            &p,
+            r#"(the book-info (book-info :title "1984" :lang (the language '(:lang "en"))))"#,
+        )
+        .unwrap();
+
+        assert_eq!(d.type_tag(), Some("book-info"));
+        assert_eq!(
+            d.to_string(),
+            r#"(the book-info (book-info :title "1984" :lang (the language '(:lang "en"))))"#
+        );
+        // get goes through the tag
+        assert_eq!(d.get_str("title").unwrap(), "1984");
+        assert_eq!(d.get_path("lang.lang"), Some(&"en".into_rpc_data()));
+        assert_eq!(d.get("lang").unwrap().type_tag(), Some("language"));
+
+        assert!(d.check_tag("book-info").is_ok());
+        assert!(d.check_tag("author").unwrap_err().is_wrong_type());
+        let inner = d.untagged();
+        assert_eq!(inner.type_tag(), None);
+        assert_eq!(inner.check_tag("author").unwrap(), inner);
+
+        assert_eq!(inner.clone().tagged("book-info").unwrap(), d);
+        assert!(inner.clone().tagged("book info").is_err());
+
+        assert!(Data::from_str(&p, "(the book-info)").is_err());
+        assert!(Data::from_str(&p, "(the book-info (a) (b))").is_err());
+        assert!(Data::from_str(&p, "(the book-info bad)").is_err());
+    }
+}
@@ -0,0 +1,293 @@
+//! The timestamp convention. The timestamp is written as
+//! `(timestamp seconds)` or `(timestamp seconds nanoseconds)` since the unix
+//! epoch, and the RFC3339 string like `"2024-03-09T16:00:00Z"` is accepted too.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use super::*;
+
+/// the name of the timestamp data
+pub const TIMESTAMP_NAME: &str = "timestamp";
+
+fn invalid_time(msg: String) -> DataError {
+    DataError {
+        msg,
+        err_type: DataErrorType::InvalidInput,
+        violations: vec![],
+    }
+}
+
+/// the seconds and the nanoseconds since the unix epoch, the nanoseconds are
+/// always positive, so the time before the epoch has the negative seconds
+fn epoch_parts(t: &SystemTime) -> (i64, u32) {
+    match t.duration_since(UNIX_EPOCH) {
+        Ok(d) => (d.as_secs() as i64, d.subsec_nanos()),
+        Err(e) => {
+            let d = e.duration();
+            match d.subsec_nanos() {
+                0 => (-(d.as_secs() as i64), 0),
+                n => (-(d.as_secs() as i64) - 1, 1_000_000_000 - n),
+            }
+        }
+    }
+}
+
+fn from_epoch_parts(secs: i64, nanos: u32) -> Result<SystemTime, DataError> {
+    if nanos >= 1_000_000_000 {
+        return Err(invalid_time(format!("nanoseconds {} out of range", nanos)));
+    }
+
+    let t = if secs >= 0 {
+        UNIX_EPOCH.checked_add(Duration::new(secs as u64, nanos))
+    } else {
+        UNIX_EPOCH
+            .checked_sub(Duration::from_secs(secs.unsigned_abs()))
+            .and_then(|t| t.checked_add(Duration::from_nanos(nanos as u64)))
+    };
+    t.ok_or_else(|| invalid_time(format!("timestamp {} out of range", secs)))
+}
+
+/// days since 1970-01-01 of the civil date
+fn days_from_civil(y: i64, m: i64, d: i64) -> i64 {
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let doy = (153 * (if m > 2 { m - 3 } else { m + 9 }) + 2) / 5 + d - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+/// the civil date (year, month, day) of the days since 1970-01-01
+fn civil_from_days(z: i64) -> (i64, i64, i64) {
+    let z = z + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    let y = yoe + era * 400;
+    (if m <= 2 { y + 1 } else { y }, m, d)
+}
+
+fn days_in_month(y: i64, m: i64) -> i64 {
+    match m {
+        2 if y % 4 == 0 && (y % 100 != 0 || y % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// the RFC3339 string of the time in UTC, like `2024-03-09T16:00:00.5Z`
+pub fn to_rfc3339(t: &SystemTime) -> String {
+    let (secs, nanos) = epoch_parts(t);
+    let (y, mo, d) = civil_from_days(secs.div_euclid(86400));
+    let sod = secs.rem_euclid(86400);
+
+    let mut s = format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+        y,
+        mo,
+        d,
+        sod / 3600,
+        sod % 3600 / 60,
+        sod % 60
+    );
+    if nanos != 0 {
+        s.push_str(format!(".{:09}", nanos).trim_end_matches('0'));
+    }
+    s.push('Z');
+    s
+}
+
+/// parse the RFC3339 string, like `2024-03-09T16:00:00Z` or `2024-03-10T01:00:00.25+09:00`
+pub fn from_rfc3339(s: &str) -> Result<SystemTime, DataError> {
+    let err = || invalid_time(format!("invalid RFC3339 time {:?}", s));
+    let b = s.as_bytes();
+    if b.len() < 20 || !s.is_ascii() {
+        return Err(err());
+    }
+
+    let num = |r: std::ops::Range<usize>| -> Result<i64, DataError> {
+        if b[r.clone()].iter().all(u8::is_ascii_digit) {
+            Ok(s[r].parse().map_err(|_| err())?)
+        } else {
+            Err(err())
+        }
+    };
+
+    if b[4] != b'-'
+        || b[7] != b'-'
+        || !matches!(b[10], b'T' | b't' | b' ')
+        || b[13] != b':'
+        || b[16] != b':'
+    {
+        return Err(err());
+    }
+    let (y, mo, d) = (num(0..4)?, num(5..7)?, num(8..10)?);
+    let (h, mi, sec) = (num(11..13)?, num(14..16)?, num(17..19)?);
+    if !(1..=12).contains(&mo) || d < 1 || d > days_in_month(y, mo) || h > 23 || mi > 59 || sec > 59
+    {
+        return Err(err());
+    }
+
+    // the fraction, more than nanoseconds are dropped
+    let mut i = 19;
+    let mut nanos = 0u32;
+    if b[i] == b'.' {
+        let digits = b[i + 1..].iter().take_while(|c| c.is_ascii_digit()).count();
+        if digits == 0 {
+            return Err(err());
+        }
+        for (n, c) in b[i + 1..i + 1 + digits].iter().enumerate() {
+            if n < 9 {
+                nanos += (c - b'0') as u32 * 10u32.pow(8 - n as u32);
+            }
+        }
+        i += 1 + digits;
+    }
+
+    let offset = match &b[i..] {
+        [b'Z' | b'z'] => 0,
+        [sign @ (b'+' | b'-'), _, _, b':', _, _] => {
+            let (oh, om) = (num(i + 1..i + 3)?, num(i + 4..i + 6)?);
+            if oh > 23 || om > 59 {
+                return Err(err());
+            }
+            let o = oh * 3600 + om * 60;
+            if *sign == b'-' { -o } else { o }
+        }
+        _ => return Err(err()),
+    };
+
+    let secs = days_from_civil(y, mo, d) * 86400 + h * 3600 + mi * 60 + sec - offset;
+    from_epoch_parts(secs, nanos)
+}
+
+/// `(timestamp seconds)`, the nanoseconds are added if they aren't zero
+impl IntoData for SystemTime {
+    fn into_rpc_data(&self) -> Data {
+        let (secs, nanos) = epoch_parts(self);
+        let mut positional = vec![secs.into_rpc_data()];
+        if nanos != 0 {
+            positional.push(nanos.into_rpc_data());
+        }
+
+        Data::Data(ExprData::from_checked_pairs(
+            TIMESTAMP_NAME.to_string(),
+            positional,
+            vec![],
+        ))
+    }
+}
+
+/// `(timestamp seconds [nanoseconds])` or the RFC3339 string
+impl FromData for SystemTime {
+    fn from_rpc_data(d: &Data) -> Result<Self, DataError> {
+        match d {
+            Data::Value(TypeValue::String(s)) => from_rfc3339(s),
+            Data::Data(e) if e.get_name() == TIMESTAMP_NAME && e.rest_args.is_empty() => {
+                match e.positional() {
+                    [Data::Value(TypeValue::Number(s))] => from_epoch_parts(*s, 0),
+                    [
+                        Data::Value(TypeValue::Number(s)),
+                        Data::Value(TypeValue::Number(n)),
+                    ] => from_epoch_parts(
+                        *s,
+                        u32::try_from(*n)
+                            .map_err(|_| invalid_time(format!("nanoseconds {} out of range", n)))?,
+                    ),
+                    _ => Err(mismatch("(timestamp seconds [nanoseconds])", d)),
+                }
+            }
+            _ => Err(mismatch("timestamp", d)),
+        }
+    }
+}
+
+impl_try_from_data!(SystemTime);
+
+#[cfg(feature = "chrono")]
+impl IntoData for chrono::DateTime<chrono::Utc> {
+    fn into_rpc_data(&self) -> Data {
+        SystemTime::from(*self).into_rpc_data()
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl FromData for chrono::DateTime<chrono::Utc> {
+    fn from_rpc_data(d: &Data) -> Result<Self, DataError> {
+        SystemTime::from_rpc_data(d).map(Self::from)
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl_try_from_data!(chrono::DateTime<chrono::Utc>);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timestamp() {
+        let p = Parser::new();
+        let t = UNIX_EPOCH + Duration::from_secs(1710000000);
+
+        assert_eq!(t.into_rpc_data().to_string(), "(timestamp 1710000000)");
+        assert_eq!(to_rfc3339(&t), "2024-03-09T16:00:00Z");
+        assert_eq!(
+            SystemTime::from_rpc_data(&Data::from_str(&p, "(timestamp 1710000000)").unwrap()),
+            Ok(t)
+        );
+        assert_eq!(
+            SystemTime::try_from(&"2024-03-10T01:00:00+09:00".into_rpc_data()),
+            Ok(t)
+        );
+
+        // the nanoseconds and the time before the epoch
+        let t = UNIX_EPOCH - Duration::from_millis(1500);
+        assert_eq!(t.into_rpc_data().to_string(), "(timestamp -2 500000000)");
+        assert_eq!(to_rfc3339(&t), "1969-12-31T23:59:58.5Z");
+        assert_eq!(SystemTime::from_rpc_data(&t.into_rpc_data()), Ok(t));
+        assert_eq!(from_rfc3339("1969-12-31T23:59:58.500Z"), Ok(t));
+        assert_eq!(
+            from_rfc3339("2000-02-29T00:00:00Z")
+                .map(|t| to_rfc3339(&t))
+                .unwrap(),
+            "2000-02-29T00:00:00Z"
+        );
+
+        for bad in [
+            "2024-03-09",
+            "2024-03-09T16:00:00",
+            "2023-02-29T00:00:00Z",
+            "2024-03-09T24:00:00Z",
+            "2024-03-09T16:00:00.Z",
+            "2024-03-09T16:00:00+0900",
+        ] {
+            assert_eq!(
+                from_rfc3339(bad).unwrap_err().err_type(),
+                DataErrorType::InvalidInput,
+                "{}",
+                bad
+            );
+        }
+        assert!(SystemTime::from_rpc_data(&1.into_rpc_data()).is_err());
+        assert!(
+            SystemTime::from_rpc_data(&Data::from_str(&p, "(timestamp 1 :tz 8)").unwrap()).is_err()
+        );
+        assert!(
+            SystemTime::from_rpc_data(&Data::from_str(&p, "(timestamp 1 -1)").unwrap()).is_err()
+        );
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_chrono_timestamp() {
+        let t: chrono::DateTime<chrono::Utc> = "2024-03-09T16:00:00Z".parse().unwrap();
+        assert_eq!(t.into_rpc_data().to_string(), "(timestamp 1710000000)");
+        assert_eq!(chrono::DateTime::try_from(&t.into_rpc_data()), Ok(t));
+    }
+}
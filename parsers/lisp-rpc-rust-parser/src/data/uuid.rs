@@ -0,0 +1,76 @@
+//! The UUID convention. The UUID is the lowercase hyphenated string like
+//! `"67e55044-10b1-426f-9247-bb680e5fe0c8"`, the `uuid` feature adds the
+//! conversions of `uuid::Uuid`.
+
+use super::*;
+
+/// check the str is the hyphenated UUID, 8-4-4-4-12 hex digits
+pub fn check_uuid(s: &str) -> Result<(), DataError> {
+    let valid = s.len() == 36
+        && s.char_indices().all(|(i, c)| match i {
+            8 | 13 | 18 | 23 => c == '-',
+            _ => c.is_ascii_hexdigit(),
+        });
+
+    if !valid {
+        return Err(DataError {
+            msg: format!("invalid uuid {:?}", s),
+            err_type: DataErrorType::InvalidInput,
+            violations: vec![],
+        });
+    }
+    Ok(())
+}
+
+#[cfg(feature = "uuid")]
+impl IntoData for ::uuid::Uuid {
+    fn into_rpc_data(&self) -> Data {
+        Data::Value(TypeValue::String(self.hyphenated().to_string()))
+    }
+}
+
+#[cfg(feature = "uuid")]
+impl FromData for ::uuid::Uuid {
+    fn from_rpc_data(d: &Data) -> Result<Self, DataError> {
+        match d {
+            Data::Value(TypeValue::String(s)) => {
+                check_uuid(s)?;
+                Ok(Self::parse_str(s).expect("checked already"))
+            }
+            _ => Err(mismatch("uuid string", d)),
+        }
+    }
+}
+
+#[cfg(feature = "uuid")]
+impl_try_from_data!(::uuid::Uuid);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_uuid() {
+        assert!(check_uuid("67e55044-10b1-426f-9247-bb680e5fe0c8").is_ok());
+        assert!(check_uuid("67E55044-10B1-426F-9247-BB680E5FE0C8").is_ok());
+        assert!(check_uuid("67e5504410b1426f9247bb680e5fe0c8").is_err());
+        assert!(check_uuid("67e55044-10b1-426f-9247-bb680e5fe0cz").is_err());
+        assert!(check_uuid("67e55044_10b1-426f-9247-bb680e5fe0c8").is_err());
+        assert!(check_uuid("").is_err());
+    }
+
+    #[cfg(feature = "uuid")]
+    #[test]
+    fn test_uuid_data() {
+        let id = ::uuid::Uuid::parse_str("67E55044-10B1-426F-9247-BB680E5FE0C8").unwrap();
+        let d = DataBuilder::new("get-user").kw("id", id).build().unwrap();
+
+        assert_eq!(
+            d.to_string(),
+            r#"(get-user :id "67e55044-10b1-426f-9247-bb680e5fe0c8")"#
+        );
+        assert_eq!(d.get_as::<::uuid::Uuid>("id").unwrap(), id);
+        assert!(::uuid::Uuid::try_from(&"67e55044".into_rpc_data()).is_err());
+        assert!(::uuid::Uuid::from_rpc_data(&1.into_rpc_data()).is_err());
+    }
+}
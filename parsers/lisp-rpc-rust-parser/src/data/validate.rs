@@ -0,0 +1,178 @@
+//! The result type of checking the data against some rules, and the
+//! Validator running the registered rules of the keywords over the data
+
+use std::ops::RangeInclusive;
+
+use super::*;
+
+/// one failed check of the data. The path is the dot separated keywords
+/// (and list indexes) to the value, like "lang.encoding" or "tags.0"
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    pub path: String,
+    pub msg: String,
+}
+
+impl Violation {
+    pub fn new(path: &str, msg: impl Into<String>) -> Self {
+        Self {
+            path: path.to_string(),
+            msg: msg.into(),
+        }
+    }
+
+    /// join the keyword to the parent path
+    pub fn join_path(path: &str, k: &str) -> String {
+        if path.is_empty() {
+            k.to_string()
+        } else {
+            format!("{}.{}", path, k)
+        }
+    }
+}
+
+impl std::fmt::Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "{}", self.msg)
+        } else {
+            write!(f, "{}: {}", self.path, self.msg)
+        }
+    }
+}
+
+type Rule = Box<dyn Fn(&Data) -> Result<(), String> + Send + Sync>;
+
+/// the registry of the validation callbacks of the keyword paths, like the
+/// format of the emails or the range of the numbers
+///
+/// ```
+/// # use lisp_rpc_rust_parser::{Parser, data::{Data, FromStr, Validator}};
+/// let v = Validator::new()
+///     .rule("email", |d| match d.to_string().contains('@') {
+///         true => Ok(()),
+///         false => Err("invalid email".to_string()),
+///     })
+///     .range("age", 0..=150);
+///
+/// let d = Data::from_str(&Parser::new(), r#"(sign-up :email "a" :age 200)"#).unwrap();
+/// let violations: Vec<String> = v.validate(&d).iter().map(|v| v.to_string()).collect();
+/// assert_eq!(violations, vec!["email: invalid email", "age: 200 is out of range 0..=150"]);
+/// ```
+#[derive(Default)]
+pub struct Validator {
+    rules: Vec<(Vec<String>, Rule)>,
+}
+
+impl Validator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// register the rule of the keyword path, dot separated like `get_path`.
+    /// The path goes through every element of the lists like `redacted`, so
+    /// `"users.email"` checks the emails of all users. The missing keywords
+    /// are skipped.
+    pub fn rule<F>(mut self, path: &str, f: F) -> Self
+    where
+        F: Fn(&Data) -> Result<(), String> + Send + Sync + 'static,
+    {
+        self.rules.push((
+            path.split('.').map(|s| s.to_string()).collect(),
+            Box::new(f),
+        ));
+        self
+    }
+
+    /// register the rule that the number of the path is in the range
+    pub fn range(self, path: &str, range: RangeInclusive<i64>) -> Self {
+        self.rule(path, move |d| match d {
+            Data::Value(TypeValue::Number(n)) if range.contains(n) => Ok(()),
+            Data::Value(TypeValue::Number(n)) => Err(format!("{} is out of range {:?}", n, range)),
+            _ => Err(format!("expect number, got {}", d)),
+        })
+    }
+
+    /// run all rules over the data, in the order they were registered
+    pub fn validate(&self, d: &Data) -> Vec<Violation> {
+        let mut out = vec![];
+        for (path, f) in &self.rules {
+            let path: Vec<&str> = path.iter().map(|s| s.as_str()).collect();
+            check_rule(d, &path, "", f, &mut out);
+        }
+        out
+    }
+}
+
+impl std::fmt::Debug for Validator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list()
+            .entries(self.rules.iter().map(|(p, _)| p.join(".")))
+            .finish()
+    }
+}
+
+fn check_rule(d: &Data, rest: &[&str], path: &str, f: &Rule, out: &mut Vec<Violation>) {
+    match (rest, d) {
+        ([], _) => {
+            if let Err(msg) = f(d) {
+                out.push(Violation::new(path, msg))
+            }
+        }
+        (_, Data::Tagged(t)) => check_rule(t.data(), rest, path, f, out),
+        (_, Data::List(l)) => {
+            for (i, ele) in l.iter().enumerate() {
+                check_rule(
+                    ele,
+                    rest,
+                    &Violation::join_path(path, &i.to_string()),
+                    f,
+                    out,
+                )
+            }
+        }
+        ([k, rest @ ..], _) => {
+            if let Some(v) = d.get(k) {
+                check_rule(v, rest, &Violation::join_path(path, k), f, out)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validator() {
+        let v = Validator::new()
+            .rule("users.email", |d| match d {
+                Data::Value(TypeValue::String(s)) if s.contains('@') => Ok(()),
+                _ => Err("invalid email".to_string()),
+            })
+            .range("users.age", 0..=150)
+            .range("limit", 1..=100);
+
+        let d = Data::from_str(
+            &Parser::new(),
+            r#"(add-users :users '('(:email "a@b.c" :age 20) '(:email "nope" :age -1) '(:age "x")) :limit 10)"#,
+        )
+        .unwrap();
+        let violations: Vec<String> = v.validate(&d).iter().map(|v| v.to_string()).collect();
+        assert_eq!(
+            violations,
+            vec![
+                "users.1.email: invalid email",
+                "users.1.age: -1 is out of range 0..=150",
+                "users.2.age: expect number, got \"x\"",
+            ]
+        );
+
+        assert_eq!(v.validate(&d.clone().tagged("add-users").unwrap()).len(), 3);
+        assert!(Validator::new().validate(&d).is_empty());
+        assert_eq!(
+            format!("{:?}", v),
+            r#"["users.email", "users.age", "limit"]"#
+        );
+    }
+}
@@ -1,9 +1,13 @@
 #![feature(iter_array_chunks)]
-#![feature(assert_matches)]
+mod canonical;
 pub mod data;
 mod macros;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+#[cfg(feature = "wasm")]
+pub mod wasm;
 
-use std::{collections::VecDeque, error::Error, io::Read};
+use std::{collections::VecDeque, error::Error, io::Read, num::IntErrorKind};
 use tracing::error;
 
 #[derive(Debug, PartialEq, Eq)]
@@ -12,6 +16,12 @@ pub enum ParserError {
     InvalidToken(&'static str),
     CorruptData(&'static str),
     UnknownToken,
+    NumberOverflow(String),
+    /// the symbol or keyword name has the reserved characters
+    InvalidSymbol(String),
+    LimitExceeded(&'static str),
+    /// the error happened in the named source (file name, url, connection id...)
+    InSource(String, Box<ParserError>),
 }
 
 impl std::fmt::Display for ParserError {
@@ -21,12 +31,36 @@ impl std::fmt::Display for ParserError {
             ParserError::InvalidToken(msg) => write!(f, "parser error: Invalid token: {}", msg),
             ParserError::UnknownToken => write!(f, "parser error: Unknown token"),
             ParserError::CorruptData(msg) => write!(f, "parser error: illegal data: {}", msg),
+            ParserError::InvalidSymbol(s) => write!(f, "parser error: invalid symbol: {:?}", s),
+            ParserError::LimitExceeded(msg) => write!(f, "parser error: limit exceeded: {}", msg),
+            ParserError::InSource(source_name, e) => write!(f, "{}: {}", source_name, e),
+            ParserError::NumberOverflow(token) => {
+                write!(f, "parser error: number out of i64 range: {}", token)
+            }
         }
     }
 }
 
 impl Error for ParserError {}
 
+impl ParserError {
+    /// the source name attached by Parser::parse_root_with_source
+    pub fn source_name(&self) -> Option<&str> {
+        match self {
+            ParserError::InSource(source_name, _) => Some(source_name),
+            _ => None,
+        }
+    }
+
+    /// attach the source name to this error
+    pub fn with_source(self, source_name: &str) -> Self {
+        match self {
+            e @ ParserError::InSource(..) => e,
+            e => ParserError::InSource(source_name.to_string(), Box::new(e)),
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Clone, Hash)]
 pub enum TypeValue {
     Symbol(String),
@@ -39,21 +73,52 @@ impl TypeValue {
     pub fn to_string(&self) -> String {
         match self {
             TypeValue::Symbol(s) => s.clone(),
-            TypeValue::String(s) => format!("\"{}\"", s),
+            TypeValue::String(s) => format!("\"{}\"", escape_string(s)),
             TypeValue::Keyword(s) => format!(":{}", s),
             TypeValue::Number(d) => d.to_string(),
         }
     }
 
     pub fn make_symbol(s: &str) -> Result<Self, Box<dyn Error>> {
-        if s.contains([' ']) {
-            Err(Box::new(ParserError::CorruptData(
-                "cannot make symbol with this str",
-            )))
-        } else {
+        if Self::is_valid_symbol(s) {
             Ok(Self::Symbol(s.to_string()))
+        } else {
+            Err(Box::new(ParserError::InvalidSymbol(s.to_string())))
+        }
+    }
+
+    /// make keyword, s is the name without the colon
+    pub fn make_keyword(s: &str) -> Result<Self, Box<dyn Error>> {
+        if Self::is_valid_symbol(s) {
+            Ok(Self::Keyword(s.to_string()))
+        } else {
+            Err(Box::new(ParserError::InvalidSymbol(s.to_string())))
         }
     }
+
+    /// check the name of symbol or keyword (without the colon).
+    /// it cannot be empty, and cannot have whitespaces, parens, quotes, colons, or backslashes
+    pub fn is_valid_symbol(s: &str) -> bool {
+        !s.is_empty()
+            && !s
+                .chars()
+                .any(|c| c.is_whitespace() || RESERVED_SYMBOL_CHARS.contains(&c))
+    }
+}
+
+/// the chars cannot be inside the symbols and keywords
+const RESERVED_SYMBOL_CHARS: [char; 6] = ['(', ')', '\'', '"', ':', '\\'];
+
+/// escape the \\ and " inside string
+fn escape_string(s: &str) -> String {
+    let mut res = String::with_capacity(s.len());
+    for c in s.chars() {
+        if c == '\\' || c == '"' {
+            res.push('\\');
+        }
+        res.push(c);
+    }
+    res
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Hash)]
@@ -98,7 +163,7 @@ impl Atom {
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
 pub enum Expr {
     Atom(Atom),
     List(Vec<Expr>),
@@ -135,6 +200,135 @@ impl Expr {
             _ => None,
         }
     }
+
+    /// return the normalized expr for comparing exprs semantically.
+    ///
+    /// + the nested quotes `''a` collapse to `'a`
+    /// + the quotes of self-evaluating atoms (string, number, keyword) are removed
+    /// + the keyword-value pairs are sorted by keyword if sort_keywords is true,
+    ///   the leading non-keyword element (like the name of data) keeps its position
+    ///
+    /// the into_tokens of the normalized expr is the canonical text
+    pub fn normalize(&self, sort_keywords: bool) -> Expr {
+        match self {
+            Expr::Atom(_) => self.clone(),
+            Expr::Quote(inner) => match inner.normalize(sort_keywords) {
+                e @ Expr::Quote(_) => e,
+                Expr::Atom(Atom {
+                    value: v @ (TypeValue::String(_) | TypeValue::Number(_) | TypeValue::Keyword(_)),
+                }) => Expr::Atom(Atom { value: v }),
+                e => Expr::Quote(Box::new(e)),
+            },
+            Expr::List(exprs) => {
+                let mut exprs: Vec<Expr> =
+                    exprs.iter().map(|e| e.normalize(sort_keywords)).collect();
+                if sort_keywords {
+                    sort_keyword_pairs(&mut exprs);
+                }
+                Expr::List(exprs)
+            }
+        }
+    }
+
+    /// depth-first walk every atom with its path.
+    /// the path is the indexes from the root, quote counts as one level with index 0
+    pub fn walk(&self) -> AtomWalker<'_> {
+        AtomWalker {
+            stack: vec![(vec![], self)],
+        }
+    }
+
+    /// the levels of nesting, atom is 0, list and quote add one level
+    pub fn depth(&self) -> usize {
+        match self {
+            Expr::Atom(_) => 0,
+            Expr::List(exprs) => 1 + exprs.iter().map(|e| e.depth()).max().unwrap_or(0),
+            Expr::Quote(expr) => 1 + expr.depth(),
+        }
+    }
+
+    /// the count of all nodes (atoms, lists, and quotes) including self
+    pub fn node_count(&self) -> usize {
+        match self {
+            Expr::Atom(_) => 1,
+            Expr::List(exprs) => 1 + exprs.iter().map(|e| e.node_count()).sum::<usize>(),
+            Expr::Quote(expr) => 1 + expr.node_count(),
+        }
+    }
+}
+
+fn keyword_of(e: &Expr) -> Option<&str> {
+    match e {
+        Expr::Atom(Atom {
+            value: TypeValue::Keyword(k),
+        }) => Some(k),
+        _ => None,
+    }
+}
+
+/// sort the keyword-value pairs in place, do nothing if exprs are not pairs
+fn sort_keyword_pairs(exprs: &mut Vec<Expr>) {
+    let start = match exprs.first() {
+        Some(e) if keyword_of(e).is_some() => 0,
+        Some(_) => 1,
+        None => return,
+    };
+
+    let rest = &exprs[start..];
+    if !rest.len().is_multiple_of(2) || !rest.iter().step_by(2).all(|e| keyword_of(e).is_some()) {
+        return;
+    }
+
+    let mut pairs: Vec<(Expr, Expr)> = exprs
+        .drain(start..)
+        .array_chunks()
+        .map(|[k, v]| (k, v))
+        .collect();
+    pairs.sort_by(|(a, _), (b, _)| keyword_of(a).cmp(&keyword_of(b)));
+
+    for (k, v) in pairs {
+        exprs.push(k);
+        exprs.push(v);
+    }
+}
+
+/// the root expr with the name of the source it comes from
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SourcedExpr {
+    pub source: String,
+    pub expr: Expr,
+}
+
+/// the iterator returned by Expr::walk
+pub struct AtomWalker<'e> {
+    stack: Vec<(Vec<usize>, &'e Expr)>,
+}
+
+impl<'e> Iterator for AtomWalker<'e> {
+    type Item = (Vec<usize>, &'e Atom);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((path, expr)) = self.stack.pop() {
+            match expr {
+                Expr::Atom(atom) => return Some((path, atom)),
+                Expr::List(exprs) => {
+                    // reversed so the first child pop first
+                    for (ind, e) in exprs.iter().enumerate().rev() {
+                        let mut p = path.clone();
+                        p.push(ind);
+                        self.stack.push((p, e));
+                    }
+                }
+                Expr::Quote(e) => {
+                    let mut p = path;
+                    p.push(0);
+                    self.stack.push((p, e));
+                }
+            }
+        }
+
+        None
+    }
 }
 
 impl std::fmt::Display for Expr {
@@ -143,16 +337,91 @@ impl std::fmt::Display for Expr {
     }
 }
 
+/// how the parser treats the case of symbols and keywords,
+/// like the readtable case in common lisp
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum SymbolCase {
+    /// keep the symbol as it is
+    #[default]
+    Preserve,
+    /// `get-book` => `GET-BOOK`
+    Upcase,
+    /// `GET-BOOK` => `get-book`
+    Downcase,
+}
+
+impl SymbolCase {
+    fn apply(&self, s: &str) -> String {
+        match self {
+            SymbolCase::Preserve => s.to_string(),
+            SymbolCase::Upcase => s.to_uppercase(),
+            SymbolCase::Downcase => s.to_lowercase(),
+        }
+    }
+}
+
+/// what the parser does when a number token does not fit in i64
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum NumberOverflow {
+    /// return the ParserError::NumberOverflow
+    Error,
+    /// clamp the number to i64::MAX or i64::MIN
+    Saturate,
+    /// read the token as the symbol
+    #[default]
+    Symbol,
+}
+
+/// the chars split the tokens, they are the tokens themselves too
+const DELIMITERS: &[u8] = b"( )'\":\n";
+
+fn flush_cache(cache: &mut Vec<u8>, res: &mut Vec<String>) -> Result<(), ParserError> {
+    if !cache.is_empty() {
+        res.push(
+            String::from_utf8(cache.clone())
+                .map_err(|_| ParserError::CorruptData("token is not utf8"))?,
+        );
+        cache.clear();
+    }
+    Ok(())
+}
+
 pub struct Parser {
     /// will read number if this field is true. default is true
     /// turn it off will treat the number as the symbol in Expr
     read_number_config: bool,
+
+    /// the case normalization of symbols and keywords. default is preserve
+    /// strings are never touched
+    symbol_case_config: SymbolCase,
+
+    /// the behavior when the number token overflows i64. default is symbol
+    number_overflow_config: NumberOverflow,
+
+    /// the max bytes of one message (one root form) tokenize reads, the source
+    /// can have several messages. default is no limit
+    max_message_bytes: Option<usize>,
+
+    /// the max bytes of one token. default is no limit
+    max_token_bytes: Option<usize>,
+
+    /// the max nesting depth of the data read by Data::from_str. default is no limit
+    max_data_depth: Option<usize>,
+
+    /// the max count of the expr nodes of the data read by Data::from_str. default is no limit
+    max_data_nodes: Option<usize>,
 }
 
 impl Default for Parser {
     fn default() -> Self {
         Self {
             read_number_config: true,
+            symbol_case_config: SymbolCase::Preserve,
+            number_overflow_config: NumberOverflow::Symbol,
+            max_message_bytes: None,
+            max_token_bytes: None,
+            max_data_depth: None,
+            max_data_nodes: None,
         }
     }
 }
@@ -161,6 +430,12 @@ impl Parser {
     pub fn new() -> Self {
         Self {
             read_number_config: true,
+            symbol_case_config: SymbolCase::Preserve,
+            number_overflow_config: NumberOverflow::Symbol,
+            max_message_bytes: None,
+            max_token_bytes: None,
+            max_data_depth: None,
+            max_data_nodes: None,
         }
     }
 
@@ -170,24 +445,103 @@ impl Parser {
         self
     }
 
+    /// set the parser number overflow config
+    pub fn config_number_overflow(mut self, v: NumberOverflow) -> Self {
+        self.number_overflow_config = v;
+        self
+    }
+
+    /// set the max bytes of one message, tokenize returns error when any root form of the
+    /// source is larger
+    pub fn config_max_message_bytes(mut self, v: Option<usize>) -> Self {
+        self.max_message_bytes = v;
+        self
+    }
+
+    /// set the max bytes of one token, tokenize returns error when any token is longer
+    pub fn config_max_token_bytes(mut self, v: Option<usize>) -> Self {
+        self.max_token_bytes = v;
+        self
+    }
+
+    /// set the max nesting depth of data, the root data is depth 1
+    pub fn config_max_data_depth(mut self, v: Option<usize>) -> Self {
+        self.max_data_depth = v;
+        self
+    }
+
+    /// set the max count of the expr nodes (atoms, lists, and quotes) of data
+    pub fn config_max_data_nodes(mut self, v: Option<usize>) -> Self {
+        self.max_data_nodes = v;
+        self
+    }
+
+    /// set the parser symbol case config
+    pub fn config_symbol_case(mut self, v: SymbolCase) -> Self {
+        self.symbol_case_config = v;
+        self
+    }
+
     /// tokenize the source code
-    pub fn tokenize(&self, mut source_code: impl Read) -> VecDeque<String> {
+    pub fn tokenize(&self, mut source_code: impl Read) -> Result<VecDeque<String>, ParserError> {
         let mut buf = [0; 1];
         let mut cache = vec![];
         let mut res = vec![];
+        // the bytes of the current root form and its paren depth
+        let mut message_bytes = 0;
+        let mut depth = 0_usize;
+        let mut in_string = false;
+        let mut escaped = false;
         loop {
             match source_code.read(&mut buf) {
                 Ok(n) if n != 0 => {
                     let c = buf.get(0).unwrap();
+                    if !in_string {
+                        match c {
+                            b'(' => depth += 1,
+                            b')' => depth = depth.saturating_sub(1),
+                            _ => (),
+                        }
+                    }
+
+                    message_bytes += n;
+                    if self
+                        .max_message_bytes
+                        .is_some_and(|max| message_bytes > max)
+                    {
+                        return Err(ParserError::LimitExceeded("message is too large"));
+                    }
+                    // the message ends when its root form is closed
+                    if depth == 0 && !in_string && matches!(c, b')' | b' ' | b'\n') {
+                        message_bytes = 0;
+                    }
+
                     match c {
-                        b'(' | b' ' | b')' | b'\'' | b'"' | b':' | b'\n' => {
-                            if !cache.is_empty() {
-                                res.push(String::from_utf8(cache.clone()).unwrap());
-                                cache.clear();
+                        // the char after \ inside string
+                        _ if escaped => {
+                            escaped = false;
+                            if DELIMITERS.contains(c) {
+                                flush_cache(&mut cache, &mut res)?;
+                                res.push(String::from_utf8(vec![*c]).unwrap())
+                            } else {
+                                cache.push(*c);
                             }
+                        }
+                        b'\\' if in_string => {
+                            flush_cache(&mut cache, &mut res)?;
+                            res.push(String::from("\\"));
+                            escaped = true;
+                        }
+                        c if DELIMITERS.contains(c) => {
+                            flush_cache(&mut cache, &mut res)?;
 
+                            if *c == b'"' {
+                                in_string = !in_string;
+                            }
+
+                            // spaces inside string are the content
                             match res.last() {
-                                Some(le) if le == " " && *c == b' ' => continue,
+                                Some(le) if le == " " && *c == b' ' && !in_string => continue,
                                 _ => (),
                             }
 
@@ -195,6 +549,9 @@ impl Parser {
                         }
                         _ => {
                             cache.push(*c);
+                            if self.max_token_bytes.is_some_and(|max| cache.len() > max) {
+                                return Err(ParserError::LimitExceeded("token is too long"));
+                            }
                         }
                     }
                 }
@@ -203,22 +560,61 @@ impl Parser {
             }
         }
 
-        if !cache.is_empty() {
-            res.push(String::from_utf8(cache.clone()).unwrap());
-        }
+        flush_cache(&mut cache, &mut res)?;
 
-        res.into()
+        Ok(res.into())
     }
 
     pub fn parse_root(&mut self, source_code: impl Read) -> Result<Vec<Expr>, ParserError> {
-        let mut tokens = self.tokenize(source_code);
+        self.parse_root_with_source(source_code, None)
+    }
+
+    /// same as parse_root, the errors carry the source name if it is given
+    pub fn parse_root_with_source(
+        &mut self,
+        source_code: impl Read,
+        source_name: Option<&str>,
+    ) -> Result<Vec<Expr>, ParserError> {
+        self.parse_root_inner(source_code)
+            .map_err(|e| match source_name {
+                Some(n) => e.with_source(n),
+                None => e,
+            })
+    }
+
+    /// parse a sequence of named sources, every root expr is tagged with its source name.
+    /// errors carry the source name too
+    pub fn parse_many(
+        &mut self,
+        sources: impl Iterator<Item = (String, impl Read)>,
+    ) -> Result<Vec<SourcedExpr>, ParserError> {
+        let mut res = vec![];
+        for (source_name, source_code) in sources {
+            for expr in self.parse_root_with_source(source_code, Some(&source_name))? {
+                res.push(SourcedExpr {
+                    source: source_name.clone(),
+                    expr,
+                });
+            }
+        }
+
+        Ok(res)
+    }
+
+    fn parse_root_inner(&mut self, source_code: impl Read) -> Result<Vec<Expr>, ParserError> {
+        let mut tokens = self.tokenize(source_code)?;
+        self.read_roots(&mut tokens)
+    }
+
+    /// read all root exprs from the tokens
+    fn read_roots(&self, tokens: &mut VecDeque<String>) -> Result<Vec<Expr>, ParserError> {
         let mut res = vec![];
 
         loop {
             match tokens.front() {
                 Some(b) => match b.as_str() {
                     "(" => {
-                        res.push(self.read_exp(&mut tokens)?);
+                        res.push(self.read_exp(tokens)?);
                     }
                     " " | "\n" => {
                         tokens.pop_front();
@@ -238,7 +634,7 @@ impl Parser {
     }
 
     pub fn parse_root_one(&mut self, source_code: impl Read) -> Result<Expr, ParserError> {
-        let mut tokens = self.tokenize(source_code);
+        let mut tokens = self.tokenize(source_code)?;
 
         loop {
             match tokens.front() {
@@ -283,11 +679,31 @@ impl Parser {
         if self.read_number_config {
             match token.parse::<i64>() {
                 Ok(n) => return Ok(Expr::Atom(Atom::read_number(&token, n))),
-                Err(_) => (),
+                Err(e) => match (e.kind(), self.number_overflow_config) {
+                    (
+                        IntErrorKind::PosOverflow | IntErrorKind::NegOverflow,
+                        NumberOverflow::Error,
+                    ) => {
+                        return Err(ParserError::NumberOverflow(token));
+                    }
+                    (IntErrorKind::PosOverflow, NumberOverflow::Saturate) => {
+                        return Ok(Expr::Atom(Atom::read_number(&token, i64::MAX)));
+                    }
+                    (IntErrorKind::NegOverflow, NumberOverflow::Saturate) => {
+                        return Ok(Expr::Atom(Atom::read_number(&token, i64::MIN)));
+                    }
+                    _ => (),
+                },
             }
         }
 
-        Ok(Expr::Atom(Atom::read(&token)))
+        if !TypeValue::is_valid_symbol(&token) {
+            return Err(ParserError::InvalidSymbol(token));
+        }
+
+        Ok(Expr::Atom(Atom::read(
+            &self.symbol_case_config.apply(&token),
+        )))
     }
 
     fn read_quote(&self, tokens: &mut VecDeque<String>) -> Result<Expr, ParserError> {
@@ -362,7 +778,13 @@ impl Parser {
             .pop_front()
             .ok_or(ParserError::InvalidToken("in read_keyword"))?;
 
-        Ok(Expr::Atom(Atom::read_keyword(&token)))
+        if !TypeValue::is_valid_symbol(&token) {
+            return Err(ParserError::InvalidSymbol(token));
+        }
+
+        Ok(Expr::Atom(Atom::read_keyword(
+            &self.symbol_case_config.apply(&token),
+        )))
     }
 }
 
@@ -378,7 +800,7 @@ mod tests {
         //
         let s = "(a b c 123 c)";
         assert_eq!(
-            parser.tokenize(Cursor::new(s.as_bytes())),
+            parser.tokenize(Cursor::new(s.as_bytes())).unwrap(),
             vec!["(", "a", " ", "b", " ", "c", " ", "123", " ", "c", ")"]
                 .into_iter()
                 .map(|s| s.to_string())
@@ -388,7 +810,7 @@ mod tests {
         //
         let s = r#"(a '(""))"#;
         assert_eq!(
-            parser.tokenize(Cursor::new(s.as_bytes())),
+            parser.tokenize(Cursor::new(s.as_bytes())).unwrap(),
             vec!["(", "a", " ", "'", "(", "\"", "\"", ")", ")"]
                 .into_iter()
                 .map(|s| s.to_string())
@@ -398,7 +820,7 @@ mod tests {
         //
         let s = r#"(a '() '1)"#;
         assert_eq!(
-            parser.tokenize(Cursor::new(s.as_bytes())),
+            parser.tokenize(Cursor::new(s.as_bytes())).unwrap(),
             vec!["(", "a", " ", "'", "(", ")", " ", "'", "1", ")"]
                 .into_iter()
                 .map(|s| s.to_string())
@@ -408,7 +830,7 @@ mod tests {
         //
         let s = r#"(def-msg language-perfer :lang 'string)"#;
         assert_eq!(
-            parser.tokenize(Cursor::new(s.as_bytes())),
+            parser.tokenize(Cursor::new(s.as_bytes())).unwrap(),
             vec![
                 "(",
                 "def-msg",
@@ -432,7 +854,7 @@ mod tests {
                      '(:title 'string :vesion 'string :lang 'language-perfer)
                     'book-info)"#;
         assert_eq!(
-            parser.tokenize(Cursor::new(s.as_bytes())),
+            parser.tokenize(Cursor::new(s.as_bytes())).unwrap(),
             vec![
                 "(",
                 "def-rpc",
@@ -474,7 +896,7 @@ mod tests {
         //
         let s = r#"(get-book :title "hello world" :version "1984")"#;
         assert_eq!(
-            parser.tokenize(Cursor::new(s.as_bytes())),
+            parser.tokenize(Cursor::new(s.as_bytes())).unwrap(),
             vec![
                 "(", "get-book", " ", ":", "title", " ", "\"", "hello", " ", "world", "\"", " ",
                 ":", "version", " ", "\"", "1984", "\"", ")"
@@ -487,7 +909,7 @@ mod tests {
         // escapr "
         let s = r#"( get-book :title "hello \"world" :version "1984")"#;
         assert_eq!(
-            parser.tokenize(Cursor::new(s.as_bytes())),
+            parser.tokenize(Cursor::new(s.as_bytes())).unwrap(),
             vec![
                 "(", " ", "get-book", " ", ":", "title", " ", "\"", "hello", " ", "\\", "\"",
                 "world", "\"", " ", ":", "version", " ", "\"", "1984", "\"", ")"
@@ -501,7 +923,7 @@ mod tests {
 
         let s = r#"( get-book :id 1984)"#;
         assert_eq!(
-            parser.tokenize(Cursor::new(s.as_bytes())),
+            parser.tokenize(Cursor::new(s.as_bytes())).unwrap(),
             vec!["(", " ", "get-book", " ", ":", "id", " ", "1984", ")"]
                 .into_iter()
                 .map(|s| s.to_string())
@@ -509,10 +931,58 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_tokenize_limits() {
+        let s = "(get-book :title \"hello world\")";
+
+        let parser = Parser::new()
+            .config_max_message_bytes(Some(s.len()))
+            .config_max_token_bytes(Some(8));
+        assert!(parser.tokenize(Cursor::new(s.as_bytes())).is_ok());
+
+        let parser = Parser::new().config_max_message_bytes(Some(10));
+        assert_eq!(
+            parser.tokenize(Cursor::new(s.as_bytes())),
+            Err(ParserError::LimitExceeded("message is too large"))
+        );
+
+        let parser = Parser::new().config_max_token_bytes(Some(4));
+        assert_eq!(
+            parser.tokenize(Cursor::new(s.as_bytes())),
+            Err(ParserError::LimitExceeded("token is too long"))
+        );
+
+        // the limit is of every message, not the whole source
+        let parser = Parser::new().config_max_message_bytes(Some(s.len()));
+        let two = format!("{}\n{}", s, s);
+        assert!(parser.tokenize(Cursor::new(two.as_bytes())).is_ok());
+        // the parens inside the string don't close the message
+        let parser = Parser::new().config_max_message_bytes(Some(10));
+        assert_eq!(
+            parser.tokenize(Cursor::new(r#"(a ")" bcdefgh)"#.as_bytes())),
+            Err(ParserError::LimitExceeded("message is too large"))
+        );
+
+        // the invalid utf8 is the error, not the panic
+        assert_eq!(
+            Parser::new().tokenize(Cursor::new(b"(a \xff)")),
+            Err(ParserError::CorruptData("token is not utf8"))
+        );
+
+        // garbage without closing paren stops early
+        let mut parser = Parser::new().config_max_message_bytes(Some(1024));
+        assert_eq!(
+            parser.parse_root(std::io::repeat(b'(')),
+            Err(ParserError::LimitExceeded("message is too large"))
+        );
+    }
+
     #[test]
     fn test_read_string() {
         let parser = Parser::new();
-        let mut t = parser.tokenize(Cursor::new(r#""hello""#.as_bytes()));
+        let mut t = parser
+            .tokenize(Cursor::new(r#""hello""#.as_bytes()))
+            .unwrap();
         assert_eq!(
             parser.read_string(&mut t),
             Ok(Expr::Atom(Atom::read_string("hello")))
@@ -520,11 +990,79 @@ mod tests {
         assert!(t.is_empty());
     }
 
+    #[test]
+    fn test_string_escape() {
+        let parser = Parser::new();
+
+        let mut t = parser
+            .tokenize(Cursor::new(r#"("a  b" "c\\d" "e\"(f)")"#.as_bytes()))
+            .unwrap();
+        assert_eq!(
+            parser.read_exp(&mut t),
+            Ok(Expr::List(vec![
+                Expr::Atom(Atom::read_string("a  b")),
+                Expr::Atom(Atom::read_string("c\\d")),
+                Expr::Atom(Atom::read_string("e\"(f)")),
+            ]))
+        );
+
+        assert_eq!(
+            TypeValue::String(r#"say "hi" \ bye"#.to_string()).to_string(),
+            r#""say \"hi\" \\ bye""#
+        );
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn test_string_round_trip(s in "(\\PC|\n){0,20}") {
+            let expr = Expr::List(vec![Expr::Atom(Atom::read_string(&s))]);
+            let mut parser = Parser::new();
+            proptest::prop_assert_eq!(
+                parser.parse_root_one(Cursor::new(expr.to_string())).unwrap(),
+                expr
+            );
+        }
+    }
+
+    #[test]
+    fn test_invalid_symbol() {
+        assert!(TypeValue::is_valid_symbol("get-book"));
+        assert!(TypeValue::is_valid_symbol("*x*"));
+        assert!(!TypeValue::is_valid_symbol(""));
+        assert!(!TypeValue::is_valid_symbol("a b"));
+        assert!(!TypeValue::is_valid_symbol("a\tb"));
+        assert!(!TypeValue::is_valid_symbol("a:b"));
+        assert!(!TypeValue::is_valid_symbol("a(b"));
+        assert!(!TypeValue::is_valid_symbol("a'b"));
+        assert!(!TypeValue::is_valid_symbol("a\"b"));
+
+        assert!(TypeValue::make_keyword("title").is_ok());
+        assert!(TypeValue::make_keyword("ti:tle").is_err());
+
+        let mut parser = Parser::new();
+        assert_eq!(
+            parser.parse_root(Cursor::new("(a : b)")),
+            Err(ParserError::InvalidSymbol(" ".to_string()))
+        );
+        assert_eq!(
+            parser.parse_root(Cursor::new("(a ::b)")),
+            Err(ParserError::InvalidSymbol(":".to_string()))
+        );
+        assert_eq!(
+            parser.parse_root(Cursor::new("(a :\"b\")")),
+            Err(ParserError::InvalidSymbol("\"".to_string()))
+        );
+        assert_eq!(
+            parser.parse_root(Cursor::new("(a b\\c)")),
+            Err(ParserError::InvalidSymbol("b\\c".to_string()))
+        );
+    }
+
     #[test]
     fn test_read_number() {
         let parser = Parser::new().config_read_number(true);
 
-        let mut t = parser.tokenize(Cursor::new(r#"123"#.as_bytes()));
+        let mut t = parser.tokenize(Cursor::new(r#"123"#.as_bytes())).unwrap();
 
         assert_eq!(
             parser.read_atom(&mut t),
@@ -532,10 +1070,91 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_number_overflow() {
+        let parser = Parser::new();
+        let mut t = parser
+            .tokenize(Cursor::new("99999999999999999999".as_bytes()))
+            .unwrap();
+        assert_eq!(
+            parser.read_atom(&mut t),
+            Ok(Expr::Atom(Atom::read("99999999999999999999")))
+        );
+
+        let parser = Parser::new().config_number_overflow(NumberOverflow::Error);
+        let mut t = parser
+            .tokenize(Cursor::new("99999999999999999999".as_bytes()))
+            .unwrap();
+        assert_eq!(
+            parser.read_atom(&mut t),
+            Err(ParserError::NumberOverflow(
+                "99999999999999999999".to_string()
+            ))
+        );
+
+        // not a number at all, still the symbol
+        let mut t = parser.tokenize(Cursor::new("abc".as_bytes())).unwrap();
+        assert_eq!(parser.read_atom(&mut t), Ok(Expr::Atom(Atom::read("abc"))));
+
+        let parser = Parser::new().config_number_overflow(NumberOverflow::Saturate);
+        let mut t = parser
+            .tokenize(Cursor::new(
+                "(99999999999999999999 -99999999999999999999)".as_bytes(),
+            ))
+            .unwrap();
+        assert_eq!(
+            parser.read_exp(&mut t),
+            Ok(Expr::List(vec![
+                Expr::Atom(Atom::read_number("99999999999999999999", i64::MAX)),
+                Expr::Atom(Atom::read_number("-99999999999999999999", i64::MIN)),
+            ]))
+        );
+    }
+
+    #[test]
+    fn test_symbol_case() {
+        let s = r#"(GET-Book :Title "Hello World")"#;
+
+        let parser = Parser::new();
+        let mut t = parser.tokenize(Cursor::new(s.as_bytes())).unwrap();
+        assert_eq!(
+            parser.read_exp(&mut t),
+            Ok(Expr::List(vec![
+                Expr::Atom(Atom::read("GET-Book")),
+                Expr::Atom(Atom::read_keyword("Title")),
+                Expr::Atom(Atom::read_string("Hello World")),
+            ]))
+        );
+
+        let parser = Parser::new().config_symbol_case(SymbolCase::Downcase);
+        let mut t = parser.tokenize(Cursor::new(s.as_bytes())).unwrap();
+        assert_eq!(
+            parser.read_exp(&mut t),
+            Ok(Expr::List(vec![
+                Expr::Atom(Atom::read("get-book")),
+                Expr::Atom(Atom::read_keyword("title")),
+                Expr::Atom(Atom::read_string("Hello World")),
+            ]))
+        );
+
+        let parser = Parser::new().config_symbol_case(SymbolCase::Upcase);
+        let mut t = parser.tokenize(Cursor::new(s.as_bytes())).unwrap();
+        assert_eq!(
+            parser.read_exp(&mut t),
+            Ok(Expr::List(vec![
+                Expr::Atom(Atom::read("GET-BOOK")),
+                Expr::Atom(Atom::read_keyword("TITLE")),
+                Expr::Atom(Atom::read_string("Hello World")),
+            ]))
+        );
+    }
+
     #[test]
     fn test_read_exp() {
         let parser = Parser::new().config_read_number(false);
-        let mut t = parser.tokenize(Cursor::new("(a b c 123 c)".as_bytes()));
+        let mut t = parser
+            .tokenize(Cursor::new("(a b c 123 c)".as_bytes()))
+            .unwrap();
         assert_eq!(
             parser.read_exp(&mut t),
             Ok(Expr::List(
@@ -553,7 +1172,9 @@ mod tests {
         assert!(t.is_empty());
 
         //
-        let mut t = parser.tokenize(Cursor::new("((a) b c 123 c)".as_bytes()));
+        let mut t = parser
+            .tokenize(Cursor::new("((a) b c 123 c)".as_bytes()))
+            .unwrap();
         assert_eq!(
             parser.read_exp(&mut t),
             Ok(Expr::List(
@@ -571,9 +1192,11 @@ mod tests {
         assert!(t.is_empty());
 
         //
-        let mut t = parser.tokenize(Cursor::new(
-            r#"(def-msg language-perfer :lang 'string)"#.as_bytes(),
-        ));
+        let mut t = parser
+            .tokenize(Cursor::new(
+                r#"(def-msg language-perfer :lang 'string)"#.as_bytes(),
+            ))
+            .unwrap();
         assert_eq!(
             parser.read_exp(&mut t),
             Ok(Expr::List(
@@ -590,12 +1213,14 @@ mod tests {
         assert!(t.is_empty());
 
         //
-        let mut t = parser.tokenize(Cursor::new(
-            r#"(def-rpc get-book
+        let mut t = parser
+            .tokenize(Cursor::new(
+                r#"(def-rpc get-book
                      '(:title 'string :version 'string :lang 'language-perfer)
                     'book-info)"#
-                .as_bytes(),
-        ));
+                    .as_bytes(),
+            ))
+            .unwrap();
         assert_eq!(
             parser.read_exp(&mut t),
             Ok(Expr::List(
@@ -622,9 +1247,11 @@ mod tests {
         assert!(t.is_empty());
 
         //
-        let mut t = parser.tokenize(Cursor::new(
-            r#"(get-book :title "hello world" :version "1984")"#.as_bytes(),
-        ));
+        let mut t = parser
+            .tokenize(Cursor::new(
+                r#"(get-book :title "hello world" :version "1984")"#.as_bytes(),
+            ))
+            .unwrap();
 
         assert_eq!(
             parser.read_exp(&mut t),
@@ -640,9 +1267,11 @@ mod tests {
             ),)
         );
 
-        let mut t = parser.tokenize(Cursor::new(
-            r#"(get-book :title "hello \"world" :version "1984")"#.as_bytes(),
-        ));
+        let mut t = parser
+            .tokenize(Cursor::new(
+                r#"(get-book :title "hello \"world" :version "1984")"#.as_bytes(),
+            ))
+            .unwrap();
 
         assert_eq!(
             parser.read_exp(&mut t),
@@ -662,9 +1291,11 @@ mod tests {
 
         let parser = Parser::new().config_read_number(true);
 
-        let mut t = parser.tokenize(Cursor::new(
-            r#"(get-book :title "hello world" :id 1984)"#.as_bytes(),
-        ));
+        let mut t = parser
+            .tokenize(Cursor::new(
+                r#"(get-book :title "hello world" :id 1984)"#.as_bytes(),
+            ))
+            .unwrap();
 
         assert_eq!(
             parser.read_exp(&mut t),
@@ -731,7 +1362,7 @@ mod tests {
         );
 
         let s0 = Cursor::new(r#"(def-msg language-perfer :lang 'string)"#.as_bytes());
-        let mut t0 = parser.tokenize(s0.clone());
+        let mut t0 = parser.tokenize(s0.clone()).unwrap();
 
         let s1 = Cursor::new(
             r#"(def-rpc get-book
@@ -739,7 +1370,7 @@ mod tests {
                     'book-info)"#
                 .as_bytes(),
         );
-        let mut t1 = parser.tokenize(s1.clone());
+        let mut t1 = parser.tokenize(s1.clone()).unwrap();
 
         let expr = parser.parse_root(&mut t).unwrap();
         assert_eq!(
@@ -751,6 +1382,78 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_read_root_with_source() {
+        let mut parser = Parser::new();
+
+        let expr = parser
+            .parse_root_with_source(Cursor::new("(a b)"), Some("a.lisp"))
+            .unwrap();
+        assert_eq!(
+            expr,
+            vec![Expr::List(vec![
+                Expr::Atom(Atom::read("a")),
+                Expr::Atom(Atom::read("b"))
+            ])]
+        );
+
+        let err = parser
+            .parse_root_with_source(Cursor::new("(a b"), Some("a.lisp"))
+            .unwrap_err();
+        assert_eq!(err.source_name(), Some("a.lisp"));
+        assert_eq!(
+            err,
+            ParserError::InSource(
+                "a.lisp".to_string(),
+                Box::new(ParserError::InvalidToken("in read_exp, the tokens run out"))
+            )
+        );
+        assert_eq!(
+            err.to_string(),
+            "a.lisp: parser error: Invalid token: in read_exp, the tokens run out"
+        );
+
+        let err = parser.parse_root(Cursor::new("(a b")).unwrap_err();
+        assert_eq!(err.source_name(), None);
+    }
+
+    #[test]
+    fn test_parse_many() {
+        let mut parser = Parser::new();
+        let exprs = parser
+            .parse_many(
+                [
+                    ("a.lisp".to_string(), Cursor::new("(a 1) (b 2)")),
+                    ("b.lisp".to_string(), Cursor::new("(c 3)")),
+                ]
+                .into_iter(),
+            )
+            .unwrap();
+
+        assert_eq!(
+            exprs
+                .iter()
+                .map(|se| (se.source.as_str(), se.expr.to_string()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("a.lisp", "(a 1)".to_string()),
+                ("a.lisp", "(b 2)".to_string()),
+                ("b.lisp", "(c 3)".to_string()),
+            ]
+        );
+
+        let err = parser
+            .parse_many(
+                [
+                    ("a.lisp".to_string(), Cursor::new("(a 1)")),
+                    ("b.lisp".to_string(), Cursor::new("(c 3")),
+                ]
+                .into_iter(),
+            )
+            .unwrap_err();
+        assert_eq!(err.source_name(), Some("b.lisp"));
+    }
+
     #[test]
     fn test_read_root_one() {
         let mut parser = Parser::new();
@@ -766,11 +1469,89 @@ mod tests {
         let expr = parser.parse_root_one(&mut t).unwrap();
 
         let s0 = Cursor::new(r#"(def-msg language-perfer :lang 'string)"#.as_bytes());
-        let mut t0 = parser.tokenize(s0.clone());
+        let mut t0 = parser.tokenize(s0.clone()).unwrap();
 
         assert_eq!(expr, parser.read_exp(&mut t0).unwrap(),);
     }
 
+    #[test]
+    fn test_normalize() {
+        let mut parser = Parser::new();
+        let expr = parser
+            .parse_root_one(Cursor::new(
+                r#"(get-book :version '"1984" :title ''"hello" :lang ''(:lang 'string) :id '12)"#,
+            ))
+            .unwrap();
+
+        assert_eq!(
+            expr.normalize(false).to_string(),
+            r#"(get-book :version "1984" :title "hello" :lang '(:lang 'string) :id 12)"#
+        );
+
+        assert_eq!(
+            expr.normalize(true).to_string(),
+            r#"(get-book :id 12 :lang '(:lang 'string) :title "hello" :version "1984")"#
+        );
+
+        // map without name
+        let expr = parser
+            .parse_root_one(Cursor::new(r#"(:b 1 :a 2)"#))
+            .unwrap();
+        assert_eq!(expr.normalize(true).to_string(), "(:a 2 :b 1)");
+
+        // not pairs, keep the order
+        let expr = parser
+            .parse_root_one(Cursor::new(r#"(list 'b 'a)"#))
+            .unwrap();
+        assert_eq!(expr.normalize(true), expr);
+
+        let a = parser
+            .parse_root_one(Cursor::new(r#"(a :x 1 :y '"s")"#))
+            .unwrap();
+        let b = parser
+            .parse_root_one(Cursor::new(r#"(a :y "s" :x 1)"#))
+            .unwrap();
+        assert_ne!(a, b);
+        assert_eq!(a.normalize(true), b.normalize(true));
+    }
+
+    #[test]
+    fn test_walk() {
+        let mut parser = Parser::new();
+        let expr = parser
+            .parse_root_one(Cursor::new(
+                r#"(def-rpc get-book '(:title 'string) 'book-info)"#,
+            ))
+            .unwrap();
+
+        assert_eq!(
+            expr.walk().collect::<Vec<_>>(),
+            vec![
+                (vec![0], &Atom::read("def-rpc")),
+                (vec![1], &Atom::read("get-book")),
+                (vec![2, 0, 0], &Atom::read_keyword("title")),
+                (vec![2, 0, 1, 0], &Atom::read("string")),
+                (vec![3, 0], &Atom::read("book-info")),
+            ]
+        );
+
+        assert_eq!(expr.depth(), 4);
+        // root list, 2 symbols, quote, list, keyword, quote, symbol, quote, symbol
+        assert_eq!(expr.node_count(), 10);
+
+        let expr = Expr::List(vec![]);
+        assert_eq!(expr.walk().count(), 0);
+        assert_eq!(expr.depth(), 1);
+        assert_eq!(expr.node_count(), 1);
+
+        let expr = Expr::Atom(Atom::read("a"));
+        assert_eq!(
+            expr.walk().collect::<Vec<_>>(),
+            vec![(vec![], &Atom::read("a"))]
+        );
+        assert_eq!(expr.depth(), 0);
+    }
+
     #[test]
     fn test_into_tokens() {
         let mut parser = Parser::new();
@@ -10,3 +10,124 @@ macro_rules! impl_into_data_for_numbers {
         )*
     };
 }
+
+#[macro_export]
+macro_rules! impl_into_data_for_tuples {
+    ($(($($name:ident $idx:tt),+)),*) => {
+        $(
+            impl<$($name: IntoData),+> IntoData for ($($name,)+) {
+                fn into_rpc_data(&self) -> Data {
+                    Data::List(ListData {
+                        inner_data: vec![$(self.$idx.into_rpc_data()),+],
+                    })
+                }
+            }
+        )*
+    };
+}
+
+#[macro_export]
+macro_rules! impl_try_from_data {
+    ($($type:ty),*) => {
+        $(
+            impl TryFrom<&Data> for $type {
+                type Error = DataError;
+
+                fn try_from(d: &Data) -> Result<Self, Self::Error> {
+                    Self::from_rpc_data(d)
+                }
+            }
+        )*
+    };
+}
+
+/// Build the nested Data literal, the `{expr}` interpolates any IntoData value.
+/// The quoted lists and maps are written in `[...]` because `'(` cannot be
+/// the rust tokens:
+///
+/// ```
+/// # use lisp_rpc_rust_parser::data;
+/// let (title, enc) = ("1984", 8);
+/// let d = data!((get-book :title {title} :lang [:lang "en" :encoding {enc}] :tags ["a" "b"]));
+/// assert_eq!(
+///     d.to_string(),
+///     r#"(get-book :title "1984" :lang '(:lang "en" :encoding 8) :tags '("a" "b"))"#
+/// );
+/// ```
+///
+/// `t` and `nil` are the boolean values. The keyword values need the
+/// interpolation like `{Data::Value(TypeValue::Keyword(..))}`.
+/// Panics if the keywords are duplicate.
+#[macro_export]
+macro_rules! data {
+    // the expr data name, like get-book
+    (@name [$($n:tt)*] $i:ident - $($rest:tt)*) => {
+        $crate::data!(@name [$($n)* $i -] $($rest)*)
+    };
+    (@name [$($n:tt)*] $i:ident $($rest:tt)*) => {
+        $crate::data!(@kws [data] ($crate::data::DataBuilder::new(concat!($(stringify!($n),)* stringify!($i)))) $($rest)*)
+    };
+
+    // the keyword-value pairs of expr data and map
+    (@kws [data] ($b:expr)) => {
+        $b.build().expect("invalid data! literal")
+    };
+    (@kws [map] ($b:expr)) => {
+        $crate::data::Data::Map($b.build().expect("invalid data! literal"))
+    };
+    (@kws [$fin:ident] ($b:expr) : $($rest:tt)*) => {
+        $crate::data!(@kw [$fin] ($b) [] $($rest)*)
+    };
+
+    // the keyword, like :book-id
+    (@kw [$fin:ident] ($b:expr) [$($k:tt)*] $i:ident - $next:ident $($rest:tt)*) => {
+        $crate::data!(@kw [$fin] ($b) [$($k)* $i -] $next $($rest)*)
+    };
+    (@kw [$fin:ident] ($b:expr) [$($k:tt)*] $i:ident $($rest:tt)*) => {
+        $crate::data!(@val [$fin] ($b) (concat!($(stringify!($k),)* stringify!($i))) $($rest)*)
+    };
+
+    // the value of keyword
+    (@val [$fin:ident] ($b:expr) ($k:expr) - $l:literal $($rest:tt)*) => {
+        $crate::data!(@kws [$fin] ($b.kw($k, $crate::data!(- $l))) $($rest)*)
+    };
+    (@val [$fin:ident] ($b:expr) ($k:expr) $v:tt $($rest:tt)*) => {
+        $crate::data!(@kws [$fin] ($b.kw($k, $crate::data!($v))) $($rest)*)
+    };
+
+    // the elements of list
+    (@list ($b:expr)) => {
+        $crate::data::Data::List($b.build().expect("invalid data! literal"))
+    };
+    (@list ($b:expr) - $l:literal $($rest:tt)*) => {
+        $crate::data!(@list ($b.item($crate::data!(- $l))) $($rest)*)
+    };
+    (@list ($b:expr) $v:tt $($rest:tt)*) => {
+        $crate::data!(@list ($b.item($crate::data!($v))) $($rest)*)
+    };
+
+    (($($body:tt)+)) => {
+        $crate::data!(@name [] $($body)+)
+    };
+    ([: $($body:tt)+]) => {
+        $crate::data!(@kws [map] ($crate::data::MapBuilder::new()) : $($body)+)
+    };
+    ([$($body:tt)*]) => {
+        $crate::data!(@list ($crate::data::ListBuilder::new()) $($body)*)
+    };
+    ({$e:expr}) => {
+        $crate::data::IntoData::into_rpc_data(&$e)
+    };
+    (t) => {
+        $crate::data::IntoData::into_rpc_data(&true)
+    };
+    (nil) => {
+        $crate::data::Data::Nil
+    };
+    (- $l:literal) => {
+        $crate::data::IntoData::into_rpc_data(&-$l)
+    };
+    ($l:literal) => {
+        $crate::data::IntoData::into_rpc_data(&$l)
+    };
+}
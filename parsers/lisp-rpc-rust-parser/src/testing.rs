@@ -0,0 +1,93 @@
+//! proptest strategies for the Expr, Atom, and TypeValue.
+//!
+//! Enable the `testing` feature to use them in other crates, like:
+//! `proptest! { fn round_trip(e in any::<Expr>()) { ... } }`
+
+use proptest::prelude::*;
+
+use crate::{Atom, Expr, TypeValue};
+
+/// symbol and keyword names, always start with a letter so they never read as number
+pub fn arb_symbol_name() -> impl Strategy<Value = String> {
+    "[a-z][a-z0-9-]{0,8}"
+}
+
+/// string contents, including the chars need escaping
+pub fn arb_string_content() -> impl Strategy<Value = String> {
+    "(\\PC|\n){0,10}"
+}
+
+pub fn arb_type_value() -> impl Strategy<Value = TypeValue> {
+    prop_oneof![
+        arb_symbol_name().prop_map(TypeValue::Symbol),
+        arb_string_content().prop_map(TypeValue::String),
+        arb_symbol_name().prop_map(TypeValue::Keyword),
+        any::<i64>().prop_map(TypeValue::Number),
+    ]
+}
+
+pub fn arb_atom() -> impl Strategy<Value = Atom> {
+    arb_type_value().prop_map(|value| Atom { value })
+}
+
+/// the nested expr, at most `depth` levels
+pub fn arb_expr_with_depth(depth: u32) -> impl Strategy<Value = Expr> {
+    arb_atom()
+        .prop_map(Expr::Atom)
+        .prop_recursive(depth, 64, 5, |inner| {
+            prop_oneof![
+                prop::collection::vec(inner.clone(), 0..5).prop_map(Expr::List),
+                inner.prop_map(|e| Expr::Quote(Box::new(e))),
+            ]
+        })
+}
+
+pub fn arb_expr() -> impl Strategy<Value = Expr> {
+    arb_expr_with_depth(4)
+}
+
+impl Arbitrary for TypeValue {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        arb_type_value().boxed()
+    }
+}
+
+impl Arbitrary for Atom {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        arb_atom().boxed()
+    }
+}
+
+impl Arbitrary for Expr {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        arb_expr().boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::Parser;
+
+    proptest! {
+        #[test]
+        fn test_round_trip(e in any::<Expr>()) {
+            // root has to be the list
+            let root = Expr::List(vec![e]);
+            let mut parser = Parser::new();
+            let parsed = parser.parse_root_one(Cursor::new(root.to_string())).unwrap();
+            prop_assert_eq!(parsed, root);
+        }
+    }
+}
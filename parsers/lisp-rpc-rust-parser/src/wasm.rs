@@ -0,0 +1,60 @@
+//! wasm bindings of the parser, so the browser side tools (spec playground, debugging UI)
+//! can reuse the same parser.
+
+use std::io::Cursor;
+
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    Expr, Parser,
+    data::{Data, FromStr, GetAbleData},
+};
+
+/// parse the source code and return all root exprs
+#[wasm_bindgen]
+pub fn parse(source: &str) -> Result<Vec<WasmExpr>, JsError> {
+    let mut parser = Parser::new();
+    let exprs = parser.parse_root(Cursor::new(source))?;
+    Ok(exprs.into_iter().map(|inner| WasmExpr { inner }).collect())
+}
+
+/// the Expr wrapper for js side
+#[wasm_bindgen]
+pub struct WasmExpr {
+    inner: Expr,
+}
+
+#[wasm_bindgen]
+impl WasmExpr {
+    #[wasm_bindgen(js_name = toString)]
+    pub fn to_js_string(&self) -> String {
+        self.inner.into_tokens()
+    }
+}
+
+/// the Data wrapper for js side
+#[wasm_bindgen]
+pub struct WasmData {
+    inner: Data,
+}
+
+#[wasm_bindgen]
+impl WasmData {
+    /// read the data from str
+    #[wasm_bindgen(constructor)]
+    pub fn new(source: &str) -> Result<WasmData, JsError> {
+        let inner =
+            Data::from_str(&Parser::new(), source).map_err(|e| JsError::new(&e.to_string()))?;
+        Ok(Self { inner })
+    }
+
+    /// get the value of keyword
+    pub fn get(&self, key: &str) -> Option<WasmData> {
+        self.inner.get(key).map(|d| WasmData { inner: d.clone() })
+    }
+
+    #[wasm_bindgen(js_name = toString)]
+    pub fn to_js_string(&self) -> String {
+        self.inner.to_string()
+    }
+}